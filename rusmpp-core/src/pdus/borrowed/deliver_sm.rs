@@ -169,6 +169,10 @@ impl<'a, const N: usize> DeliverSm<'a, N> {
         self.tlvs.clear();
     }
 
+    pub fn retain_tlvs(&mut self, f: impl FnMut(&Tlv<'a>) -> bool) {
+        self.tlvs.retain(f);
+    }
+
     pub fn push_tlv(
         &mut self,
         tlv: impl Into<MessageDeliveryRequestTlvValue<'a>>,
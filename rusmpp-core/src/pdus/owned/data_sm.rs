@@ -109,6 +109,10 @@ impl DataSm {
         self.tlvs.clear();
     }
 
+    pub fn retain_tlvs(&mut self, f: impl FnMut(&Tlv) -> bool) {
+        self.tlvs.retain(f);
+    }
+
     pub fn push_tlv(&mut self, tlv: impl Into<MessageSubmissionRequestTlvValue>) {
         self.tlvs.push(Tlv::from(tlv.into()));
     }
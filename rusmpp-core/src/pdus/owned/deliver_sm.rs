@@ -167,6 +167,10 @@ impl DeliverSm {
         self.tlvs.clear();
     }
 
+    pub fn retain_tlvs(&mut self, f: impl FnMut(&Tlv) -> bool) {
+        self.tlvs.retain(f);
+    }
+
     pub fn push_tlv(&mut self, tlv: impl Into<MessageDeliveryRequestTlvValue>) {
         self.tlvs.push(Tlv::from(tlv.into()));
     }
@@ -1,4 +1,6 @@
-use crate::{CloseRequest, PendingResponses, RegisteredRequest, Request, UnregisteredRequest};
+use crate::{
+    CloseRequest, DrainRequest, PendingResponses, RegisteredRequest, Request, UnregisteredRequest,
+};
 
 #[allow(clippy::large_enum_variant)]
 #[derive(Debug)]
@@ -10,6 +12,9 @@ pub enum Action {
     Remove(u32),
     /// The connection will stop reading from the server, stop time keeping, close the requests channel, flush pending requests and terminate.
     Close(CloseRequest),
+    /// The connection will reject new operations except [`Unbind`](rusmpp::Pdu::Unbind)
+    /// and acknowledge the request once no registered requests are waiting for a response.
+    Drain(DrainRequest),
     /// Sent from the client to the connection to check if the connection is closed or not.
     ///
     /// The client would fail to send this action through the channel if the connection is closed.
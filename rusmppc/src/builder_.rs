@@ -1,14 +1,22 @@
-use std::time::Duration;
+use std::{sync::Arc, time::Duration};
 
 use futures::Stream;
 use rusmpp::tokio_codec::CommandCodec;
 use tokio::io::{AsyncRead, AsyncWrite};
 use tokio_util::codec::Framed;
 
+#[cfg(feature = "tokio")]
+use crate::proxy_::Proxy;
+#[cfg(all(feature = "tokio", feature = "rustls"))]
+use crate::tls_::{TlsFiles, TlsPin};
 use crate::{
     Client,
+    congestion_::CongestionControl,
     event_::{DefaultEventChannel, DiscardEventChannel, EventChannel, InsightEventChannel},
     runtime_::{Delay, Timeout, tokio::Tokio, wasm::Wasm},
+    sequence_::{SequenceNumberAllocator, SharedSequenceNumberAllocator},
+    span_::{RequestSpanHook, SharedRequestSpanHook},
+    tracking_::{DeliveryTracking, InMemoryTrackingStore, TrackingStore},
 };
 
 /// Default [`tokio`] connection builder that sends [`Event`](crate::event::Event)s through the event stream.
@@ -29,9 +37,26 @@ pub struct ConnectionBuilder<E, R> {
     /// Timeout for waiting for a response from the server.
     pub(crate) response_timeout: Option<Duration>,
     pub(crate) check_interface_version: bool,
+    pub(crate) check_session_state: bool,
+    /// Value of the `connection` label of the connection metrics. If None, the URL is used.
+    pub(crate) metrics_label: Option<String>,
+    /// Delivery receipt tracking. If None, [`Client::submit_sm_tracked`] is disabled.
+    pub(crate) delivery_tracking: Option<DeliveryTracking>,
+    /// Sequence number allocator provided by the user. If None, each connection counts from 1.
+    pub(crate) sequence_number_allocator: Option<SharedSequenceNumberAllocator>,
+    /// Hook called with every new request span. If None, request spans are left as they are.
+    pub(crate) request_span_hook: Option<SharedRequestSpanHook>,
+    /// Window of outstanding submissions adapted to the congestion state of the server. If None, submissions are not held back.
+    pub(crate) congestion_control: Option<CongestionControl>,
+    /// Proxy to tunnel the connection through. If None, the server is dialed directly.
+    #[cfg(feature = "tokio")]
+    proxy: Option<Proxy>,
     /// TLS configurations provided by the user. If None, default configurations will be used.
     #[cfg(all(feature = "tokio", feature = "rustls"))]
     rustls_config: Option<rustls::ClientConfig>,
+    /// CA bundle, client certificate and pins loaded on a blocking thread on every connect. Ignored if `rustls_config` is set.
+    #[cfg(all(feature = "tokio", feature = "rustls"))]
+    tls_files: TlsFiles,
     /// Native TLS connector provided by the user. If None, default connector will be used.
     #[cfg(all(feature = "tokio", feature = "native-tls"))]
     native_tls_connector: Option<native_tls::TlsConnector>,
@@ -55,7 +80,15 @@ impl DefaultTokioConnectionBuilder {
     /// - `auto_enquire_link_response`: true
     /// - `response_timeout`: 5 seconds
     /// - `check_interface_version`: true
+    /// - `check_session_state`: false
+    /// - `metrics_label`: the scheme, host and port of the URL, or `stream` for existing connections
+    /// - `delivery_tracking`: disabled
+    /// - `sequence_number_allocator`: an [`AtomicSequenceNumberAllocator`](crate::sequence::AtomicSequenceNumberAllocator) per connection
+    /// - `request_span_hook`: none
+    /// - `congestion_control`: none
+    /// - `proxy`: none
     /// - `rustls_config`: default configuration will be used if TLS is enabled. See [`rustls_config`](Self::rustls_config) for more details.
+    /// - `tls_ca_file`, `tls_client_auth_pem`, `tls_pin`: none
     /// - `native_tls_connector`: default connector will be used if TLS is enabled. See [`native_tls_connector`](Self::native_tls_connector) for more details.
     pub fn new() -> Self {
         Self {
@@ -65,8 +98,18 @@ impl DefaultTokioConnectionBuilder {
             auto_enquire_link_response: true,
             response_timeout: Some(Duration::from_secs(5)),
             check_interface_version: true,
+            check_session_state: false,
+            metrics_label: None,
+            delivery_tracking: None,
+            sequence_number_allocator: None,
+            request_span_hook: None,
+            congestion_control: None,
+            #[cfg(feature = "tokio")]
+            proxy: None,
             #[cfg(all(feature = "tokio", feature = "rustls"))]
             rustls_config: None,
+            #[cfg(all(feature = "tokio", feature = "rustls"))]
+            tls_files: TlsFiles::default(),
             #[cfg(all(feature = "tokio", feature = "native-tls"))]
             native_tls_connector: None,
             _e: std::marker::PhantomData,
@@ -98,6 +141,12 @@ impl DefaultWasmConnectionBuilder {
     /// - `auto_enquire_link_response`: true
     /// - `response_timeout`: 5 seconds
     /// - `check_interface_version`: true
+    /// - `check_session_state`: false
+    /// - `metrics_label`: the scheme, host and port of the URL, or `stream` for existing connections
+    /// - `delivery_tracking`: disabled
+    /// - `sequence_number_allocator`: an [`AtomicSequenceNumberAllocator`](crate::sequence::AtomicSequenceNumberAllocator) per connection
+    /// - `request_span_hook`: none
+    /// - `congestion_control`: none
     pub fn new_wasm() -> Self {
         Self {
             max_command_length: 4096,
@@ -106,8 +155,18 @@ impl DefaultWasmConnectionBuilder {
             auto_enquire_link_response: true,
             response_timeout: Some(Duration::from_secs(5)),
             check_interface_version: true,
+            check_session_state: false,
+            metrics_label: None,
+            delivery_tracking: None,
+            sequence_number_allocator: None,
+            request_span_hook: None,
+            congestion_control: None,
+            #[cfg(feature = "tokio")]
+            proxy: None,
             #[cfg(all(feature = "tokio", feature = "rustls"))]
             rustls_config: None,
+            #[cfg(all(feature = "tokio", feature = "rustls"))]
+            tls_files: TlsFiles::default(),
             #[cfg(all(feature = "tokio", feature = "native-tls"))]
             native_tls_connector: None,
             _e: std::marker::PhantomData,
@@ -151,25 +210,42 @@ impl<E: EventChannel> ConnectionBuilder<E, Tokio> {
     /// # }
     /// ```
     ///
+    /// Connect to an `SMPP` server accepting `WebSocket` connections on localhost at port 7777.
+    ///
+    /// ```
+    /// # use rusmppc::ConnectionBuilder;
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let (client, events) = ConnectionBuilder::new()
+    ///     .connect("ws://localhost:7777")
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
     /// # Supported URL schemes
     /// - `smpp`: Connect using plain TCP.
     /// - `ssmpp` or `smpps`: Connect using TLS. Requires the `rustls` or `native-tls` features to be enabled.
+    /// - `ws`: Connect using `WebSocket`, every command is sent in its own binary message. Requires the `websocket` feature to be enabled.
+    /// - `wss`: Connect using `WebSocket` over TLS. Requires the `websocket` feature and the `rustls` or `native-tls` features to be enabled.
     ///
     /// # Notes
-    /// - If no port is specified in the URL, the default port `2775` will be used.
-    /// - Path and query parameters in the URL are ignored silently.
+    /// - If no port is specified in the URL, the default port `2775` will be used, or `80` and `443` for `ws` and `wss`.
+    /// - Path and query parameters in the URL are sent in the `WebSocket` handshake, and ignored silently otherwise.
     ///
     /// # Errors
     ///
     /// This function will return an error in the following cases:
     ///
     /// - If the URL is invalid. See [`url::Url::parse`] for more details.
-    /// - If the URL scheme is not supported. Supported schemes are `smpp`, `ssmpp`, and `smpps`.
+    /// - If the URL scheme is not supported. Supported schemes are `smpp`, `ssmpp`, `smpps`, `ws`, and `wss`.
     /// - If the URL does not have a host.
     /// - If DNS resolution fails.
-    /// - If the connection to the server fails.
-    /// - If TLS is enabled (when using `ssmpp` or `smpps` schemes) but the `rustls` or `native-tls` features are not enabled.
+    /// - If the connection to the server or to the [`proxy`](ConnectionBuilder::proxy) fails.
+    /// - If the proxy refuses to open a tunnel to the server.
+    /// - If TLS is enabled (when using `ssmpp`, `smpps` or `wss` schemes) but the `rustls` or `native-tls` features are not enabled.
     /// - If TLS handshake fails.
+    /// - If `WebSocket` is used (when using `ws` or `wss` schemes) but the `websocket` feature is not enabled.
+    /// - If `WebSocket` handshake fails.
     pub async fn connect(
         self,
         url: impl AsRef<str>,
@@ -213,6 +289,38 @@ impl<E: EventChannel> ConnectionBuilder<E, Tokio> {
 
 #[cfg(feature = "wasm")]
 impl<E: EventChannel> ConnectionBuilder<E, Wasm> {
+    /// Connects to the `SMPP` server through the browser `WebSocket`.
+    ///
+    /// Opens and manages a connection in the background and returns a client and an event stream.
+    ///
+    /// - The client is used as a handle to communicate with the server through the managed connection.
+    /// - The event stream is used to receive events from the server, such as incoming messages or errors.
+    ///
+    /// Every command is sent in its own binary message.
+    ///
+    /// # Supported URL schemes
+    /// - `ws`: Connect using `WebSocket`.
+    /// - `wss`: Connect using `WebSocket` over TLS.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error in the following cases:
+    ///
+    /// - If the URL is invalid. See [`url::Url::parse`] for more details.
+    /// - If the URL scheme is not supported. Supported schemes are `ws` and `wss`.
+    /// - If the browser fails to open the `WebSocket`.
+    pub async fn connect(
+        self,
+        url: impl AsRef<str>,
+    ) -> Result<(Client<Wasm>, impl Stream<Item = E::Event> + Unpin + 'static), crate::error::Error>
+    {
+        let (client, events, connection) = self.no_spawn().connect(url).await?;
+
+        Wasm::spawn(connection);
+
+        Ok((client, events))
+    }
+
     /// Creates a client from an existing connection.
     ///
     /// Manages a connection in the background and returns a client and an event stream.
@@ -371,6 +479,140 @@ impl<E, R> ConnectionBuilder<E, R> {
         self
     }
 
+    /// Enables the session state check.
+    ///
+    /// See [`with_session_state_check`](Self::with_session_state_check) for more details.
+    pub fn enable_session_state_check(mut self) -> Self {
+        self.check_session_state = true;
+        self
+    }
+
+    /// Disables the session state check.
+    ///
+    /// See [`with_session_state_check`](Self::with_session_state_check) for more details.
+    pub fn disable_session_state_check(mut self) -> Self {
+        self.check_session_state = false;
+        self
+    }
+
+    /// Enables or disables the session state check.
+    ///
+    /// By default, the session state check is disabled.
+    ///
+    /// The connection tracks its [`SessionState`](rusmpp::session::SessionState) from bind responses, unbind and outbind,
+    /// and validates commands against the operation matrix of the `SMPP v5` specification:
+    ///
+    /// - Commands that may not be sent in the current state fail with [`Error::InvalidSessionState`](crate::error::Error::InvalidSessionState) without being sent.
+    /// - Operations from the server that may not be received in the current state are rejected with a [`GenericNack`](rusmpp::Pdu::GenericNack)
+    ///   with [`EsmeRinvbndsts`](rusmpp::CommandStatus::EsmeRinvbndsts) and are not passed to the event stream.
+    ///
+    /// The session state is always tracked and can be read with [`Client::session_state`].
+    pub fn with_session_state_check(mut self, check: bool) -> Self {
+        self.check_session_state = check;
+        self
+    }
+
+    /// Sets the value of the `connection` label of the connection metrics recorded with the `metrics` feature.
+    ///
+    /// By default, connections opened with `connect` are labeled with the scheme, host and port of the URL
+    /// and connections created from existing streams are labeled with `stream`.
+    pub fn metrics_label(mut self, label: impl Into<String>) -> Self {
+        self.metrics_label = Some(label.into());
+        self
+    }
+
+    /// Enables delivery receipt tracking with an [`InMemoryTrackingStore`].
+    ///
+    /// See [`delivery_tracking_store`](Self::delivery_tracking_store) for more details.
+    pub fn delivery_tracking(self, ttl: Duration) -> Self {
+        self.delivery_tracking_store(ttl, InMemoryTrackingStore::new())
+    }
+
+    /// Enables delivery receipt tracking with a custom [`TrackingStore`].
+    ///
+    /// Enables [`Client::submit_sm_tracked`], which returns a [`DeliveryTracker`](crate::tracking::DeliveryTracker)
+    /// resolving to the final [`DeliveryReceipt`](crate::tracking::DeliveryReceipt) of the submission.
+    ///
+    /// Receipts are matched using the `receipted_message_id` TLV or the `id` field of the receipt text.
+    /// If no final receipt arrives within `ttl`, the tracker resolves to [`Error::DeliveryReceiptTimeout`](crate::error::Error::DeliveryReceiptTimeout).
+    ///
+    /// The store is shared by all connections created from this builder, e.g. reconnects of a [`ManagedClient`](crate::managed::ManagedClient).
+    /// Receipts are still emitted through the event stream.
+    pub fn delivery_tracking_store(mut self, ttl: Duration, store: impl TrackingStore) -> Self {
+        self.delivery_tracking = Some(DeliveryTracking::new(Arc::new(store), ttl));
+        self
+    }
+
+    /// Disables delivery receipt tracking.
+    pub fn no_delivery_tracking(mut self) -> Self {
+        self.delivery_tracking = None;
+        self
+    }
+
+    /// Sets the allocator for the sequence numbers of outgoing requests.
+    ///
+    /// The allocator is shared by all connections created from this builder. Pass the same [`Arc`] to several builders
+    /// to make sequence numbers unique across all of their connections.
+    ///
+    /// Sequence numbers still waiting for a response on a connection are skipped.
+    pub fn sequence_number_allocator(mut self, allocator: impl SequenceNumberAllocator) -> Self {
+        self.sequence_number_allocator =
+            Some(SharedSequenceNumberAllocator::new(Arc::new(allocator)));
+        self
+    }
+
+    /// Uses a new [`AtomicSequenceNumberAllocator`](crate::sequence::AtomicSequenceNumberAllocator) for each connection.
+    pub fn no_sequence_number_allocator(mut self) -> Self {
+        self.sequence_number_allocator = None;
+        self
+    }
+
+    /// Sets the hook called with every new request span.
+    ///
+    /// See [`RequestSpanHook`] for propagating an `OpenTelemetry` context.
+    pub fn request_span_hook(mut self, hook: impl RequestSpanHook) -> Self {
+        self.request_span_hook = Some(SharedRequestSpanHook::new(Arc::new(hook)));
+        self
+    }
+
+    /// Removes the request span hook.
+    pub fn no_request_span_hook(mut self) -> Self {
+        self.request_span_hook = None;
+        self
+    }
+
+    /// Adapts the number of outstanding submissions to the `congestion_state` reported by the server.
+    ///
+    /// See [`CongestionControl`] for more details.
+    pub fn congestion_control(mut self, congestion_control: CongestionControl) -> Self {
+        self.congestion_control = Some(congestion_control);
+        self
+    }
+
+    /// Sends submissions without holding them back, regardless of the congestion state of the server.
+    pub fn no_congestion_control(mut self) -> Self {
+        self.congestion_control = None;
+        self
+    }
+
+    /// Tunnels the connection through the given proxy.
+    ///
+    /// Used by [`connect`](Self::connect) and by the reconnects of a [`ManagedClient`](crate::managed::ManagedClient).
+    #[cfg(feature = "tokio")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "tokio")))]
+    pub fn proxy(mut self, proxy: Proxy) -> Self {
+        self.proxy = Some(proxy);
+        self
+    }
+
+    /// Dials the server directly.
+    #[cfg(feature = "tokio")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "tokio")))]
+    pub fn no_proxy(mut self) -> Self {
+        self.proxy = None;
+        self
+    }
+
     /// Sets a custom `rustls` client configuration.
     ///
     /// If not set, a default configuration will be used.
@@ -382,6 +624,87 @@ impl<E, R> ConnectionBuilder<E, R> {
         self
     }
 
+    /// Trusts the CA certificates of the given PEM bundle instead of the default roots.
+    ///
+    /// The file is read on a blocking thread on every connect. Ignored if a [`rustls_config`](Self::rustls_config) is set.
+    #[cfg(all(feature = "tokio", feature = "rustls"))]
+    pub fn tls_ca_file(mut self, path: impl Into<std::path::PathBuf>) -> Self {
+        self.tls_files.ca_file = Some(path.into());
+        self
+    }
+
+    /// Trusts the default roots. See [`rustls_config`](Self::rustls_config).
+    #[cfg(all(feature = "tokio", feature = "rustls"))]
+    pub fn no_tls_ca_file(mut self) -> Self {
+        self.tls_files.ca_file = None;
+        self
+    }
+
+    /// Presents the PEM certificate chain and private key to the server for mutual TLS.
+    ///
+    /// The files are read on a blocking thread on every connect, so reconnects of a [`ManagedClient`](crate::managed::ManagedClient) pick up rotated certificates.
+    /// Ignored if a [`rustls_config`](Self::rustls_config) is set.
+    #[cfg(all(feature = "tokio", feature = "rustls"))]
+    pub fn tls_client_auth_pem(
+        mut self,
+        cert_path: impl Into<std::path::PathBuf>,
+        key_path: impl Into<std::path::PathBuf>,
+    ) -> Self {
+        self.tls_files.client_auth = Some((cert_path.into(), key_path.into()));
+        self
+    }
+
+    /// Does not present a client certificate.
+    #[cfg(all(feature = "tokio", feature = "rustls"))]
+    pub fn no_tls_client_auth(mut self) -> Self {
+        self.tls_files.client_auth = None;
+        self
+    }
+
+    /// Adds a pinned server certificate or public key.
+    ///
+    /// Once a pin is added, the server is accepted only if its chain is valid and one of its certificates matches one of the pins.
+    /// Ignored if a [`rustls_config`](Self::rustls_config) is set.
+    #[cfg(all(feature = "tokio", feature = "rustls"))]
+    pub fn tls_pin(mut self, pin: TlsPin) -> Self {
+        self.tls_files.pins.push(pin);
+        self
+    }
+
+    /// Removes all pins.
+    #[cfg(all(feature = "tokio", feature = "rustls"))]
+    pub fn no_tls_pins(mut self) -> Self {
+        self.tls_files.pins.clear();
+        self
+    }
+
+    /// Takes the custom `rustls` configuration, or builds one from the TLS files if any are set.
+    ///
+    /// The files are loaded on a blocking thread to keep file I/O off the runtime.
+    #[cfg(all(feature = "tokio", feature = "rustls"))]
+    async fn take_rustls_config(
+        &mut self,
+    ) -> Result<Option<rustls::ClientConfig>, crate::error::Error> {
+        if let Some(config) = self.rustls_config.take() {
+            if !self.tls_files.is_empty() {
+                tracing::warn!(target: "rusmppc::connection::tls::rustls", "Custom rustls configuration is set, ignoring TLS files and pins");
+            }
+
+            return Ok(Some(config));
+        }
+
+        if self.tls_files.is_empty() {
+            return Ok(None);
+        }
+
+        let files = self.tls_files.clone();
+
+        Tokio::spawn_blocking(move || files.client_config())
+            .await
+            .map_err(|err| crate::error::Error::Connect(std::io::Error::other(err)))?
+            .map(Some)
+    }
+
     /// Sets a custom `native-tls` connector.
     ///
     /// If not set, a default connector will be used.
@@ -412,8 +735,18 @@ impl<E, R> ConnectionBuilder<E, R> {
             auto_enquire_link_response: self.auto_enquire_link_response,
             response_timeout: self.response_timeout,
             check_interface_version: self.check_interface_version,
+            check_session_state: self.check_session_state,
+            metrics_label: self.metrics_label,
+            delivery_tracking: self.delivery_tracking,
+            sequence_number_allocator: self.sequence_number_allocator,
+            request_span_hook: self.request_span_hook,
+            congestion_control: self.congestion_control,
+            #[cfg(feature = "tokio")]
+            proxy: self.proxy,
             #[cfg(all(feature = "tokio", feature = "rustls"))]
             rustls_config: self.rustls_config,
+            #[cfg(all(feature = "tokio", feature = "rustls"))]
+            tls_files: self.tls_files,
             #[cfg(all(feature = "tokio", feature = "native-tls"))]
             native_tls_connector: self.native_tls_connector,
             _e: std::marker::PhantomData,
@@ -430,8 +763,18 @@ impl<E, R> ConnectionBuilder<E, R> {
             auto_enquire_link_response: self.auto_enquire_link_response,
             response_timeout: self.response_timeout,
             check_interface_version: self.check_interface_version,
+            check_session_state: self.check_session_state,
+            metrics_label: self.metrics_label,
+            delivery_tracking: self.delivery_tracking,
+            sequence_number_allocator: self.sequence_number_allocator,
+            request_span_hook: self.request_span_hook,
+            congestion_control: self.congestion_control,
+            #[cfg(feature = "tokio")]
+            proxy: self.proxy,
             #[cfg(all(feature = "tokio", feature = "rustls"))]
             rustls_config: self.rustls_config,
+            #[cfg(all(feature = "tokio", feature = "rustls"))]
+            tls_files: self.tls_files,
             #[cfg(all(feature = "tokio", feature = "native-tls"))]
             native_tls_connector: self.native_tls_connector,
             _e: std::marker::PhantomData,
@@ -455,12 +798,15 @@ impl<E: EventChannel> NoSpawnConnectionBuilder<E, Tokio> {
     /// This function will return an error in the following cases:
     ///
     /// - If the URL is invalid. See [`url::Url::parse`] for more details.
-    /// - If the URL scheme is not supported. Supported schemes are `smpp`, `ssmpp`, and `smpps`.
+    /// - If the URL scheme is not supported. Supported schemes are `smpp`, `ssmpp`, `smpps`, `ws`, and `wss`.
     /// - If the URL does not have a host.
     /// - If DNS resolution fails.
-    /// - If the connection to the server fails.
-    /// - If TLS is enabled (when using `ssmpp` or `smpps` schemes) but the `rustls` or `native-tls` features are not enabled.
+    /// - If the connection to the server or to the [`proxy`](ConnectionBuilder::proxy) fails.
+    /// - If the proxy refuses to open a tunnel to the server.
+    /// - If TLS is enabled (when using `ssmpp`, `smpps` or `wss` schemes) but the `rustls` or `native-tls` features are not enabled.
     /// - If TLS handshake fails.
+    /// - If `WebSocket` is used (when using `ws` or `wss` schemes) but the `websocket` feature is not enabled.
+    /// - If `WebSocket` handshake fails.
     #[allow(unused_mut)]
     pub async fn connect(
         mut self,
@@ -478,6 +824,23 @@ impl<E: EventChannel> NoSpawnConnectionBuilder<E, Tokio> {
         enum Scheme {
             Smpp,
             Ssmpp,
+            #[cfg(feature = "websocket")]
+            Ws,
+            #[cfg(feature = "websocket")]
+            Wss,
+        }
+
+        impl Scheme {
+            const fn is_tls(&self) -> bool {
+                match self {
+                    Scheme::Smpp => false,
+                    Scheme::Ssmpp => true,
+                    #[cfg(feature = "websocket")]
+                    Scheme::Ws => false,
+                    #[cfg(feature = "websocket")]
+                    Scheme::Wss => true,
+                }
+            }
         }
 
         let url = url::Url::parse(url.as_ref()).map_err(|err| {
@@ -490,11 +853,22 @@ impl<E: EventChannel> NoSpawnConnectionBuilder<E, Tokio> {
         let scheme = match url.scheme() {
             "smpp" => Scheme::Smpp,
             "ssmpp" | "smpps" => Scheme::Ssmpp,
+            #[cfg(feature = "websocket")]
+            "ws" => Scheme::Ws,
+            #[cfg(feature = "websocket")]
+            "wss" => Scheme::Wss,
+            #[cfg(not(feature = "websocket"))]
+            "ws" | "wss" => {
+                return Err(Error::Connect(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    "WebSocket support is not enabled, enable the `websocket` feature to use ws/wss",
+                )));
+            }
             scheme => {
                 return Err(Error::Connect(std::io::Error::new(
                     std::io::ErrorKind::InvalidInput,
                     format!(
-                        "Unsupported URL scheme: {scheme}, supported schemes are smpp and ssmpp/smpps"
+                        "Unsupported URL scheme: {scheme}, supported schemes are smpp, ssmpp/smpps and ws/wss"
                     ),
                 )));
             }
@@ -507,82 +881,169 @@ impl<E: EventChannel> NoSpawnConnectionBuilder<E, Tokio> {
             ))
         })?;
 
-        let port = url.port().unwrap_or(2775);
-
-        tracing::debug!(target: "rusmppc::connection::dns", domain, "Resolving domain");
+        let port = url.port_or_known_default().unwrap_or(2775);
 
-        let resolver = hickory_resolver::TokioResolver::builder_tokio()
-            .and_then(|builder| builder.build())
-            .map_err(|err| {
-                Error::Connect(std::io::Error::other(format!(
-                    "Failed to create DNS resolver: {err}"
-                )))
-            })?;
+        self.builder
+            .metrics_label
+            .get_or_insert_with(|| crate::metrics_::url_label(&url));
 
-        let ip_addr = resolver
-            .lookup_ip(domain)
-            .await
-            .map_err(|err| {
-                Error::Connect(std::io::Error::other(format!("Failed to lookup IP: {err}")))
-            })?
-            .iter()
-            .next()
-            .ok_or_else(|| {
-                Error::Connect(std::io::Error::new(
-                    std::io::ErrorKind::NotFound,
-                    "No addresses found for the given host",
-                ))
-            })?;
-
-        let socket_addr = std::net::SocketAddr::new(ip_addr, port);
-
-        tracing::debug!(target: "rusmppc::connection::tcp", %socket_addr, "Connecting");
-
-        let stream = tokio::net::TcpStream::connect(socket_addr)
-            .await
-            .map_err(Error::Connect)?;
+        let stream = match self.builder.proxy {
+            Some(ref proxy) => {
+                let stream = connect_tcp(proxy.host(), proxy.port()).await?;
 
-        tracing::debug!(target: "rusmppc::connection::tcp", %socket_addr, "Connected");
+                proxy.tunnel(stream, domain, port).await?
+            }
+            None => connect_tcp(domain, port).await?,
+        };
 
-        let stream = match scheme {
-            Scheme::Smpp => MaybeTlsStream::plain(stream),
-            Scheme::Ssmpp => {
-                #[cfg(all(feature = "rustls", not(feature = "native-tls")))]
-                {
-                    MaybeTlsStream::rustls(stream, domain, self.builder.rustls_config.take())
-                        .await?
-                }
-                // If both features are enabled, prefer rustls.
-                #[cfg(all(feature = "rustls", feature = "native-tls"))]
-                {
-                    tracing::warn!(target: "rusmppc::connection::tls", "Both `rustls` and `native-tls` features are enabled, preferring `rustls` for TLS connections");
+        let stream = if !scheme.is_tls() {
+            MaybeTlsStream::plain(stream)
+        } else {
+            #[cfg(all(feature = "rustls", not(feature = "native-tls")))]
+            {
+                MaybeTlsStream::rustls(stream, domain, self.builder.take_rustls_config().await?)
+                    .await?
+            }
+            // If both features are enabled, prefer rustls.
+            #[cfg(all(feature = "rustls", feature = "native-tls"))]
+            {
+                tracing::warn!(target: "rusmppc::connection::tls", "Both `rustls` and `native-tls` features are enabled, preferring `rustls` for TLS connections");
 
-                    MaybeTlsStream::rustls(stream, domain, self.builder.rustls_config.take())
-                        .await?
-                }
-                #[cfg(all(not(feature = "rustls"), feature = "native-tls"))]
-                {
-                    MaybeTlsStream::native_tls(
-                        stream,
-                        domain,
-                        self.builder.native_tls_connector.take(),
-                    )
+                MaybeTlsStream::rustls(stream, domain, self.builder.take_rustls_config().await?)
                     .await?
-                }
-                #[cfg(not(any(feature = "rustls", feature = "native-tls")))]
-                {
-                    return Err(Error::Connect(std::io::Error::new(
-                        std::io::ErrorKind::InvalidInput,
-                        "TLS support is not enabled, enable the `rustls` or `native-tls` feature to use ssmpp/smpps",
-                    )));
-                }
             }
+            #[cfg(all(not(feature = "rustls"), feature = "native-tls"))]
+            {
+                MaybeTlsStream::native_tls(stream, domain, self.builder.native_tls_connector.take())
+                    .await?
+            }
+            #[cfg(not(any(feature = "rustls", feature = "native-tls")))]
+            {
+                return Err(Error::Connect(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    "TLS support is not enabled, enable the `rustls` or `native-tls` feature to use ssmpp/smpps/wss",
+                )));
+            }
+        };
+
+        #[cfg(feature = "websocket")]
+        let framed = match scheme {
+            Scheme::Ws | Scheme::Wss => futures::future::Either::Right(
+                crate::websocket_::connect(&url, stream, self.builder.max_command_length).await?,
+            ),
+            Scheme::Smpp | Scheme::Ssmpp => futures::future::Either::Left(Framed::new(
+                stream,
+                CommandCodec::new().with_max_length(self.builder.max_command_length),
+            )),
         };
 
-        Ok(self.connected(stream))
+        #[cfg(not(feature = "websocket"))]
+        let framed = Framed::new(
+            stream,
+            CommandCodec::new().with_max_length(self.builder.max_command_length),
+        );
+
+        Ok(self.raw(framed))
     }
 }
 
+#[cfg(feature = "wasm")]
+impl<E: EventChannel> NoSpawnConnectionBuilder<E, Wasm> {
+    /// Connects to the `SMPP` server through the browser `WebSocket` without spawning the connection in the background.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error in the following cases:
+    ///
+    /// - If the URL is invalid. See [`url::Url::parse`] for more details.
+    /// - If the URL scheme is not supported. Supported schemes are `ws` and `wss`.
+    /// - If the browser fails to open the `WebSocket`.
+    pub async fn connect(
+        mut self,
+        url: impl AsRef<str>,
+    ) -> Result<
+        (
+            Client<Wasm>,
+            impl Stream<Item = E::Event> + Unpin + 'static,
+            impl Future<Output = ()> + 'static,
+        ),
+        crate::error::Error,
+    > {
+        use crate::error::Error;
+
+        let url = url::Url::parse(url.as_ref()).map_err(|err| {
+            Error::Connect(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("Invalid URL: {err}"),
+            ))
+        })?;
+
+        match url.scheme() {
+            "ws" | "wss" => {}
+            scheme => {
+                return Err(Error::Connect(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    format!("Unsupported URL scheme: {scheme}, supported schemes are ws and wss"),
+                )));
+            }
+        }
+
+        let framed = crate::websocket_::open(&url, self.builder.max_command_length).await?;
+
+        self.builder
+            .metrics_label
+            .get_or_insert_with(|| crate::metrics_::url_label(&url));
+
+        Ok(self.raw(framed))
+    }
+}
+
+/// Resolves the host and opens a `TCP` connection.
+#[cfg(feature = "tokio")]
+async fn connect_tcp(
+    domain: &str,
+    port: u16,
+) -> Result<tokio::net::TcpStream, crate::error::Error> {
+    use crate::error::Error;
+
+    tracing::debug!(target: "rusmppc::connection::dns", domain, "Resolving domain");
+
+    let resolver = hickory_resolver::TokioResolver::builder_tokio()
+        .and_then(|builder| builder.build())
+        .map_err(|err| {
+            Error::Connect(std::io::Error::other(format!(
+                "Failed to create DNS resolver: {err}"
+            )))
+        })?;
+
+    let ip_addr = resolver
+        .lookup_ip(domain)
+        .await
+        .map_err(|err| {
+            Error::Connect(std::io::Error::other(format!("Failed to lookup IP: {err}")))
+        })?
+        .iter()
+        .next()
+        .ok_or_else(|| {
+            Error::Connect(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                "No addresses found for the given host",
+            ))
+        })?;
+
+    let socket_addr = std::net::SocketAddr::new(ip_addr, port);
+
+    tracing::debug!(target: "rusmppc::connection::tcp", %socket_addr, "Connecting");
+
+    let stream = tokio::net::TcpStream::connect(socket_addr)
+        .await
+        .map_err(Error::Connect)?;
+
+    tracing::debug!(target: "rusmppc::connection::tcp", %socket_addr, "Connected");
+
+    Ok(stream)
+}
+
 impl<E: EventChannel, R: Delay + Timeout> NoSpawnConnectionBuilder<E, R> {
     /// Creates a client from an existing connection without spawning the connection in the background.
     pub fn connected<S>(
@@ -621,8 +1082,18 @@ impl<E, R> EventsConnectionBuilder<E, R> {
             auto_enquire_link_response: self.builder.auto_enquire_link_response,
             response_timeout: self.builder.response_timeout,
             check_interface_version: self.builder.check_interface_version,
+            check_session_state: self.builder.check_session_state,
+            metrics_label: self.builder.metrics_label,
+            delivery_tracking: self.builder.delivery_tracking,
+            sequence_number_allocator: self.builder.sequence_number_allocator,
+            request_span_hook: self.builder.request_span_hook,
+            congestion_control: self.builder.congestion_control,
+            #[cfg(feature = "tokio")]
+            proxy: self.builder.proxy,
             #[cfg(all(feature = "tokio", feature = "rustls"))]
             rustls_config: self.builder.rustls_config,
+            #[cfg(all(feature = "tokio", feature = "rustls"))]
+            tls_files: self.builder.tls_files,
             #[cfg(all(feature = "tokio", feature = "native-tls"))]
             native_tls_connector: self.builder.native_tls_connector,
             _e: std::marker::PhantomData,
@@ -639,8 +1110,18 @@ impl<E, R> EventsConnectionBuilder<E, R> {
             auto_enquire_link_response: self.builder.auto_enquire_link_response,
             response_timeout: self.builder.response_timeout,
             check_interface_version: self.builder.check_interface_version,
+            check_session_state: self.builder.check_session_state,
+            metrics_label: self.builder.metrics_label,
+            delivery_tracking: self.builder.delivery_tracking,
+            sequence_number_allocator: self.builder.sequence_number_allocator,
+            request_span_hook: self.builder.request_span_hook,
+            congestion_control: self.builder.congestion_control,
+            #[cfg(feature = "tokio")]
+            proxy: self.builder.proxy,
             #[cfg(all(feature = "tokio", feature = "rustls"))]
             rustls_config: self.builder.rustls_config,
+            #[cfg(all(feature = "tokio", feature = "rustls"))]
+            tls_files: self.builder.tls_files,
             #[cfg(all(feature = "tokio", feature = "native-tls"))]
             native_tls_connector: self.builder.native_tls_connector,
             _e: std::marker::PhantomData,
@@ -662,8 +1143,18 @@ impl<E, R> ConnectionBuilder<E, R> {
             auto_enquire_link_response: self.auto_enquire_link_response,
             response_timeout: self.response_timeout,
             check_interface_version: self.check_interface_version,
+            check_session_state: self.check_session_state,
+            metrics_label: self.metrics_label,
+            delivery_tracking: self.delivery_tracking,
+            sequence_number_allocator: self.sequence_number_allocator,
+            request_span_hook: self.request_span_hook,
+            congestion_control: self.congestion_control,
+            #[cfg(feature = "tokio")]
+            proxy: self.proxy,
             #[cfg(all(feature = "tokio", feature = "rustls"))]
             rustls_config: self.rustls_config,
+            #[cfg(all(feature = "tokio", feature = "rustls"))]
+            tls_files: self.tls_files,
             #[cfg(all(feature = "tokio", feature = "native-tls"))]
             native_tls_connector: self.native_tls_connector,
             _e: std::marker::PhantomData,
@@ -6,12 +6,12 @@ use std::{
     fmt::Debug,
     sync::{
         Arc,
-        atomic::{AtomicU32, Ordering},
+        atomic::{AtomicU16, Ordering},
     },
     time::Duration,
 };
 
-use futures::TryFutureExt;
+use futures::{FutureExt, TryFutureExt};
 use rusmpp::{
     Command, CommandId, CommandStatus, Pdu,
     command::CommandParts,
@@ -21,19 +21,39 @@ use rusmpp::{
         DataSmResp, DeliverSmResp, QueryBroadcastSm, QueryBroadcastSmResp, QuerySm, QuerySmResp,
         ReplaceSm, SubmitMulti, SubmitMultiResp, SubmitSm, SubmitSmResp,
     },
-    values::InterfaceVersion,
+    session::SessionState,
+    values::{CongestionState, InterfaceVersion},
 };
 use tokio::sync::{mpsc::UnboundedSender, oneshot, watch};
+use tracing::Instrument;
 
 use crate::{
     Action, CloseRequest, CommandExt, DefaultTokioConnectionBuilder, DefaultWasmConnectionBuilder,
-    PendingResponses, RegisteredRequest, RequestFutureGuard, UnregisteredRequest,
+    DrainRequest, PendingResponses, RegisteredRequest, RequestFutureGuard, UnregisteredRequest,
     error::Error,
     runtime_::{Timeout, tokio::Tokio, wasm::Wasm},
+    sequence_::SequenceNumbers,
+    session_::SessionParams,
+    span_::{SessionSpans, record_response},
+    tracking_::{DeliveryTracker, PendingReceiptGuard, Tracker},
 };
 
 const TARGET: &str = "rusmppc::client";
 
+/// Stored congestion state meaning that no congestion state is reported.
+const NO_CONGESTION_STATE: u16 = u16::MAX;
+
+fn encode_congestion_state(congestion_state: Option<CongestionState>) -> u16 {
+    match congestion_state {
+        Some(congestion_state) => u16::from(u8::from(congestion_state)),
+        None => NO_CONGESTION_STATE,
+    }
+}
+
+fn decode_congestion_state(value: u16) -> Option<CongestionState> {
+    u8::try_from(value).ok().map(CongestionState::from)
+}
+
 /// `SMPP` Client.
 ///
 /// The client is a handle to communicate with the `SMPP` server through a managed connection in the background.
@@ -80,20 +100,23 @@ impl Client<Wasm> {
     }
 }
 
+/// The parts a [`Client`] shares with its connection.
+pub(crate) struct ClientParams {
+    pub actions: UnboundedSender<Action>,
+    pub response_timeout: Option<Duration>,
+    pub check_interface_version: bool,
+    pub tracker: Option<Arc<Tracker>>,
+    pub session_state: watch::Receiver<SessionState>,
+    pub session_params: watch::Receiver<Option<SessionParams>>,
+    pub sequence_numbers: Arc<SequenceNumbers>,
+    pub spans: Arc<SessionSpans>,
+    pub watch: watch::Sender<()>,
+}
+
 impl<T: Timeout> Client<T> {
-    pub(crate) fn new(
-        actions: UnboundedSender<Action>,
-        response_timeout: Option<Duration>,
-        check_interface_version: bool,
-        watch: watch::Sender<()>,
-    ) -> Self {
+    pub(crate) fn new(params: ClientParams) -> Self {
         Self {
-            inner: Arc::new(ClientInner::new(
-                actions,
-                response_timeout,
-                check_interface_version,
-                watch,
-            )),
+            inner: Arc::new(ClientInner::new(params)),
         }
     }
 
@@ -204,6 +227,20 @@ impl<T: Timeout> Client<T> {
         self.registered_request().submit_sm(submit_sm).await
     }
 
+    /// Sends a [`SubmitSm`] command to the server, waits for a successful [`SubmitSmResp`] and starts tracking its delivery receipt.
+    ///
+    /// The returned [`DeliveryTracker`] resolves to the final [`DeliveryReceipt`](crate::tracking::DeliveryReceipt) of the submission.
+    /// Set [`registered_delivery`](SubmitSm::registered_delivery) accordingly, otherwise the server will not send a receipt.
+    ///
+    /// Requires delivery tracking to be enabled with [`delivery_tracking`](crate::ConnectionBuilder::delivery_tracking),
+    /// otherwise [`Error::DeliveryTrackingDisabled`] is returned.
+    pub async fn submit_sm_tracked(
+        &self,
+        submit_sm: impl Into<SubmitSm>,
+    ) -> Result<(SubmitSmResp, DeliveryTracker<T>), Error> {
+        self.registered_request().submit_sm_tracked(submit_sm).await
+    }
+
     /// Sends an [`Unbind`](Pdu::Unbind) command to the server and waits for a successful [`UnbindResp`](Pdu::UnbindResp).
     pub async fn unbind(&self) -> Result<(), Error> {
         self.registered_request().unbind().await
@@ -270,6 +307,62 @@ impl<T: Timeout> Client<T> {
         Ok(())
     }
 
+    /// Gracefully shuts down the connection.
+    ///
+    /// 1. New operations, except [`Unbind`](Pdu::Unbind), are rejected with [`Error::ShuttingDown`].
+    ///    Responses to the server, e.g. [`DeliverSmResp`], can still be sent.
+    /// 2. Waits up to `deadline` for the responses of outstanding requests.
+    /// 3. Sends an [`Unbind`](Pdu::Unbind) and waits for the [`UnbindResp`](Pdu::UnbindResp) if the session is bound.
+    /// 4. Closes the connection and waits for it to terminate.
+    ///
+    /// # Errors
+    ///
+    /// - [`Error::ConnectionClosed`] if the connection was already closed.
+    /// - [`Error::ShutdownDeadline`] if outstanding requests were not answered before the `deadline`.
+    /// - Any error returned by unbinding.
+    ///
+    /// The connection is closed in every case.
+    pub async fn shutdown(&self, deadline: Duration) -> Result<(), Error> {
+        tracing::debug!(target: TARGET, ?deadline, "Shutting down");
+
+        let drained = match T::timeout(deadline, self.inner.drain()).await {
+            Some(drained) => drained,
+            None => {
+                let pending = self
+                    .pending_responses()
+                    .await
+                    .map(|pending| pending.len())
+                    .unwrap_or_default();
+
+                tracing::warn!(target: TARGET, ?deadline, pending, "Shutdown deadline elapsed");
+
+                Err(Error::ShutdownDeadline { deadline, pending })
+            }
+        };
+
+        if let Err(Error::ConnectionClosed) = drained {
+            return Err(Error::ConnectionClosed);
+        }
+
+        let unbound = if self.session_state().is_bound() {
+            self.unbind().await
+        } else {
+            Ok(())
+        };
+
+        // The server may close the connection right after the unbind response.
+        match self.close().await {
+            Ok(()) | Err(Error::ConnectionClosed) => {}
+            Err(err) => return Err(err),
+        }
+
+        self.closed().await;
+
+        tracing::debug!(target: TARGET, "Shut down");
+
+        drained.and(unbound)
+    }
+
     /// Checks if the connection is active.
     ///
     /// The connection is considered active if:
@@ -289,6 +382,59 @@ impl<T: Timeout> Client<T> {
         self.inner.actions.send(Action::Ping).is_ok()
     }
 
+    /// Returns the current [`SessionState`] of the connection.
+    ///
+    /// The session state is tracked by the connection from bind responses, unbind and outbind.
+    /// Returns [`SessionState::Closed`] if the connection is closed.
+    pub fn session_state(&self) -> SessionState {
+        if self.is_closed() {
+            return SessionState::Closed;
+        }
+
+        *self.inner.session_state.borrow()
+    }
+
+    /// Returns the [`SessionParams`] negotiated by the last successful bind.
+    ///
+    /// Returns `None` if the connection is not bound or closed.
+    pub fn session_params(&self) -> Option<SessionParams> {
+        if self.is_closed() {
+            return None;
+        }
+
+        self.inner.session_params.borrow().clone()
+    }
+
+    /// Returns the last `congestion_state` reported by the server.
+    ///
+    /// Returns `None` if the server did not report its congestion state in this session.
+    /// Changes are also emitted as [`Insight::CongestionStateChanged`](crate::event::Insight::CongestionStateChanged).
+    pub fn congestion_state(&self) -> Option<CongestionState> {
+        self.session_params()?.congestion_state()
+    }
+
+    /// Reports the congestion state of the client to the server.
+    ///
+    /// The `congestion_state` TLV is added to every [`DeliverSmResp`] and [`DataSmResp`] sent by this client, unless the response already has one.
+    /// The TLV is only added while the session supports `SMPP v5.0`, see [`SessionParams::supports_smpp5`].
+    ///
+    /// Pass `None` to stop reporting.
+    pub fn set_congestion_state(&self, congestion_state: Option<CongestionState>) {
+        tracing::debug!(target: TARGET, ?congestion_state, "Reporting congestion state");
+
+        self.inner
+            .congestion_state
+            .store(encode_congestion_state(congestion_state), Ordering::Relaxed);
+    }
+
+    /// Returns the number of requests waiting for a response on this connection.
+    ///
+    /// Unlike [`pending_responses`](Self::pending_responses), this does not round trip through the connection.
+    /// Enquire links sent by the connection are included.
+    pub fn in_flight(&self) -> usize {
+        self.inner.sequence_numbers.in_flight_len()
+    }
+
     /// Returns a vector of pending responses.
     pub async fn pending_responses(&self) -> Result<Vec<u32>, Error> {
         let (pending_responses, ack) = PendingResponses::new();
@@ -347,31 +493,80 @@ impl<T: Timeout> Client<T> {
 struct ClientInner<T = Tokio> {
     actions: UnboundedSender<Action>,
     response_timeout: Option<Duration>,
-    sequence_number: AtomicU32,
+    sequence_numbers: Arc<SequenceNumbers>,
+    spans: Arc<SessionSpans>,
     check_interface_version: bool,
+    tracker: Option<Arc<Tracker>>,
+    session_state: watch::Receiver<SessionState>,
+    session_params: watch::Receiver<Option<SessionParams>>,
+    // Reported to the server in delivery responses. See [`encode_congestion_state`].
+    congestion_state: AtomicU16,
     watch: watch::Sender<()>,
     _t: std::marker::PhantomData<T>,
 }
 
 impl<T: Timeout> ClientInner<T> {
-    const fn new(
-        actions: UnboundedSender<Action>,
-        response_timeout: Option<Duration>,
-        check_interface_version: bool,
-        watch: watch::Sender<()>,
-    ) -> Self {
+    fn new(params: ClientParams) -> Self {
+        let ClientParams {
+            actions,
+            response_timeout,
+            check_interface_version,
+            tracker,
+            session_state,
+            session_params,
+            sequence_numbers,
+            spans,
+            watch,
+        } = params;
+
         Self {
             actions,
             response_timeout,
-            sequence_number: AtomicU32::new(1),
+            sequence_numbers,
+            spans,
             check_interface_version,
+            tracker,
+            session_state,
+            session_params,
+            congestion_state: AtomicU16::new(NO_CONGESTION_STATE),
             watch,
             _t: std::marker::PhantomData,
         }
     }
 
-    fn next_sequence_number(&self) -> u32 {
-        self.sequence_number.fetch_add(2, Ordering::Relaxed)
+    fn next_sequence_number(&self) -> Result<u32, Error> {
+        self.sequence_numbers.next()
+    }
+
+    /// Adapts a [`Command`] to the negotiated session parameters. See [`SessionParams`].
+    fn adapt(&self, command: Command) -> Result<Command, Error> {
+        let congestion_state =
+            decode_congestion_state(self.congestion_state.load(Ordering::Relaxed));
+
+        match &*self.session_params.borrow() {
+            Some(params) => {
+                let command = params.adapt(command)?;
+
+                match congestion_state {
+                    Some(congestion_state) => {
+                        Ok(params.report_congestion_state(command, congestion_state))
+                    }
+                    None => Ok(command),
+                }
+            }
+            None => Ok(command),
+        }
+    }
+
+    /// Completes when no registered request is waiting for a response.
+    async fn drain(&self) -> Result<(), Error> {
+        let (request, ack) = DrainRequest::new();
+
+        self.actions
+            .send(Action::Drain(request))
+            .map_err(|_| Error::ConnectionClosed)?;
+
+        ack.await.map_err(|_| Error::ConnectionClosed)
     }
 
     async fn close(&self) -> Result<(), Error> {
@@ -442,16 +637,28 @@ impl<T: Timeout> ClientInner<T> {
         command: Command,
         response_timeout: Option<Duration>,
     ) -> Result<Command, Error> {
+        let command = self.adapt(command)?;
+
         let sequence_number = command.sequence_number();
         let status = command.status();
         let id = command.id();
 
-        let response = self.send_registered(command).await?;
+        let span = self.spans.request(&command);
 
-        tracing::trace!(target: TARGET, sequence_number, ?status, ?id, timeout = ?response_timeout, "Starting response timer");
+        let response = async {
+            let response = self.send_registered(command).await?;
 
-        self.await_response(response, sequence_number, response_timeout)
-            .await
+            tracing::trace!(target: TARGET, sequence_number, ?status, ?id, timeout = ?response_timeout, "Starting response timer");
+
+            self.await_response(response, sequence_number, response_timeout)
+                .await
+        }
+        .instrument(span.clone())
+        .await;
+
+        record_response(&span, &response);
+
+        response
     }
 }
 
@@ -508,6 +715,8 @@ impl<'a, T: Timeout> UnregisteredRequestBuilder<'a, T> {
             .sequence_number(sequence_number)
             .pdu(pdu.into());
 
+        let command = self.client.inner.adapt(command)?;
+
         let sequence_number = command.sequence_number();
         let status = command.status();
         let id = command.id();
@@ -719,9 +928,18 @@ impl<'a, T: Timeout> RegisteredRequestBuilder<'a, T> {
         Ok(())
     }
 
-    fn request(&self, pdu: impl Into<Pdu>) -> impl Future<Output = Result<Command, Error>> {
-        let sequence_number = self.client.inner.next_sequence_number();
+    async fn request(&self, pdu: impl Into<Pdu>) -> Result<Command, Error> {
+        let sequence_number = self.client.inner.next_sequence_number()?;
+
+        self.request_with_sequence_number(sequence_number, pdu)
+            .await
+    }
 
+    fn request_with_sequence_number(
+        &self,
+        sequence_number: u32,
+        pdu: impl Into<Pdu>,
+    ) -> impl Future<Output = Result<Command, Error>> {
         let command = Command::builder()
             .status(self.status)
             .sequence_number(sequence_number)
@@ -740,7 +958,21 @@ impl<'a, T: Timeout> RegisteredRequestBuilder<'a, T> {
         pdu: impl Into<Pdu>,
         extract: fn(Pdu) -> Result<R, Pdu>,
     ) -> Result<R, Error> {
-        self.request(pdu.into())
+        self.request_extract_with_sequence_number(
+            self.client.inner.next_sequence_number()?,
+            pdu,
+            extract,
+        )
+        .await
+    }
+
+    async fn request_extract_with_sequence_number<R>(
+        &self,
+        sequence_number: u32,
+        pdu: impl Into<Pdu>,
+        extract: fn(Pdu) -> Result<R, Pdu>,
+    ) -> Result<R, Error> {
+        self.request_with_sequence_number(sequence_number, pdu.into())
             .await?
             .ok()
             .map_err(Error::unexpected_response)
@@ -875,6 +1107,39 @@ impl<'a, T: Timeout> RegisteredRequestBuilder<'a, T> {
             .await
     }
 
+    /// Sends a [`SubmitSm`] command to the server, waits for a successful [`SubmitSmResp`] and starts tracking its delivery receipt.
+    ///
+    /// See [`Client::submit_sm_tracked`] for more details.
+    pub async fn submit_sm_tracked(
+        &self,
+        submit_sm: impl Into<SubmitSm>,
+    ) -> Result<(SubmitSmResp, DeliveryTracker<T>), Error> {
+        let tracker = self
+            .client
+            .inner
+            .tracker
+            .clone()
+            .ok_or(Error::DeliveryTrackingDisabled)?;
+
+        let sequence_number = self.client.inner.next_sequence_number()?;
+
+        // Registered before sending, so that the connection can move it into the store as soon as the response arrives.
+        let receipt = tracker.register(sequence_number);
+        let _guard = PendingReceiptGuard::new(tracker.clone(), sequence_number);
+
+        let response = self
+            .request_extract_with_sequence_number(
+                sequence_number,
+                submit_sm.into(),
+                extract!(SubmitSmResp),
+            )
+            .await?;
+
+        let message_id = response.message_id().as_str().to_owned();
+
+        Ok((response, DeliveryTracker::new(message_id, tracker, receipt)))
+    }
+
     /// Sends an [`Unbind`](Pdu::Unbind) command to the server and waits for a successful [`UnbindResp`](Pdu::UnbindResp).
     pub async fn unbind(&self) -> Result<(), Error> {
         self.request_ok_and_matches(Pdu::Unbind, CommandId::UnbindResp)
@@ -912,7 +1177,7 @@ impl<'a, T: Timeout> NoWaitRequestBuilder<'a, T> {
 
     /// Sends a [`Pdu`] to the server without waiting for the response.
     async fn send(&self, pdu: impl Into<Pdu>) -> Result<u32, Error> {
-        let sequence_number = self.client.inner.next_sequence_number();
+        let sequence_number = self.client.inner.next_sequence_number()?;
 
         self.unregistered_request()
             .unregistered_request(pdu.into(), sequence_number)
@@ -1026,28 +1291,34 @@ impl<'a, T: Timeout> RawRegisteredRequestBuilder<'a, T> {
     ///
     /// - If the sent command is not an operation expecting a response, and the response timeout is unset, the response future will never resolve and should be dropped.
     /// - The response timeout is started when the response future is awaited.
-    /// - No interface version check is performed.
+    /// - No interface version check is performed and the request is not adapted to the negotiated [`SessionParams`].
     pub fn send(
         self,
         pdu: impl Into<Pdu>,
     ) -> impl Future<Output = Result<(u32, impl Future<Output = Result<Command, Error>>), Error>>
     {
         let sequence_number = self.client.inner.next_sequence_number();
+        let pdu = pdu.into();
 
-        let command = Command::builder()
-            .status(self.status)
-            .sequence_number(sequence_number)
-            .pdu(pdu.into());
+        async move {
+            let sequence_number = sequence_number?;
 
-        let id = command.id();
+            let command = Command::builder()
+                .status(self.status)
+                .sequence_number(sequence_number)
+                .pdu(pdu);
 
-        let future = self
-            .client
-            .inner
-            .send_registered(command)
-            .and_then(move |response| futures::future::ok((sequence_number, response)));
+            let id = command.id();
+
+            let span = self.client.inner.spans.request(&command);
+
+            let future = self
+                .client
+                .inner
+                .send_registered(command)
+                .and_then(move |response| futures::future::ok((sequence_number, response)))
+                .instrument(span.clone());
 
-        async move {
             let (sequence_number, response) =
                 RequestFutureGuard::new(&self.client.inner.actions, sequence_number, future)
                     .await?;
@@ -1056,6 +1327,8 @@ impl<'a, T: Timeout> RawRegisteredRequestBuilder<'a, T> {
                 .client
                 .inner
                 .await_response(response, sequence_number, self.response_timeout)
+                .instrument(span.clone())
+                .inspect(move |response| record_response(&span, response))
                 .and_then(move |command| async move {
                     // XXX: it is ok to match against responses only, as this is a registered request
                     // If the request does not have a matching response, the user should not be awaiting it here anyway
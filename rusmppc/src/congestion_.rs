@@ -0,0 +1,80 @@
+use rusmpp::{CommandId, values::CongestionState};
+
+#[cfg(test)]
+mod tests;
+
+/// Window of outstanding submissions adapted to the `congestion_state` reported by the `MC`.
+///
+/// Set with [`ConnectionBuilder::congestion_control`](crate::ConnectionBuilder::congestion_control).
+///
+/// [`SubmitSm`](rusmpp::pdus::SubmitSm), [`SubmitMulti`](rusmpp::pdus::SubmitMulti), [`DataSm`](rusmpp::pdus::DataSm) and [`BroadcastSm`](rusmpp::pdus::BroadcastSm)
+/// requests are held back by the connection while the number of requests waiting for a response reaches the window.
+/// Held back requests are sent in order as responses arrive. The response timeout starts once a request is sent.
+///
+/// The window is [`max_window`](Self::new) while the congestion state is unknown or at most [`threshold`](Self::threshold),
+/// and shrinks linearly to [`min_window`](Self::min_window) as the congestion state approaches [`Congested`](CongestionState::Congested).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CongestionControl {
+    max_window: usize,
+    min_window: usize,
+    threshold: u8,
+}
+
+impl CongestionControl {
+    /// Creates a new [`CongestionControl`] with the given maximum window.
+    ///
+    /// # Defaults
+    /// - `min_window`: 1
+    /// - `threshold`: 80, the start of [`OptimumLoad`](CongestionState::OptimumLoad)
+    pub const fn new(max_window: usize) -> Self {
+        Self {
+            max_window,
+            min_window: 1,
+            threshold: 80,
+        }
+    }
+
+    /// Sets the window used while the `MC` is [`Congested`](CongestionState::Congested).
+    ///
+    /// Values of `0` are treated as `1`, values above the maximum window as the maximum window.
+    pub const fn min_window(mut self, min_window: usize) -> Self {
+        self.min_window = min_window;
+        self
+    }
+
+    /// Sets the congestion state up to which the maximum window is used.
+    pub const fn threshold(mut self, threshold: u8) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Returns the window for the given congestion state.
+    pub fn window(&self, congestion_state: Option<CongestionState>) -> usize {
+        let max = self.max_window.max(1);
+        let min = self.min_window.clamp(1, max);
+
+        let Some(congestion_state) = congestion_state else {
+            return max;
+        };
+
+        let threshold = usize::from(self.threshold.min(100));
+        let congestion = usize::from(u8::from(congestion_state).min(100));
+
+        if congestion <= threshold {
+            return max;
+        }
+
+        max - (max - min) * (congestion - threshold) / (100 - threshold)
+    }
+
+    /// Whether requests with the given command id are limited by the window.
+    pub(crate) fn is_windowed(id: CommandId) -> bool {
+        matches!(
+            id,
+            CommandId::SubmitSm
+                | CommandId::SubmitMulti
+                | CommandId::DataSm
+                | CommandId::BroadcastSm
+        )
+    }
+}
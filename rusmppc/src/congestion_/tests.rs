@@ -0,0 +1,307 @@
+use std::{str::FromStr, time::Duration};
+
+use futures::{SinkExt, StreamExt};
+use rusmpp::{
+    Command, CommandStatus, Pdu,
+    pdus::{BindTransceiver, BindTransceiverResp, DeliverSmResp, SubmitSm, SubmitSmResp},
+    tlvs::TlvTag,
+    tokio_codec::CommandCodec,
+    types::COctetString,
+    values::InterfaceVersion,
+};
+use tokio::{
+    io::{AsyncWriteExt, DuplexStream},
+    sync::mpsc,
+};
+use tokio_util::codec::Framed;
+
+use super::*;
+use crate::{
+    ConnectionBuilder,
+    event::{Insight, InsightEvent},
+    tests::{encode_with_congestion_state, init_tracing},
+};
+
+/// `SMPP v5.0` server reporting every received command.
+///
+/// [`SubmitSm`]s are held until a congestion state is sent through `respond`,
+/// then all held submissions are answered with that `congestion_state` TLV.
+async fn run_server(
+    stream: DuplexStream,
+    received: mpsc::UnboundedSender<Command>,
+    mut respond: mpsc::UnboundedReceiver<u8>,
+) {
+    let mut framed = Framed::new(stream, CommandCodec::new());
+    let mut held = Vec::new();
+
+    loop {
+        tokio::select! {
+            command = framed.next() => {
+                let Some(Ok(command)) = command else {
+                    break;
+                };
+
+                match command.id() {
+                    CommandId::BindTransceiver => {
+                        let response = Command::builder()
+                            .status(CommandStatus::EsmeRok)
+                            .sequence_number(command.sequence_number())
+                            .pdu(BindTransceiverResp::new(
+                                COctetString::from_str("SMSC").unwrap(),
+                                Some(InterfaceVersion::Smpp5_0),
+                            ));
+
+                        framed.send(response).await.unwrap();
+                    }
+                    CommandId::SubmitSm => held.push(command.sequence_number()),
+                    _ => {}
+                }
+
+                let _ = received.send(command);
+            }
+            Some(congestion_state) = respond.recv() => {
+                for sequence_number in held.drain(..) {
+                    let response = Command::builder()
+                        .status(CommandStatus::EsmeRok)
+                        .sequence_number(sequence_number)
+                        .pdu(SubmitSmResp::default());
+
+                    let buf = encode_with_congestion_state(&response, congestion_state);
+
+                    framed.get_mut().write_all(&buf).await.unwrap();
+                }
+            }
+        }
+    }
+}
+
+async fn recv_submits(received: &mut mpsc::UnboundedReceiver<Command>, count: usize) {
+    for _ in 0..count {
+        let command = received.recv().await.unwrap();
+
+        assert_eq!(command.id(), CommandId::SubmitSm);
+    }
+
+    // Nothing else is sent while the window is full.
+    assert!(
+        tokio::time::timeout(Duration::from_millis(100), received.recv())
+            .await
+            .is_err()
+    );
+}
+
+#[test]
+fn window_should_shrink_above_threshold() {
+    let control = CongestionControl::new(10).min_window(2).threshold(80);
+
+    assert_eq!(control.window(None), 10);
+    assert_eq!(control.window(Some(CongestionState::Idle)), 10);
+    assert_eq!(control.window(Some(CongestionState::OptimumLoad(80))), 10);
+    assert_eq!(
+        control.window(Some(CongestionState::NearingCongestion(90))),
+        6
+    );
+    assert_eq!(control.window(Some(CongestionState::Congested)), 2);
+    assert_eq!(control.window(Some(CongestionState::Other(200))), 2);
+
+    assert_eq!(CongestionControl::new(0).window(None), 1);
+    assert_eq!(
+        CongestionControl::new(4)
+            .min_window(8)
+            .window(Some(CongestionState::Congested)),
+        4
+    );
+}
+
+#[tokio::test]
+async fn submissions_should_be_held_back_by_the_congestion_window() {
+    init_tracing();
+
+    let (server, client) = tokio::io::duplex(4096);
+    let (received_tx, mut received) = mpsc::unbounded_channel();
+    let (respond, respond_rx) = mpsc::unbounded_channel();
+
+    tokio::spawn(run_server(server, received_tx, respond_rx));
+
+    let (client, mut events) = ConnectionBuilder::new()
+        .no_enquire_link_interval()
+        .congestion_control(CongestionControl::new(4))
+        .events()
+        .insights()
+        .connected(client);
+
+    client
+        .bind_transceiver(BindTransceiver::default())
+        .await
+        .expect("Failed to bind");
+
+    assert_eq!(
+        received.recv().await.unwrap().id(),
+        CommandId::BindTransceiver
+    );
+
+    let submits = (0..6)
+        .map(|_| {
+            let client = client.clone();
+
+            tokio::spawn(async move { client.submit_sm(SubmitSm::default()).await })
+        })
+        .collect::<Vec<_>>();
+
+    // Unknown congestion state, full window.
+    recv_submits(&mut received, 4).await;
+
+    respond.send(100).unwrap();
+
+    // Congested, minimum window.
+    recv_submits(&mut received, 1).await;
+
+    assert_eq!(client.congestion_state(), Some(CongestionState::Congested));
+
+    respond.send(0).unwrap();
+
+    recv_submits(&mut received, 1).await;
+
+    respond.send(0).unwrap();
+
+    for submit in submits {
+        submit.await.unwrap().expect("Failed to submit");
+    }
+
+    let mut changes = Vec::new();
+
+    while changes.len() < 2 {
+        if let Some(InsightEvent::Insight(Insight::CongestionStateChanged(state))) =
+            events.next().await
+        {
+            changes.push(state);
+        }
+    }
+
+    assert_eq!(
+        changes,
+        vec![CongestionState::Congested, CongestionState::Idle]
+    );
+}
+
+#[tokio::test]
+async fn cancelled_submissions_should_be_dropped_from_the_congestion_window() {
+    init_tracing();
+
+    let (server, client) = tokio::io::duplex(4096);
+    let (received_tx, mut received) = mpsc::unbounded_channel();
+    let (respond, respond_rx) = mpsc::unbounded_channel();
+
+    tokio::spawn(run_server(server, received_tx, respond_rx));
+
+    let (client, _events) = ConnectionBuilder::new()
+        .no_enquire_link_interval()
+        .congestion_control(CongestionControl::new(1))
+        .events()
+        .insights()
+        .connected(client);
+
+    client
+        .bind_transceiver(BindTransceiver::default())
+        .await
+        .expect("Failed to bind");
+
+    assert_eq!(
+        received.recv().await.unwrap().id(),
+        CommandId::BindTransceiver
+    );
+
+    let submit = || {
+        let client = client.clone();
+
+        tokio::spawn(async move { client.submit_sm(SubmitSm::default()).await })
+    };
+
+    let sent = submit();
+
+    recv_submits(&mut received, 1).await;
+
+    // Held back by the full window, then cancelled.
+    let cancelled = submit();
+
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    cancelled.abort();
+
+    let held = submit();
+
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    respond.send(0).unwrap();
+
+    sent.await.unwrap().expect("Failed to submit");
+
+    // Only the submission still awaited is released.
+    recv_submits(&mut received, 1).await;
+
+    respond.send(0).unwrap();
+
+    held.await.unwrap().expect("Failed to submit");
+}
+
+#[tokio::test]
+async fn client_should_report_its_congestion_state_in_delivery_responses() {
+    init_tracing();
+
+    let (server, client) = tokio::io::duplex(4096);
+    let (received_tx, mut received) = mpsc::unbounded_channel();
+    let (_respond, respond_rx) = mpsc::unbounded_channel();
+
+    tokio::spawn(run_server(server, received_tx, respond_rx));
+
+    let (client, _events) = ConnectionBuilder::new()
+        .no_enquire_link_interval()
+        .connected(client);
+
+    client
+        .bind_transceiver(BindTransceiver::default())
+        .await
+        .expect("Failed to bind");
+
+    assert_eq!(
+        received.recv().await.unwrap().id(),
+        CommandId::BindTransceiver
+    );
+
+    let congestion_tags = |command: Command| {
+        let Some(Pdu::DeliverSmResp(deliver_sm_resp)) = command.into_parts().raw().3 else {
+            panic!("Expected a DeliverSmResp");
+        };
+
+        deliver_sm_resp
+            .tlvs()
+            .iter()
+            .filter(|tlv| tlv.tag() == TlvTag::CongestionState)
+            .count()
+    };
+
+    client
+        .deliver_sm_resp(1, DeliverSmResp::default())
+        .await
+        .expect("Failed to respond");
+
+    assert_eq!(congestion_tags(received.recv().await.unwrap()), 0);
+
+    client.set_congestion_state(Some(CongestionState::HighLoad(60)));
+
+    client
+        .deliver_sm_resp(2, DeliverSmResp::default())
+        .await
+        .expect("Failed to respond");
+
+    assert_eq!(congestion_tags(received.recv().await.unwrap()), 1);
+
+    client.set_congestion_state(None);
+
+    client
+        .deliver_sm_resp(3, DeliverSmResp::default())
+        .await
+        .expect("Failed to respond");
+
+    assert_eq!(congestion_tags(received.recv().await.unwrap()), 0);
+}
@@ -1,6 +1,7 @@
 use std::{
     collections::{BTreeMap, VecDeque},
     pin::Pin,
+    sync::Arc,
     task::{Context, Poll},
     time::Duration,
 };
@@ -8,22 +9,32 @@ use std::{
 use crate::{
     Action, Client, Request, Timer,
     builder_::NoSpawnConnectionBuilder,
+    client::ClientParams,
+    congestion_::CongestionControl,
     error::Error,
     event_::{EventChannel, Insight},
+    metrics_::ConnectionMetrics,
     request::ObligatedRequest,
     runtime_::{Delay, Timeout},
+    sequence_::SequenceNumbers,
+    session_::{SessionParams, bind_interface_version, congestion_state},
+    span_::SessionSpans,
+    tracking_::Tracker,
 };
 use futures::{FutureExt, Sink, SinkExt, Stream};
 use pin_project_lite::pin_project;
 use rusmpp::{
     Command, CommandId, CommandStatus, Pdu,
+    session::SessionState,
     tokio_codec::{DecodeError, EncodeError},
+    values::InterfaceVersion,
 };
 use tokio::sync::{
     mpsc::{self, UnboundedSender},
     oneshot, watch,
 };
 use tokio_stream::wrappers::UnboundedReceiverStream;
+use tracing::Instrument;
 
 const CONN: &str = "rusmppc::connection::smpp";
 const TIMER: &str = "rusmppc::connection::smpp::timer";
@@ -48,8 +59,12 @@ enum State {
 pin_project! {
     pub struct Connection<F, E, D: Delay> {
         state: State,
-        sequence_number: u32,
+        // Shared with the client. Registered requests and enquire links are marked as in flight until their response arrives.
+        sequence_numbers: Arc<SequenceNumbers>,
         requests: VecDeque<Request>,
+        // Submissions held back by the congestion window. Moved to `requests` as responses arrive.
+        windowed: VecDeque<Request>,
+        congestion_control: Option<CongestionControl>,
         // This is a request that has been written to the sink using start_send, but not yet flushed.
         pending_request: Option<Request>,
         responses: BTreeMap<u32, oneshot::Sender<Command>>,
@@ -57,6 +72,19 @@ pin_project! {
         last_enquire_link_sequence_number: Option<u32>,
         enquire_link_response_timeout: Duration,
         auto_enquire_link_response: bool,
+        // Moves tracked submissions into the tracking store and resolves them on receipts.
+        tracker: Option<Arc<Tracker>>,
+        // Tracked from bind responses, unbind and outbind. Shared with the client.
+        session_state: watch::Sender<SessionState>,
+        check_session_state: bool,
+        // Negotiated by the last successful bind. Shared with the client.
+        session_params: watch::Sender<Option<SessionParams>>,
+        // Interface version of the last sent bind request.
+        bind_interface_version: Option<InterfaceVersion>,
+        // Set by a drain request. New operations except unbind are rejected.
+        draining: bool,
+        drained: Vec<oneshot::Sender<()>>,
+        metrics: ConnectionMetrics,
         events: E,
         // Used to let the client wait for the connection to be closed
         _watch: watch::Receiver<()>,
@@ -71,17 +99,42 @@ pin_project! {
     }
 }
 
+/// The configuration and shared state of a new [`Connection`].
+pub struct ConnectionParams {
+    pub enquire_link_interval: Option<Duration>,
+    pub enquire_link_response_timeout: Duration,
+    pub auto_enquire_link_response: bool,
+    pub tracker: Option<Arc<Tracker>>,
+    pub session_state: watch::Sender<SessionState>,
+    pub check_session_state: bool,
+    pub session_params: watch::Sender<Option<SessionParams>>,
+    pub congestion_control: Option<CongestionControl>,
+    pub sequence_numbers: Arc<SequenceNumbers>,
+    pub metrics: ConnectionMetrics,
+}
+
 impl<E: EventChannel, D: Delay> Connection<(), E, D> {
     pub fn new(
-        enquire_link_interval: Option<Duration>,
-        enquire_link_response_timeout: Duration,
-        auto_enquire_link_response: bool,
+        params: ConnectionParams,
     ) -> (
         Self,
         watch::Sender<()>,
         UnboundedSender<Action>,
         UnboundedReceiverStream<E::Event>,
     ) {
+        let ConnectionParams {
+            enquire_link_interval,
+            enquire_link_response_timeout,
+            auto_enquire_link_response,
+            tracker,
+            session_state,
+            check_session_state,
+            session_params,
+            congestion_control,
+            sequence_numbers,
+            metrics,
+        } = params;
+
         let (events_tx, events_rx) = mpsc::unbounded_channel::<E::Event>();
         let events = E::new(events_tx);
 
@@ -91,14 +144,24 @@ impl<E: EventChannel, D: Delay> Connection<(), E, D> {
         (
             Self {
                 state: State::Active,
-                sequence_number: 2,
+                sequence_numbers,
                 requests: VecDeque::new(),
+                windowed: VecDeque::new(),
+                congestion_control,
                 pending_request: None,
                 responses: BTreeMap::new(),
                 enquire_link_interval,
                 last_enquire_link_sequence_number: None,
                 enquire_link_response_timeout,
                 auto_enquire_link_response,
+                tracker,
+                session_state,
+                check_session_state,
+                session_params,
+                bind_interface_version: None,
+                draining: false,
+                drained: Vec::new(),
+                metrics,
                 enquire_link_timer: enquire_link_interval
                     .map(|duration| Timer::active(duration))
                     .unwrap_or(Timer::inactive()),
@@ -117,14 +180,24 @@ impl<E: EventChannel, D: Delay> Connection<(), E, D> {
     pub fn with_framed<F>(self, framed: F) -> Connection<F, E, D> {
         Connection {
             state: self.state,
-            sequence_number: self.sequence_number,
+            sequence_numbers: self.sequence_numbers,
             requests: self.requests,
+            windowed: self.windowed,
+            congestion_control: self.congestion_control,
             pending_request: self.pending_request,
             responses: self.responses,
             enquire_link_interval: self.enquire_link_interval,
             last_enquire_link_sequence_number: self.last_enquire_link_sequence_number,
             enquire_link_response_timeout: self.enquire_link_response_timeout,
             auto_enquire_link_response: self.auto_enquire_link_response,
+            tracker: self.tracker,
+            session_state: self.session_state,
+            check_session_state: self.check_session_state,
+            session_params: self.session_params,
+            bind_interface_version: self.bind_interface_version,
+            draining: self.draining,
+            drained: self.drained,
+            metrics: self.metrics,
             events: self.events,
             _watch: self._watch,
             enquire_link_timer: self.enquire_link_timer,
@@ -145,14 +218,34 @@ where
         sequence_number: u32,
         response: oneshot::Sender<Command>,
     ) {
-        self.project().responses.insert(sequence_number, response);
+        let this = self.project();
+
+        this.sequence_numbers.insert_in_flight(sequence_number);
+        this.metrics.registered(sequence_number);
+        this.responses.insert(sequence_number, response);
     }
 
     fn remove_response(
         self: Pin<&mut Self>,
         sequence_number: u32,
     ) -> Option<oneshot::Sender<Command>> {
-        self.project().responses.remove(&sequence_number)
+        let this = self.project();
+
+        this.sequence_numbers.remove_in_flight(sequence_number);
+        this.metrics.unregistered(sequence_number);
+        this.responses.remove(&sequence_number)
+    }
+
+    /// Drops a held back submission whose caller stopped waiting, so that it is never sent.
+    fn remove_windowed(self: Pin<&mut Self>, sequence_number: u32) {
+        let windowed = self.project().windowed;
+        let len = windowed.len();
+
+        windowed.retain(|request| request.command().sequence_number() != sequence_number);
+
+        if windowed.len() < len {
+            tracing::debug!(target: CONN, sequence_number, "Removed from congestion window");
+        }
     }
 
     fn requests_push_back(self: Pin<&mut Self>, request: Request) {
@@ -194,11 +287,88 @@ where
     }
 
     fn set_last_enquire_link_sequence_number(self: Pin<&mut Self>, sequence_number: u32) {
-        *self.project().last_enquire_link_sequence_number = Some(sequence_number);
+        let this = self.project();
+
+        this.sequence_numbers.insert_in_flight(sequence_number);
+        *this.last_enquire_link_sequence_number = Some(sequence_number);
     }
 
     fn unset_last_enquire_link_sequence_number(self: Pin<&mut Self>) {
-        *self.project().last_enquire_link_sequence_number = None;
+        let this = self.project();
+
+        if let Some(sequence_number) = this.last_enquire_link_sequence_number.take() {
+            this.sequence_numbers.remove_in_flight(sequence_number);
+        }
+    }
+
+    /// Acknowledges the drain requests once no registered request is queued or waiting for a response.
+    fn notify_drained(self: Pin<&mut Self>) {
+        if self.drained.is_empty() || !self.responses.is_empty() {
+            return;
+        }
+
+        let is_registered = |request: &Request| matches!(request, Request::Registered(_));
+
+        if !self.windowed.is_empty()
+            || self.requests.iter().any(is_registered)
+            || self.pending_request.as_ref().is_some_and(is_registered)
+        {
+            return;
+        }
+
+        tracing::debug!(target: CONN, "Drained");
+
+        for drained in self.project().drained.drain(..) {
+            let _ = drained.send(());
+        }
+    }
+
+    /// Moves held back submissions to the request queue while the congestion window allows.
+    ///
+    /// Returns `true` if a request was moved.
+    fn release_windowed(self: Pin<&mut Self>) -> bool {
+        let Some(congestion_control) = self.congestion_control else {
+            return false;
+        };
+
+        if self.windowed.is_empty() {
+            return false;
+        }
+
+        // Requests are not held back while closing, since no more responses are read.
+        let window = match self.state {
+            State::Closing => usize::MAX,
+            _ => congestion_control.window(
+                self.session_params
+                    .borrow()
+                    .as_ref()
+                    .and_then(SessionParams::congestion_state),
+            ),
+        };
+
+        let is_registered = |request: &Request| matches!(request, Request::Registered(_));
+
+        let mut outstanding = self.responses.len()
+            + self.requests.iter().filter(|r| is_registered(r)).count()
+            + usize::from(self.pending_request.as_ref().is_some_and(is_registered));
+
+        let this = self.project();
+        let mut released = false;
+
+        while outstanding < window {
+            let Some(request) = this.windowed.pop_front() else {
+                break;
+            };
+
+            tracing::trace!(target: CONN, sequence_number = request.command().sequence_number(), window, outstanding, "Released from congestion window");
+
+            this.requests.push_back(request);
+
+            outstanding += 1;
+            released = true;
+        }
+
+        released
     }
 
     fn deactivate_enquire_link_response_timer(self: Pin<&mut Self>) {
@@ -215,18 +385,59 @@ where
         tracing::trace!(target: TIMER, ?delay, "Activated enquire_link_response_timer");
     }
 
-    /// [`Self::sequence_number`] is incremented by 2 after each call.
-    ///
-    /// The clients also hold an atomic sequence number, which is incremented by 2 for each request, starting from 1.
-    ///
-    /// This is done to ensure that commands sent by the connection [`EnquireLink`](Pdu::EnquireLink) are differentiated from the commands sent by the client,
-    /// without the use of atomic operations in the connection.
-    fn sequence_number_fetch_and_increment(self: Pin<&mut Self>) -> u32 {
-        let sequence_number = self.sequence_number;
+    fn session_state(&self) -> SessionState {
+        *self.session_state.borrow()
+    }
+
+    fn set_session_state(self: Pin<&mut Self>, state: SessionState) {
+        let changed = self.session_state.send_if_modified(|current| {
+            if *current == state {
+                return false;
+            }
+
+            *current = state;
+
+            true
+        });
+
+        if changed {
+            tracing::debug!(target: CONN, ?state, "Session state changed");
+
+            if !state.is_bound() {
+                self.session_params
+                    .send_if_modified(|params| params.take().is_some());
+            }
+
+            let _ = self
+                .events
+                .send_insight(Insight::SessionStateChanged(state));
+        }
+    }
 
-        *(self.project().sequence_number) += 2;
+    fn set_session_params(&self, params: SessionParams) {
+        tracing::debug!(target: CONN, ?params, "Session negotiated");
 
-        sequence_number
+        self.session_params.send_replace(Some(params));
+    }
+
+    fn record_congestion_state(&self, command: &Command) {
+        let Some(congestion_state) = congestion_state(command) else {
+            return;
+        };
+
+        let changed = self.session_params.send_if_modified(|params| {
+            params
+                .as_mut()
+                .is_some_and(|params| params.set_congestion_state(congestion_state))
+        });
+
+        if changed {
+            tracing::debug!(target: CONN, ?congestion_state, "Congestion state changed");
+
+            let _ = self
+                .events
+                .send_insight(Insight::CongestionStateChanged(congestion_state));
+        }
     }
 }
 
@@ -247,6 +458,8 @@ where
         'main: loop {
             tracing::trace!(target: CONN, "Entering main poll loop");
 
+            self.as_mut().notify_drained();
+
             if matches!(self.state, State::Active) {
                 match self.as_mut().project().enquire_link_response_timer.poll(cx) {
                     Poll::Ready(()) => {
@@ -267,8 +480,21 @@ where
                 }
 
                 match self.as_mut().project().enquire_link_timer.poll(cx) {
-                    Poll::Ready(()) => {
-                        let sequence_number = self.as_mut().sequence_number_fetch_and_increment();
+                    Poll::Ready(()) => 'enquire_link: {
+                        let sequence_number = match self.sequence_numbers.next() {
+                            Ok(sequence_number) => sequence_number,
+                            Err(err) => {
+                                tracing::error!(target: TIMER, ?err, "Failed to allocate an EnquireLink sequence number");
+
+                                // Try again on the next interval.
+                                self.as_mut().activate_enquire_link_timer();
+
+                                // Poll the enquire_link_timer again to register the waker
+                                let _ = self.as_mut().project().enquire_link_timer.poll(cx);
+
+                                break 'enquire_link;
+                            }
+                        };
 
                         tracing::trace!(target: TIMER, sequence_number, "EnquireLink");
 
@@ -330,12 +556,63 @@ where
                                     "Received request"
                                 );
 
+                                let state = self.session_state();
+                                let id = request.command().id();
+
+                                if self.check_session_state && !state.can_send_as_esme(id) {
+                                    tracing::warn!(target: CONN,
+                                        sequence_number=request.command().sequence_number(),
+                                        ?state,
+                                        ?id,
+                                        "Command not allowed in session state"
+                                    );
+
+                                    // Client not waiting, nothing was sent.
+                                    let _ = request
+                                        .send_ack(Err(Error::InvalidSessionState { state, id }));
+
+                                    continue 'actions;
+                                }
+
+                                if self.draining && id.is_operation() && id != CommandId::Unbind {
+                                    tracing::warn!(target: CONN,
+                                        sequence_number=request.command().sequence_number(),
+                                        ?id,
+                                        "Command rejected while shutting down"
+                                    );
+
+                                    let _ = request.send_ack(Err(Error::ShuttingDown));
+
+                                    continue 'actions;
+                                }
+
+                                if self.congestion_control.is_some()
+                                    && matches!(request, Request::Registered(_))
+                                    && CongestionControl::is_windowed(id)
+                                {
+                                    self.as_mut().project().windowed.push_back(request);
+
+                                    continue 'actions;
+                                }
+
                                 self.as_mut().requests_push_back(request);
                             }
                             Action::Remove(sequence_number) => {
                                 tracing::debug!(target: CONN, sequence_number, "Received remove response");
 
                                 self.as_mut().remove_response(sequence_number);
+                                self.as_mut().remove_windowed(sequence_number);
+                                self.as_mut().notify_drained();
+                            }
+                            Action::Drain(request) => {
+                                tracing::debug!(target: CONN, "Received drain");
+
+                                let this = self.as_mut().project();
+
+                                *this.draining = true;
+                                this.drained.push(request.ack);
+
+                                self.as_mut().notify_drained();
                             }
                             Action::Close(request) => {
                                 tracing::debug!(target: CONN, "Received close");
@@ -370,6 +647,8 @@ where
                     }
                 }
 
+                self.as_mut().release_windowed();
+
                 let mut i: u8 = 0;
 
                 'sink: loop {
@@ -380,6 +659,9 @@ where
                     if i > SINK_POLL_LIMIT {
                         tracing::trace!(target: CONN, %i, "Exiting sink poll loop");
 
+                        // Queued requests are not woken by the sink, make sure they are sent.
+                        cx.waker().wake_by_ref();
+
                         break 'sink;
                     }
 
@@ -395,6 +677,14 @@ where
                                 Poll::Ready(Ok(_)) => {
                                     tracing::debug!(target: CONN, sequence_number, ?status, ?id, "Sent command");
 
+                                    self.as_mut().project().metrics.sent(request.command());
+
+                                    if let Some(version) = bind_interface_version(request.command())
+                                    {
+                                        *self.as_mut().project().bind_interface_version =
+                                            Some(version);
+                                    }
+
                                     match request {
                                         Request::Registered(request) => {
                                             tracing::debug!(target: CONN, sequence_number, ?status, ?id, "Registered");
@@ -427,6 +717,10 @@ where
                                         }
                                     }
 
+                                    if let CommandId::UnbindResp = id {
+                                        self.as_mut().set_session_state(SessionState::Unbound);
+                                    }
+
                                     continue 'sink;
                                 }
                                 Poll::Ready(Err(err)) => {
@@ -576,6 +870,8 @@ where
 
                             tracing::debug!(target: CONN, sequence_number, ?status, ?id, "Received command");
 
+                            self.as_mut().project().metrics.received(&command);
+
                             // Auto respond to enquire link requests from the server only if auto_enquire_link_response is enabled.
                             if let CommandId::EnquireLink = command.id()
                                 && self.auto_enquire_link_response
@@ -624,6 +920,41 @@ where
                             }
 
                             if id.is_response() {
+                                if let CommandStatus::EsmeRok = status {
+                                    match id {
+                                        CommandId::BindTransmitterResp => {
+                                            self.as_mut().set_session_state(SessionState::BoundTx)
+                                        }
+                                        CommandId::BindReceiverResp => {
+                                            self.as_mut().set_session_state(SessionState::BoundRx)
+                                        }
+                                        CommandId::BindTransceiverResp => {
+                                            self.as_mut().set_session_state(SessionState::BoundTrx)
+                                        }
+                                        CommandId::UnbindResp => {
+                                            self.as_mut().set_session_state(SessionState::Unbound)
+                                        }
+                                        _ => {}
+                                    }
+
+                                    let version = self
+                                        .bind_interface_version
+                                        .unwrap_or(InterfaceVersion::Smpp5_0);
+
+                                    if let Some(params) =
+                                        SessionParams::from_bind_resp(version, &command)
+                                    {
+                                        self.set_session_params(params);
+                                    }
+                                }
+
+                                self.record_congestion_state(&command);
+
+                                // Must happen before the client sees the response, so that a fast receipt finds its tracker.
+                                if let Some(tracker) = &self.tracker {
+                                    tracker.on_response(&command);
+                                }
+
                                 match self.as_mut().remove_response(sequence_number) {
                                     Some(response) => {
                                         tracing::trace!(target: CONN, sequence_number, ?status, ?id, "Found response");
@@ -650,10 +981,48 @@ where
                                     }
                                 }
 
+                                if self.as_mut().release_windowed() {
+                                    continue 'main;
+                                }
+
                                 continue 'stream;
                             }
 
                             // Command is an operation from the server.
+                            let state = self.session_state();
+
+                            if self.check_session_state && !state.can_receive_as_esme(id) {
+                                tracing::warn!(target: CONN, sequence_number, ?state, ?id, "Rejecting command not allowed in session state");
+
+                                let response = Command::builder()
+                                    .status(CommandStatus::EsmeRinvbndsts)
+                                    .sequence_number(sequence_number)
+                                    .pdu(Pdu::GenericNack);
+
+                                self.as_mut().requests_push_front(Request::Obligated(
+                                    ObligatedRequest::new(response),
+                                ));
+
+                                let _ =
+                                    self.as_mut().events.send_insight(Insight::RejectedCommand {
+                                        sequence_number,
+                                        id,
+                                        state,
+                                    });
+
+                                continue 'main;
+                            }
+
+                            if let CommandId::Outbind = id {
+                                self.as_mut().set_session_state(SessionState::Outbound);
+                            }
+
+                            self.record_congestion_state(&command);
+
+                            if let Some(tracker) = &self.tracker {
+                                tracker.on_incoming(&command);
+                            }
+
                             let _ = self.as_mut().events.send_incoming(command);
                         }
                         Poll::Ready(Some(Err(err))) => {
@@ -680,6 +1049,8 @@ where
                         Poll::Pending => {
                             tracing::trace!(target: CONN, "No incoming commands");
 
+                            self.as_mut().notify_drained();
+
                             tracing::trace!(target: CONN, "Pending");
 
                             return Poll::Pending;
@@ -705,20 +1076,66 @@ impl<E: EventChannel, R: Delay + Timeout> NoSpawnConnectionBuilder<E, R> {
         F: Stream<Item = Result<Command, DecodeError>>
             + for<'a> Sink<&'a Command, Error = EncodeError>,
     {
-        let (connection, watch, actions, events) = Connection::<_, E, R>::new(
-            self.builder.enquire_link_interval,
-            self.builder.enquire_link_response_timeout,
-            self.builder.auto_enquire_link_response,
-        );
+        self.raw_with_session_state(framed, SessionState::Open)
+    }
 
-        let client = Client::new(
+    /// See [`Self::raw`]. The connection starts in the given [`SessionState`].
+    pub(crate) fn raw_with_session_state<F>(
+        self,
+        framed: F,
+        session_state: SessionState,
+    ) -> (
+        Client<R>,
+        impl Stream<Item = E::Event> + Unpin + 'static,
+        impl Future<Output = ()>,
+    )
+    where
+        F: Stream<Item = Result<Command, DecodeError>>
+            + for<'a> Sink<&'a Command, Error = EncodeError>,
+    {
+        let tracker = self
+            .builder
+            .delivery_tracking
+            .map(|tracking| Arc::new(Tracker::new(tracking)));
+
+        let (session_state, session_state_rx) = watch::channel(session_state);
+        let (session_params, session_params_rx) = watch::channel(None);
+
+        let sequence_numbers =
+            Arc::new(SequenceNumbers::new(self.builder.sequence_number_allocator));
+
+        let spans = Arc::new(SessionSpans::new(self.builder.request_span_hook));
+
+        let (connection, watch, actions, events) = Connection::<_, E, R>::new(ConnectionParams {
+            enquire_link_interval: self.builder.enquire_link_interval,
+            enquire_link_response_timeout: self.builder.enquire_link_response_timeout,
+            auto_enquire_link_response: self.builder.auto_enquire_link_response,
+            tracker: tracker.clone(),
+            session_state,
+            check_session_state: self.builder.check_session_state,
+            session_params,
+            congestion_control: self.builder.congestion_control,
+            sequence_numbers: sequence_numbers.clone(),
+            metrics: ConnectionMetrics::new(
+                self.builder
+                    .metrics_label
+                    .unwrap_or_else(|| String::from("stream")),
+            ),
+        });
+
+        let client = Client::new(ClientParams {
             actions,
-            self.builder.response_timeout,
-            self.builder.check_interface_version,
+            response_timeout: self.builder.response_timeout,
+            check_interface_version: self.builder.check_interface_version,
+            tracker,
+            session_state: session_state_rx,
+            session_params: session_params_rx,
+            sequence_numbers,
+            spans: spans.clone(),
             watch,
-        );
+        });
 
-        (client, events, async move {
+        let connection = async move {
             let mut framed = std::pin::pin!(framed);
 
             let connection = connection.with_framed(&mut framed);
@@ -731,7 +1148,13 @@ impl<E: EventChannel, R: Delay + Timeout> NoSpawnConnectionBuilder<E, R> {
             if let Err(err) = framed.close().await {
                 tracing::error!(target: "rusmppc::connection::tcp", ?err, "Failed to shutdown stream");
             }
-        })
+        };
+
+        (
+            client,
+            events,
+            connection.instrument(spans.session().clone()),
+        )
     }
 }
 
@@ -339,7 +339,7 @@ async fn sink_first_poll_flush_pending_pending_request_should_be_sent() {
         .returning(|_cx| Poll::Ready(Ok(())));
 
     for n in 0..submit_count {
-        let i = 1 + n * 2;
+        let i = 1 + n;
 
         // Assert start send gets submit sm with correct sequence number
         framed
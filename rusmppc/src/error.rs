@@ -3,7 +3,8 @@
 use std::time::Duration;
 
 use rusmpp::{
-    Command,
+    Command, CommandId,
+    session::SessionState,
     tokio_codec::{DecodeError, EncodeError},
     values::InterfaceVersion,
 };
@@ -104,6 +105,129 @@ pub enum Error {
         /// The version that is supported by the library.
         supported_version: InterfaceVersion,
     },
+    /// The command is not supported by the interface version negotiated with the server.
+    ///
+    /// The command was not sent to the server. See [`SessionParams`](crate::session::SessionParams).
+    #[error("Unsupported by server: command id: {id:?}, interface version: {version:?}")]
+    UnsupportedByServer {
+        /// The id of the rejected command.
+        id: CommandId,
+        /// The negotiated interface version.
+        version: InterfaceVersion,
+    },
+    /// Delivery tracking is not enabled on the connection.
+    ///
+    /// This error is returned by [`submit_sm_tracked`](crate::client::Client::submit_sm_tracked) if the connection was not built with
+    /// [`delivery_tracking`](crate::ConnectionBuilder::delivery_tracking).
+    #[error("Delivery tracking is not enabled")]
+    DeliveryTrackingDisabled,
+    /// No delivery receipt was received for a tracked submission within the tracking TTL.
+    ///
+    /// This error is returned by awaiting a [`DeliveryTracker`](crate::tracking::DeliveryTracker).
+    #[error("Delivery receipt timed out: message id: {message_id}, timeout: {timeout:?}")]
+    DeliveryReceiptTimeout {
+        /// The `message_id` of the tracked submission.
+        message_id: String,
+        /// The tracking TTL.
+        timeout: Duration,
+    },
+    /// The tracked submission was removed from the [`TrackingStore`](crate::tracking::TrackingStore) without a receipt.
+    ///
+    /// This error is returned by awaiting a [`DeliveryTracker`](crate::tracking::DeliveryTracker).
+    #[error("Delivery tracking closed: message id: {message_id}")]
+    DeliveryTrackingClosed {
+        /// The `message_id` of the tracked submission.
+        message_id: String,
+    },
+    /// The [`ClientPool`](crate::pool::ClientPool) has no transmitter or transceiver members.
+    ///
+    /// This error is returned by [`ClientPool::get`](crate::pool::ClientPool::get).
+    #[error("No transmitter available in the pool")]
+    NoTransmitterAvailable,
+    /// The `MC` did not send an [`Outbind`](rusmpp::pdus::Outbind) in time.
+    ///
+    /// This error is returned by [`OutbindConnectionBuilder::accepted`](crate::outbind::OutbindConnectionBuilder::accepted).
+    #[error("Outbind timed out: timeout: {timeout:?}")]
+    OutbindTimeout {
+        /// The duration after which the outbind timed out.
+        timeout: Duration,
+    },
+    /// The first command sent by the `MC` was not an [`Outbind`](rusmpp::pdus::Outbind).
+    ///
+    /// This error is returned by [`OutbindConnectionBuilder::accepted`](crate::outbind::OutbindConnectionBuilder::accepted).
+    #[error("Expected outbind: command: {command:?}")]
+    OutbindExpected {
+        /// The received command.
+        command: Box<Command>,
+    },
+    /// The `system_id` or `password` of the [`Outbind`](rusmpp::pdus::Outbind) did not match the expected credentials.
+    ///
+    /// This error is returned by [`OutbindConnectionBuilder::accepted`](crate::outbind::OutbindConnectionBuilder::accepted).
+    #[error("Outbind rejected: system id: {system_id}")]
+    OutbindRejected {
+        /// The `system_id` sent by the `MC`.
+        system_id: String,
+    },
+    /// The command may not be sent in the current session state.
+    ///
+    /// The command was not sent to the server. See [`ConnectionBuilder::with_session_state_check`](crate::ConnectionBuilder::with_session_state_check).
+    #[error("Invalid session state: state: {state:?}, command id: {id:?}")]
+    InvalidSessionState {
+        /// The session state of the connection.
+        state: SessionState,
+        /// The id of the rejected command.
+        id: CommandId,
+    },
+    /// The text could not be encoded or split into parts.
+    ///
+    /// This error is returned by [`Client::send_text`](crate::Client::send_text).
+    #[error("Text encoding error: {0}")]
+    TextEncoding(#[source] Box<dyn std::error::Error + Send + Sync + 'static>),
+    /// The client is shutting down and does not accept new requests.
+    ///
+    /// See [`Client::shutdown`](crate::Client::shutdown).
+    #[error("Shutting down")]
+    ShuttingDown,
+    /// Outstanding requests were not answered before the shutdown deadline.
+    ///
+    /// The connection was unbound and closed anyway. This error is returned by [`Client::shutdown`](crate::Client::shutdown).
+    #[error("Shutdown deadline elapsed: deadline: {deadline:?}, pending: {pending}")]
+    ShutdownDeadline {
+        /// The shutdown deadline.
+        deadline: Duration,
+        /// The number of requests still waiting for a response.
+        pending: usize,
+    },
+    /// The [`OutboxStore`](crate::outbox::OutboxStore) failed.
+    ///
+    /// This error is returned by the methods of [`Outbox`](crate::outbox::Outbox).
+    #[error("Outbox store error: {0}")]
+    Outbox(#[source] std::io::Error),
+    /// The [`SequenceNumberAllocator`](crate::sequence::SequenceNumberAllocator) returned a sequence number outside of
+    /// [`MIN_SEQUENCE_NUMBER`](crate::sequence::MIN_SEQUENCE_NUMBER)..=[`MAX_SEQUENCE_NUMBER`](crate::sequence::MAX_SEQUENCE_NUMBER).
+    ///
+    /// The request was not sent.
+    #[error("Invalid sequence number: {sequence_number}")]
+    InvalidSequenceNumber {
+        /// The returned sequence number.
+        sequence_number: u32,
+    },
+    /// The [`SequenceNumberAllocator`](crate::sequence::SequenceNumberAllocator) only returned sequence numbers that are still waiting for a response.
+    ///
+    /// The request was not sent.
+    #[error("Sequence numbers exhausted: attempts: {attempts}")]
+    SequenceNumbersExhausted {
+        /// The number of allocation attempts.
+        attempts: usize,
+    },
+    /// A submission with the same idempotency key is already in flight.
+    ///
+    /// This error is returned by [`Outbox::submit`](crate::outbox::Outbox::submit).
+    #[error("Outbox submission in flight: key: {key}")]
+    OutboxInFlight {
+        /// The idempotency key.
+        key: String,
+    },
 }
 
 impl Error {
@@ -120,6 +244,10 @@ impl Error {
         }
     }
 
+    pub(crate) fn text_encoding(error: impl std::error::Error + Send + Sync + 'static) -> Self {
+        Self::TextEncoding(Box::new(error))
+    }
+
     pub(crate) const fn response_timeout(sequence_number: u32, timeout: Duration) -> Self {
         Self::ResponseTimeout {
             sequence_number,
@@ -1,6 +1,6 @@
 use std::fmt::Debug;
 
-use rusmpp::Command;
+use rusmpp::{Command, CommandId, session::SessionState, values::CongestionState};
 use tokio::sync::mpsc::error::SendError;
 
 use crate::error::Error;
@@ -43,6 +43,21 @@ pub enum Insight {
     ReceivedEnquireLink(u32),
     /// Sent EnquireLinkResp to the server.
     SentEnquireLinkResp(u32),
+    /// Rejected a command from the server that is not allowed in the current session state.
+    ///
+    /// The connection responded with a GenericNack.
+    RejectedCommand {
+        /// The sequence number of the rejected command.
+        sequence_number: u32,
+        /// The id of the rejected command.
+        id: CommandId,
+        /// The session state of the connection.
+        state: SessionState,
+    },
+    /// The session state of the connection changed.
+    SessionStateChanged(SessionState),
+    /// The server reported a new `congestion_state`.
+    CongestionStateChanged(CongestionState),
 }
 
 pub trait EventChannel: Send + 'static {
@@ -5,13 +5,15 @@
 //! ## Features
 //!
 //! - `tokio`: Enables support for [`tokio`](https://docs.rs/tokio/latest/tokio/) runtime. Enabled by default.
-//! - `wasm`: Enables support for `wasm`.
+//! - `wasm`: Enables support for `wasm`. Connections are opened through the browser `WebSocket`.
+//! - `websocket`: Enables the `ws` and `wss` URL schemes for the [`tokio`](https://docs.rs/tokio/latest/tokio/) runtime. Enables the `tokio` feature.
 //! - `rustls`:  Enables TLS support via [`rustls`](https://docs.rs/rustls/latest/rustls/). Enabled by default.
 //! - `rustls-tls-native-roots`: Uses the platform's native root certificates through [`rustls-native-certs`](https://docs.rs/rustls-native-certs/latest/rustls_native_certs/) while using default configuration. Enables the `rustls` feature and is enabled by default.
 //! - `rustls-tls-webpki-roots`: Uses the [`webpki-roots`](https://docs.rs/webpki-roots/latest/webpki_roots/) crate's root certificates while using default configuration. Enables the `rustls` feature and is enabled by default.
 //! - `rustls-aws-lc-rs`: Uses [`aws-lc-rs`](https://docs.rs/aws-lc-rs/latest/aws_lc_rs/) as the TLS backend for [`rustls`](https://docs.rs/rustls/latest/rustls/). Enabled by default.
 //! - `rustls-ring`: Uses [`ring`](https://docs.rs/ring/latest/ring/) as the TLS backend for [`rustls`](https://docs.rs/rustls/latest/rustls/).
 //! - `native-tls`: Enables TLS support via [`native-tls`](https://docs.rs/native-tls/latest/native_tls/).
+//! - `metrics`: Records connection metrics through the [`metrics`](https://docs.rs/metrics/latest/metrics/) facade. The metric names are listed in the `metrics` module.
 //!
 //! # Example
 //!
@@ -133,8 +135,94 @@ pub mod builder {
     };
 }
 
+mod tracking_;
+
+pub mod tracking {
+    //! Types related to correlating submissions with their delivery receipts.
+    pub use super::tracking_::{
+        DeliveryReceipt, DeliveryTracker, InMemoryTrackingStore, TrackedSubmission, TrackingStore,
+    };
+}
+
+mod metrics_;
+
+#[cfg(feature = "metrics")]
+#[cfg_attr(docsrs, doc(cfg(feature = "metrics")))]
+pub mod metrics {
+    //! Names of the metrics recorded through the [`metrics`](https://docs.rs/metrics) facade.
+    //!
+    //! Install any `metrics` recorder, e.g. a Prometheus exporter, to collect them.
+    //!
+    //! Every metric is labeled with [`CONNECTION_LABEL`], see [`ConnectionBuilder::metrics_label`](crate::ConnectionBuilder::metrics_label).
+    pub use super::metrics_::{
+        BIND_FAILURES, BYTES_RECEIVED, BYTES_SENT, COMMANDS_RECEIVED, COMMANDS_SENT,
+        CONNECTION_LABEL, OUTSTANDING_REQUESTS, RECONNECTS, RESPONSE_LATENCY,
+    };
+}
+
+#[cfg(feature = "tokio")]
+mod proxy_;
+#[cfg(feature = "tokio")]
+#[cfg_attr(docsrs, doc(cfg(feature = "tokio")))]
+pub mod proxy {
+    //! Proxies to tunnel connections through.
+
+    pub use super::proxy_::Proxy;
+}
+
+#[cfg(all(feature = "tokio", feature = "rustls"))]
+mod tls_;
+#[cfg(all(feature = "tokio", feature = "rustls"))]
+#[cfg_attr(docsrs, doc(cfg(all(feature = "tokio", feature = "rustls"))))]
+pub mod tls {
+    //! TLS configuration helpers.
+
+    pub use super::tls_::TlsPin;
+}
+
+mod session_;
+pub mod session {
+    //! Session parameters negotiated on bind.
+
+    pub use super::session_::SessionParams;
+}
+
+mod congestion_;
+pub mod congestion {
+    //! Congestion control based on the `congestion_state` TLV.
+
+    pub use super::congestion_::CongestionControl;
+}
+
+mod sequence_;
+pub mod sequence {
+    //! Types related to allocating sequence numbers.
+    pub use super::sequence_::{
+        AtomicSequenceNumberAllocator, MAX_SEQUENCE_NUMBER, MIN_SEQUENCE_NUMBER,
+        SequenceNumberAllocator,
+    };
+}
+
+mod span_;
+pub mod span {
+    //! Tracing spans of sessions and requests.
+
+    pub use super::span_::{RequestSpanHook, SPAN_TARGET};
+}
+
+mod text_;
+
+pub mod text {
+    //! Types related to sending texts with [`Client::send_text`](crate::Client::send_text).
+    pub use super::text_::{
+        ConcatenationMode, MultipartSubmitResult, SendTextOptions, TextEncoding,
+    };
+}
+
 mod request;
-pub(crate) use request::{CloseRequest, RegisteredRequest, Request, UnregisteredRequest};
+pub(crate) use request::{
+    CloseRequest, DrainRequest, RegisteredRequest, Request, UnregisteredRequest,
+};
 
 mod timer;
 pub(crate) use timer::Timer;
@@ -153,6 +241,9 @@ pub(crate) use futures::RequestFutureGuard;
 mod response;
 pub(crate) use response::PendingResponses;
 
+#[cfg(any(feature = "websocket", feature = "wasm"))]
+mod websocket_;
+
 #[cfg(feature = "tokio")]
 mod tcp_stream;
 #[cfg(feature = "tokio")]
@@ -177,7 +268,7 @@ mod managed_;
 #[cfg_attr(docsrs, doc(cfg(feature = "tokio")))]
 pub mod managed {
     //! A managed `SMPP` client that automatically handles reconnection and binding.
-    pub use super::managed_::{ManagedClient, ManagedEvent};
+    pub use super::managed_::{ActiveEndpoint, Endpoint, ManagedClient, ManagedEvent};
 
     pub mod builder {
         //! Types related to building a managed `SMPP` client.
@@ -186,3 +277,44 @@ pub mod managed {
         };
     }
 }
+
+#[cfg(feature = "tokio")]
+mod pool_;
+
+#[cfg(feature = "tokio")]
+#[cfg_attr(docsrs, doc(cfg(feature = "tokio")))]
+pub mod pool {
+    //! A pool of managed `SMPP` clients with load balancing and health tracking.
+    pub use super::pool_::{
+        ClientPool, Health, HealthSignal, MemberRole, MemberStatus, PoolEvent, PooledClient,
+        Selection,
+    };
+
+    pub mod builder {
+        //! Types related to building a client pool.
+        pub use super::super::pool_::ClientPoolBuilder;
+    }
+}
+
+#[cfg(feature = "tokio")]
+mod outbind_;
+
+#[cfg(feature = "tokio")]
+#[cfg_attr(docsrs, doc(cfg(feature = "tokio")))]
+pub mod outbind {
+    //! Accepting outbind connections initiated by the `MC`.
+    pub use super::outbind_::{OutbindConnectionBuilder, UnboundOutbindConnectionBuilder};
+}
+
+#[cfg(feature = "tokio")]
+mod outbox_;
+
+#[cfg(feature = "tokio")]
+#[cfg_attr(docsrs, doc(cfg(feature = "tokio")))]
+pub mod outbox {
+    //! Durable outbound queue with at-least-once submission.
+    pub use super::outbox_::{
+        DEFAULT_OUTCOME_RETENTION, FileOutboxStore, InMemoryOutboxStore, Outbox, OutboxEntry,
+        OutboxOutcome, OutboxStatus, OutboxStore,
+    };
+}
@@ -3,8 +3,10 @@
 use std::{
     fmt::Debug,
     pin::Pin,
-    sync::Arc,
-    task::{Context, Poll},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
     time::Duration,
 };
 
@@ -23,21 +25,33 @@ use crate::{
     Client, ConnectionBuilder,
     error::Error,
     event_::EventChannel,
+    metrics_::record_reconnect,
     runtime_::{Delay, Timeout, tokio::Tokio},
 };
 
+mod endpoint;
+use endpoint::Endpoints;
+pub use endpoint::{ActiveEndpoint, Endpoint};
+
 #[cfg(test)]
 mod tests;
 
 const TARGET: &str = "rusmppc::managed::client";
 
+/// How long the connection replaced by failing back may drain its outstanding requests.
+const FAIL_BACK_SHUTDOWN_DEADLINE: Duration = Duration::from_secs(30);
+
 /// Events emitted by the [`ManagedClient`].
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum ManagedEvent<E> {
     /// Emitted when the client is connected to the server.
     Connected,
     /// Emitted when the client is successfully bound to the server.
     Bound,
+    /// Emitted after [`Bound`](ManagedEvent::Bound) when connecting through [`connect_endpoints`](ManagedConnectionBuilder::connect_endpoints),
+    /// i.e. on the first connection, on every failover and on every fail-back.
+    EndpointActive(ActiveEndpoint),
     /// Emitted when the client is disconnected from the server.
     Disconnected,
     /// Emitted when the client receives an event from the server.
@@ -69,6 +83,8 @@ impl Debug for ManagedClient {
 struct ManagedClientInner {
     creator: Box<dyn BoundClientCreator<Tokio>>,
     client: RwLock<Client<Tokio>>,
+    // Set by shutdown. Stops reconnecting.
+    shutting_down: AtomicBool,
 }
 
 impl ManagedClientInner {
@@ -76,9 +92,14 @@ impl ManagedClientInner {
         Self {
             creator,
             client: RwLock::new(client),
+            shutting_down: AtomicBool::new(false),
         }
     }
 
+    fn is_shutting_down(&self) -> bool {
+        self.shutting_down.load(Ordering::Relaxed)
+    }
+
     async fn get(&self) -> Result<RwLockReadGuard<'_, Client<Tokio>>, Error> {
         {
             let client = self.client.read().await;
@@ -90,10 +111,47 @@ impl ManagedClientInner {
 
         let mut client = self.client.write().await;
 
+        if self.is_shutting_down() {
+            return Err(Error::ShuttingDown);
+        }
+
         *client = self.creator.connect().await?;
 
+        record_reconnect(self.creator.metrics_label());
+
         Ok(client.downgrade())
     }
+
+    /// Replaces the active client with a client connected to a primary endpoint, if failing back is due.
+    async fn fail_back(&self) -> Result<(), Error> {
+        let Some(result) = self.creator.fail_back().await else {
+            return Ok(());
+        };
+
+        let previous = std::mem::replace(&mut *self.client.write().await, result?);
+
+        record_reconnect(self.creator.metrics_label());
+
+        Tokio::spawn(async move {
+            tracing::debug!(target: TARGET, "Shutting down the previous connection after failing back");
+
+            let _ = previous.shutdown(FAIL_BACK_SHUTDOWN_DEADLINE).await;
+        });
+
+        Ok(())
+    }
+
+    async fn shutdown(&self, deadline: Duration) -> Result<(), Error> {
+        self.shutting_down.store(true, Ordering::Relaxed);
+
+        let client = self.client.read().await.clone();
+
+        match client.shutdown(deadline).await {
+            // Nothing to drain, the connection was lost.
+            Err(Error::ConnectionClosed) => Ok(()),
+            result => result,
+        }
+    }
 }
 
 impl ManagedClient {
@@ -111,6 +169,32 @@ impl ManagedClient {
         self.inner.get().await.map(|client| client.clone())
     }
 
+    /// Gracefully shuts down the managed connection.
+    ///
+    /// Stops reconnecting and shuts down the current [`Client`]. See [`Client::shutdown`] for more details.
+    /// After calling this method, [`get`](Self::get) returns [`Error::ShuttingDown`] once the connection is closed.
+    pub async fn shutdown(&self, deadline: Duration) -> Result<(), Error> {
+        self.inner.shutdown(deadline).await
+    }
+
+    /// Returns the number of requests of the current [`Client`] waiting for a response. See [`Client::in_flight`].
+    ///
+    /// Returns `0` while the client is being replaced.
+    pub fn in_flight(&self) -> usize {
+        self.inner
+            .client
+            .try_read()
+            .map(|client| client.in_flight())
+            .unwrap_or_default()
+    }
+
+    /// Returns the endpoint the current [`Client`] is connected to.
+    ///
+    /// Returns `None` if the client was not connected through [`connect_endpoints`](ManagedConnectionBuilder::connect_endpoints).
+    pub fn active_endpoint(&self) -> Option<ActiveEndpoint> {
+        self.inner.creator.active_endpoint()
+    }
+
     /// Gets a connected and bound [`Client`] with a timeout.
     pub async fn get_with_timeout(
         &self,
@@ -121,7 +205,7 @@ impl ManagedClient {
 }
 
 #[derive(Debug, Clone)]
-enum BindMode {
+pub(crate) enum BindMode {
     None,
     Transmitter(BindTransmitter),
     Receiver(BindReceiver),
@@ -183,6 +267,7 @@ pub struct ManagedConnectionBuilder<E: EventChannel + Clone + Send + Sync + 'sta
     max_delay: Option<Duration>,
     back_off: BackOff,
     max_retries: u32,
+    fail_back_after: Option<Duration>,
 }
 
 impl<E: EventChannel + Clone + Send + Sync + 'static> ManagedConnectionBuilder<E> {
@@ -194,6 +279,7 @@ impl<E: EventChannel + Clone + Send + Sync + 'static> ManagedConnectionBuilder<E
             max_delay: None,
             back_off: BackOff::Exponential(ExponentialBackoff::new(Duration::from_secs(2))),
             max_retries: 10,
+            fail_back_after: Some(Duration::from_secs(60)),
         }
     }
 
@@ -269,6 +355,35 @@ impl<E: EventChannel + Clone + Send + Sync + 'static> ManagedConnectionBuilder<E
         self.max_retries = retries;
         self
     }
+
+    /// Sets how long a secondary endpoint must be stable before failing back to a primary endpoint.
+    ///
+    /// Only used with [`connect_endpoints`](Self::connect_endpoints). Failing back is checked by the automatic reconnection task,
+    /// see [`auto_reconnect_interval`](Self::auto_reconnect_interval).
+    ///
+    /// Defaults to 60 seconds.
+    pub fn fail_back_after(mut self, stable: Duration) -> Self {
+        self.fail_back_after = Some(stable);
+        self
+    }
+
+    /// Disables failing back to a primary endpoint.
+    ///
+    /// The client stays on a secondary endpoint until it disconnects.
+    pub fn no_fail_back(mut self) -> Self {
+        self.fail_back_after = None;
+        self
+    }
+
+    /// Sets how long a secondary endpoint must be stable before failing back to a primary endpoint.
+    pub fn with_fail_back_after(mut self, stable: Option<Duration>) -> Self {
+        self.fail_back_after = stable;
+        self
+    }
+
+    pub(crate) const fn bind_mode(&self) -> &BindMode {
+        &self.bind
+    }
 }
 
 impl<E: EventChannel> ManagedConnectionBuilder<E>
@@ -289,13 +404,18 @@ where
         let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
         let rx = UnboundedReceiverStream::new(rx);
 
+        let retry = RetryPolicy {
+            max_delay: self.max_delay,
+            back_off: self.back_off,
+            max_retries: self.max_retries,
+        };
+
         let creator = BoundClientCreatorImpl::new(
             self.builder,
             connect,
             self.bind,
-            self.max_delay,
-            self.back_off,
-            self.max_retries,
+            retry,
+            self.fail_back_after,
             tx,
         );
 
@@ -318,12 +438,24 @@ where
                             break;
                         }
                         _ = Tokio::delay(interval) => {
+                            if client_c.is_shutting_down() {
+                                tracing::debug!(target: TARGET, "Shutting down, stopping reconnect task");
+
+                                break;
+                            }
+
                             tracing::trace!(target: TARGET, "Triggering reconnection");
 
                             // Trigger a reconnection if the connection was closed
 
                             if let Err(err) = client_c.get().await {
                                 tracing::error!(target: TARGET, ?err, "Failed to reconnect");
+
+                                continue;
+                            }
+
+                            if let Err(err) = client_c.fail_back().await {
+                                tracing::warn!(target: TARGET, ?err, "Failed to fail back to a primary endpoint");
                             }
                         }
                     }
@@ -372,21 +504,61 @@ where
     > {
         self.run(Connect::Url(url.into())).await
     }
+
+    /// Connects to the first reachable of several `SMPP` servers.
+    ///
+    /// Endpoints are grouped by [`priority`](Endpoint::priority). Without explicit priorities, the list order is used:
+    /// the first endpoint is the primary one and the others are tried in order.
+    /// Explicit priorities must be given for all endpoints or none of them.
+    /// Within a group, endpoints are picked by their [`weight`](Endpoint::weight).
+    ///
+    /// Each group is retried using the configured backoff. Once the retries are exhausted, the client fails over to the next group,
+    /// wrapping around after the last one. While connected to a secondary endpoint, the client fails back to a primary endpoint
+    /// after the connection has been stable for [`fail_back_after`](Self::fail_back_after).
+    ///
+    /// The endpoint in use is available through [`ManagedClient::active_endpoint`] and reported by [`ManagedEvent::EndpointActive`].
+    ///
+    /// See [`ConnectionBuilder::connect`] for more details.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`Error::Connect`] if `endpoints` is empty or mixes explicit and implicit priorities, or the last connection error if no endpoint is reachable.
+    pub async fn connect_endpoints(
+        self,
+        endpoints: impl IntoIterator<Item = impl Into<Endpoint>>,
+    ) -> Result<
+        (
+            ManagedClient,
+            impl Stream<Item = ManagedEvent<E::Event>> + Unpin + 'static,
+        ),
+        Error,
+    > {
+        let endpoints =
+            Endpoints::new(endpoints.into_iter().map(Into::into).collect()).map_err(|err| {
+                Error::Connect(std::io::Error::new(std::io::ErrorKind::InvalidInput, err))
+            })?;
+
+        self.run(Connect::Endpoints(endpoints)).await
+    }
 }
 
+type Events<E> = Pin<Box<dyn Stream<Item = E> + Send + 'static>>;
+
 enum Connect {
     Url(String),
     Connector(Box<dyn Connector>),
+    Endpoints(Endpoints),
 }
 
 struct BoundClientCreatorImpl<E: EventChannel, R: Delay + Timeout> {
     builder: ConnectionBuilder<E, R>,
     connect: Connect,
     bind: BindMode,
-    max_delay: Option<Duration>,
-    back_off: BackOff,
-    max_retries: u32,
+    retry: RetryPolicy,
+    fail_back_after: Option<Duration>,
     tx: UnboundedSender<ManagedEvent<E::Event>>,
+    /// Set when the current connection is intentionally replaced, to suppress its [`ManagedEvent::Disconnected`].
+    replaced: Mutex<Arc<AtomicBool>>,
 }
 
 impl<E: EventChannel, R: Delay + Timeout> BoundClientCreatorImpl<E, R>
@@ -400,19 +572,18 @@ where
         builder: ConnectionBuilder<E, R>,
         connect: Connect,
         bind: BindMode,
-        max_delay: Option<Duration>,
-        back_off: BackOff,
-        max_retries: u32,
+        retry: RetryPolicy,
+        fail_back_after: Option<Duration>,
         tx: UnboundedSender<ManagedEvent<E::Event>>,
     ) -> Self {
         Self {
             builder,
             connect,
             bind,
-            max_delay,
-            back_off,
-            max_retries,
+            retry,
+            fail_back_after,
             tx,
+            replaced: Mutex::new(Arc::new(AtomicBool::new(false))),
         }
     }
 }
@@ -422,41 +593,178 @@ where
     E: Clone + Send + Sync + 'static,
     E::Event: Send + Sync + 'static,
 {
-    async fn connect_(&self) -> Result<Client<Tokio>, Error> {
-        tracing::debug!(target: TARGET, "Connecting");
+    async fn connect_url(&self, url: &str) -> Result<(Client<Tokio>, Events<E::Event>), Error> {
+        self.builder
+            .clone()
+            .connect(url)
+            .await
+            .map(|(client, events)| (client, events.boxed()))
+    }
 
-        let connect = move || async move {
-            match self.connect {
-                Connect::Url(ref url) => self
-                    .builder
-                    .clone()
-                    .connect(url)
-                    .await
-                    .map(|(client, events)| (client, EventStream::new_a(events))),
-                Connect::Connector(ref connector) => connector
-                    .connect()
-                    .await
-                    .map_err(Error::Connect)
-                    .map(|stream| self.builder.clone().connected(stream))
-                    .map(|(client, events)| (client, EventStream::new_b(events))),
-            }
-        };
+    /// Retries `connect` using the configured backoff.
+    async fn retry<F, Fut, T>(&self, connect: F) -> Result<T, Error>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, Error>>,
+    {
+        let RetryPolicy {
+            max_delay,
+            back_off,
+            max_retries,
+        } = self.retry;
 
-        let max_delay = self.max_delay;
-        let max_retries = self.max_retries;
         let mut fut = tryhard::retry_fn(connect)
-            .retries(self.max_retries)
-            .custom_backoff(self.back_off)
+            .retries(max_retries)
+            .custom_backoff(back_off)
             .on_retry(|attempt, next_delay, _| async move {
                 tracing::warn!(target: TARGET, ?attempt, ?max_retries, ?next_delay, ?max_delay, "Connection attempt failed");
             });
 
-        if let Some(delay) = self.max_delay {
+        if let Some(delay) = max_delay {
             fut = fut.max_delay(delay)
         };
 
-        let (client, mut events) = fut.await?;
+        fut.await
+    }
+
+    /// Tries every endpoint group starting with the current one, failing over when a group's retries are exhausted.
+    async fn connect_endpoints(
+        &self,
+        endpoints: &Endpoints,
+    ) -> Result<(Client<Tokio>, Events<E::Event>, usize), Error> {
+        let start = endpoints.current_tier();
+        let mut last_error = None;
+
+        for offset in 0..endpoints.tiers() {
+            let tier = (start + offset) % endpoints.tiers();
+
+            let result = self
+                .retry(|| async move {
+                    let index = endpoints.pick(tier);
+                    let url = endpoints.url(index);
+
+                    tracing::debug!(target: TARGET, url, tier, "Connecting to endpoint");
+
+                    self.connect_url(url)
+                        .await
+                        .map(|(client, events)| (client, events, index))
+                })
+                .await;
+
+            match result {
+                Ok((client, events, index)) => {
+                    endpoints.activate(tier, index);
+
+                    return Ok((client, events, index));
+                }
+                Err(err) => {
+                    tracing::warn!(target: TARGET, tier, ?err, "Endpoint group exhausted, failing over");
+
+                    last_error = Some(err);
+                }
+            }
+        }
+
+        Err(last_error.expect("Endpoints are never empty"))
+    }
+
+    async fn connect_(&self) -> Result<Client<Tokio>, Error> {
+        tracing::debug!(target: TARGET, "Connecting");
+
+        let (client, events, endpoint) = match self.connect {
+            Connect::Url(ref url) => self
+                .retry(|| self.connect_url(url))
+                .await
+                .map(|(client, events)| (client, events, None))?,
+            Connect::Connector(ref connector) => self
+                .retry(|| async move {
+                    connector
+                        .connect()
+                        .await
+                        .map_err(Error::Connect)
+                        .map(|stream| self.builder.clone().connected(stream))
+                })
+                .await
+                .map(|(client, events)| (client, events.boxed(), None))?,
+            Connect::Endpoints(ref endpoints) => self
+                .connect_endpoints(endpoints)
+                .await
+                .map(|(client, events, index)| (client, events, Some(index)))?,
+        };
+
+        let client = self.establish(client, events).await?;
+
+        if let (Connect::Endpoints(endpoints), Some(index)) = (&self.connect, endpoint) {
+            self.endpoint_active(endpoints, index);
+        }
+
+        Ok(client)
+    }
 
+    /// Tries each primary endpoint once if failing back is due.
+    ///
+    /// Returns `None` if the client is not connected through endpoints, is already on a primary endpoint,
+    /// or the secondary endpoint has not been stable long enough.
+    async fn fail_back_(&self) -> Option<Result<Client<Tokio>, Error>> {
+        let Connect::Endpoints(ref endpoints) = self.connect else {
+            return None;
+        };
+
+        if !endpoints.should_fail_back(self.fail_back_after?) {
+            return None;
+        }
+
+        tracing::debug!(target: TARGET, "Failing back to a primary endpoint");
+
+        let mut last_error = None;
+
+        for _ in 0..endpoints.tier_len(0) {
+            let index = endpoints.pick(0);
+
+            match self.connect_url(endpoints.url(index)).await {
+                Ok((client, events)) => {
+                    // The current connection is about to be replaced, its disconnection is expected.
+                    let previous = self.replaced.lock().expect("Creator poisoned").clone();
+
+                    let result = self.establish(client, events).await;
+
+                    if result.is_ok() {
+                        previous.store(true, Ordering::Relaxed);
+                        endpoints.activate(0, index);
+
+                        self.endpoint_active(endpoints, index);
+                    }
+
+                    return Some(result);
+                }
+                Err(err) => last_error = Some(err),
+            }
+        }
+
+        endpoints.restart_stable_period();
+
+        last_error.map(Err)
+    }
+
+    /// Emits [`ManagedEvent::EndpointActive`] for the endpoint at `index`.
+    fn endpoint_active(&self, endpoints: &Endpoints, index: usize) {
+        let url = endpoints.url(index);
+        let primary = endpoints.is_primary(index);
+
+        tracing::info!(target: TARGET, url, primary, "Endpoint active");
+
+        let _ = self.tx.send(ManagedEvent::EndpointActive(ActiveEndpoint {
+            url: url.to_owned(),
+            primary,
+        }));
+    }
+
+    /// Emits the connection events, binds the client and forwards its events.
+    async fn establish(
+        &self,
+        client: Client<Tokio>,
+        mut events: Events<E::Event>,
+    ) -> Result<Client<Tokio>, Error> {
         let _ = self.tx.send(ManagedEvent::Connected);
 
         tracing::debug!(target: TARGET, "Connected");
@@ -480,6 +788,10 @@ where
             tracing::debug!(target: TARGET, "Bound");
         }
 
+        let replaced = Arc::new(AtomicBool::new(false));
+
+        *self.replaced.lock().expect("Creator poisoned") = replaced.clone();
+
         let tx = self.tx.clone();
 
         Tokio::spawn(async move {
@@ -487,6 +799,12 @@ where
                 let _ = tx.send(ManagedEvent::Event(event));
             }
 
+            if replaced.load(Ordering::Relaxed) {
+                tracing::debug!(target: TARGET, "Replaced connection closed");
+
+                return;
+            }
+
             let _ = tx.send(ManagedEvent::Disconnected);
 
             tracing::warn!(target: TARGET, "Disconnected");
@@ -498,6 +816,16 @@ where
 
 trait BoundClientCreator<T>: Send + Sync + 'static {
     fn connect(&self) -> Pin<Box<dyn Future<Output = Result<Client<T>, Error>> + Send + '_>>;
+
+    fn active_endpoint(&self) -> Option<ActiveEndpoint>;
+
+    /// The `connection` label of the current connection metrics.
+    fn metrics_label(&self) -> String;
+
+    #[allow(clippy::type_complexity)]
+    fn fail_back(
+        &self,
+    ) -> Pin<Box<dyn Future<Output = Option<Result<Client<T>, Error>>> + Send + '_>>;
 }
 
 impl<E: EventChannel> BoundClientCreator<Tokio> for BoundClientCreatorImpl<E, Tokio>
@@ -508,6 +836,39 @@ where
     fn connect(&self) -> Pin<Box<dyn Future<Output = Result<Client<Tokio>, Error>> + Send + '_>> {
         Box::pin(async move { self.connect_().await })
     }
+
+    fn active_endpoint(&self) -> Option<ActiveEndpoint> {
+        match self.connect {
+            Connect::Endpoints(ref endpoints) => endpoints.active(),
+            _ => None,
+        }
+    }
+
+    fn metrics_label(&self) -> String {
+        if let Some(ref label) = self.builder.metrics_label {
+            return label.clone();
+        }
+
+        let url = match self.connect {
+            Connect::Url(ref url) => url.clone(),
+            Connect::Connector(_) => return String::from("stream"),
+            Connect::Endpoints(ref endpoints) => match endpoints.active() {
+                Some(endpoint) => endpoint.url,
+                None => return String::from("stream"),
+            },
+        };
+
+        // Connecting succeeded, the URL is valid.
+        url::Url::parse(&url)
+            .map(|url| crate::metrics_::url_label(&url))
+            .unwrap_or_default()
+    }
+
+    fn fail_back(
+        &self,
+    ) -> Pin<Box<dyn Future<Output = Option<Result<Client<Tokio>, Error>>> + Send + '_>> {
+        Box::pin(async move { self.fail_back_().await })
+    }
 }
 
 trait UnpinAsyncReadWrite: AsyncRead + AsyncWrite + Unpin + Send {}
@@ -541,53 +902,12 @@ where
     }
 }
 
-pin_project_lite::pin_project! {
-    pub struct EventStream<A, B, E> {
-        #[pin]
-        stream: StreamOrStream<A, B>,
-        _marker: std::marker::PhantomData<E>,
-    }
-}
-
-impl<A, B, E> EventStream<A, B, E> {
-    pub fn new_a(stream: A) -> Self {
-        Self {
-            stream: StreamOrStream::A { stream },
-            _marker: std::marker::PhantomData,
-        }
-    }
-
-    pub fn new_b(stream: B) -> Self {
-        Self {
-            stream: StreamOrStream::B { stream },
-            _marker: std::marker::PhantomData,
-        }
-    }
-}
-
-pin_project_lite::pin_project! {
-    #[project = StreamOrStreamProj]
-    pub enum StreamOrStream<A, B> {
-        A { #[pin] stream: A },
-        B { #[pin] stream: B },
-    }
-}
-
-impl<A, B, E> Stream for EventStream<A, B, E>
-where
-    A: Stream<Item = E>,
-    B: Stream<Item = E>,
-{
-    type Item = E;
-
-    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        let this = self.project();
-
-        match this.stream.project() {
-            StreamOrStreamProj::A { stream } => stream.poll_next(cx),
-            StreamOrStreamProj::B { stream } => stream.poll_next(cx),
-        }
-    }
+/// How a [`BoundClientCreatorImpl`] retries connecting.
+#[derive(Debug, Clone, Copy)]
+struct RetryPolicy {
+    max_delay: Option<Duration>,
+    back_off: BackOff,
+    max_retries: u32,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -0,0 +1,234 @@
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+#[cfg(test)]
+mod tests;
+
+/// An `SMPP` server endpoint used by the [`ManagedClient`](super::ManagedClient) for failover.
+///
+/// Endpoints with a lower priority are preferred. Endpoints sharing the same priority form a group
+/// and are selected by their weight using a smooth weighted round robin.
+///
+/// Either all endpoints passed to [`connect_endpoints`](super::ManagedConnectionBuilder::connect_endpoints) have an explicit priority or none of them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Endpoint {
+    url: String,
+    priority: Option<u32>,
+    weight: u32,
+}
+
+impl Endpoint {
+    /// Creates a new [`Endpoint`] with the given URL.
+    ///
+    /// # Defaults
+    /// - `priority`: the position of the endpoint in the list passed to [`connect_endpoints`](super::ManagedConnectionBuilder::connect_endpoints).
+    /// - `weight`: 1
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            priority: None,
+            weight: 1,
+        }
+    }
+
+    /// Creates a new primary [`Endpoint`] (priority 0).
+    pub fn primary(url: impl Into<String>) -> Self {
+        Self::new(url).priority(0)
+    }
+
+    /// Creates a new secondary [`Endpoint`] (priority 1).
+    pub fn secondary(url: impl Into<String>) -> Self {
+        Self::new(url).priority(1)
+    }
+
+    /// Sets the priority of the endpoint. Lower is preferred.
+    pub const fn priority(mut self, priority: u32) -> Self {
+        self.priority = Some(priority);
+        self
+    }
+
+    /// Sets the weight of the endpoint within its priority group.
+    ///
+    /// A weight of 0 is treated as 1.
+    pub const fn weight(mut self, weight: u32) -> Self {
+        self.weight = weight;
+        self
+    }
+
+    /// Returns the URL of the endpoint.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+}
+
+impl From<&str> for Endpoint {
+    fn from(url: &str) -> Self {
+        Self::new(url)
+    }
+}
+
+impl From<String> for Endpoint {
+    fn from(url: String) -> Self {
+        Self::new(url)
+    }
+}
+
+/// The endpoint a [`ManagedClient`](super::ManagedClient) is connected to.
+///
+/// See [`ManagedClient::active_endpoint`](super::ManagedClient::active_endpoint).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ActiveEndpoint {
+    /// The URL of the endpoint.
+    pub url: String,
+    /// Whether the endpoint belongs to the primary group.
+    pub primary: bool,
+}
+
+/// Endpoints grouped by priority, with the failover state of the managed client.
+#[derive(Debug)]
+pub(crate) struct Endpoints {
+    endpoints: Vec<Endpoint>,
+    /// Indices into `endpoints`, grouped by ascending priority. The first tier is the primary one.
+    tiers: Vec<Vec<usize>>,
+    state: Mutex<EndpointsState>,
+}
+
+#[derive(Debug)]
+struct EndpointsState {
+    /// The tier the next connection attempt starts with.
+    tier: usize,
+    /// Smooth weighted round robin state, one per endpoint.
+    current_weights: Vec<i64>,
+    /// The active endpoint and since when it is active.
+    active: Option<(usize, Instant)>,
+}
+
+impl Endpoints {
+    /// Creates the endpoints.
+    ///
+    /// Fails if the list is empty or mixes endpoints with and without an explicit priority.
+    pub(crate) fn new(endpoints: Vec<Endpoint>) -> Result<Self, &'static str> {
+        if endpoints.is_empty() {
+            return Err("At least one endpoint is required");
+        }
+
+        let explicit = endpoints
+            .iter()
+            .filter(|endpoint| endpoint.priority.is_some())
+            .count();
+
+        if explicit != 0 && explicit != endpoints.len() {
+            return Err("Either all endpoints or none must have an explicit priority");
+        }
+
+        // Without explicit priorities, the list order is the priority.
+        let priority = |index: usize| endpoints[index].priority.unwrap_or(index as u32);
+
+        let mut indices: Vec<usize> = (0..endpoints.len()).collect();
+
+        // Stable: keeps the list order within the same priority.
+        indices.sort_by_key(|&index| priority(index));
+
+        let mut tiers: Vec<Vec<usize>> = Vec::new();
+
+        for index in indices {
+            match tiers.last_mut() {
+                Some(tier) if priority(tier[0]) == priority(index) => tier.push(index),
+                _ => tiers.push(vec![index]),
+            }
+        }
+
+        Ok(Self {
+            state: Mutex::new(EndpointsState {
+                tier: 0,
+                current_weights: vec![0; endpoints.len()],
+                active: None,
+            }),
+            endpoints,
+            tiers,
+        })
+    }
+
+    pub(crate) fn tiers(&self) -> usize {
+        self.tiers.len()
+    }
+
+    pub(crate) fn tier_len(&self, tier: usize) -> usize {
+        self.tiers[tier].len()
+    }
+
+    /// The tier the next connection attempt starts with.
+    pub(crate) fn current_tier(&self) -> usize {
+        self.state.lock().expect("Endpoints poisoned").tier
+    }
+
+    pub(crate) fn url(&self, index: usize) -> &str {
+        &self.endpoints[index].url
+    }
+
+    pub(crate) fn is_primary(&self, index: usize) -> bool {
+        self.tiers[0].contains(&index)
+    }
+
+    /// Picks the next endpoint of the given tier.
+    pub(crate) fn pick(&self, tier: usize) -> usize {
+        let mut state = self.state.lock().expect("Endpoints poisoned");
+
+        let group = &self.tiers[tier];
+        let weight = |index: usize| i64::from(self.endpoints[index].weight.max(1));
+        let total: i64 = group.iter().map(|&index| weight(index)).sum();
+
+        let mut picked = group[0];
+
+        for &index in group {
+            state.current_weights[index] += weight(index);
+
+            if state.current_weights[index] > state.current_weights[picked] {
+                picked = index;
+            }
+        }
+
+        state.current_weights[picked] -= total;
+
+        picked
+    }
+
+    /// Marks the endpoint as active.
+    pub(crate) fn activate(&self, tier: usize, index: usize) {
+        let mut state = self.state.lock().expect("Endpoints poisoned");
+
+        state.tier = tier;
+        state.active = Some((index, Instant::now()));
+    }
+
+    /// Returns the active endpoint.
+    pub(crate) fn active(&self) -> Option<ActiveEndpoint> {
+        let state = self.state.lock().expect("Endpoints poisoned");
+
+        state.active.map(|(index, _)| ActiveEndpoint {
+            url: self.url(index).to_owned(),
+            primary: self.is_primary(index),
+        })
+    }
+
+    /// Returns `true` if a secondary endpoint has been active for at least `stable`.
+    pub(crate) fn should_fail_back(&self, stable: Duration) -> bool {
+        let state = self.state.lock().expect("Endpoints poisoned");
+
+        state.tier != 0
+            && state
+                .active
+                .is_some_and(|(_, since)| since.elapsed() >= stable)
+    }
+
+    /// Restarts the stable period of the active endpoint after a failed fail back.
+    pub(crate) fn restart_stable_period(&self) {
+        let mut state = self.state.lock().expect("Endpoints poisoned");
+
+        if let Some((_, since)) = state.active.as_mut() {
+            *since = Instant::now();
+        }
+    }
+}
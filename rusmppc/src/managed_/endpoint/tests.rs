@@ -0,0 +1,64 @@
+use super::*;
+
+#[test]
+fn ordered_endpoints_form_one_tier_each() {
+    let endpoints = Endpoints::new(vec!["a".into(), "b".into(), "c".into()]).unwrap();
+
+    assert_eq!(endpoints.tiers, vec![vec![0], vec![1], vec![2]]);
+    assert!(endpoints.is_primary(0));
+    assert!(!endpoints.is_primary(1));
+}
+
+#[test]
+fn explicit_priorities_group_endpoints() {
+    let endpoints = Endpoints::new(vec![
+        Endpoint::secondary("c"),
+        Endpoint::primary("a"),
+        Endpoint::primary("b"),
+    ])
+    .unwrap();
+
+    assert_eq!(endpoints.tiers, vec![vec![1, 2], vec![0]]);
+}
+
+#[test]
+fn weighted_round_robin_respects_weights() {
+    let endpoints = Endpoints::new(vec![
+        Endpoint::primary("a").weight(3),
+        Endpoint::primary("b").weight(1),
+    ])
+    .unwrap();
+
+    let picks: Vec<usize> = (0..8).map(|_| endpoints.pick(0)).collect();
+
+    assert_eq!(picks.iter().filter(|&&index| index == 0).count(), 6);
+    assert_eq!(picks.iter().filter(|&&index| index == 1).count(), 2);
+}
+
+#[test]
+fn empty_endpoints() {
+    assert!(Endpoints::new(Vec::new()).is_err());
+}
+
+#[test]
+fn mixed_priorities_are_rejected() {
+    assert!(Endpoints::new(vec![Endpoint::primary("a"), "b".into()]).is_err());
+    assert!(Endpoints::new(vec!["a".into(), Endpoint::new("b").priority(0)]).is_err());
+}
+
+#[test]
+fn active_endpoint() {
+    let endpoints = Endpoints::new(vec!["a".into(), "b".into()]).unwrap();
+
+    assert_eq!(endpoints.active(), None);
+
+    endpoints.activate(1, 1);
+
+    assert_eq!(
+        endpoints.active(),
+        Some(ActiveEndpoint {
+            url: String::from("b"),
+            primary: false,
+        })
+    );
+}
@@ -16,7 +16,12 @@ use rusmpp::{
 use tokio::io::{AsyncRead, AsyncWrite, DuplexStream};
 use tokio_util::codec::Framed;
 
-use crate::{ConnectionBuilder, managed::ManagedEvent, tests::init_tracing};
+use crate::{
+    ConnectionBuilder,
+    error::Error,
+    managed::{ActiveEndpoint, ManagedEvent},
+    tests::init_tracing,
+};
 
 /// Server that binds successfully and echoes [`SubmitSmResp`]s.
 ///
@@ -284,3 +289,217 @@ async fn managed_client_auto_reconnect_interval_triggers_reconnection() {
     );
     assert!(connect_count.load(Ordering::SeqCst) >= 2);
 }
+
+/// Binds a listener on a free local port and serves every accepted connection with [`run_ok_server`].
+async fn spawn_tcp_server() -> (String, tokio::task::JoinHandle<()>) {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("Failed to bind listener");
+
+    let url = format!("smpp://{}", listener.local_addr().unwrap());
+
+    let handle = tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            tokio::spawn(run_ok_server(stream));
+        }
+    });
+
+    (url, handle)
+}
+
+/// Returns a URL of a local port nobody listens on.
+async fn unreachable_url() -> String {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("Failed to bind listener");
+
+    format!("smpp://{}", listener.local_addr().unwrap())
+}
+
+fn active_endpoint(url: &str, primary: bool) -> Option<ActiveEndpoint> {
+    Some(ActiveEndpoint {
+        url: url.to_owned(),
+        primary,
+    })
+}
+
+#[tokio::test]
+async fn managed_client_fails_over_to_secondary_endpoint() {
+    init_tracing();
+
+    let primary = unreachable_url().await;
+    let (secondary, _server) = spawn_tcp_server().await;
+
+    let (managed, _events) = ConnectionBuilder::new()
+        .managed()
+        .transceiver(BindTransceiver::default())
+        .no_backoff()
+        .max_retries(1)
+        .no_auto_reconnect_interval()
+        .connect_endpoints([primary.as_str(), secondary.as_str()])
+        .await
+        .expect("Failed to build managed client");
+
+    assert_eq!(
+        managed.active_endpoint(),
+        active_endpoint(&secondary, false)
+    );
+
+    let client = managed.get().await.expect("Failed to get client");
+
+    client
+        .submit_sm(SubmitSm::default())
+        .await
+        .expect("Failed to submit SM");
+}
+
+#[tokio::test]
+async fn managed_client_fails_back_to_primary_endpoint() {
+    init_tracing();
+
+    let primary = unreachable_url().await;
+    let (secondary, _server) = spawn_tcp_server().await;
+
+    let (managed, mut events) = ConnectionBuilder::new()
+        .managed()
+        .transceiver(BindTransceiver::default())
+        .no_backoff()
+        .max_retries(0)
+        .auto_reconnect_interval(Duration::from_millis(20))
+        .fail_back_after(Duration::from_millis(50))
+        .connect_endpoints([primary.clone(), secondary.clone()])
+        .await
+        .expect("Failed to build managed client");
+
+    assert_eq!(
+        managed.active_endpoint(),
+        active_endpoint(&secondary, false)
+    );
+
+    // Bring the primary endpoint up.
+    let listener = tokio::net::TcpListener::bind(primary.trim_start_matches("smpp://"))
+        .await
+        .expect("Failed to bind primary listener");
+
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            tokio::spawn(run_ok_server(stream));
+        }
+    });
+
+    tokio::time::timeout(Duration::from_secs(2), async {
+        while managed.active_endpoint() != active_endpoint(&primary, true) {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("Did not fail back in time");
+
+    // The replaced secondary connection must not be reported as a disconnect.
+    let next = tokio::time::timeout(Duration::from_millis(200), events.next()).await;
+
+    assert!(!matches!(next, Ok(Some(ManagedEvent::Disconnected))));
+}
+
+#[tokio::test]
+async fn managed_client_emits_endpoint_active_on_failover_and_fail_back() {
+    init_tracing();
+
+    let primary = unreachable_url().await;
+    let (secondary, _server) = spawn_tcp_server().await;
+
+    let (_managed, mut events) = ConnectionBuilder::new()
+        .managed()
+        .transceiver(BindTransceiver::default())
+        .no_backoff()
+        .max_retries(0)
+        .auto_reconnect_interval(Duration::from_millis(20))
+        .fail_back_after(Duration::from_millis(50))
+        .connect_endpoints([primary.clone(), secondary.clone()])
+        .await
+        .expect("Failed to build managed client");
+
+    let mut next_active_endpoint = async || {
+        tokio::time::timeout(Duration::from_secs(2), async {
+            loop {
+                match events.next().await {
+                    Some(ManagedEvent::EndpointActive(endpoint)) => return Some(endpoint),
+                    Some(_) => {}
+                    None => panic!("Events ended"),
+                }
+            }
+        })
+        .await
+        .expect("Timed out waiting for an active endpoint")
+    };
+
+    assert_eq!(
+        next_active_endpoint().await,
+        active_endpoint(&secondary, false)
+    );
+
+    // Bring the primary endpoint up.
+    let listener = tokio::net::TcpListener::bind(primary.trim_start_matches("smpp://"))
+        .await
+        .expect("Failed to bind primary listener");
+
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            tokio::spawn(run_ok_server(stream));
+        }
+    });
+
+    assert_eq!(
+        next_active_endpoint().await,
+        active_endpoint(&primary, true)
+    );
+}
+
+#[tokio::test]
+async fn managed_client_connect_endpoints_requires_an_endpoint() {
+    let result = ConnectionBuilder::new()
+        .managed()
+        .unbound()
+        .no_auto_reconnect_interval()
+        .connect_endpoints(Vec::<String>::new())
+        .await;
+
+    assert!(matches!(result, Err(crate::error::Error::Connect(_))));
+}
+
+#[tokio::test]
+async fn managed_client_shutdown_should_stop_reconnecting() {
+    init_tracing();
+
+    let (connector, connect_count) = counting_connector();
+
+    let (managed, mut events) = ConnectionBuilder::new()
+        .managed()
+        .transceiver(BindTransceiver::default())
+        .auto_reconnect_interval(Duration::from_millis(20))
+        .connect_fn(connector)
+        .await
+        .expect("Failed to build managed client");
+
+    assert!(matches!(events.next().await, Some(ManagedEvent::Connected)));
+    assert!(matches!(events.next().await, Some(ManagedEvent::Bound)));
+
+    let client = managed.get().await.expect("Failed to get client");
+
+    managed
+        .shutdown(Duration::from_secs(1))
+        .await
+        .expect("Failed to shut down");
+
+    assert!(client.is_closed());
+
+    assert!(matches!(
+        events.next().await,
+        Some(ManagedEvent::Disconnected)
+    ));
+
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    assert_eq!(connect_count.load(Ordering::SeqCst), 1);
+    assert!(matches!(managed.get().await, Err(Error::ShuttingDown)));
+}
@@ -0,0 +1,207 @@
+//! Connection metrics recorded through the `metrics` facade.
+//!
+//! Without the `metrics` feature, [`ConnectionMetrics`] and [`record_reconnect`] do nothing.
+
+use rusmpp::Command;
+
+#[cfg(feature = "metrics")]
+use std::{collections::HashMap, time::Instant};
+
+#[cfg(feature = "metrics")]
+use rusmpp::{CommandId, CommandStatus, encode::Length};
+
+#[cfg(all(test, feature = "metrics"))]
+mod tests;
+
+/// Counter of commands written to the server, labeled by `command_id`.
+#[cfg(feature = "metrics")]
+pub const COMMANDS_SENT: &str = "rusmppc_commands_sent_total";
+
+/// Counter of commands read from the server, labeled by `command_id` and `command_status`.
+#[cfg(feature = "metrics")]
+pub const COMMANDS_RECEIVED: &str = "rusmppc_commands_received_total";
+
+/// Histogram of the seconds between sending a request and receiving its response, labeled by the `command_id` of the response.
+#[cfg(feature = "metrics")]
+pub const RESPONSE_LATENCY: &str = "rusmppc_response_latency_seconds";
+
+/// Gauge of the requests waiting for a response.
+#[cfg(feature = "metrics")]
+pub const OUTSTANDING_REQUESTS: &str = "rusmppc_outstanding_requests";
+
+/// Counter of bytes written to the server.
+#[cfg(feature = "metrics")]
+pub const BYTES_SENT: &str = "rusmppc_bytes_sent_total";
+
+/// Counter of bytes read from the server.
+#[cfg(feature = "metrics")]
+pub const BYTES_RECEIVED: &str = "rusmppc_bytes_received_total";
+
+/// Counter of bind responses with an error status, labeled by `command_id` and `command_status`.
+#[cfg(feature = "metrics")]
+pub const BIND_FAILURES: &str = "rusmppc_bind_failures_total";
+
+/// Counter of reconnections of [`ManagedClient`](crate::managed::ManagedClient)s.
+#[cfg(feature = "metrics")]
+pub const RECONNECTS: &str = "rusmppc_reconnects_total";
+
+/// The name of the label identifying the connection of every metric.
+#[cfg(feature = "metrics")]
+pub const CONNECTION_LABEL: &str = "connection";
+
+/// Per connection metrics state.
+///
+/// Keeps the send time of every request waiting for a response, to record the response latency.
+#[derive(Debug)]
+pub(crate) struct ConnectionMetrics {
+    #[cfg(feature = "metrics")]
+    connection: metrics::SharedString,
+    #[cfg(feature = "metrics")]
+    sent_at: HashMap<u32, Instant>,
+}
+
+/// Returns the `connection` label of a connection to the given URL.
+///
+/// Only the scheme, host and port are kept, so that credentials or paths never end up in labels.
+#[cfg(any(feature = "tokio", feature = "wasm"))]
+pub(crate) fn url_label(url: &url::Url) -> String {
+    match (url.host_str(), url.port_or_known_default()) {
+        (Some(host), Some(port)) => format!("{}://{host}:{port}", url.scheme()),
+        (Some(host), None) => format!("{}://{host}", url.scheme()),
+        _ => url.scheme().to_owned(),
+    }
+}
+
+#[cfg(feature = "metrics")]
+fn command_length(command: &Command) -> u64 {
+    4 + command.length() as u64
+}
+
+#[cfg(feature = "metrics")]
+fn label(value: impl std::fmt::Debug) -> String {
+    format!("{value:?}")
+}
+
+#[cfg(feature = "metrics")]
+impl ConnectionMetrics {
+    pub(crate) fn new(connection: String) -> Self {
+        Self {
+            connection: connection.into(),
+            sent_at: HashMap::new(),
+        }
+    }
+
+    /// A command was written to the server.
+    pub(crate) fn sent(&mut self, command: &Command) {
+        metrics::counter!(
+            COMMANDS_SENT,
+            CONNECTION_LABEL => self.connection.clone(),
+            "command_id" => label(command.id())
+        )
+        .increment(1);
+        metrics::counter!(BYTES_SENT, CONNECTION_LABEL => self.connection.clone())
+            .increment(command_length(command));
+    }
+
+    /// The connection waits for the response of the request with the given sequence number.
+    pub(crate) fn registered(&mut self, sequence_number: u32) {
+        if self
+            .sent_at
+            .insert(sequence_number, Instant::now())
+            .is_none()
+        {
+            metrics::gauge!(OUTSTANDING_REQUESTS, CONNECTION_LABEL => self.connection.clone())
+                .increment(1);
+        }
+    }
+
+    /// The connection no longer waits for the response of the request with the given sequence number.
+    pub(crate) fn unregistered(&mut self, sequence_number: u32) -> Option<Instant> {
+        let sent_at = self.sent_at.remove(&sequence_number);
+
+        if sent_at.is_some() {
+            metrics::gauge!(OUTSTANDING_REQUESTS, CONNECTION_LABEL => self.connection.clone())
+                .decrement(1);
+        }
+
+        sent_at
+    }
+
+    /// A command was read from the server.
+    pub(crate) fn received(&mut self, command: &Command) {
+        let id = command.id();
+        let status = command.status();
+
+        metrics::counter!(
+            COMMANDS_RECEIVED,
+            CONNECTION_LABEL => self.connection.clone(),
+            "command_id" => label(id),
+            "command_status" => label(status)
+        )
+        .increment(1);
+        metrics::counter!(BYTES_RECEIVED, CONNECTION_LABEL => self.connection.clone())
+            .increment(command_length(command));
+
+        if matches!(
+            id,
+            CommandId::BindTransmitterResp
+                | CommandId::BindReceiverResp
+                | CommandId::BindTransceiverResp
+        ) && status != CommandStatus::EsmeRok
+        {
+            metrics::counter!(
+                BIND_FAILURES,
+                CONNECTION_LABEL => self.connection.clone(),
+                "command_id" => label(id),
+                "command_status" => label(status)
+            )
+            .increment(1);
+        }
+
+        if id.is_response() {
+            if let Some(sent_at) = self.unregistered(command.sequence_number()) {
+                metrics::histogram!(
+                    RESPONSE_LATENCY,
+                    CONNECTION_LABEL => self.connection.clone(),
+                    "command_id" => label(id)
+                )
+                .record(sent_at.elapsed());
+            }
+        }
+    }
+}
+
+#[cfg(feature = "metrics")]
+impl Drop for ConnectionMetrics {
+    fn drop(&mut self) {
+        if !self.sent_at.is_empty() {
+            metrics::gauge!(OUTSTANDING_REQUESTS, CONNECTION_LABEL => self.connection.clone())
+                .decrement(self.sent_at.len() as f64);
+        }
+    }
+}
+
+#[cfg(not(feature = "metrics"))]
+impl ConnectionMetrics {
+    pub(crate) fn new(_connection: String) -> Self {
+        Self {}
+    }
+
+    pub(crate) fn sent(&mut self, _command: &Command) {}
+
+    pub(crate) fn registered(&mut self, _sequence_number: u32) {}
+
+    pub(crate) fn unregistered(&mut self, _sequence_number: u32) {}
+
+    pub(crate) fn received(&mut self, _command: &Command) {}
+}
+
+/// A [`ManagedClient`](crate::managed::ManagedClient) reconnected, `connection` is the label of the new connection.
+#[cfg(feature = "tokio")]
+pub(crate) fn record_reconnect(connection: String) {
+    #[cfg(feature = "metrics")]
+    metrics::counter!(RECONNECTS, CONNECTION_LABEL => connection).increment(1);
+
+    #[cfg(not(feature = "metrics"))]
+    let _ = connection;
+}
@@ -0,0 +1,253 @@
+use std::time::Duration;
+
+use futures::{SinkExt, Stream, StreamExt};
+use rusmpp::{
+    Command, Pdu,
+    pdus::{BindReceiver, BindTransceiver, Outbind},
+    session::SessionState,
+    tokio_codec::CommandCodec,
+    types::COctetString,
+};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio_util::codec::Framed;
+
+use crate::{
+    Client, ConnectionBuilder,
+    error::Error,
+    event_::EventChannel,
+    runtime_::{Timeout, tokio::Tokio},
+};
+
+#[cfg(test)]
+mod tests;
+
+const TARGET: &str = "rusmppc::outbind";
+
+#[derive(Debug, Clone)]
+enum OutbindBindMode {
+    Receiver(BindReceiver),
+    Transceiver(BindTransceiver),
+}
+
+/// Builder for accepting an outbind connection before choosing how to bind.
+#[derive(Debug)]
+pub struct UnboundOutbindConnectionBuilder<E> {
+    builder: ConnectionBuilder<E, Tokio>,
+}
+
+impl<E: EventChannel> UnboundOutbindConnectionBuilder<E> {
+    pub(crate) const fn new(builder: ConnectionBuilder<E, Tokio>) -> Self {
+        Self { builder }
+    }
+
+    /// Binds as a receiver after receiving the [`Outbind`].
+    pub fn receiver(self, bind: BindReceiver) -> OutbindConnectionBuilder<E> {
+        OutbindConnectionBuilder::new(self.builder, OutbindBindMode::Receiver(bind))
+    }
+
+    /// Binds as a transceiver after receiving the [`Outbind`].
+    pub fn transceiver(self, bind: BindTransceiver) -> OutbindConnectionBuilder<E> {
+        OutbindConnectionBuilder::new(self.builder, OutbindBindMode::Transceiver(bind))
+    }
+}
+
+/// Builder for accepting an outbind connection from the `MC`.
+///
+/// The `MC` connects to the `ESME` and sends an [`Outbind`]. After verifying the `system_id` and `password` of the [`Outbind`],
+/// the client binds as a receiver or transceiver over the same connection.
+#[derive(Debug)]
+pub struct OutbindConnectionBuilder<E> {
+    builder: ConnectionBuilder<E, Tokio>,
+    bind: OutbindBindMode,
+    system_id: Option<COctetString<1, 16>>,
+    password: Option<COctetString<1, 9>>,
+    outbind_timeout: Option<Duration>,
+}
+
+impl<E: EventChannel> OutbindConnectionBuilder<E> {
+    const fn new(builder: ConnectionBuilder<E, Tokio>, bind: OutbindBindMode) -> Self {
+        Self {
+            builder,
+            bind,
+            system_id: None,
+            password: None,
+            outbind_timeout: Some(Duration::from_secs(30)),
+        }
+    }
+
+    /// Sets the expected `system_id` of the `MC`.
+    ///
+    /// If not set, any `system_id` is accepted.
+    pub fn system_id(mut self, system_id: COctetString<1, 16>) -> Self {
+        self.system_id = Some(system_id);
+        self
+    }
+
+    /// Sets the expected `password` of the `MC`.
+    ///
+    /// If not set, any `password` is accepted.
+    pub fn password(mut self, password: COctetString<1, 9>) -> Self {
+        self.password = Some(password);
+        self
+    }
+
+    /// Sets how long to wait for the [`Outbind`] after accepting a connection.
+    ///
+    /// Defaults to 30 seconds.
+    pub fn outbind_timeout(mut self, timeout: Duration) -> Self {
+        self.outbind_timeout = Some(timeout);
+        self
+    }
+
+    /// Waits for the [`Outbind`] without a timeout.
+    pub fn no_outbind_timeout(mut self) -> Self {
+        self.outbind_timeout = None;
+        self
+    }
+
+    /// Sets how long to wait for the [`Outbind`] after accepting a connection.
+    pub fn with_outbind_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.outbind_timeout = timeout;
+        self
+    }
+
+    /// Accepts the next connection on the listener and performs the outbind.
+    ///
+    /// See [`accepted`](Self::accepted) for more details.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Connect`] if accepting the connection fails, otherwise see [`accepted`](Self::accepted).
+    pub async fn accept(
+        self,
+        listener: &tokio::net::TcpListener,
+    ) -> Result<
+        (
+            Client<Tokio>,
+            impl Stream<Item = E::Event> + Unpin + 'static,
+        ),
+        Error,
+    > {
+        let (stream, addr) = listener.accept().await.map_err(Error::Connect)?;
+
+        tracing::debug!(target: TARGET, %addr, "Accepted connection");
+
+        self.accepted(stream).await
+    }
+
+    /// Performs the outbind on an already accepted connection.
+    ///
+    /// Waits for the [`Outbind`], verifies it and binds. On success, returns the same client and event stream as [`ConnectionBuilder::connect`].
+    ///
+    /// # Errors
+    ///
+    /// - [`Error::OutbindTimeout`] if no command is received within the [`outbind_timeout`](Self::outbind_timeout).
+    /// - [`Error::OutbindExpected`] if the first command is not an [`Outbind`].
+    /// - [`Error::OutbindRejected`] if the `system_id` or `password` do not match.
+    /// - Any error returned by binding.
+    ///
+    /// The connection is closed on error.
+    pub async fn accepted<S>(
+        self,
+        stream: S,
+    ) -> Result<
+        (
+            Client<Tokio>,
+            impl Stream<Item = E::Event> + Unpin + 'static,
+        ),
+        Error,
+    >
+    where
+        S: AsyncRead + AsyncWrite + Send + Unpin + 'static,
+    {
+        let mut framed = Framed::new(
+            stream,
+            CommandCodec::new().with_max_length(self.builder.max_command_length),
+        );
+
+        let outbind = match self.outbind_timeout {
+            None => read_outbind(&mut framed).await,
+            Some(timeout) => Tokio::timeout(timeout, read_outbind(&mut framed))
+                .await
+                .unwrap_or(Err(Error::OutbindTimeout { timeout })),
+        }
+        .and_then(|outbind| self.verify(outbind));
+
+        if let Err(err) = outbind {
+            tracing::warn!(target: TARGET, ?err, "Outbind failed");
+
+            let _ = SinkExt::<&Command>::close(&mut framed).await;
+
+            return Err(err);
+        }
+
+        let (client, events, connection) = self
+            .builder
+            .no_spawn()
+            .raw_with_session_state(framed, SessionState::Outbound);
+
+        Tokio::spawn(connection);
+
+        let bound = match self.bind {
+            OutbindBindMode::Receiver(bind) => client.bind_receiver(bind).await.map(|_| ()),
+            OutbindBindMode::Transceiver(bind) => client.bind_transceiver(bind).await.map(|_| ()),
+        };
+
+        if let Err(err) = bound {
+            let _ = client.close().await;
+
+            return Err(err);
+        }
+
+        tracing::debug!(target: TARGET, "Bound");
+
+        Ok((client, events))
+    }
+
+    fn verify(&self, outbind: Outbind) -> Result<(), Error> {
+        let system_id_matches = self
+            .system_id
+            .as_ref()
+            .is_none_or(|system_id| *system_id == outbind.system_id);
+
+        let password_matches = self
+            .password
+            .as_ref()
+            .is_none_or(|password| *password == outbind.password);
+
+        if !(system_id_matches && password_matches) {
+            return Err(Error::OutbindRejected {
+                system_id: outbind.system_id.to_string(),
+            });
+        }
+
+        tracing::debug!(target: TARGET, system_id=%outbind.system_id, "Received outbind");
+
+        Ok(())
+    }
+}
+
+async fn read_outbind<S>(framed: &mut Framed<S, CommandCodec>) -> Result<Outbind, Error>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let command = match framed.next().await {
+        Some(Ok(command)) => command,
+        Some(Err(err)) => return Err(Error::from(err)),
+        None => return Err(Error::UnexpectedEndOfStream),
+    };
+
+    match command.pdu() {
+        Some(Pdu::Outbind(outbind)) => Ok(outbind.clone()),
+        _ => Err(Error::OutbindExpected {
+            command: Box::new(command),
+        }),
+    }
+}
+
+impl<E: EventChannel> ConnectionBuilder<E, Tokio> {
+    /// Creates an outbind connection builder that waits for the `MC` to connect and send an [`Outbind`].
+    pub fn outbind(self) -> UnboundOutbindConnectionBuilder<E> {
+        UnboundOutbindConnectionBuilder::new(self)
+    }
+}
@@ -0,0 +1,186 @@
+use std::{str::FromStr, time::Duration};
+
+use futures::{SinkExt, StreamExt};
+use rusmpp::{
+    Command, CommandId, CommandStatus, Pdu,
+    pdus::{
+        BindReceiver, BindReceiverResp, BindTransceiver, BindTransceiverResp, DeliverSm, Outbind,
+    },
+    tokio_codec::CommandCodec,
+    types::COctetString,
+};
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    net::{TcpListener, TcpStream},
+};
+use tokio_util::codec::Framed;
+
+use crate::{ConnectionBuilder, error::Error, event::Event, tests::init_tracing};
+
+fn outbind(system_id: &str, password: &str) -> Command {
+    Command::builder()
+        .status(CommandStatus::EsmeRok)
+        .sequence_number(1)
+        .pdu(Outbind::new(
+            COctetString::from_str(system_id).unwrap(),
+            COctetString::from_str(password).unwrap(),
+        ))
+}
+
+/// `MC` that sends the given first command, answers the bind and then delivers a short message.
+async fn run_mc<S: AsyncRead + AsyncWrite + Send + Unpin + 'static>(stream: S, first: Command) {
+    let mut framed = Framed::new(stream, CommandCodec::new());
+
+    framed.send(first).await.expect("Failed to send command");
+
+    while let Some(Ok(command)) = framed.next().await {
+        let pdu: Pdu = match command.id() {
+            CommandId::BindReceiver => BindReceiverResp::default().into(),
+            CommandId::BindTransceiver => BindTransceiverResp::default().into(),
+            CommandId::EnquireLink => Pdu::EnquireLinkResp,
+            CommandId::Unbind => Pdu::UnbindResp,
+            _ => continue,
+        };
+
+        let bound = matches!(
+            command.id(),
+            CommandId::BindReceiver | CommandId::BindTransceiver
+        );
+
+        let response = Command::builder()
+            .status(CommandStatus::EsmeRok)
+            .sequence_number(command.sequence_number())
+            .pdu(pdu);
+
+        if framed.send(response).await.is_err() {
+            break;
+        }
+
+        if bound {
+            let deliver_sm = Command::builder()
+                .status(CommandStatus::EsmeRok)
+                .sequence_number(2)
+                .pdu(DeliverSm::default());
+
+            if framed.send(deliver_sm).await.is_err() {
+                break;
+            }
+        }
+    }
+}
+
+#[tokio::test]
+async fn outbind_should_bind_receiver() {
+    init_tracing();
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        let stream = TcpStream::connect(addr).await.unwrap();
+
+        run_mc(stream, outbind("mc", "secret")).await;
+    });
+
+    let (client, mut events) = ConnectionBuilder::new()
+        .outbind()
+        .receiver(BindReceiver::default())
+        .system_id(COctetString::from_str("mc").unwrap())
+        .password(COctetString::from_str("secret").unwrap())
+        .accept(&listener)
+        .await
+        .expect("Failed to accept outbind");
+
+    while let Some(event) = events.next().await {
+        if let Event::Incoming(command) = event {
+            assert_eq!(command.id(), CommandId::DeliverSm);
+
+            break;
+        }
+    }
+
+    client.close().await.expect("Failed to close");
+}
+
+#[tokio::test]
+async fn outbind_should_bind_transceiver_on_accepted_stream() {
+    init_tracing();
+
+    let (server, client) = tokio::io::duplex(4096);
+
+    tokio::spawn(run_mc(server, outbind("mc", "secret")));
+
+    let (client, _events) = ConnectionBuilder::new()
+        .outbind()
+        .transceiver(BindTransceiver::default())
+        .accepted(client)
+        .await
+        .expect("Failed to accept outbind");
+
+    client.unbind().await.expect("Failed to unbind");
+}
+
+#[tokio::test]
+async fn outbind_with_wrong_password_should_be_rejected() {
+    init_tracing();
+
+    let (server, client) = tokio::io::duplex(4096);
+
+    tokio::spawn(run_mc(server, outbind("mc", "wrong")));
+
+    let err = ConnectionBuilder::new()
+        .outbind()
+        .receiver(BindReceiver::default())
+        .system_id(COctetString::from_str("mc").unwrap())
+        .password(COctetString::from_str("secret").unwrap())
+        .accepted(client)
+        .await
+        .err()
+        .expect("Expected error");
+
+    assert!(matches!(err, Error::OutbindRejected { system_id } if system_id == "mc"));
+}
+
+#[tokio::test]
+async fn outbind_expected_as_first_command() {
+    init_tracing();
+
+    let (server, client) = tokio::io::duplex(4096);
+
+    let first = Command::builder()
+        .status(CommandStatus::EsmeRok)
+        .sequence_number(1)
+        .pdu(Pdu::EnquireLink);
+
+    tokio::spawn(run_mc(server, first));
+
+    let err = ConnectionBuilder::new()
+        .outbind()
+        .receiver(BindReceiver::default())
+        .accepted(client)
+        .await
+        .err()
+        .expect("Expected error");
+
+    assert!(
+        matches!(err, Error::OutbindExpected { command } if command.id() == CommandId::EnquireLink)
+    );
+}
+
+#[tokio::test]
+async fn outbind_should_time_out() {
+    init_tracing();
+
+    let (_server, client) = tokio::io::duplex(4096);
+
+    let err = ConnectionBuilder::new()
+        .outbind()
+        .receiver(BindReceiver::default())
+        .outbind_timeout(Duration::from_millis(50))
+        .accepted(client)
+        .await
+        .err()
+        .expect("Expected error");
+
+    assert!(matches!(err, Error::OutbindTimeout { .. }));
+}
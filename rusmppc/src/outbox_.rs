@@ -0,0 +1,746 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Debug,
+    fs::{File, OpenOptions},
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use futures::{Stream, StreamExt};
+use rusmpp::{Command, CommandStatus, Pdu, pdus::SubmitSm, tokio_codec::CommandCodec};
+use tokio_util::{
+    bytes::{Buf, BufMut, BytesMut},
+    codec::{Decoder, Encoder},
+};
+
+use crate::{
+    error::Error,
+    managed_::{ManagedClient, ManagedEvent},
+    runtime_::tokio::Tokio,
+};
+
+#[cfg(test)]
+mod tests;
+
+const TARGET: &str = "rusmppc::outbox";
+
+/// The default number of outcomes kept by [`FileOutboxStore::compact`].
+pub const DEFAULT_OUTCOME_RETENTION: usize = 100_000;
+
+/// A submission recorded in an [`OutboxStore`].
+#[derive(Debug, Clone)]
+pub struct OutboxEntry {
+    key: String,
+    submit_sm: SubmitSm,
+}
+
+impl OutboxEntry {
+    /// Creates a new [`OutboxEntry`].
+    pub fn new(key: impl Into<String>, submit_sm: SubmitSm) -> Self {
+        Self {
+            key: key.into(),
+            submit_sm,
+        }
+    }
+
+    /// Returns the idempotency key.
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Returns the submitted [`SubmitSm`].
+    pub const fn submit_sm(&self) -> &SubmitSm {
+        &self.submit_sm
+    }
+
+    /// Consumes the entry and returns the idempotency key and the [`SubmitSm`].
+    pub fn into_parts(self) -> (String, SubmitSm) {
+        (self.key, self.submit_sm)
+    }
+}
+
+/// The final outcome of a submission.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OutboxOutcome {
+    /// The server accepted the submission.
+    Accepted {
+        /// The `message_id` assigned by the server.
+        message_id: String,
+    },
+    /// The server rejected the submission with a non retryable status.
+    Rejected {
+        /// The status of the response.
+        status: CommandStatus,
+    },
+}
+
+/// The recorded status of a submission.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OutboxStatus {
+    /// The submission has no outcome yet and is replayed by [`Outbox::replay`].
+    Pending,
+    /// The submission has a final outcome and is never submitted again.
+    Completed(OutboxOutcome),
+}
+
+/// Durable storage for the submissions of an [`Outbox`], keyed by idempotency key.
+///
+/// Implementations must persist [`insert`](Self::insert) and [`complete`](Self::complete) before returning,
+/// so that pending submissions survive a restart of the process.
+pub trait OutboxStore: Send + Sync + 'static {
+    /// Records a new pending submission.
+    fn insert(&self, entry: &OutboxEntry) -> io::Result<()>;
+
+    /// Records the outcome of the submission with the given key.
+    fn complete(&self, key: &str, outcome: &OutboxOutcome) -> io::Result<()>;
+
+    /// Returns the status of the submission with the given key, if known.
+    fn status(&self, key: &str) -> io::Result<Option<OutboxStatus>>;
+
+    /// Returns the pending submissions in insertion order.
+    fn pending(&self) -> io::Result<Vec<OutboxEntry>>;
+}
+
+/// Submissions of an [`OutboxStore`] indexed by key.
+#[derive(Debug, Default)]
+struct Records {
+    // `None` once completed, the submission is no longer needed.
+    submissions: HashMap<String, (u64, Option<SubmitSm>)>,
+    // With the order of completion.
+    outcomes: HashMap<String, (u64, OutboxOutcome)>,
+    next: u64,
+}
+
+impl Records {
+    fn insert(&mut self, entry: &OutboxEntry) {
+        let order = self.next;
+
+        self.next += 1;
+
+        self.submissions
+            .insert(entry.key.clone(), (order, Some(entry.submit_sm.clone())));
+    }
+
+    fn complete(&mut self, key: &str, outcome: OutboxOutcome) {
+        if let Some((_, submit_sm)) = self.submissions.get_mut(key) {
+            *submit_sm = None;
+        }
+
+        let order = self.next;
+
+        self.next += 1;
+
+        self.outcomes.insert(key.to_owned(), (order, outcome));
+    }
+
+    fn status(&self, key: &str) -> Option<OutboxStatus> {
+        if let Some((_, outcome)) = self.outcomes.get(key) {
+            return Some(OutboxStatus::Completed(outcome.clone()));
+        }
+
+        self.submissions
+            .contains_key(key)
+            .then_some(OutboxStatus::Pending)
+    }
+
+    fn pending(&self) -> Vec<OutboxEntry> {
+        let mut pending = self
+            .submissions
+            .iter()
+            .filter(|(key, _)| !self.outcomes.contains_key(*key))
+            .filter_map(|(key, (order, submit_sm))| {
+                submit_sm
+                    .as_ref()
+                    .map(|submit_sm| (*order, OutboxEntry::new(key.clone(), submit_sm.clone())))
+            })
+            .collect::<Vec<_>>();
+
+        pending.sort_by_key(|(order, _)| *order);
+
+        pending.into_iter().map(|(_, entry)| entry).collect()
+    }
+
+    /// Returns the `retain` most recent outcomes in completion order.
+    fn recent_outcomes(&self, retain: usize) -> Vec<(&str, &OutboxOutcome)> {
+        let mut outcomes = self
+            .outcomes
+            .iter()
+            .map(|(key, (order, outcome))| (*order, key.as_str(), outcome))
+            .collect::<Vec<_>>();
+
+        outcomes.sort_by_key(|(order, _, _)| *order);
+
+        let skip = outcomes.len().saturating_sub(retain);
+
+        outcomes
+            .into_iter()
+            .skip(skip)
+            .map(|(_, key, outcome)| (key, outcome))
+            .collect()
+    }
+}
+
+/// An [`OutboxStore`] that keeps submissions in memory.
+///
+/// Submissions do not survive a restart of the process, but are still replayed after reconnecting.
+#[derive(Debug, Default)]
+pub struct InMemoryOutboxStore {
+    records: Mutex<Records>,
+}
+
+impl InMemoryOutboxStore {
+    /// Creates a new empty [`InMemoryOutboxStore`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl OutboxStore for InMemoryOutboxStore {
+    fn insert(&self, entry: &OutboxEntry) -> io::Result<()> {
+        self.records
+            .lock()
+            .expect("Outbox store poisoned")
+            .insert(entry);
+
+        Ok(())
+    }
+
+    fn complete(&self, key: &str, outcome: &OutboxOutcome) -> io::Result<()> {
+        self.records
+            .lock()
+            .expect("Outbox store poisoned")
+            .complete(key, outcome.clone());
+
+        Ok(())
+    }
+
+    fn status(&self, key: &str) -> io::Result<Option<OutboxStatus>> {
+        Ok(self
+            .records
+            .lock()
+            .expect("Outbox store poisoned")
+            .status(key))
+    }
+
+    fn pending(&self) -> io::Result<Vec<OutboxEntry>> {
+        Ok(self
+            .records
+            .lock()
+            .expect("Outbox store poisoned")
+            .pending())
+    }
+}
+
+const RECORD_INSERTED: u8 = 1;
+const RECORD_ACCEPTED: u8 = 2;
+const RECORD_REJECTED: u8 = 3;
+
+/// An [`OutboxStore`] backed by an append-only file.
+///
+/// Every insert and outcome is appended as a record and synced to disk before returning.
+/// The file is read on [`open`](Self::open) to restore the submissions. A record cut short by a crash is ignored.
+///
+/// The file grows with every submission. Use [`compact`](Self::compact) to rewrite it without the submissions that already have an outcome
+/// and without the oldest outcomes beyond the [`outcome_retention`](Self::outcome_retention).
+#[derive(Debug)]
+pub struct FileOutboxStore {
+    path: PathBuf,
+    outcome_retention: usize,
+    inner: Mutex<FileOutboxStoreInner>,
+}
+
+#[derive(Debug)]
+struct FileOutboxStoreInner {
+    file: File,
+    records: Records,
+}
+
+impl FileOutboxStore {
+    /// Opens or creates the store at the given path.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(&path)?;
+
+        let mut bytes = Vec::new();
+
+        file.read_to_end(&mut bytes)?;
+
+        let (records, valid) = read_records(&bytes);
+
+        if valid < bytes.len() {
+            // Drop the incomplete record, so that new records are appended after the last complete one.
+            file.set_len(valid as u64)?;
+        }
+
+        tracing::debug!(target: TARGET, path=%path.display(), pending=records.pending().len(), "Opened outbox store");
+
+        Ok(Self {
+            path,
+            outcome_retention: DEFAULT_OUTCOME_RETENTION,
+            inner: Mutex::new(FileOutboxStoreInner { file, records }),
+        })
+    }
+
+    /// Sets the number of outcomes kept by [`compact`](Self::compact), the most recent ones are kept.
+    ///
+    /// A key whose outcome was dropped is forgotten: submitting it again sends it again.
+    ///
+    /// Defaults to [`DEFAULT_OUTCOME_RETENTION`].
+    pub const fn outcome_retention(mut self, outcomes: usize) -> Self {
+        self.outcome_retention = outcomes;
+        self
+    }
+
+    /// Rewrites the file, keeping only the pending submissions and the most recent outcomes up to the [`outcome_retention`](Self::outcome_retention).
+    pub fn compact(&self) -> io::Result<()> {
+        let mut inner = self.inner.lock().expect("Outbox store poisoned");
+
+        let mut bytes = BytesMut::new();
+
+        let outcomes = inner.records.recent_outcomes(self.outcome_retention);
+        let dropped = inner.records.outcomes.len() - outcomes.len();
+
+        for (key, outcome) in outcomes {
+            write_outcome(&mut bytes, key, outcome);
+        }
+
+        for entry in inner.records.pending() {
+            write_entry(&mut bytes, &entry)?;
+        }
+
+        let mut tmp = self.path.clone().into_os_string();
+        tmp.push(".tmp");
+
+        {
+            let mut file = File::create(&tmp)?;
+
+            file.write_all(&bytes)?;
+            file.sync_all()?;
+        }
+
+        std::fs::rename(&tmp, &self.path)?;
+
+        inner.file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .open(&self.path)?;
+
+        // Forget the dropped outcomes and the submissions that already have an outcome.
+        inner.records = read_records(&bytes).0;
+
+        tracing::debug!(target: TARGET, path=%self.path.display(), dropped, "Compacted outbox store");
+
+        Ok(())
+    }
+
+    fn append(&self, bytes: &[u8], apply: impl FnOnce(&mut Records)) -> io::Result<()> {
+        let mut inner = self.inner.lock().expect("Outbox store poisoned");
+
+        inner.file.write_all(bytes)?;
+        inner.file.sync_data()?;
+
+        apply(&mut inner.records);
+
+        Ok(())
+    }
+}
+
+impl OutboxStore for FileOutboxStore {
+    fn insert(&self, entry: &OutboxEntry) -> io::Result<()> {
+        let mut bytes = BytesMut::new();
+
+        write_entry(&mut bytes, entry)?;
+
+        self.append(&bytes, |records| records.insert(entry))
+    }
+
+    fn complete(&self, key: &str, outcome: &OutboxOutcome) -> io::Result<()> {
+        let mut bytes = BytesMut::new();
+
+        write_outcome(&mut bytes, key, outcome);
+
+        self.append(&bytes, |records| records.complete(key, outcome.clone()))
+    }
+
+    fn status(&self, key: &str) -> io::Result<Option<OutboxStatus>> {
+        Ok(self
+            .inner
+            .lock()
+            .expect("Outbox store poisoned")
+            .records
+            .status(key))
+    }
+
+    fn pending(&self) -> io::Result<Vec<OutboxEntry>> {
+        Ok(self
+            .inner
+            .lock()
+            .expect("Outbox store poisoned")
+            .records
+            .pending())
+    }
+}
+
+// Record layout: tag (u8), key length (u16), key, then
+// - inserted: the submit_sm encoded as a command
+// - accepted: message_id length (u16), message_id
+// - rejected: command status (u32)
+
+fn write_key(dst: &mut BytesMut, tag: u8, key: &str) {
+    dst.put_u8(tag);
+    dst.put_u16(key.len() as u16);
+    dst.put_slice(key.as_bytes());
+}
+
+fn write_entry(dst: &mut BytesMut, entry: &OutboxEntry) -> io::Result<()> {
+    if entry.key.len() > u16::MAX as usize {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Idempotency key too long",
+        ));
+    }
+
+    write_key(dst, RECORD_INSERTED, &entry.key);
+
+    let command = Command::builder()
+        .status(CommandStatus::EsmeRok)
+        .sequence_number(1)
+        .pdu(entry.submit_sm.clone());
+
+    CommandCodec::new()
+        .without_max_length()
+        .encode(&command, dst)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+fn write_outcome(dst: &mut BytesMut, key: &str, outcome: &OutboxOutcome) {
+    match outcome {
+        OutboxOutcome::Accepted { message_id } => {
+            write_key(dst, RECORD_ACCEPTED, key);
+
+            dst.put_u16(message_id.len() as u16);
+            dst.put_slice(message_id.as_bytes());
+        }
+        OutboxOutcome::Rejected { status } => {
+            write_key(dst, RECORD_REJECTED, key);
+
+            dst.put_u32(u32::from(*status));
+        }
+    }
+}
+
+/// Returns the records and the length of the complete records.
+fn read_records(bytes: &[u8]) -> (Records, usize) {
+    let mut records = Records::default();
+    let mut src = BytesMut::from(bytes);
+
+    while !src.is_empty() {
+        if read_record(&mut src, &mut records).is_none() {
+            tracing::warn!(target: TARGET, remaining=src.len(), "Ignoring incomplete outbox record");
+
+            break;
+        }
+    }
+
+    (records, bytes.len() - src.len())
+}
+
+fn read_record(src: &mut BytesMut, records: &mut Records) -> Option<()> {
+    let mut peek = &src[..];
+
+    if peek.remaining() < 3 {
+        return None;
+    }
+
+    let tag = peek.get_u8();
+    let key_len = peek.get_u16() as usize;
+
+    if peek.remaining() < key_len {
+        return None;
+    }
+
+    let key = String::from_utf8(peek[..key_len].to_vec()).ok()?;
+
+    peek.advance(key_len);
+
+    let consumed = src.len() - peek.remaining();
+
+    match tag {
+        RECORD_INSERTED => {
+            let mut body = BytesMut::from(peek);
+
+            let command = CommandCodec::new()
+                .without_max_length()
+                .decode(&mut body)
+                .ok()??;
+
+            let Some(Pdu::SubmitSm(submit_sm)) = command.into_parts().raw().3 else {
+                return None;
+            };
+
+            src.advance(src.len() - body.len());
+
+            records.insert(&OutboxEntry::new(key, submit_sm));
+        }
+        RECORD_ACCEPTED => {
+            if peek.remaining() < 2 {
+                return None;
+            }
+
+            let len = peek.get_u16() as usize;
+
+            if peek.remaining() < len {
+                return None;
+            }
+
+            let message_id = String::from_utf8(peek[..len].to_vec()).ok()?;
+
+            src.advance(consumed + 2 + len);
+
+            records.complete(&key, OutboxOutcome::Accepted { message_id });
+        }
+        RECORD_REJECTED => {
+            if peek.remaining() < 4 {
+                return None;
+            }
+
+            let status = CommandStatus::from(peek.get_u32());
+
+            src.advance(consumed + 4);
+
+            records.complete(&key, OutboxOutcome::Rejected { status });
+        }
+        _ => return None,
+    }
+
+    Some(())
+}
+
+/// Outbound queue with at-least-once submission on top of a [`ManagedClient`].
+///
+/// Every submission is recorded in an [`OutboxStore`] under an idempotency key before it is sent, and its outcome is recorded when the response arrives.
+/// Submissions without an outcome, e.g. because the connection dropped or the process restarted, are replayed by [`replay`](Self::replay).
+///
+/// A key with a recorded outcome is never submitted again, so the same logical message can be handed to the outbox repeatedly.
+/// A submission whose response was lost is sent again on replay, which may deliver it twice.
+///
+/// Store operations run on the blocking thread pool, since stores persist synchronously.
+#[derive(Clone)]
+pub struct Outbox {
+    inner: Arc<OutboxInner>,
+}
+
+struct OutboxInner {
+    client: ManagedClient,
+    store: Arc<dyn OutboxStore>,
+    // Keys currently being submitted by this outbox.
+    in_flight: Mutex<HashSet<String>>,
+}
+
+impl Debug for Outbox {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Outbox")
+            .field("client", &self.inner.client)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Removes the key from the in flight keys on drop.
+struct InFlightGuard<'a> {
+    in_flight: &'a Mutex<HashSet<String>>,
+    key: String,
+}
+
+impl Drop for InFlightGuard<'_> {
+    fn drop(&mut self) {
+        self.in_flight
+            .lock()
+            .expect("Outbox poisoned")
+            .remove(&self.key);
+    }
+}
+
+impl Outbox {
+    /// Creates a new [`Outbox`] submitting through the given [`ManagedClient`].
+    pub fn new(client: ManagedClient, store: impl OutboxStore) -> Self {
+        Self {
+            inner: Arc::new(OutboxInner {
+                client,
+                store: Arc::new(store),
+                in_flight: Mutex::new(HashSet::new()),
+            }),
+        }
+    }
+
+    /// Returns the recorded status of the submission with the given key.
+    pub fn status(&self, key: &str) -> Result<Option<OutboxStatus>, Error> {
+        self.inner.store.status(key).map_err(Error::Outbox)
+    }
+
+    /// Runs a store operation on the blocking thread pool, stores persist synchronously.
+    async fn store<T, F>(&self, f: F) -> Result<T, Error>
+    where
+        T: Send + 'static,
+        F: FnOnce(&dyn OutboxStore) -> io::Result<T> + Send + 'static,
+    {
+        let store = self.inner.store.clone();
+
+        Tokio::spawn_blocking(move || f(store.as_ref()))
+            .await
+            .map_err(|err| Error::Outbox(io::Error::other(err)))?
+            .map_err(Error::Outbox)
+    }
+
+    /// Records and submits a [`SubmitSm`] under the given idempotency key.
+    ///
+    /// If the key already has an outcome, the recorded outcome is returned and nothing is sent.
+    ///
+    /// # Errors
+    ///
+    /// - [`Error::Outbox`] if the store fails.
+    /// - [`Error::OutboxInFlight`] if a submission with the same key is in flight.
+    /// - Any error returned by submitting. The submission stays pending and is sent again by [`replay`](Self::replay).
+    pub async fn submit(
+        &self,
+        key: impl Into<String>,
+        submit_sm: SubmitSm,
+    ) -> Result<OutboxOutcome, Error> {
+        let entry = OutboxEntry::new(key, submit_sm);
+
+        let _guard = self.enter(entry.key())?;
+
+        let key = entry.key().to_owned();
+
+        match self.store(move |store| store.status(&key)).await? {
+            Some(OutboxStatus::Completed(outcome)) => {
+                tracing::debug!(target: TARGET, key=entry.key(), ?outcome, "Already completed");
+
+                return Ok(outcome);
+            }
+            Some(OutboxStatus::Pending) => {}
+            None => {
+                let entry = entry.clone();
+
+                self.store(move |store| store.insert(&entry)).await?
+            }
+        }
+
+        self.deliver(entry).await
+    }
+
+    /// Submits all pending submissions in insertion order.
+    ///
+    /// Returns the number of submissions that got an outcome. Stops at the first error, the remaining submissions stay pending.
+    /// Submissions in flight are skipped.
+    pub async fn replay(&self) -> Result<usize, Error> {
+        let pending = self.store(|store| store.pending()).await?;
+
+        tracing::debug!(target: TARGET, pending=pending.len(), "Replaying");
+
+        let mut completed = 0;
+
+        for entry in pending {
+            let Ok(_guard) = self.enter(entry.key()) else {
+                continue;
+            };
+
+            self.deliver(entry).await?;
+
+            completed += 1;
+        }
+
+        Ok(completed)
+    }
+
+    /// Wraps the events of the [`ManagedClient`] to [`replay`](Self::replay) pending submissions every time the client is bound.
+    ///
+    /// The replay runs in the background and the events are passed through unchanged.
+    pub fn replay_on_bound<S, E>(
+        &self,
+        events: S,
+    ) -> impl Stream<Item = ManagedEvent<E>> + use<S, E>
+    where
+        S: Stream<Item = ManagedEvent<E>>,
+    {
+        let outbox = self.clone();
+
+        events.inspect(move |event| {
+            if let ManagedEvent::Bound = event {
+                let outbox = outbox.clone();
+
+                Tokio::spawn(async move {
+                    match outbox.replay().await {
+                        Ok(completed) => {
+                            tracing::debug!(target: TARGET, completed, "Replayed");
+                        }
+                        Err(err) => {
+                            tracing::warn!(target: TARGET, ?err, "Replay failed");
+                        }
+                    }
+                });
+            }
+        })
+    }
+
+    fn enter(&self, key: &str) -> Result<InFlightGuard<'_>, Error> {
+        let mut in_flight = self.inner.in_flight.lock().expect("Outbox poisoned");
+
+        if !in_flight.insert(key.to_owned()) {
+            return Err(Error::OutboxInFlight {
+                key: key.to_owned(),
+            });
+        }
+
+        Ok(InFlightGuard {
+            in_flight: &self.inner.in_flight,
+            key: key.to_owned(),
+        })
+    }
+
+    async fn deliver(&self, entry: OutboxEntry) -> Result<OutboxOutcome, Error> {
+        let (key, submit_sm) = entry.into_parts();
+
+        let client = self.inner.client.get().await?;
+
+        let outcome = match client.submit_sm(submit_sm).await {
+            Ok(response) => OutboxOutcome::Accepted {
+                message_id: response.message_id().to_string(),
+            },
+            Err(Error::UnexpectedResponse { response }) if !is_retryable(response.status()) => {
+                OutboxOutcome::Rejected {
+                    status: response.status(),
+                }
+            }
+            Err(err) => {
+                tracing::debug!(target: TARGET, key, ?err, "Submission stays pending");
+
+                return Err(err);
+            }
+        };
+
+        let outcome = {
+            let key = key.clone();
+
+            self.store(move |store| store.complete(&key, &outcome).map(|_| outcome))
+                .await?
+        };
+
+        tracing::debug!(target: TARGET, key, ?outcome, "Completed");
+
+        Ok(outcome)
+    }
+}
+
+/// Statuses after which the submission is kept pending.
+fn is_retryable(status: CommandStatus) -> bool {
+    matches!(
+        status,
+        CommandStatus::EsmeRthrottled | CommandStatus::EsmeRmsgqful | CommandStatus::EsmeRsyserr
+    )
+}
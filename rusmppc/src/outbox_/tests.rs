@@ -0,0 +1,422 @@
+use std::{
+    path::PathBuf,
+    str::FromStr,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use futures::{SinkExt, StreamExt};
+use rusmpp::{
+    Command, CommandId, CommandStatus, Pdu,
+    pdus::{BindTransceiver, BindTransceiverResp, SubmitSm, SubmitSmResp},
+    tokio_codec::CommandCodec,
+    types::{COctetString, OctetString},
+};
+use tokio::{io::DuplexStream, sync::mpsc};
+use tokio_util::codec::Framed;
+
+use super::*;
+use crate::{ConnectionBuilder, tests::init_tracing};
+
+fn temp_path() -> PathBuf {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    std::env::temp_dir().join(format!(
+        "rusmppc-outbox-{}-{}",
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    ))
+}
+
+fn submit_sm(text: &str) -> SubmitSm {
+    SubmitSm::builder()
+        .short_message(OctetString::from_str(text).unwrap())
+        .build()
+}
+
+/// Server that answers [`SubmitSm`]s with the short message as `message_id` and forwards the short messages through the channel.
+///
+/// Short messages starting with `reject` are answered with [`CommandStatus::EsmeRinvdstadr`].
+async fn run_server(stream: DuplexStream, received: mpsc::UnboundedSender<String>) {
+    let mut framed = Framed::new(stream, CommandCodec::new());
+
+    while let Some(Ok(command)) = framed.next().await {
+        let sequence_number = command.sequence_number();
+
+        let (status, pdu): (CommandStatus, Pdu) = match command.id() {
+            CommandId::BindTransceiver => (
+                CommandStatus::EsmeRok,
+                BindTransceiverResp::default().into(),
+            ),
+            CommandId::SubmitSm => {
+                let Some(Pdu::SubmitSm(sm)) = command.into_parts().raw().3 else {
+                    unreachable!()
+                };
+
+                let text = sm.short_message().to_str().unwrap().to_owned();
+
+                let _ = received.send(text.clone());
+
+                if text.starts_with("reject") {
+                    (
+                        CommandStatus::EsmeRinvdstadr,
+                        SubmitSmResp::default().into(),
+                    )
+                } else {
+                    (
+                        CommandStatus::EsmeRok,
+                        SubmitSmResp::builder()
+                            .message_id(COctetString::from_str(&text).unwrap())
+                            .build()
+                            .into(),
+                    )
+                }
+            }
+            CommandId::EnquireLink => (CommandStatus::EsmeRok, Pdu::EnquireLinkResp),
+            CommandId::Unbind => (CommandStatus::EsmeRok, Pdu::UnbindResp),
+            _ => continue,
+        };
+
+        let response = Command::builder()
+            .status(status)
+            .sequence_number(sequence_number)
+            .pdu(pdu);
+
+        if framed.send(response).await.is_err() {
+            break;
+        }
+    }
+}
+
+async fn connect() -> (
+    ManagedClient,
+    impl Stream<Item = ManagedEvent<crate::event::Event>> + Unpin,
+    mpsc::UnboundedReceiver<String>,
+) {
+    init_tracing();
+
+    let (tx, rx) = mpsc::unbounded_channel();
+
+    let (client, events) = ConnectionBuilder::new()
+        .managed()
+        .transceiver(BindTransceiver::default())
+        .no_auto_reconnect_interval()
+        .connect_fn(move || {
+            let tx = tx.clone();
+
+            async move {
+                let (server, client) = tokio::io::duplex(4096);
+
+                tokio::spawn(run_server(server, tx));
+
+                Ok::<_, std::io::Error>(client)
+            }
+        })
+        .await
+        .expect("Failed to connect");
+
+    (client, events, rx)
+}
+
+#[test]
+fn file_store_should_restore_records() {
+    let path = temp_path();
+
+    {
+        let store = FileOutboxStore::open(&path).unwrap();
+
+        store
+            .insert(&OutboxEntry::new("a", submit_sm("a")))
+            .unwrap();
+        store
+            .insert(&OutboxEntry::new("b", submit_sm("b")))
+            .unwrap();
+        store
+            .insert(&OutboxEntry::new("c", submit_sm("c")))
+            .unwrap();
+
+        store
+            .complete(
+                "b",
+                &OutboxOutcome::Accepted {
+                    message_id: String::from("id-b"),
+                },
+            )
+            .unwrap();
+
+        store
+            .complete(
+                "c",
+                &OutboxOutcome::Rejected {
+                    status: CommandStatus::EsmeRinvdstadr,
+                },
+            )
+            .unwrap();
+    }
+
+    let store = FileOutboxStore::open(&path).unwrap();
+
+    assert_eq!(store.status("a").unwrap(), Some(OutboxStatus::Pending));
+    assert_eq!(
+        store.status("b").unwrap(),
+        Some(OutboxStatus::Completed(OutboxOutcome::Accepted {
+            message_id: String::from("id-b")
+        }))
+    );
+    assert_eq!(
+        store.status("c").unwrap(),
+        Some(OutboxStatus::Completed(OutboxOutcome::Rejected {
+            status: CommandStatus::EsmeRinvdstadr
+        }))
+    );
+    assert_eq!(store.status("d").unwrap(), None);
+
+    let pending = store.pending().unwrap();
+
+    assert_eq!(pending.len(), 1);
+    assert_eq!(pending[0].key(), "a");
+    assert_eq!(pending[0].submit_sm().short_message().to_str(), Ok("a"));
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn file_store_should_ignore_incomplete_record() {
+    let path = temp_path();
+
+    {
+        let store = FileOutboxStore::open(&path).unwrap();
+
+        store
+            .insert(&OutboxEntry::new("a", submit_sm("a")))
+            .unwrap();
+    }
+
+    let length = std::fs::metadata(&path).unwrap().len();
+
+    {
+        let store = FileOutboxStore::open(&path).unwrap();
+
+        store
+            .insert(&OutboxEntry::new("b", submit_sm("b")))
+            .unwrap();
+    }
+
+    // Simulate a crash while appending `b`.
+    std::fs::File::options()
+        .write(true)
+        .open(&path)
+        .unwrap()
+        .set_len(length + 5)
+        .unwrap();
+
+    {
+        let store = FileOutboxStore::open(&path).unwrap();
+
+        assert_eq!(store.status("b").unwrap(), None);
+
+        store
+            .insert(&OutboxEntry::new("c", submit_sm("c")))
+            .unwrap();
+    }
+
+    let store = FileOutboxStore::open(&path).unwrap();
+
+    let keys = store
+        .pending()
+        .unwrap()
+        .into_iter()
+        .map(|entry| entry.key().to_owned())
+        .collect::<Vec<_>>();
+
+    assert_eq!(keys, vec!["a", "c"]);
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn file_store_compact_should_keep_outcomes_and_pending() {
+    let path = temp_path();
+
+    let store = FileOutboxStore::open(&path).unwrap();
+
+    store
+        .insert(&OutboxEntry::new("a", submit_sm("a")))
+        .unwrap();
+    store
+        .insert(&OutboxEntry::new("b", submit_sm("b")))
+        .unwrap();
+    store
+        .complete(
+            "a",
+            &OutboxOutcome::Accepted {
+                message_id: String::from("id-a"),
+            },
+        )
+        .unwrap();
+
+    let length = std::fs::metadata(&path).unwrap().len();
+
+    store.compact().unwrap();
+
+    assert!(std::fs::metadata(&path).unwrap().len() < length);
+
+    store
+        .insert(&OutboxEntry::new("c", submit_sm("c")))
+        .unwrap();
+
+    drop(store);
+
+    let store = FileOutboxStore::open(&path).unwrap();
+
+    assert!(matches!(
+        store.status("a").unwrap(),
+        Some(OutboxStatus::Completed(_))
+    ));
+
+    let keys = store
+        .pending()
+        .unwrap()
+        .into_iter()
+        .map(|entry| entry.key().to_owned())
+        .collect::<Vec<_>>();
+
+    assert_eq!(keys, vec!["b", "c"]);
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[tokio::test]
+async fn submit_with_same_key_should_be_sent_once() {
+    let (client, _events, mut received) = connect().await;
+
+    let outbox = Outbox::new(client, InMemoryOutboxStore::new());
+
+    let first = outbox.submit("key", submit_sm("hello")).await.unwrap();
+    let second = outbox.submit("key", submit_sm("hello")).await.unwrap();
+
+    let accepted = OutboxOutcome::Accepted {
+        message_id: String::from("hello"),
+    };
+
+    assert_eq!(first, accepted);
+    assert_eq!(second, accepted);
+
+    assert_eq!(received.recv().await.as_deref(), Some("hello"));
+    assert!(received.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn rejected_submission_should_be_completed() {
+    let (client, _events, mut received) = connect().await;
+
+    let outbox = Outbox::new(client, InMemoryOutboxStore::new());
+
+    let outcome = outbox.submit("key", submit_sm("reject")).await.unwrap();
+
+    assert_eq!(
+        outcome,
+        OutboxOutcome::Rejected {
+            status: CommandStatus::EsmeRinvdstadr
+        }
+    );
+
+    assert_eq!(outbox.replay().await.unwrap(), 0);
+    assert_eq!(received.recv().await.as_deref(), Some("reject"));
+    assert!(received.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn pending_submissions_should_be_replayed_on_bound() {
+    let path = temp_path();
+
+    {
+        // Recorded by a previous process that crashed before the submissions were answered.
+        let store = FileOutboxStore::open(&path).unwrap();
+
+        store
+            .insert(&OutboxEntry::new("a", submit_sm("first")))
+            .unwrap();
+        store
+            .insert(&OutboxEntry::new("b", submit_sm("second")))
+            .unwrap();
+    }
+
+    let (client, events, mut received) = connect().await;
+
+    let outbox = Outbox::new(client, FileOutboxStore::open(&path).unwrap());
+
+    let mut events = outbox.replay_on_bound(events);
+
+    tokio::spawn(async move { while events.next().await.is_some() {} });
+
+    assert_eq!(received.recv().await.as_deref(), Some("first"));
+    assert_eq!(received.recv().await.as_deref(), Some("second"));
+
+    tokio::time::timeout(Duration::from_secs(1), async {
+        while outbox.status("b").unwrap() == Some(OutboxStatus::Pending) {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("Replay did not complete");
+
+    assert_eq!(
+        outbox.status("a").unwrap(),
+        Some(OutboxStatus::Completed(OutboxOutcome::Accepted {
+            message_id: String::from("first")
+        }))
+    );
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn file_store_compact_should_drop_the_oldest_outcomes() {
+    let path = temp_path();
+
+    let store = FileOutboxStore::open(&path).unwrap().outcome_retention(1);
+
+    for key in ["a", "b"] {
+        store
+            .insert(&OutboxEntry::new(key, submit_sm(key)))
+            .unwrap();
+        store
+            .complete(
+                key,
+                &OutboxOutcome::Rejected {
+                    status: CommandStatus::EsmeRinvdstadr,
+                },
+            )
+            .unwrap();
+    }
+
+    store.compact().unwrap();
+
+    assert_eq!(store.status("a").unwrap(), None);
+    assert!(matches!(
+        store.status("b").unwrap(),
+        Some(OutboxStatus::Completed(_))
+    ));
+
+    drop(store);
+
+    let store = FileOutboxStore::open(&path).unwrap();
+
+    assert_eq!(store.status("a").unwrap(), None);
+    assert!(matches!(
+        store.status("b").unwrap(),
+        Some(OutboxStatus::Completed(_))
+    ));
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn ok_status_should_not_be_retryable() {
+    assert!(!is_retryable(CommandStatus::EsmeRok));
+    assert!(!is_retryable(CommandStatus::EsmeRinvdstadr));
+    assert!(is_retryable(CommandStatus::EsmeRthrottled));
+    assert!(is_retryable(CommandStatus::EsmeRmsgqful));
+    assert!(is_retryable(CommandStatus::EsmeRsyserr));
+}
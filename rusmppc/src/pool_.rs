@@ -0,0 +1,608 @@
+// XXX: Only available with tokio, because the pool is built on top of the managed client.
+
+use std::{
+    fmt::Debug,
+    ops::Deref,
+    pin::Pin,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicU16, AtomicUsize, Ordering},
+    },
+};
+
+use futures::{Stream, StreamExt, future::BoxFuture, stream::BoxStream};
+use rusmpp::values::CongestionState;
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    sync::mpsc::UnboundedSender,
+};
+use tokio_stream::wrappers::UnboundedReceiverStream;
+
+use crate::{
+    Client,
+    error::Error,
+    event_::{Event, EventChannel, Insight, InsightEvent},
+    managed_::{BindMode, ManagedClient, ManagedConnectionBuilder, ManagedEvent},
+    runtime_::tokio::Tokio,
+};
+
+#[cfg(test)]
+mod tests;
+
+const TARGET: &str = "rusmppc::pool";
+
+/// Stored while a member did not report a congestion state.
+const UNKNOWN_CONGESTION: u16 = u16::MAX;
+
+/// The bind role of a [`ClientPool`] member.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemberRole {
+    /// The member binds as a transmitter.
+    Transmitter,
+    /// The member binds as a receiver.
+    Receiver,
+    /// The member binds as a transceiver.
+    Transceiver,
+    /// The member does not bind.
+    Unbound,
+}
+
+impl MemberRole {
+    /// Returns `true` if requests can be sent through a member with this role.
+    ///
+    /// Only [`MemberRole::Transmitter`] and [`MemberRole::Transceiver`] members are selected by [`ClientPool::get`].
+    pub const fn can_transmit(&self) -> bool {
+        matches!(self, MemberRole::Transmitter | MemberRole::Transceiver)
+    }
+}
+
+impl From<&BindMode> for MemberRole {
+    fn from(bind: &BindMode) -> Self {
+        match bind {
+            BindMode::None => MemberRole::Unbound,
+            BindMode::Transmitter(_) => MemberRole::Transmitter,
+            BindMode::Receiver(_) => MemberRole::Receiver,
+            BindMode::Transceiver(_) => MemberRole::Transceiver,
+        }
+    }
+}
+
+/// Strategy used by [`ClientPool::get`] to select a member.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Selection {
+    /// Cycles through the healthy members.
+    #[default]
+    RoundRobin,
+    /// Selects the healthy member with the fewest requests waiting for a response. See [`Client::in_flight`].
+    LeastOutstanding,
+    /// Selects the healthy member with the lowest `congestion_state` reported by the server.
+    ///
+    /// Members without a reported congestion state count as idle.
+    /// Requires an event channel reporting [`Insight::CongestionStateChanged`], e.g. [`InsightEventChannel`](crate::channel::InsightEventChannel).
+    LeastCongested,
+}
+
+/// The health reported by an event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Health {
+    /// The connection is working.
+    Healthy,
+    /// The connection is failing.
+    Unhealthy,
+}
+
+/// Events that carry information about the health of a connection.
+///
+/// Implemented for the event types of the built-in event channels.
+/// Use [`InsightEventChannel`](crate::channel::InsightEventChannel) to let successful enquire links mark a member as healthy.
+pub trait HealthSignal {
+    /// Returns the health reported by this event, or `None` if the event does not affect the health.
+    fn health(&self) -> Option<Health>;
+
+    /// Returns the congestion state of the server reported by this event, or `None` if the event does not report one.
+    fn congestion_state(&self) -> Option<CongestionState> {
+        None
+    }
+}
+
+impl HealthSignal for Event {
+    fn health(&self) -> Option<Health> {
+        match self {
+            Event::Error(_) => Some(Health::Unhealthy),
+            Event::Incoming(_) => None,
+        }
+    }
+}
+
+impl HealthSignal for InsightEvent {
+    fn health(&self) -> Option<Health> {
+        match self {
+            InsightEvent::Error(_) => Some(Health::Unhealthy),
+            InsightEvent::Insight(Insight::ReceivedEnquireLinkResp(_)) => Some(Health::Healthy),
+            _ => None,
+        }
+    }
+
+    fn congestion_state(&self) -> Option<CongestionState> {
+        match self {
+            InsightEvent::Insight(Insight::CongestionStateChanged(congestion_state)) => {
+                Some(*congestion_state)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl HealthSignal for () {
+    fn health(&self) -> Option<Health> {
+        None
+    }
+}
+
+impl<E: HealthSignal> HealthSignal for ManagedEvent<E> {
+    fn health(&self) -> Option<Health> {
+        match self {
+            ManagedEvent::Connected | ManagedEvent::EndpointActive(_) => None,
+            ManagedEvent::Bound => Some(Health::Healthy),
+            ManagedEvent::Disconnected => Some(Health::Unhealthy),
+            ManagedEvent::Event(event) => event.health(),
+        }
+    }
+
+    fn congestion_state(&self) -> Option<CongestionState> {
+        match self {
+            ManagedEvent::Event(event) => event.congestion_state(),
+            _ => None,
+        }
+    }
+}
+
+/// Events emitted by the [`ClientPool`].
+#[derive(Debug)]
+pub struct PoolEvent<E> {
+    /// The index of the member that emitted the event.
+    pub member: usize,
+    /// The event.
+    pub event: ManagedEvent<E>,
+}
+
+/// A snapshot of a [`ClientPool`] member.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemberStatus {
+    /// The index of the member.
+    pub member: usize,
+    /// The bind role of the member.
+    pub role: MemberRole,
+    /// Whether the member is considered healthy.
+    pub healthy: bool,
+    /// The number of requests of the member waiting for a response. See [`Client::in_flight`].
+    pub outstanding: usize,
+    /// The last `congestion_state` reported by the server to the member, or `None` if not reported since the member bound.
+    pub congestion_state: Option<CongestionState>,
+}
+
+#[derive(Debug)]
+struct MemberState {
+    role: MemberRole,
+    healthy: AtomicBool,
+    congestion: AtomicU16,
+}
+
+impl MemberState {
+    const fn new(role: MemberRole) -> Self {
+        Self {
+            role,
+            // Members are bound when the pool is created.
+            healthy: AtomicBool::new(true),
+            congestion: AtomicU16::new(UNKNOWN_CONGESTION),
+        }
+    }
+
+    fn set_health(&self, member: usize, health: Health) {
+        let healthy = matches!(health, Health::Healthy);
+
+        if self.healthy.swap(healthy, Ordering::Relaxed) != healthy {
+            tracing::debug!(target: TARGET, member, ?health, "Health changed");
+        }
+    }
+
+    fn set_congestion_state(&self, congestion_state: Option<CongestionState>) {
+        let congestion = congestion_state
+            .map(|congestion_state| u16::from(u8::from(congestion_state)))
+            .unwrap_or(UNKNOWN_CONGESTION);
+
+        self.congestion.store(congestion, Ordering::Relaxed);
+    }
+
+    fn congestion_state(&self) -> Option<CongestionState> {
+        match self.congestion.load(Ordering::Relaxed) {
+            UNKNOWN_CONGESTION => None,
+            congestion => Some(CongestionState::from(congestion as u8)),
+        }
+    }
+
+    fn is_healthy(&self) -> bool {
+        self.healthy.load(Ordering::Relaxed)
+    }
+}
+
+#[derive(Debug)]
+struct Member {
+    client: ManagedClient,
+    state: Arc<MemberState>,
+}
+
+/// A pool of [`ManagedClient`]s with load balancing.
+///
+/// Requests are spread over the healthy transmitter and transceiver members using the configured [`Selection`].
+/// Receiver members only contribute to the merged event stream.
+///
+/// A member is marked unhealthy when it reports an error, disconnects or fails to reconnect,
+/// and healthy again when it binds or, with [`InsightEventChannel`](crate::channel::InsightEventChannel), receives an enquire link response.
+/// If no member is healthy, all transmitting members are tried.
+#[derive(Debug, Clone)]
+pub struct ClientPool {
+    inner: Arc<ClientPoolInner>,
+}
+
+#[derive(Debug)]
+struct ClientPoolInner {
+    members: Vec<Member>,
+    selection: Selection,
+    next: AtomicUsize,
+}
+
+impl ClientPool {
+    /// Creates a new [`ClientPoolBuilder`].
+    pub fn builder<E>() -> ClientPoolBuilder<E>
+    where
+        E: EventChannel + Clone + Send + Sync + 'static,
+        E::Event: HealthSignal + Send + Sync + 'static,
+    {
+        ClientPoolBuilder::new()
+    }
+
+    /// Gets a connected and bound [`Client`] from a transmitting member.
+    ///
+    /// Members are tried in the order given by the [`Selection`] strategy until one of them returns a client.
+    ///
+    /// # Errors
+    ///
+    /// - [`Error::NoTransmitterAvailable`] if the pool has no transmitter or transceiver members.
+    /// - The error of the last tried member if all members fail.
+    pub async fn get(&self) -> Result<PooledClient, Error> {
+        let mut last_error = None;
+
+        for member in self.inner.candidates() {
+            let state = &self.inner.members[member].state;
+
+            match self.inner.members[member].client.get().await {
+                Ok(client) => {
+                    state.set_health(member, Health::Healthy);
+
+                    return Ok(PooledClient::new(client, member));
+                }
+                Err(err) => {
+                    tracing::warn!(target: TARGET, member, ?err, "Failed to get client");
+
+                    state.set_health(member, Health::Unhealthy);
+
+                    last_error = Some(err);
+                }
+            }
+        }
+
+        Err(last_error.unwrap_or(Error::NoTransmitterAvailable))
+    }
+
+    /// Returns the [`ManagedClient`] of the member at the given index.
+    pub fn member(&self, member: usize) -> Option<&ManagedClient> {
+        self.inner.members.get(member).map(|member| &member.client)
+    }
+
+    /// Returns the number of members in the pool.
+    pub fn len(&self) -> usize {
+        self.inner.members.len()
+    }
+
+    /// Returns `true` if the pool has no members.
+    pub fn is_empty(&self) -> bool {
+        self.inner.members.is_empty()
+    }
+
+    /// Returns a snapshot of every member.
+    pub fn status(&self) -> Vec<MemberStatus> {
+        self.inner
+            .members
+            .iter()
+            .enumerate()
+            .map(|(index, member)| MemberStatus {
+                member: index,
+                role: member.state.role,
+                healthy: member.state.is_healthy(),
+                outstanding: member.client.in_flight(),
+                congestion_state: member.state.congestion_state(),
+            })
+            .collect()
+    }
+}
+
+impl ClientPoolInner {
+    /// Returns the transmitting members in the order they should be tried.
+    fn candidates(&self) -> Vec<usize> {
+        let transmitters: Vec<usize> = (0..self.members.len())
+            .filter(|&index| self.members[index].state.role.can_transmit())
+            .collect();
+
+        if transmitters.is_empty() {
+            return transmitters;
+        }
+
+        // Rotate for round robin and as a tie breaker for least outstanding.
+        let start = self.next.fetch_add(1, Ordering::Relaxed) % transmitters.len();
+
+        let mut candidates: Vec<usize> = transmitters[start..]
+            .iter()
+            .chain(&transmitters[..start])
+            .copied()
+            .collect();
+
+        // Stable sorts keep the rotation among equal members.
+        match self.selection {
+            Selection::RoundRobin => {}
+            Selection::LeastOutstanding => {
+                candidates.sort_by_key(|&index| self.members[index].client.in_flight());
+            }
+            Selection::LeastCongested => {
+                candidates.sort_by_key(|&index| {
+                    self.members[index]
+                        .state
+                        .congestion_state()
+                        .map(u8::from)
+                        .unwrap_or_default()
+                });
+            }
+        }
+
+        // Healthy members first, unhealthy members as a last resort.
+        candidates.sort_by_key(|&index| !self.members[index].state.is_healthy());
+
+        candidates
+    }
+}
+
+/// A [`Client`] handed out by the [`ClientPool`].
+pub struct PooledClient {
+    client: Client<Tokio>,
+    member: usize,
+}
+
+impl PooledClient {
+    const fn new(client: Client<Tokio>, member: usize) -> Self {
+        Self { client, member }
+    }
+
+    /// Returns the index of the member this client belongs to.
+    pub const fn member(&self) -> usize {
+        self.member
+    }
+
+    /// Returns the underlying [`Client`].
+    pub fn client(&self) -> &Client<Tokio> {
+        &self.client
+    }
+}
+
+impl Debug for PooledClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PooledClient")
+            .field("member", &self.member)
+            .finish()
+    }
+}
+
+impl Deref for PooledClient {
+    type Target = Client<Tokio>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.client
+    }
+}
+
+type MemberConnect<E> = Box<
+    dyn FnOnce() -> BoxFuture<
+            'static,
+            Result<(ManagedClient, BoxStream<'static, ManagedEvent<E>>), Error>,
+        > + Send,
+>;
+
+/// Builder for creating a [`ClientPool`].
+pub struct ClientPoolBuilder<E: EventChannel> {
+    members: Vec<(MemberRole, MemberConnect<E::Event>)>,
+    selection: Selection,
+}
+
+impl<E: EventChannel> Debug for ClientPoolBuilder<E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ClientPoolBuilder")
+            .field(
+                "members",
+                &self
+                    .members
+                    .iter()
+                    .map(|(role, _)| role)
+                    .collect::<Vec<_>>(),
+            )
+            .field("selection", &self.selection)
+            .finish()
+    }
+}
+
+impl<E> Default for ClientPoolBuilder<E>
+where
+    E: EventChannel + Clone + Send + Sync + 'static,
+    E::Event: HealthSignal + Send + Sync + 'static,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<E> ClientPoolBuilder<E>
+where
+    E: EventChannel + Clone + Send + Sync + 'static,
+    E::Event: HealthSignal + Send + Sync + 'static,
+{
+    /// Creates a new empty [`ClientPoolBuilder`].
+    ///
+    /// # Defaults
+    /// - `selection`: [`Selection::RoundRobin`]
+    pub fn new() -> Self {
+        Self {
+            members: Vec::new(),
+            selection: Selection::default(),
+        }
+    }
+
+    /// Sets the member selection strategy.
+    pub const fn selection(mut self, selection: Selection) -> Self {
+        self.selection = selection;
+        self
+    }
+
+    /// Adds a member connecting to the given URL.
+    ///
+    /// The role of the member is taken from the bind mode of the [`ManagedConnectionBuilder`].
+    ///
+    /// See [`ManagedConnectionBuilder::connect`] for more details.
+    pub fn member(mut self, builder: ManagedConnectionBuilder<E>, url: impl Into<String>) -> Self {
+        let role = MemberRole::from(builder.bind_mode());
+        let url = url.into();
+
+        self.members.push((
+            role,
+            Box::new(move || {
+                Box::pin(async move {
+                    builder
+                        .connect(url)
+                        .await
+                        .map(|(client, events)| (client, events.boxed()))
+                })
+            }),
+        ));
+
+        self
+    }
+
+    /// Adds a member connecting using the given function.
+    ///
+    /// The role of the member is taken from the bind mode of the [`ManagedConnectionBuilder`].
+    ///
+    /// See [`ManagedConnectionBuilder::connect_fn`] for more details.
+    pub fn member_fn<F, Fut, S>(mut self, builder: ManagedConnectionBuilder<E>, f: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<S, std::io::Error>> + Send + 'static,
+        S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    {
+        let role = MemberRole::from(builder.bind_mode());
+
+        self.members.push((
+            role,
+            Box::new(move || {
+                Box::pin(async move {
+                    builder
+                        .connect_fn(f)
+                        .await
+                        .map(|(client, events)| (client, events.boxed()))
+                })
+            }),
+        ));
+
+        self
+    }
+
+    /// Connects and binds all members.
+    ///
+    /// Returns the [`ClientPool`] and a single event stream merging the events of all members.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error if any member fails to connect or bind.
+    pub async fn connect(
+        self,
+    ) -> Result<
+        (
+            ClientPool,
+            impl Stream<Item = PoolEvent<E::Event>> + Unpin + 'static,
+        ),
+        Error,
+    > {
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+
+        let connected = futures::future::try_join_all(
+            self.members
+                .into_iter()
+                .map(|(role, connect)| async move { connect().await.map(|c| (role, c)) }),
+        )
+        .await?;
+
+        let members = connected
+            .into_iter()
+            .enumerate()
+            .map(|(index, (role, (client, events)))| {
+                let state = Arc::new(MemberState::new(role));
+
+                forward(index, events, state.clone(), tx.clone());
+
+                Member { client, state }
+            })
+            .collect::<Vec<_>>();
+
+        tracing::debug!(target: TARGET, members = members.len(), selection = ?self.selection, "Connected");
+
+        let pool = ClientPool {
+            inner: Arc::new(ClientPoolInner {
+                members,
+                selection: self.selection,
+                next: AtomicUsize::new(0),
+            }),
+        };
+
+        Ok((pool, UnboundedReceiverStream::new(rx)))
+    }
+}
+
+/// Forwards the events of a member to the merged event stream, updating its health on the way.
+///
+/// Health is tracked even if the merged event stream is dropped.
+fn forward<E: HealthSignal + Send + 'static>(
+    member: usize,
+    mut events: Pin<Box<dyn Stream<Item = ManagedEvent<E>> + Send>>,
+    state: Arc<MemberState>,
+    tx: UnboundedSender<PoolEvent<E>>,
+) {
+    Tokio::spawn(async move {
+        while let Some(event) = events.next().await {
+            if let Some(health) = event.health() {
+                state.set_health(member, health);
+            }
+
+            match &event {
+                // A new session starts without a reported congestion state.
+                ManagedEvent::Bound | ManagedEvent::Disconnected => {
+                    state.set_congestion_state(None)
+                }
+                event => {
+                    if let Some(congestion_state) = event.congestion_state() {
+                        state.set_congestion_state(Some(congestion_state));
+                    }
+                }
+            }
+
+            let _ = tx.send(PoolEvent { member, event });
+        }
+
+        tracing::trace!(target: TARGET, member, "Event stream closed");
+    });
+}
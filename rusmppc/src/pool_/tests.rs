@@ -0,0 +1,417 @@
+use std::{collections::HashSet, pin::Pin};
+
+use futures::{SinkExt, StreamExt};
+use rusmpp::{
+    Command, CommandId, CommandStatus, Pdu,
+    pdus::{
+        BindReceiver, BindReceiverResp, BindTransceiver, BindTransceiverResp, BindTransmitter,
+        BindTransmitterResp, SubmitSm, SubmitSmResp,
+    },
+    tokio_codec::CommandCodec,
+    values::CongestionState,
+};
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt, DuplexStream};
+use tokio_util::codec::Framed;
+
+use crate::{
+    ConnectionBuilder,
+    error::Error,
+    event::{Insight, InsightEvent},
+    managed::ManagedEvent,
+    pool::{ClientPool, MemberRole, PoolEvent, Selection},
+    tests::{encode_with_congestion_state, init_tracing},
+};
+
+/// Server that binds successfully and answers [`SubmitSm`]s.
+///
+/// Closes the connection after binding if `close_after_bind` is set.
+async fn run_server<S: AsyncRead + AsyncWrite + Send + Unpin + 'static>(
+    stream: S,
+    close_after_bind: bool,
+) {
+    let mut framed = Framed::new(stream, CommandCodec::new());
+
+    while let Some(Ok(command)) = framed.next().await {
+        let pdu: Pdu = match command.id() {
+            CommandId::BindTransmitter => BindTransmitterResp::default().into(),
+            CommandId::BindReceiver => BindReceiverResp::default().into(),
+            CommandId::BindTransceiver => BindTransceiverResp::default().into(),
+            CommandId::SubmitSm => SubmitSmResp::default().into(),
+            CommandId::EnquireLink => Pdu::EnquireLinkResp,
+            CommandId::Unbind => Pdu::UnbindResp,
+            _ => continue,
+        };
+
+        let response = Command::builder()
+            .status(CommandStatus::EsmeRok)
+            .sequence_number(command.sequence_number())
+            .pdu(pdu);
+
+        if framed.send(response).await.is_err() {
+            break;
+        }
+
+        if close_after_bind {
+            break;
+        }
+    }
+}
+
+fn connector(
+    close_after_bind: bool,
+) -> impl Fn() -> Pin<Box<dyn Future<Output = Result<DuplexStream, std::io::Error>> + Send>>
++ Send
++ Sync
++ 'static {
+    move || {
+        Box::pin(async move {
+            let (server, client) = tokio::io::duplex(4096);
+
+            tokio::spawn(run_server(server, close_after_bind));
+
+            Ok(client)
+        })
+    }
+}
+
+/// Server that binds successfully and never answers [`SubmitSm`]s.
+async fn run_silent_server(stream: DuplexStream) {
+    let mut framed = Framed::new(stream, CommandCodec::new());
+
+    while let Some(Ok(command)) = framed.next().await {
+        if command.id() != CommandId::BindTransceiver {
+            continue;
+        }
+
+        let response = Command::builder()
+            .status(CommandStatus::EsmeRok)
+            .sequence_number(command.sequence_number())
+            .pdu(BindTransceiverResp::default());
+
+        if framed.send(response).await.is_err() {
+            break;
+        }
+    }
+}
+
+fn silent_connector()
+-> impl Fn() -> Pin<Box<dyn Future<Output = Result<DuplexStream, std::io::Error>> + Send>>
++ Send
++ Sync
++ 'static {
+    move || {
+        Box::pin(async move {
+            let (server, client) = tokio::io::duplex(4096);
+
+            tokio::spawn(run_silent_server(server));
+
+            Ok(client)
+        })
+    }
+}
+
+/// Server that binds successfully and answers [`SubmitSm`]s with the given `congestion_state` TLV.
+async fn run_congested_server(stream: DuplexStream, congestion_state: u8) {
+    let mut framed = Framed::new(stream, CommandCodec::new());
+
+    while let Some(Ok(command)) = framed.next().await {
+        let response = Command::builder()
+            .status(CommandStatus::EsmeRok)
+            .sequence_number(command.sequence_number());
+
+        let sent = match command.id() {
+            CommandId::BindTransceiver => framed
+                .send(response.pdu(BindTransceiverResp::default()))
+                .await
+                .is_ok(),
+            CommandId::SubmitSm => {
+                let buf = encode_with_congestion_state(
+                    &response.pdu(SubmitSmResp::default()),
+                    congestion_state,
+                );
+
+                framed.get_mut().write_all(&buf).await.is_ok()
+            }
+            _ => true,
+        };
+
+        if !sent {
+            break;
+        }
+    }
+}
+
+fn congested_connector(
+    congestion_state: u8,
+) -> impl Fn() -> Pin<Box<dyn Future<Output = Result<DuplexStream, std::io::Error>> + Send>>
++ Send
++ Sync
++ 'static {
+    move || {
+        Box::pin(async move {
+            let (server, client) = tokio::io::duplex(4096);
+
+            tokio::spawn(run_congested_server(server, congestion_state));
+
+            Ok(client)
+        })
+    }
+}
+
+#[tokio::test]
+async fn pool_round_robin_should_skip_receivers() {
+    init_tracing();
+
+    let (pool, _events) = ClientPool::builder()
+        .member_fn(
+            ConnectionBuilder::new()
+                .managed()
+                .transmitter(BindTransmitter::default())
+                .no_auto_reconnect_interval(),
+            connector(false),
+        )
+        .member_fn(
+            ConnectionBuilder::new()
+                .managed()
+                .receiver(BindReceiver::default())
+                .no_auto_reconnect_interval(),
+            connector(false),
+        )
+        .member_fn(
+            ConnectionBuilder::new()
+                .managed()
+                .transceiver(BindTransceiver::default())
+                .no_auto_reconnect_interval(),
+            connector(false),
+        )
+        .connect()
+        .await
+        .expect("Failed to build pool");
+
+    assert_eq!(pool.len(), 3);
+    assert_eq!(pool.status()[1].role, MemberRole::Receiver);
+
+    let mut members = Vec::new();
+
+    for _ in 0..4 {
+        let client = pool.get().await.expect("Failed to get client");
+
+        client
+            .submit_sm(SubmitSm::default())
+            .await
+            .expect("Failed to submit SM");
+
+        members.push(client.member());
+    }
+
+    assert_eq!(members, vec![0, 2, 0, 2]);
+}
+
+#[tokio::test]
+async fn pool_least_outstanding_should_prefer_members_with_fewer_in_flight_requests() {
+    init_tracing();
+
+    let builder = || {
+        ConnectionBuilder::new()
+            .managed()
+            .transceiver(BindTransceiver::default())
+            .no_auto_reconnect_interval()
+    };
+
+    let (pool, _events) = ClientPool::builder()
+        .selection(Selection::LeastOutstanding)
+        .member_fn(builder(), silent_connector())
+        .member_fn(builder(), connector(false))
+        .connect()
+        .await
+        .expect("Failed to build pool");
+
+    // Handed out clients do not count, only requests waiting for a response.
+    let idle = pool.get().await.expect("Failed to get client");
+
+    assert!(pool.status().iter().all(|status| status.outstanding == 0));
+
+    let busy = pool
+        .member(0)
+        .expect("Member not found")
+        .get()
+        .await
+        .expect("Failed to get client");
+
+    let submit = tokio::spawn(async move { busy.submit_sm(SubmitSm::default()).await });
+
+    while pool.status()[0].outstanding == 0 {
+        tokio::task::yield_now().await;
+    }
+
+    for _ in 0..3 {
+        let client = pool.get().await.expect("Failed to get client");
+
+        assert_eq!(client.member(), 1);
+
+        client
+            .submit_sm(SubmitSm::default())
+            .await
+            .expect("Failed to submit SM");
+    }
+
+    submit.abort();
+    drop(idle);
+}
+
+#[tokio::test]
+async fn pool_least_congested_should_prefer_members_with_lower_congestion() {
+    init_tracing();
+
+    let builder = || {
+        ConnectionBuilder::new()
+            .events()
+            .insights()
+            .managed()
+            .transceiver(BindTransceiver::default())
+            .no_auto_reconnect_interval()
+    };
+
+    let (pool, mut events) = ClientPool::builder()
+        .selection(Selection::LeastCongested)
+        .member_fn(builder(), congested_connector(95))
+        .member_fn(builder(), congested_connector(10))
+        .connect()
+        .await
+        .expect("Failed to build pool");
+
+    assert!(
+        pool.status()
+            .iter()
+            .all(|status| status.congestion_state.is_none())
+    );
+
+    for member in 0..2 {
+        pool.member(member)
+            .unwrap()
+            .get()
+            .await
+            .expect("Failed to get client")
+            .submit_sm(SubmitSm::default())
+            .await
+            .expect("Failed to submit SM");
+    }
+
+    let mut reported = HashSet::new();
+
+    while let Some(PoolEvent { member, event }) = events.next().await {
+        if let ManagedEvent::Event(InsightEvent::Insight(Insight::CongestionStateChanged(_))) =
+            event
+        {
+            reported.insert(member);
+        }
+
+        if reported.len() == 2 {
+            break;
+        }
+    }
+
+    assert_eq!(
+        pool.status()[0].congestion_state,
+        Some(CongestionState::NearingCongestion(95))
+    );
+    assert_eq!(
+        pool.status()[1].congestion_state,
+        Some(CongestionState::LowLoad(10))
+    );
+
+    for _ in 0..3 {
+        let client = pool.get().await.expect("Failed to get client");
+
+        assert_eq!(client.member(), 1);
+    }
+}
+
+#[tokio::test]
+async fn pool_should_skip_unhealthy_members() {
+    init_tracing();
+
+    let builder = || {
+        ConnectionBuilder::new()
+            .managed()
+            .transmitter(BindTransmitter::default())
+            .no_auto_reconnect_interval()
+    };
+
+    let (pool, mut events) = ClientPool::builder()
+        .member_fn(builder(), connector(true))
+        .member_fn(builder(), connector(false))
+        .connect()
+        .await
+        .expect("Failed to build pool");
+
+    // Wait for the first member to lose its connection.
+    while let Some(PoolEvent { member, event }) = events.next().await {
+        if member == 0 && matches!(event, ManagedEvent::Disconnected) {
+            break;
+        }
+    }
+
+    assert!(!pool.status()[0].healthy);
+    assert!(pool.status()[1].healthy);
+
+    for _ in 0..3 {
+        let client = pool.get().await.expect("Failed to get client");
+
+        assert_eq!(client.member(), 1);
+    }
+}
+
+#[tokio::test]
+async fn pool_should_merge_member_events() {
+    init_tracing();
+
+    let builder = || {
+        ConnectionBuilder::new()
+            .managed()
+            .transceiver(BindTransceiver::default())
+            .no_auto_reconnect_interval()
+    };
+
+    let (_pool, mut events) = ClientPool::builder()
+        .member_fn(builder(), connector(false))
+        .member_fn(builder(), connector(false))
+        .connect()
+        .await
+        .expect("Failed to build pool");
+
+    let mut bound = HashSet::new();
+
+    while let Some(PoolEvent { member, event }) = events.next().await {
+        if let ManagedEvent::Bound = event {
+            bound.insert(member);
+        }
+
+        if bound.len() == 2 {
+            break;
+        }
+    }
+
+    assert_eq!(bound, HashSet::from([0, 1]));
+}
+
+#[tokio::test]
+async fn pool_without_transmitters_should_fail() {
+    init_tracing();
+
+    let (pool, _events) = ClientPool::builder()
+        .member_fn(
+            ConnectionBuilder::new()
+                .managed()
+                .receiver(BindReceiver::default())
+                .no_auto_reconnect_interval(),
+            connector(false),
+        )
+        .connect()
+        .await
+        .expect("Failed to build pool");
+
+    let err = pool.get().await.expect_err("Expected error");
+
+    assert!(matches!(err, Error::NoTransmitterAvailable));
+}
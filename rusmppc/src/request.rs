@@ -111,3 +111,17 @@ impl CloseRequest {
         (Self { ack }, rx)
     }
 }
+
+#[derive(Debug)]
+pub struct DrainRequest {
+    /// ack result means that all registered requests were answered or removed.
+    pub ack: oneshot::Sender<()>,
+}
+
+impl DrainRequest {
+    pub fn new() -> (Self, oneshot::Receiver<()>) {
+        let (ack, rx) = oneshot::channel();
+
+        (Self { ack }, rx)
+    }
+}
@@ -63,5 +63,13 @@ const _: () = {
         {
             tokio::spawn(future)
         }
+
+        pub(crate) fn spawn_blocking<F, T>(f: F) -> tokio::task::JoinHandle<T>
+        where
+            F: FnOnce() -> T + Send + 'static,
+            T: Send + 'static,
+        {
+            tokio::task::spawn_blocking(f)
+        }
     }
 };
@@ -0,0 +1,208 @@
+use std::{
+    collections::HashSet,
+    fmt::Debug,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU32, Ordering},
+    },
+};
+
+use crate::error::Error;
+
+#[cfg(test)]
+mod tests;
+
+const TARGET: &str = "rusmppc::sequence";
+
+/// The smallest valid sequence number.
+pub const MIN_SEQUENCE_NUMBER: u32 = 0x00000001;
+
+/// The largest valid sequence number.
+pub const MAX_SEQUENCE_NUMBER: u32 = 0x7FFFFFFF;
+
+/// Source of sequence numbers for outgoing requests.
+///
+/// Every returned number must be in the range [`MIN_SEQUENCE_NUMBER`]..=[`MAX_SEQUENCE_NUMBER`],
+/// otherwise the request fails with [`Error::InvalidSequenceNumber`].
+/// Numbers still waiting for a response on the connection are skipped by the connection itself,
+/// so an allocator only has to cycle through the range. The connection gives up with [`Error::SequenceNumbersExhausted`]
+/// if it does not get a free number within `in flight + 1` attempts.
+///
+/// An allocator can be shared by several connections, e.g. to make sequence numbers unique across all connections for log correlation.
+/// [`SequenceNumberAllocator`] is implemented for [`Arc<A>`], so the same allocator can be passed to several [`ConnectionBuilder`](crate::ConnectionBuilder)s.
+pub trait SequenceNumberAllocator: Send + Sync + 'static {
+    /// Returns the next sequence number.
+    fn next(&self) -> u32;
+}
+
+impl<A: SequenceNumberAllocator + ?Sized> SequenceNumberAllocator for Arc<A> {
+    fn next(&self) -> u32 {
+        (**self).next()
+    }
+}
+
+/// The default [`SequenceNumberAllocator`].
+///
+/// Counts up from [`MIN_SEQUENCE_NUMBER`] and wraps back to it after [`MAX_SEQUENCE_NUMBER`].
+#[derive(Debug)]
+pub struct AtomicSequenceNumberAllocator {
+    next: AtomicU32,
+}
+
+impl AtomicSequenceNumberAllocator {
+    /// Creates a new [`AtomicSequenceNumberAllocator`] starting at [`MIN_SEQUENCE_NUMBER`].
+    pub const fn new() -> Self {
+        Self::starting_at(MIN_SEQUENCE_NUMBER)
+    }
+
+    /// Creates a new [`AtomicSequenceNumberAllocator`] starting at the given sequence number.
+    ///
+    /// Values outside of the valid range start at [`MIN_SEQUENCE_NUMBER`].
+    pub const fn starting_at(sequence_number: u32) -> Self {
+        let sequence_number = if is_valid(sequence_number) {
+            sequence_number
+        } else {
+            MIN_SEQUENCE_NUMBER
+        };
+
+        Self {
+            next: AtomicU32::new(sequence_number),
+        }
+    }
+}
+
+impl Default for AtomicSequenceNumberAllocator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SequenceNumberAllocator for AtomicSequenceNumberAllocator {
+    fn next(&self) -> u32 {
+        let previous = self
+            .next
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |current| {
+                Some(following(current))
+            })
+            .expect("The closure always returns Some");
+
+        // Values outside of the range can only be observed if `starting_at` was bypassed.
+        if is_valid(previous) {
+            previous
+        } else {
+            MIN_SEQUENCE_NUMBER
+        }
+    }
+}
+
+const fn is_valid(sequence_number: u32) -> bool {
+    sequence_number >= MIN_SEQUENCE_NUMBER && sequence_number <= MAX_SEQUENCE_NUMBER
+}
+
+const fn following(sequence_number: u32) -> u32 {
+    if sequence_number >= MAX_SEQUENCE_NUMBER {
+        MIN_SEQUENCE_NUMBER
+    } else {
+        sequence_number + 1
+    }
+}
+
+/// Sequence number allocator configuration.
+///
+/// Shared by all connections created from the same [`ConnectionBuilder`](crate::ConnectionBuilder).
+#[derive(Clone)]
+pub(crate) struct SharedSequenceNumberAllocator {
+    allocator: Arc<dyn SequenceNumberAllocator>,
+}
+
+impl Debug for SharedSequenceNumberAllocator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SharedSequenceNumberAllocator")
+            .finish_non_exhaustive()
+    }
+}
+
+impl SharedSequenceNumberAllocator {
+    pub(crate) fn new(allocator: Arc<dyn SequenceNumberAllocator>) -> Self {
+        Self { allocator }
+    }
+}
+
+/// Per connection sequence numbers shared by the [`Client`](crate::Client) and the background connection.
+///
+/// The connection marks a sequence number as in flight while it waits for the response,
+/// and the allocation skips those numbers after the allocator wrapped around.
+pub(crate) struct SequenceNumbers {
+    allocator: Arc<dyn SequenceNumberAllocator>,
+    in_flight: Mutex<HashSet<u32>>,
+}
+
+impl Debug for SequenceNumbers {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SequenceNumbers")
+            .field("in_flight", &self.in_flight_len())
+            .finish()
+    }
+}
+
+impl SequenceNumbers {
+    pub(crate) fn new(allocator: Option<SharedSequenceNumberAllocator>) -> Self {
+        Self {
+            allocator: allocator
+                .map(|shared| shared.allocator)
+                .unwrap_or_else(|| Arc::new(AtomicSequenceNumberAllocator::new())),
+            in_flight: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// Returns the next sequence number that is not in flight.
+    ///
+    /// An allocator cycling through the range yields a free number within `in flight + 1` attempts.
+    ///
+    /// # Errors
+    ///
+    /// - [`Error::InvalidSequenceNumber`] if the allocator returns a number outside of the valid range.
+    /// - [`Error::SequenceNumbersExhausted`] if every attempt returned a number in flight.
+    pub(crate) fn next(&self) -> Result<u32, Error> {
+        let in_flight = self.in_flight.lock().expect("Sequence numbers poisoned");
+
+        let attempts = in_flight.len() + 1;
+
+        for _ in 0..attempts {
+            let sequence_number = self.allocator.next();
+
+            if !is_valid(sequence_number) {
+                return Err(Error::InvalidSequenceNumber { sequence_number });
+            }
+
+            if !in_flight.contains(&sequence_number) {
+                return Ok(sequence_number);
+            }
+
+            tracing::trace!(target: TARGET, sequence_number, "Skipping in flight sequence number");
+        }
+
+        Err(Error::SequenceNumbersExhausted { attempts })
+    }
+
+    pub(crate) fn insert_in_flight(&self, sequence_number: u32) {
+        self.in_flight
+            .lock()
+            .expect("Sequence numbers poisoned")
+            .insert(sequence_number);
+    }
+
+    pub(crate) fn remove_in_flight(&self, sequence_number: u32) {
+        self.in_flight
+            .lock()
+            .expect("Sequence numbers poisoned")
+            .remove(&sequence_number);
+    }
+
+    pub(crate) fn in_flight_len(&self) -> usize {
+        self.in_flight
+            .lock()
+            .map(|in_flight| in_flight.len())
+            .unwrap_or_default()
+    }
+}
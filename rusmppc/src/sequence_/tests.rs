@@ -0,0 +1,172 @@
+use std::sync::Arc;
+
+use futures::{SinkExt, StreamExt};
+use rusmpp::{Command, CommandId, CommandStatus, Pdu, tokio_codec::CommandCodec};
+use tokio::{io::DuplexStream, sync::mpsc};
+use tokio_util::codec::Framed;
+
+use super::*;
+use crate::{ConnectionBuilder, tests::init_tracing};
+
+#[test]
+fn atomic_allocator_should_wrap_to_min() {
+    let allocator = AtomicSequenceNumberAllocator::starting_at(MAX_SEQUENCE_NUMBER - 1);
+
+    assert_eq!(allocator.next(), MAX_SEQUENCE_NUMBER - 1);
+    assert_eq!(allocator.next(), MAX_SEQUENCE_NUMBER);
+    assert_eq!(allocator.next(), MIN_SEQUENCE_NUMBER);
+    assert_eq!(allocator.next(), MIN_SEQUENCE_NUMBER + 1);
+}
+
+#[test]
+fn atomic_allocator_should_start_at_min_for_invalid_values() {
+    assert_eq!(AtomicSequenceNumberAllocator::starting_at(0).next(), 1);
+    assert_eq!(
+        AtomicSequenceNumberAllocator::starting_at(MAX_SEQUENCE_NUMBER + 1).next(),
+        1
+    );
+    assert_eq!(
+        AtomicSequenceNumberAllocator::starting_at(u32::MAX).next(),
+        1
+    );
+}
+
+#[test]
+fn in_flight_sequence_numbers_should_be_skipped() {
+    let sequence_numbers =
+        SequenceNumbers::new(Some(SharedSequenceNumberAllocator::new(Arc::new(
+            AtomicSequenceNumberAllocator::starting_at(MAX_SEQUENCE_NUMBER),
+        ))));
+
+    assert_eq!(sequence_numbers.next().unwrap(), MAX_SEQUENCE_NUMBER);
+
+    sequence_numbers.insert_in_flight(1);
+    sequence_numbers.insert_in_flight(2);
+
+    assert_eq!(sequence_numbers.next().unwrap(), 3);
+
+    sequence_numbers.remove_in_flight(1);
+
+    assert_eq!(sequence_numbers.next().unwrap(), 4);
+}
+
+/// Allocator always returning the same sequence number.
+struct FixedAllocator(u32);
+
+impl SequenceNumberAllocator for FixedAllocator {
+    fn next(&self) -> u32 {
+        self.0
+    }
+}
+
+fn fixed(sequence_number: u32) -> SequenceNumbers {
+    SequenceNumbers::new(Some(SharedSequenceNumberAllocator::new(Arc::new(
+        FixedAllocator(sequence_number),
+    ))))
+}
+
+#[test]
+fn allocation_should_give_up_when_only_in_flight_numbers_are_returned() {
+    let sequence_numbers = fixed(5);
+
+    sequence_numbers.insert_in_flight(5);
+    sequence_numbers.insert_in_flight(6);
+
+    assert!(matches!(
+        sequence_numbers.next(),
+        Err(Error::SequenceNumbersExhausted { attempts: 3 })
+    ));
+}
+
+#[test]
+fn allocation_should_reject_numbers_outside_of_the_range() {
+    assert!(matches!(
+        fixed(0).next(),
+        Err(Error::InvalidSequenceNumber { sequence_number: 0 })
+    ));
+
+    assert!(matches!(
+        fixed(MAX_SEQUENCE_NUMBER + 1).next(),
+        Err(Error::InvalidSequenceNumber { .. })
+    ));
+}
+
+/// Server that answers every [`EnquireLink`](Pdu::EnquireLink) and forwards the received sequence numbers.
+async fn run_server(stream: DuplexStream, sequence_numbers: mpsc::UnboundedSender<u32>) {
+    let mut framed = Framed::new(stream, CommandCodec::new());
+
+    while let Some(Ok(command)) = framed.next().await {
+        if command.id() != CommandId::EnquireLink {
+            continue;
+        }
+
+        let _ = sequence_numbers.send(command.sequence_number());
+
+        let response = Command::builder()
+            .status(CommandStatus::EsmeRok)
+            .sequence_number(command.sequence_number())
+            .pdu(Pdu::EnquireLinkResp);
+
+        if framed.send(response).await.is_err() {
+            break;
+        }
+    }
+}
+
+#[tokio::test]
+async fn shared_allocator_should_give_unique_sequence_numbers_across_connections() {
+    init_tracing();
+
+    let allocator = Arc::new(AtomicSequenceNumberAllocator::new());
+    let (tx, mut rx) = mpsc::unbounded_channel();
+
+    let mut clients = Vec::new();
+
+    for _ in 0..2 {
+        let (server, client) = tokio::io::duplex(1024);
+
+        tokio::spawn(run_server(server, tx.clone()));
+
+        let (client, _events) = ConnectionBuilder::new()
+            .sequence_number_allocator(allocator.clone())
+            .connected(client);
+
+        clients.push(client);
+    }
+
+    for _ in 0..3 {
+        for client in &clients {
+            client.enquire_link().await.expect("Failed to enquire link");
+        }
+    }
+
+    let mut received = Vec::new();
+
+    for _ in 0..6 {
+        received.push(rx.recv().await.expect("Server closed"));
+    }
+
+    assert_eq!(received, vec![1, 2, 3, 4, 5, 6]);
+}
+
+#[tokio::test]
+async fn client_sequence_numbers_should_wrap_around() {
+    init_tracing();
+
+    let (server, client) = tokio::io::duplex(1024);
+    let (tx, mut rx) = mpsc::unbounded_channel();
+
+    tokio::spawn(run_server(server, tx));
+
+    let (client, _events) = ConnectionBuilder::new()
+        .sequence_number_allocator(AtomicSequenceNumberAllocator::starting_at(
+            MAX_SEQUENCE_NUMBER,
+        ))
+        .connected(client);
+
+    client.enquire_link().await.expect("Failed to enquire link");
+    client.enquire_link().await.expect("Failed to enquire link");
+
+    assert_eq!(rx.recv().await, Some(MAX_SEQUENCE_NUMBER));
+    assert_eq!(rx.recv().await, Some(MIN_SEQUENCE_NUMBER));
+}
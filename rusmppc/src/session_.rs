@@ -0,0 +1,270 @@
+use rusmpp::{
+    Command, CommandId, Pdu,
+    tlvs::{MessageDeliveryResponseTlvValue, Tlv, TlvTag, TlvValue},
+    types::{AnyOctetString, COctetString},
+    values::{CongestionState, InterfaceVersion},
+};
+
+use crate::error::Error;
+
+#[cfg(test)]
+mod tests;
+
+const TARGET: &str = "rusmppc::session";
+
+/// Session parameters negotiated with the `MC` by the last successful bind.
+///
+/// Returned by [`Client::session_params`](crate::Client::session_params).
+///
+/// While the negotiated [`interface_version`](SessionParams::interface_version) is lower than `SMPP v5.0`, the client adapts the requests it sends:
+///
+/// - `SMPP v5.0` TLVs, e.g. `billing_identification`, `source_network_id` or `congestion_state`, are removed from [`SubmitSm`](rusmpp::pdus::SubmitSm), [`SubmitMulti`](rusmpp::pdus::SubmitMulti) and [`DataSm`](rusmpp::pdus::DataSm).
+/// - All TLVs are removed if the `MC` reports a version lower than `SMPP v3.4`, which does not support TLVs.
+/// - Broadcast operations are rejected with [`Error::UnsupportedByServer`] without being sent.
+///
+/// Raw requests sent with [`Client::raw`](crate::Client::raw) are not adapted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionParams {
+    system_id: COctetString<1, 16>,
+    bind_interface_version: InterfaceVersion,
+    sc_interface_version: Option<InterfaceVersion>,
+    congestion_state: Option<CongestionState>,
+}
+
+impl SessionParams {
+    pub(crate) fn new(
+        bind_interface_version: InterfaceVersion,
+        system_id: COctetString<1, 16>,
+        sc_interface_version: Option<InterfaceVersion>,
+    ) -> Self {
+        Self {
+            system_id,
+            bind_interface_version,
+            sc_interface_version,
+            congestion_state: None,
+        }
+    }
+
+    /// Creates the session parameters from a successful bind response.
+    ///
+    /// Returns `None` if the command is not a bind response.
+    pub(crate) fn from_bind_resp(
+        bind_interface_version: InterfaceVersion,
+        command: &Command,
+    ) -> Option<Self> {
+        let (system_id, sc_interface_version) = match command.pdu()? {
+            Pdu::BindTransmitterResp(resp) => (&resp.system_id, resp.sc_interface_version()),
+            Pdu::BindReceiverResp(resp) => (&resp.system_id, resp.sc_interface_version()),
+            Pdu::BindTransceiverResp(resp) => (&resp.system_id, resp.sc_interface_version()),
+            _ => return None,
+        };
+
+        Some(Self::new(
+            bind_interface_version,
+            system_id.clone(),
+            sc_interface_version,
+        ))
+    }
+
+    /// The `system_id` of the `MC`.
+    pub fn system_id(&self) -> &COctetString<1, 16> {
+        &self.system_id
+    }
+
+    /// The interface version sent by the client in the bind request.
+    pub fn bind_interface_version(&self) -> InterfaceVersion {
+        self.bind_interface_version
+    }
+
+    /// The `sc_interface_version` TLV of the bind response.
+    ///
+    /// `None` if the `MC` did not report its version.
+    pub fn sc_interface_version(&self) -> Option<InterfaceVersion> {
+        self.sc_interface_version
+    }
+
+    /// The interface version of the session.
+    ///
+    /// The lower of [`bind_interface_version`](Self::bind_interface_version) and [`sc_interface_version`](Self::sc_interface_version).
+    /// If the `MC` did not report its version, the version of the bind request is assumed.
+    pub fn interface_version(&self) -> InterfaceVersion {
+        match self.sc_interface_version {
+            Some(version) if u8::from(version) < u8::from(self.bind_interface_version) => version,
+            _ => self.bind_interface_version,
+        }
+    }
+
+    /// Whether the session supports `SMPP v5.0` operations and TLVs.
+    pub fn supports_smpp5(&self) -> bool {
+        u8::from(self.interface_version()) >= u8::from(InterfaceVersion::Smpp5_0)
+    }
+
+    /// Whether the session supports TLVs, i.e. the interface version is at least `SMPP v3.4`.
+    pub fn supports_tlvs(&self) -> bool {
+        u8::from(self.interface_version()) >= u8::from(InterfaceVersion::Smpp3_4)
+    }
+
+    /// The last `congestion_state` TLV received from the `MC`.
+    ///
+    /// `None` if the `MC` did not report its congestion state in this session.
+    pub fn congestion_state(&self) -> Option<CongestionState> {
+        self.congestion_state
+    }
+
+    pub(crate) fn set_congestion_state(&mut self, congestion_state: CongestionState) -> bool {
+        let changed = self.congestion_state != Some(congestion_state);
+
+        self.congestion_state = Some(congestion_state);
+
+        changed
+    }
+
+    /// Adapts an outgoing command to the negotiated interface version.
+    pub(crate) fn adapt(&self, command: Command) -> Result<Command, Error> {
+        if self.supports_smpp5() {
+            return Ok(command);
+        }
+
+        let id = command.id();
+
+        if matches!(
+            id,
+            CommandId::BroadcastSm | CommandId::QueryBroadcastSm | CommandId::CancelBroadcastSm
+        ) {
+            return Err(Error::UnsupportedByServer {
+                id,
+                version: self.interface_version(),
+            });
+        }
+
+        if !matches!(
+            id,
+            CommandId::SubmitSm | CommandId::SubmitMulti | CommandId::DataSm
+        ) {
+            return Ok(command);
+        }
+
+        let supports_tlvs = self.supports_tlvs();
+        let retain = |tlv: &Tlv| supports_tlvs && !is_smpp5_tlv(tlv.tag());
+
+        let (id, status, sequence_number, pdu) = command.into_parts().raw();
+
+        let pdu = pdu.map(|mut pdu| {
+            let before = tlvs(&pdu).len();
+
+            match &mut pdu {
+                Pdu::SubmitSm(submit_sm) => submit_sm.retain_tlvs(retain),
+                Pdu::SubmitMulti(submit_multi) => submit_multi.retain_tlvs(retain),
+                Pdu::DataSm(data_sm) => data_sm.retain_tlvs(retain),
+                _ => {}
+            }
+
+            let removed = before - tlvs(&pdu).len();
+
+            if removed > 0 {
+                tracing::debug!(target: TARGET, sequence_number, ?id, removed, version = ?self.interface_version(), "Removed TLVs not supported by the session");
+            }
+
+            pdu
+        });
+
+        Ok(Command::from_parts(rusmpp::command::CommandParts::new(
+            id,
+            status,
+            sequence_number,
+            pdu,
+        )))
+    }
+
+    /// Adds the `congestion_state` TLV of the client to an outgoing delivery response.
+    ///
+    /// Responses that already report a congestion state are left as they are.
+    pub(crate) fn report_congestion_state(
+        &self,
+        command: Command,
+        congestion_state: CongestionState,
+    ) -> Command {
+        if !self.supports_smpp5()
+            || !matches!(
+                command.id(),
+                CommandId::DeliverSmResp | CommandId::DataSmResp
+            )
+        {
+            return command;
+        }
+
+        let (id, status, sequence_number, pdu) = command.into_parts().raw();
+
+        let pdu = pdu.map(|mut pdu| {
+            if tlvs(&pdu)
+                .iter()
+                .any(|tlv| tlv.tag() == TlvTag::CongestionState)
+            {
+                return pdu;
+            }
+
+            // The message delivery response TLVs do not include `congestion_state`.
+            let tlv = MessageDeliveryResponseTlvValue::Other {
+                tag: TlvTag::CongestionState,
+                value: AnyOctetString::from_slice(&[u8::from(congestion_state)]),
+            };
+
+            match &mut pdu {
+                Pdu::DeliverSmResp(deliver_sm_resp) => deliver_sm_resp.push_tlv(tlv),
+                Pdu::DataSmResp(data_sm_resp) => data_sm_resp.push_tlv(tlv),
+                _ => {}
+            }
+
+            pdu
+        });
+
+        Command::from_parts(rusmpp::command::CommandParts::new(
+            id,
+            status,
+            sequence_number,
+            pdu,
+        ))
+    }
+}
+
+/// TLVs introduced in `SMPP v5.0`.
+pub(crate) fn is_smpp5_tlv(tag: TlvTag) -> bool {
+    // Broadcast, billing, network and node ids and number portability TLVs share the `0x0600` range.
+    matches!(tag, TlvTag::CongestionState) || (0x0600..=0x06FF).contains(&u16::from(tag))
+}
+
+fn tlvs(pdu: &Pdu) -> &[Tlv] {
+    match pdu {
+        Pdu::SubmitSm(pdu) => pdu.tlvs(),
+        Pdu::SubmitMulti(pdu) => pdu.tlvs(),
+        Pdu::DataSm(pdu) => pdu.tlvs(),
+        Pdu::DeliverSm(pdu) => pdu.tlvs(),
+        Pdu::SubmitSmResp(pdu) => pdu.tlvs(),
+        Pdu::SubmitMultiResp(pdu) => pdu.tlvs(),
+        Pdu::DataSmResp(pdu) => pdu.tlvs(),
+        Pdu::DeliverSmResp(pdu) => pdu.tlvs(),
+        Pdu::BroadcastSmResp(pdu) => pdu.tlvs(),
+        Pdu::QueryBroadcastSmResp(pdu) => pdu.tlvs(),
+        _ => &[],
+    }
+}
+
+/// The `congestion_state` TLV of a command received from the `MC`.
+pub(crate) fn congestion_state(command: &Command) -> Option<CongestionState> {
+    tlvs(command.pdu()?)
+        .iter()
+        .find_map(|tlv| match tlv.value() {
+            Some(TlvValue::CongestionState(state)) => Some(*state),
+            _ => None,
+        })
+}
+
+/// The interface version of a bind request.
+pub(crate) fn bind_interface_version(command: &Command) -> Option<InterfaceVersion> {
+    match command.pdu()? {
+        Pdu::BindTransmitter(bind) => Some(bind.interface_version),
+        Pdu::BindReceiver(bind) => Some(bind.interface_version),
+        Pdu::BindTransceiver(bind) => Some(bind.interface_version),
+        _ => None,
+    }
+}
@@ -0,0 +1,256 @@
+use std::str::FromStr;
+
+use futures::{SinkExt, StreamExt};
+use rusmpp::{
+    CommandStatus,
+    pdus::{BindTransceiver, BindTransceiverResp, BroadcastSm, SubmitSm, SubmitSmResp},
+    tlvs::MessageSubmissionRequestTlvValue,
+    tokio_codec::CommandCodec,
+    types::{COctetString, OctetString},
+    values::MessagePayload,
+};
+use tokio::{
+    io::{AsyncWriteExt, DuplexStream},
+    sync::mpsc,
+};
+use tokio_util::codec::Framed;
+
+use super::*;
+use crate::{ConnectionBuilder, tests::encode_with_congestion_state};
+
+fn params(bind: InterfaceVersion, sc: Option<InterfaceVersion>) -> SessionParams {
+    SessionParams::new(bind, COctetString::from_str("SMSC").unwrap(), sc)
+}
+
+/// Server reporting `sc_interface_version` and answering [`SubmitSm`]s with the `congestion_state` TLV.
+///
+/// Received [`SubmitSm`]s are reported.
+async fn run_server(
+    stream: DuplexStream,
+    sc_interface_version: Option<InterfaceVersion>,
+    congestion_state: u8,
+    submits: mpsc::UnboundedSender<SubmitSm>,
+) {
+    let mut framed = Framed::new(stream, CommandCodec::new());
+
+    while let Some(Ok(command)) = framed.next().await {
+        let sequence_number = command.sequence_number();
+
+        match command.id() {
+            CommandId::BindTransceiver => {
+                let response = Command::builder()
+                    .status(CommandStatus::EsmeRok)
+                    .sequence_number(sequence_number)
+                    .pdu(BindTransceiverResp::new(
+                        COctetString::from_str("SMSC").unwrap(),
+                        sc_interface_version,
+                    ));
+
+                framed.send(response).await.unwrap();
+            }
+            CommandId::SubmitSm => {
+                let Some(Pdu::SubmitSm(submit_sm)) = command.into_parts().raw().3 else {
+                    unreachable!()
+                };
+
+                let _ = submits.send(submit_sm);
+
+                let response = Command::builder()
+                    .status(CommandStatus::EsmeRok)
+                    .sequence_number(sequence_number)
+                    .pdu(
+                        SubmitSmResp::builder()
+                            .message_id(COctetString::from_str("id").unwrap())
+                            .build(),
+                    );
+
+                let buf = encode_with_congestion_state(&response, congestion_state);
+
+                framed.get_mut().write_all(&buf).await.unwrap();
+            }
+            CommandId::Unbind => {
+                let response = Command::builder()
+                    .status(CommandStatus::EsmeRok)
+                    .sequence_number(sequence_number)
+                    .pdu(Pdu::UnbindResp);
+
+                framed.send(response).await.unwrap();
+            }
+            _ => {}
+        }
+    }
+}
+
+fn submit_sm() -> SubmitSm {
+    SubmitSm::builder()
+        .destination_addr(COctetString::from_str("123456").unwrap())
+        .tlvs(vec![
+            MessageSubmissionRequestTlvValue::BillingIdentification(
+                OctetString::from_static_str("billing").unwrap(),
+            ),
+            MessageSubmissionRequestTlvValue::MessagePayload(MessagePayload::new(
+                rusmpp::types::AnyOctetString::from_static_slice(b"payload"),
+            )),
+        ])
+        .build()
+}
+
+#[test]
+fn interface_version_should_be_the_lower_of_both_versions() {
+    let v3_4 = params(InterfaceVersion::Smpp5_0, Some(InterfaceVersion::Smpp3_4));
+
+    assert_eq!(v3_4.interface_version(), InterfaceVersion::Smpp3_4);
+    assert!(!v3_4.supports_smpp5());
+    assert!(v3_4.supports_tlvs());
+
+    let v5_0 = params(InterfaceVersion::Smpp5_0, Some(InterfaceVersion::Smpp5_0));
+
+    assert_eq!(v5_0.interface_version(), InterfaceVersion::Smpp5_0);
+    assert!(v5_0.supports_smpp5());
+
+    let unreported = params(InterfaceVersion::Smpp3_4, None);
+
+    assert_eq!(unreported.interface_version(), InterfaceVersion::Smpp3_4);
+
+    let v3_3 = params(
+        InterfaceVersion::Smpp5_0,
+        Some(InterfaceVersion::Smpp3_3OrEarlier(0x33)),
+    );
+
+    assert!(!v3_3.supports_tlvs());
+}
+
+#[test]
+fn smpp5_tlvs_should_be_removed_from_v3_3_and_v3_4_sessions() {
+    let command = |params: &SessionParams| {
+        let command = Command::new(CommandStatus::EsmeRok, 1, submit_sm());
+
+        let Some(Pdu::SubmitSm(submit_sm)) = params.adapt(command).unwrap().into_parts().raw().3
+        else {
+            unreachable!()
+        };
+
+        submit_sm
+            .tlvs()
+            .iter()
+            .map(|tlv| tlv.tag())
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        command(&params(
+            InterfaceVersion::Smpp5_0,
+            Some(InterfaceVersion::Smpp5_0)
+        )),
+        vec![TlvTag::BillingIdentification, TlvTag::MessagePayload]
+    );
+    assert_eq!(
+        command(&params(
+            InterfaceVersion::Smpp5_0,
+            Some(InterfaceVersion::Smpp3_4)
+        )),
+        vec![TlvTag::MessagePayload]
+    );
+    assert_eq!(
+        command(&params(
+            InterfaceVersion::Smpp5_0,
+            Some(InterfaceVersion::Smpp3_3OrEarlier(0x33))
+        )),
+        vec![]
+    );
+}
+
+#[tokio::test]
+async fn client_should_adapt_to_negotiated_session() {
+    let (server, client) = tokio::io::duplex(1024);
+    let (tx, mut rx) = mpsc::unbounded_channel();
+
+    tokio::spawn(run_server(server, Some(InterfaceVersion::Smpp3_4), 95, tx));
+
+    let (client, _events) = ConnectionBuilder::new()
+        .no_enquire_link_interval()
+        .connected(client);
+
+    assert_eq!(client.session_params(), None);
+
+    client
+        .bind_transceiver(BindTransceiver::default())
+        .await
+        .expect("Failed to bind");
+
+    let params = client
+        .session_params()
+        .expect("Session should be negotiated");
+
+    assert_eq!(params.system_id().as_str(), "SMSC");
+    assert_eq!(params.bind_interface_version(), InterfaceVersion::Smpp5_0);
+    assert_eq!(
+        params.sc_interface_version(),
+        Some(InterfaceVersion::Smpp3_4)
+    );
+    assert_eq!(params.interface_version(), InterfaceVersion::Smpp3_4);
+    assert_eq!(params.congestion_state(), None);
+
+    client
+        .submit_sm(submit_sm())
+        .await
+        .expect("Failed to submit");
+
+    let submitted = rx.recv().await.unwrap();
+
+    assert_eq!(submitted.tlvs().len(), 1);
+    assert_eq!(submitted.tlvs()[0].tag(), TlvTag::MessagePayload);
+
+    assert_eq!(
+        client.session_params().unwrap().congestion_state(),
+        Some(CongestionState::NearingCongestion(95))
+    );
+
+    let error = client
+        .broadcast_sm(BroadcastSm::default())
+        .await
+        .expect_err("Broadcast should be rejected");
+
+    assert!(matches!(
+        error,
+        Error::UnsupportedByServer {
+            id: CommandId::BroadcastSm,
+            version: InterfaceVersion::Smpp3_4
+        }
+    ));
+
+    client.unbind().await.expect("Failed to unbind");
+
+    assert_eq!(client.session_params(), None);
+}
+
+#[tokio::test]
+async fn smpp5_session_should_keep_smpp5_tlvs() {
+    let (server, client) = tokio::io::duplex(1024);
+    let (tx, mut rx) = mpsc::unbounded_channel();
+
+    tokio::spawn(run_server(server, Some(InterfaceVersion::Smpp5_0), 0, tx));
+
+    let (client, _events) = ConnectionBuilder::new()
+        .no_enquire_link_interval()
+        .connected(client);
+
+    client
+        .bind_transceiver(BindTransceiver::default())
+        .await
+        .expect("Failed to bind");
+
+    assert!(client.session_params().unwrap().supports_smpp5());
+
+    client
+        .submit_sm(submit_sm())
+        .await
+        .expect("Failed to submit");
+
+    assert_eq!(rx.recv().await.unwrap().tlvs().len(), 2);
+
+    assert_eq!(
+        client.session_params().unwrap().congestion_state(),
+        Some(CongestionState::Idle)
+    );
+}
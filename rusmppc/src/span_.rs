@@ -0,0 +1,167 @@
+use std::{
+    fmt::Debug,
+    sync::{Arc, Mutex},
+};
+
+use rusmpp::{Command, Pdu};
+use tracing::{Span, field::Empty};
+
+#[cfg(test)]
+mod tests;
+
+/// The target of the spans created by the client.
+///
+/// - `smpp_session`: wraps the background connection. Fields: `system_id`.
+/// - `smpp_request`: wraps a request from sending it until its response arrives or it times out.
+///   Fields: `system_id`, `sequence_number`, `command_id`, `destination`, `command_status` and `error`.
+pub const SPAN_TARGET: &str = "rusmppc::span";
+
+/// Hook called with every new request span, before the request is sent.
+///
+/// Request spans are children of the current span when the request is issued.
+/// Use the hook to attach context that does not flow through [`tracing`] on its own,
+/// e.g. an `OpenTelemetry` context extracted from the headers of an incoming `HTTP` request:
+///
+/// ```rust, ignore
+/// use tracing_opentelemetry::OpenTelemetrySpanExt;
+///
+/// let builder = ConnectionBuilder::new().request_span_hook(|span: &tracing::Span, _: &Command| {
+///     span.set_parent(opentelemetry::Context::current());
+/// });
+/// ```
+///
+/// [`RequestSpanHook`] is implemented for closures taking a [`Span`] and the [`Command`] about to be sent.
+pub trait RequestSpanHook: Send + Sync + 'static {
+    /// Called with the span of the request and the command about to be sent.
+    fn on_request(&self, span: &Span, command: &Command);
+}
+
+impl<F> RequestSpanHook for F
+where
+    F: Fn(&Span, &Command) + Send + Sync + 'static,
+{
+    fn on_request(&self, span: &Span, command: &Command) {
+        self(span, command)
+    }
+}
+
+/// Request span hook configuration.
+///
+/// Shared by all connections created from the same [`ConnectionBuilder`](crate::ConnectionBuilder).
+#[derive(Clone)]
+pub(crate) struct SharedRequestSpanHook {
+    hook: Arc<dyn RequestSpanHook>,
+}
+
+impl Debug for SharedRequestSpanHook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SharedRequestSpanHook")
+            .finish_non_exhaustive()
+    }
+}
+
+impl SharedRequestSpanHook {
+    pub(crate) fn new(hook: Arc<dyn RequestSpanHook>) -> Self {
+        Self { hook }
+    }
+}
+
+/// Per connection spans shared by the [`Client`](crate::Client) and the background connection.
+pub(crate) struct SessionSpans {
+    session: Span,
+    // Taken from the last bind request.
+    system_id: Mutex<Option<String>>,
+    hook: Option<SharedRequestSpanHook>,
+}
+
+impl Debug for SessionSpans {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SessionSpans")
+            .field("session", &self.session)
+            .field("hook", &self.hook)
+            .finish_non_exhaustive()
+    }
+}
+
+impl SessionSpans {
+    pub(crate) fn new(hook: Option<SharedRequestSpanHook>) -> Self {
+        Self {
+            session: tracing::info_span!(target: SPAN_TARGET, parent: None, "smpp_session", system_id = Empty),
+            system_id: Mutex::new(None),
+            hook,
+        }
+    }
+
+    /// The span wrapping the background connection.
+    pub(crate) fn session(&self) -> &Span {
+        &self.session
+    }
+
+    /// Creates the span of a request and calls the hook.
+    ///
+    /// A bind request sets the `system_id` of the session and all following requests.
+    pub(crate) fn request(&self, command: &Command) -> Span {
+        let mut system_id = self.system_id.lock().expect("Session spans poisoned");
+
+        if let Some(bind_system_id) = bind_system_id(command) {
+            self.session.record("system_id", bind_system_id);
+
+            *system_id = Some(bind_system_id.to_owned());
+        }
+
+        let span = tracing::info_span!(
+            target: SPAN_TARGET,
+            "smpp_request",
+            system_id = system_id.as_deref(),
+            sequence_number = command.sequence_number(),
+            command_id = ?command.id(),
+            destination = Empty,
+            command_status = Empty,
+            error = Empty,
+        );
+
+        drop(system_id);
+
+        if let Some(destination) = destination(command) {
+            span.record("destination", destination);
+        }
+
+        span.follows_from(&self.session);
+
+        if let Some(hook) = &self.hook {
+            hook.hook.on_request(&span, command);
+        }
+
+        span
+    }
+}
+
+/// Records the outcome of a request on its span.
+pub(crate) fn record_response<E: std::fmt::Display>(span: &Span, response: &Result<Command, E>) {
+    match response {
+        Ok(command) => {
+            span.record("command_status", tracing::field::debug(command.status()));
+        }
+        Err(err) => {
+            span.record("error", tracing::field::display(err));
+        }
+    }
+}
+
+fn bind_system_id(command: &Command) -> Option<&str> {
+    match command.pdu()? {
+        Pdu::BindTransmitter(bind) => Some(bind.system_id.as_str()),
+        Pdu::BindReceiver(bind) => Some(bind.system_id.as_str()),
+        Pdu::BindTransceiver(bind) => Some(bind.system_id.as_str()),
+        _ => None,
+    }
+}
+
+fn destination(command: &Command) -> Option<&str> {
+    match command.pdu()? {
+        Pdu::SubmitSm(submit_sm) => Some(submit_sm.destination_addr.as_str()),
+        Pdu::DataSm(data_sm) => Some(data_sm.destination_addr.as_str()),
+        Pdu::CancelSm(cancel_sm) => Some(cancel_sm.destination_addr.as_str()),
+        _ => None,
+    }
+}
@@ -0,0 +1,263 @@
+use std::{collections::HashMap, str::FromStr, time::Duration};
+
+use futures::{SinkExt, StreamExt};
+use rusmpp::{
+    CommandId, CommandStatus,
+    pdus::{BindTransceiver, BindTransceiverResp, SubmitSm, SubmitSmResp},
+    tokio_codec::CommandCodec,
+    types::COctetString,
+};
+use tokio::io::DuplexStream;
+use tokio_util::codec::Framed;
+use tracing::{
+    Instrument, Subscriber,
+    field::{Field, Visit},
+    span::{Attributes, Id, Record},
+};
+use tracing_subscriber::{Layer, layer::Context, prelude::*, registry::LookupSpan};
+
+use super::*;
+use crate::ConnectionBuilder;
+
+#[derive(Debug, Default, Clone)]
+struct RecordedSpan {
+    name: &'static str,
+    parent: Option<&'static str>,
+    fields: HashMap<&'static str, String>,
+    closed: bool,
+}
+
+impl Visit for RecordedSpan {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.fields.insert(field.name(), value.to_owned());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.fields.insert(field.name(), format!("{value:?}"));
+    }
+}
+
+/// Records the spans of [`SPAN_TARGET`] and the `http` spans of the tests in creation order.
+#[derive(Clone, Default)]
+struct Recorder {
+    spans: Arc<Mutex<Vec<(Id, RecordedSpan)>>>,
+}
+
+impl Recorder {
+    fn spans(&self, name: &str) -> Vec<RecordedSpan> {
+        self.spans
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(_, span)| span)
+            .filter(|span| span.name == name)
+            .cloned()
+            .collect()
+    }
+
+    fn update(&self, id: &Id, f: impl FnOnce(&mut RecordedSpan)) {
+        if let Some((_, span)) = self
+            .spans
+            .lock()
+            .unwrap()
+            .iter_mut()
+            .find(|(span_id, _)| span_id == id)
+        {
+            f(span)
+        }
+    }
+}
+
+impl<S: Subscriber + for<'a> LookupSpan<'a>> Layer<S> for Recorder {
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let mut span = RecordedSpan {
+            name: attrs.metadata().name(),
+            parent: ctx
+                .span(id)
+                .and_then(|span| span.parent())
+                .map(|parent| parent.name()),
+            ..Default::default()
+        };
+
+        if attrs.metadata().target() == SPAN_TARGET || span.name == "http" {
+            attrs.record(&mut span);
+
+            self.spans.lock().unwrap().push((id.clone(), span));
+        }
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, _ctx: Context<'_, S>) {
+        self.update(id, |span| values.record(span));
+    }
+
+    fn on_close(&self, id: Id, _ctx: Context<'_, S>) {
+        self.update(&id, |span| span.closed = true);
+    }
+}
+
+fn install() -> (Recorder, tracing::subscriber::DefaultGuard) {
+    let recorder = Recorder::default();
+
+    let guard =
+        tracing::subscriber::set_default(tracing_subscriber::registry().with(recorder.clone()));
+
+    (recorder, guard)
+}
+
+/// Server that answers binds and [`SubmitSm`]s. [`SubmitSm`]s to `timeout` are not answered.
+async fn run_server(stream: DuplexStream) {
+    let mut framed = Framed::new(stream, CommandCodec::new());
+
+    while let Some(Ok(command)) = framed.next().await {
+        let sequence_number = command.sequence_number();
+
+        let pdu: Pdu = match command.id() {
+            CommandId::BindTransceiver => BindTransceiverResp::default().into(),
+            CommandId::SubmitSm => {
+                let Some(Pdu::SubmitSm(sm)) = command.into_parts().raw().3 else {
+                    unreachable!()
+                };
+
+                if sm.destination_addr.as_str() == "timeout" {
+                    continue;
+                }
+
+                SubmitSmResp::default().into()
+            }
+            _ => continue,
+        };
+
+        let response = Command::builder()
+            .status(CommandStatus::EsmeRok)
+            .sequence_number(sequence_number)
+            .pdu(pdu);
+
+        if framed.send(response).await.is_err() {
+            break;
+        }
+    }
+}
+
+fn submit_sm(destination: &str) -> SubmitSm {
+    SubmitSm::builder()
+        .destination_addr(COctetString::from_str(destination).unwrap())
+        .build()
+}
+
+#[tokio::test]
+async fn request_span_should_carry_request_and_response_fields() {
+    let (recorder, _guard) = install();
+
+    let (server, client) = tokio::io::duplex(1024);
+
+    tokio::spawn(run_server(server));
+
+    let (client, _events) = ConnectionBuilder::new()
+        .no_enquire_link_interval()
+        .connected(client);
+
+    client
+        .bind_transceiver(
+            BindTransceiver::builder()
+                .system_id(COctetString::from_str("esme").unwrap())
+                .build(),
+        )
+        .await
+        .expect("Failed to bind");
+
+    client
+        .submit_sm(submit_sm("123456"))
+        .await
+        .expect("Failed to submit");
+
+    let requests = recorder.spans("smpp_request");
+
+    assert_eq!(requests.len(), 2);
+
+    let bind = &requests[0];
+
+    assert_eq!(bind.fields["system_id"], "esme");
+    assert_eq!(bind.fields["command_id"], "BindTransceiver");
+    assert_eq!(bind.fields["command_status"], "EsmeRok");
+
+    let submit = &requests[1];
+
+    assert_eq!(submit.fields["system_id"], "esme");
+    assert_eq!(submit.fields["command_id"], "SubmitSm");
+    assert_eq!(submit.fields["sequence_number"], "2");
+    assert_eq!(submit.fields["destination"], "123456");
+    assert_eq!(submit.fields["command_status"], "EsmeRok");
+    assert!(submit.closed);
+
+    let sessions = recorder.spans("smpp_session");
+
+    assert_eq!(sessions.len(), 1);
+    assert_eq!(sessions[0].fields["system_id"], "esme");
+}
+
+#[tokio::test]
+async fn request_span_should_be_closed_on_timeout() {
+    let (recorder, _guard) = install();
+
+    let (server, client) = tokio::io::duplex(1024);
+
+    tokio::spawn(run_server(server));
+
+    let (client, _events) = ConnectionBuilder::new()
+        .no_enquire_link_interval()
+        .response_timeout(Duration::from_millis(50))
+        .connected(client);
+
+    client
+        .submit_sm(submit_sm("timeout"))
+        .await
+        .expect_err("Response should time out");
+
+    let requests = recorder.spans("smpp_request");
+
+    assert_eq!(requests.len(), 1);
+    assert!(requests[0].fields.contains_key("error"));
+    assert!(!requests[0].fields.contains_key("command_status"));
+    assert!(requests[0].closed);
+}
+
+#[tokio::test]
+async fn request_span_should_be_a_child_of_the_current_span_and_passed_to_the_hook() {
+    let (recorder, _guard) = install();
+
+    let (server, client) = tokio::io::duplex(1024);
+
+    tokio::spawn(run_server(server));
+
+    let hooked = Arc::new(Mutex::new(Vec::new()));
+
+    let (client, _events) = ConnectionBuilder::new()
+        .no_enquire_link_interval()
+        .request_span_hook({
+            let hooked = hooked.clone();
+
+            move |span: &Span, command: &Command| {
+                hooked
+                    .lock()
+                    .unwrap()
+                    .push((span.metadata().map(|m| m.name()), command.id()));
+            }
+        })
+        .connected(client);
+
+    client
+        .submit_sm(submit_sm("123456"))
+        .instrument(tracing::info_span!("http"))
+        .await
+        .expect("Failed to submit");
+
+    let requests = recorder.spans("smpp_request");
+
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].parent, Some("http"));
+
+    assert_eq!(
+        *hooked.lock().unwrap(),
+        vec![(Some("smpp_request"), CommandId::SubmitSm)]
+    );
+}
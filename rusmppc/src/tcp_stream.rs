@@ -35,42 +35,7 @@ impl<S: AsyncRead + AsyncWrite + Unpin> MaybeTlsStream<S> {
         let config = match config {
             Some(config) => std::sync::Arc::new(config),
             None => {
-                #[allow(unused_mut)]
-                let mut root_store = rustls::RootCertStore::empty();
-                #[cfg(feature = "rustls-tls-native-roots")]
-                {
-                    tracing::debug!(target: "rusmppc::connection::tls::rustls", "Loading native root CA certificates");
-
-                    let rustls_native_certs::CertificateResult { certs, errors, .. } =
-                        rustls_native_certs::load_native_certs();
-
-                    if !errors.is_empty() {
-                        tracing::warn!(target: "rusmppc::connection::tls::rustls",?errors, "Native root CA certificate loading errors");
-                    }
-
-                    // Not finding any native root CA certificates is not fatal if the
-                    // "rustls-tls-webpki-roots" feature is enabled.
-                    #[cfg(not(feature = "rustls-tls-webpki-roots"))]
-                    if certs.is_empty() {
-                        return Err(crate::error::Error::Connect(std::io::Error::new(
-                            std::io::ErrorKind::NotFound,
-                            format!("No native root CA certificates found (errors: {errors:?})"),
-                        )));
-                    }
-
-                    let total = certs.len();
-                    let (added, ignored) = root_store.add_parsable_certificates(certs);
-
-                    tracing::debug!(target: "rusmppc::connection::tls::rustls", total, added, ignored, "Added native root certificates");
-                }
-                #[cfg(feature = "rustls-tls-webpki-roots")]
-                {
-                    tracing::debug!(target: "rusmppc::connection::tls::rustls", "Loading webpki root CA certificates");
-
-                    root_store.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
-
-                    tracing::debug!(target: "rusmppc::connection::tls::rustls", added = webpki_roots::TLS_SERVER_ROOTS.len(), "Added webpki root certificates");
-                }
+                let root_store = default_root_store()?;
 
                 std::sync::Arc::new(
                     rustls::ClientConfig::builder()
@@ -125,6 +90,49 @@ impl<S: AsyncRead + AsyncWrite + Unpin> MaybeTlsStream<S> {
     }
 }
 
+/// Loads the root certificates enabled by the `rustls-tls-native-roots` and `rustls-tls-webpki-roots` features.
+#[cfg(feature = "rustls")]
+pub(crate) fn default_root_store() -> Result<rustls::RootCertStore, crate::error::Error> {
+    #[allow(unused_mut)]
+    let mut root_store = rustls::RootCertStore::empty();
+    #[cfg(feature = "rustls-tls-native-roots")]
+    {
+        tracing::debug!(target: "rusmppc::connection::tls::rustls", "Loading native root CA certificates");
+
+        let rustls_native_certs::CertificateResult { certs, errors, .. } =
+            rustls_native_certs::load_native_certs();
+
+        if !errors.is_empty() {
+            tracing::warn!(target: "rusmppc::connection::tls::rustls",?errors, "Native root CA certificate loading errors");
+        }
+
+        // Not finding any native root CA certificates is not fatal if the
+        // "rustls-tls-webpki-roots" feature is enabled.
+        #[cfg(not(feature = "rustls-tls-webpki-roots"))]
+        if certs.is_empty() {
+            return Err(crate::error::Error::Connect(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("No native root CA certificates found (errors: {errors:?})"),
+            )));
+        }
+
+        let total = certs.len();
+        let (added, ignored) = root_store.add_parsable_certificates(certs);
+
+        tracing::debug!(target: "rusmppc::connection::tls::rustls", total, added, ignored, "Added native root certificates");
+    }
+    #[cfg(feature = "rustls-tls-webpki-roots")]
+    {
+        tracing::debug!(target: "rusmppc::connection::tls::rustls", "Loading webpki root CA certificates");
+
+        root_store.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+
+        tracing::debug!(target: "rusmppc::connection::tls::rustls", added = webpki_roots::TLS_SERVER_ROOTS.len(), "Added webpki root certificates");
+    }
+
+    Ok(root_store)
+}
+
 impl<S: AsyncRead + AsyncWrite + Unpin> AsyncRead for MaybeTlsStream<S> {
     fn poll_read(
         self: Pin<&mut Self>,
@@ -5,6 +5,7 @@
 //! For more in depth tests, see `connection/tests.rs`.
 
 use std::{
+    str::FromStr,
     sync::{
         Arc,
         atomic::{AtomicBool, Ordering},
@@ -17,10 +18,13 @@ use futures::{SinkExt, StreamExt};
 use rusmpp::{
     Command, CommandId, CommandStatus, Pdu,
     pdus::{
-        AlertNotification, BindReceiverResp, BindTransceiverResp, BindTransmitterResp, DeliverSm,
-        SubmitSm, SubmitSmResp,
+        AlertNotification, BindReceiver, BindReceiverResp, BindTransceiverResp, BindTransmitter,
+        BindTransmitterResp, DeliverSm, SubmitSm, SubmitSmResp,
     },
+    session::SessionState,
     tokio_codec::CommandCodec,
+    types::{COctetString, OctetString},
+    values::{EsmClass, MessageState, MessageType},
 };
 use tokio::io::{AsyncRead, AsyncWrite};
 use tokio_util::codec::Framed;
@@ -197,6 +201,24 @@ pub fn init_tracing() {
         .try_init();
 }
 
+/// Encodes a command with the `congestion_state` TLV appended, including the `command_length`.
+///
+/// The response TLVs of rusmpp do not include `congestion_state`, so it is appended to the encoded command.
+pub fn encode_with_congestion_state(command: &Command, congestion_state: u8) -> Vec<u8> {
+    use rusmpp::encode::{Encode, Length};
+
+    // The encoded command does not include the `command_length`.
+    let mut buf = vec![0; 4 + command.length()];
+    command.encode(&mut buf[4..]);
+
+    buf.extend_from_slice(&[0x04, 0x28, 0x00, 0x01, congestion_state]);
+
+    let length = buf.len() as u32;
+    buf[..4].copy_from_slice(&length.to_be_bytes());
+
+    buf
+}
+
 #[tokio::test]
 async fn cancel_request_future_should_remove_pending_response() {
     init_tracing();
@@ -916,8 +938,8 @@ async fn sent_enquire_link_and_received_enquire_link_response_should_be_sent_thr
         .connected(client);
 
     let expected_events = vec![
-        Insight::SentEnquireLink(2),
-        Insight::ReceivedEnquireLinkResp(2),
+        Insight::SentEnquireLink(1),
+        Insight::ReceivedEnquireLinkResp(1),
     ];
 
     let mut collected_events = Vec::new();
@@ -1110,3 +1132,410 @@ async fn raw_awaiting_a_response_for_an_operation_that_does_not_require_a_respon
 
     client.closed().await;
 }
+
+/// A server that accepts submissions with incrementing message ids and sends a delivery receipt for each after a delay.
+async fn run_receipt_server<S: AsyncRead + AsyncWrite + Send + Unpin + 'static>(
+    stream: S,
+    receipt_delay: Duration,
+) {
+    let mut framed = Framed::new(stream, CommandCodec::new());
+    let mut message_id = 0;
+
+    while let Some(Ok(command)) = framed.next().await {
+        if !matches!(command.id(), CommandId::SubmitSm) {
+            continue;
+        }
+
+        message_id += 1;
+
+        let id = format!("msg-{message_id}");
+
+        framed
+            .send(
+                Command::builder()
+                    .status(CommandStatus::EsmeRok)
+                    .sequence_number(command.sequence_number())
+                    .pdu(
+                        SubmitSmResp::builder()
+                            .message_id(COctetString::from_str(&id).unwrap())
+                            .build(),
+                    ),
+            )
+            .await
+            .expect("Failed to send SubmitSmResp");
+
+        tokio::time::sleep(receipt_delay).await;
+
+        let text = format!(
+            "id:{id} sub:001 dlvrd:001 submit date:2401011200 done date:2401011201 stat:DELIVRD err:000 text:"
+        );
+
+        framed
+            .send(
+                Command::builder()
+                    .status(CommandStatus::EsmeRok)
+                    .sequence_number(message_id * 2)
+                    .pdu(
+                        DeliverSm::builder()
+                            .esm_class(EsmClass {
+                                message_type: MessageType::ShortMessageContainsMcDeliveryReceipt,
+                                ..Default::default()
+                            })
+                            .short_message(OctetString::from_str(&text).unwrap())
+                            .build(),
+                    ),
+            )
+            .await
+            .expect("Failed to send DeliverSm");
+    }
+}
+
+#[tokio::test]
+async fn submit_sm_tracked_should_resolve_on_delivery_receipt() {
+    init_tracing();
+
+    let (server, client) = tokio::io::duplex(1024);
+
+    tokio::spawn(run_receipt_server(server, Duration::ZERO));
+
+    let (client, mut events) = ConnectionBuilder::new()
+        .delivery_tracking(Duration::from_secs(5))
+        .connected(client);
+
+    let (response, tracker) = client
+        .submit_sm_tracked(SubmitSm::default())
+        .await
+        .expect("Failed to submit SM");
+
+    assert_eq!(response.message_id().as_str(), "msg-1");
+    assert_eq!(tracker.message_id(), "msg-1");
+
+    let receipt = tracker.await.expect("Failed to receive receipt");
+
+    assert_eq!(receipt.message_id, "msg-1");
+    assert_eq!(receipt.message_state, Some(MessageState::Delivered));
+
+    // Receipts are still sent through the event stream.
+    let Some(Event::Incoming(command)) = events.next().await else {
+        panic!("Expected incoming event");
+    };
+
+    assert!(matches!(command.id(), CommandId::DeliverSm));
+}
+
+#[tokio::test]
+async fn submit_sm_tracked_should_time_out_without_delivery_receipt() {
+    init_tracing();
+
+    let (server, client) = tokio::io::duplex(1024);
+
+    tokio::spawn(run_receipt_server(server, Duration::from_secs(5)));
+
+    let (client, _events) = ConnectionBuilder::new()
+        .delivery_tracking(Duration::from_millis(100))
+        .connected(client);
+
+    let (_, tracker) = client
+        .submit_sm_tracked(SubmitSm::default())
+        .await
+        .expect("Failed to submit SM");
+
+    let err = tracker.await.expect_err("Expected timeout");
+
+    assert!(matches!(
+        err,
+        Error::DeliveryReceiptTimeout { message_id, .. } if message_id == "msg-1"
+    ));
+}
+
+#[tokio::test]
+async fn submit_sm_tracked_without_delivery_tracking_should_fail() {
+    init_tracing();
+
+    let (server, client) = tokio::io::duplex(1024);
+
+    tokio::spawn(run_receipt_server(server, Duration::ZERO));
+
+    let (client, _events) = ConnectionBuilder::new().connected(client);
+
+    let err = client
+        .submit_sm_tracked(SubmitSm::default())
+        .await
+        .expect_err("Expected error");
+
+    assert!(matches!(err, Error::DeliveryTrackingDisabled));
+}
+
+#[tokio::test]
+async fn submit_sm_before_bind_should_fail_locally() {
+    init_tracing();
+
+    let (server, client) = tokio::io::duplex(1024);
+
+    tokio::spawn(async move {
+        Server::new().run(server).await;
+    });
+
+    let (client, _events) = ConnectionBuilder::new()
+        .enable_session_state_check()
+        .connected(client);
+
+    assert_eq!(client.session_state(), SessionState::Open);
+
+    let err = client
+        .submit_sm(SubmitSm::default())
+        .await
+        .expect_err("Expected error");
+
+    assert!(matches!(
+        err,
+        Error::InvalidSessionState {
+            state: SessionState::Open,
+            id: CommandId::SubmitSm
+        }
+    ));
+
+    assert!(client.pending_responses().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn session_state_should_follow_bind_and_unbind() {
+    init_tracing();
+
+    let (server, client) = tokio::io::duplex(1024);
+
+    tokio::spawn(async move {
+        Server::new()
+            .bind_delay(Duration::ZERO)
+            .response_delay(Duration::ZERO)
+            .run(server)
+            .await;
+    });
+
+    let (client, _events) = ConnectionBuilder::new()
+        .enable_session_state_check()
+        .connected(client);
+
+    client
+        .bind_receiver(BindReceiver::default())
+        .await
+        .expect("Failed to bind");
+
+    assert_eq!(client.session_state(), SessionState::BoundRx);
+
+    let err = client
+        .submit_sm(SubmitSm::default())
+        .await
+        .expect_err("Expected error");
+
+    assert!(matches!(
+        err,
+        Error::InvalidSessionState {
+            state: SessionState::BoundRx,
+            id: CommandId::SubmitSm
+        }
+    ));
+
+    client.unbind().await.expect("Failed to unbind");
+
+    assert_eq!(client.session_state(), SessionState::Unbound);
+
+    client.close_and_wait().await.expect("Failed to close");
+
+    assert_eq!(client.session_state(), SessionState::Closed);
+}
+
+#[tokio::test]
+async fn incoming_operation_not_allowed_in_session_state_should_be_rejected() {
+    init_tracing();
+
+    let (server, client) = tokio::io::duplex(1024);
+
+    let server = tokio::spawn(async move {
+        let mut framed = Framed::new(server, CommandCodec::new());
+
+        let bind = framed.next().await.unwrap().unwrap();
+
+        framed
+            .send(
+                Command::builder()
+                    .status(CommandStatus::EsmeRok)
+                    .sequence_number(bind.sequence_number())
+                    .pdu(BindTransmitterResp::default()),
+            )
+            .await
+            .unwrap();
+
+        // A transmitter may not receive a DeliverSm.
+        framed
+            .send(
+                Command::builder()
+                    .status(CommandStatus::EsmeRok)
+                    .sequence_number(7)
+                    .pdu(DeliverSm::default()),
+            )
+            .await
+            .unwrap();
+
+        framed.next().await.unwrap().unwrap()
+    });
+
+    let (client, mut events) = ConnectionBuilder::new()
+        .enable_session_state_check()
+        .no_enquire_link_interval()
+        .events()
+        .insights()
+        .connected(client);
+
+    client
+        .bind_transmitter(BindTransmitter::default())
+        .await
+        .expect("Failed to bind");
+
+    while let Some(event) = events.next().await {
+        match event {
+            InsightEvent::Incoming(command) => panic!("Unexpected command: {command:?}"),
+            InsightEvent::Insight(Insight::RejectedCommand {
+                sequence_number,
+                id,
+                state,
+            }) => {
+                assert_eq!(sequence_number, 7);
+                assert_eq!(id, CommandId::DeliverSm);
+                assert_eq!(state, SessionState::BoundTx);
+
+                break;
+            }
+            _ => {}
+        }
+    }
+
+    let nack = server.await.unwrap();
+
+    assert_eq!(nack.id(), CommandId::GenericNack);
+    assert_eq!(nack.status(), CommandStatus::EsmeRinvbndsts);
+    assert_eq!(nack.sequence_number(), 7);
+}
+
+#[tokio::test]
+async fn shutdown_should_drain_pending_requests_then_unbind_and_close() {
+    init_tracing();
+
+    let (server, client) = tokio::io::duplex(1024);
+
+    tokio::spawn(async move {
+        Server::new()
+            .bind_delay(Duration::from_millis(0))
+            .response_delay(Duration::from_millis(100))
+            .run(server)
+            .await;
+    });
+
+    let (client, _events) = ConnectionBuilder::new().connected(client);
+
+    client
+        .bind_transmitter(BindTransmitter::default())
+        .await
+        .expect("Failed to bind");
+
+    let submits = (0..3)
+        .map(|_| {
+            let client = client.clone();
+
+            tokio::spawn(async move { client.submit_sm(SubmitSm::default()).await })
+        })
+        .collect::<Vec<_>>();
+
+    // Let the submits reach the connection before shutting down.
+    tokio::time::sleep(Duration::from_millis(20)).await;
+
+    let shutdown = tokio::spawn({
+        let client = client.clone();
+
+        async move { client.shutdown(Duration::from_secs(5)).await }
+    });
+
+    tokio::time::sleep(Duration::from_millis(20)).await;
+
+    let err = client
+        .submit_sm(SubmitSm::default())
+        .await
+        .expect_err("Expected error");
+
+    assert!(matches!(err, Error::ShuttingDown));
+
+    for submit in submits {
+        submit
+            .await
+            .unwrap()
+            .expect("Pending submit should be answered");
+    }
+
+    shutdown
+        .await
+        .unwrap()
+        .expect("Failed to shut down gracefully");
+
+    assert!(client.is_closed());
+    assert_eq!(client.session_state(), SessionState::Closed);
+}
+
+#[tokio::test]
+async fn shutdown_deadline_should_elapse_and_still_close() {
+    init_tracing();
+
+    let (server, client) = tokio::io::duplex(1024);
+
+    tokio::spawn(async move {
+        Server::new()
+            .bind_delay(Duration::from_millis(0))
+            .response_delay(Duration::from_millis(300))
+            .run(server)
+            .await;
+    });
+
+    let (client, _events) = ConnectionBuilder::new().connected(client);
+
+    client
+        .bind_transmitter(BindTransmitter::default())
+        .await
+        .expect("Failed to bind");
+
+    let submit = tokio::spawn({
+        let client = client.clone();
+
+        async move { client.submit_sm(SubmitSm::default()).await }
+    });
+
+    tokio::time::sleep(Duration::from_millis(20)).await;
+
+    let err = client
+        .shutdown(Duration::from_millis(50))
+        .await
+        .expect_err("Expected error");
+
+    assert!(matches!(err, Error::ShutdownDeadline { pending: 1, .. }));
+
+    assert!(client.is_closed());
+
+    // The response arrived while unbinding.
+    submit.await.unwrap().expect("Failed to submit");
+}
+
+#[tokio::test]
+async fn shutdown_closed_connection_should_fail() {
+    init_tracing();
+
+    let (_server, client) = tokio::io::duplex(1024);
+
+    let (client, _events) = ConnectionBuilder::new().connected(client);
+
+    client.close_and_wait().await.expect("Failed to close");
+
+    let err = client
+        .shutdown(Duration::from_secs(1))
+        .await
+        .expect_err("Expected error");
+
+    assert!(matches!(err, Error::ConnectionClosed));
+}
@@ -0,0 +1,267 @@
+use std::sync::atomic::{AtomicU16, Ordering};
+
+use futures::future::join_all;
+use rusmpp::{
+    extra::{
+        concatenation::{Concatenator, SubmitSmMultipartExt},
+        encoding::{Encoder, gsm7bit::Gsm7BitUnpacked, latin1::Latin1, ucs2::Ucs2},
+        fallback::Fallback,
+    },
+    pdus::{SubmitSm, SubmitSmResp},
+    tlvs::MessageSubmissionRequestTlvValue,
+    types::{AnyOctetString, COctetString, OctetString},
+    values::{DataCoding, MessagePayload},
+};
+
+use crate::{Client, error::Error, runtime_::Timeout};
+
+#[cfg(test)]
+mod tests;
+
+const TARGET: &str = "rusmppc::text";
+
+/// Reference numbers used for concatenated messages when none is set in the [`SendTextOptions`].
+static REFERENCE: AtomicU16 = AtomicU16::new(0);
+
+/// The encoding used by [`Client::send_text`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TextEncoding {
+    /// Tries [`Gsm7BitUnpacked`], then [`Latin1`], then [`Ucs2`].
+    #[default]
+    Auto,
+    /// [`Gsm7BitUnpacked`] only.
+    Gsm7Bit,
+    /// [`Latin1`] only.
+    Latin1,
+    /// [`Ucs2`] only.
+    Ucs2,
+}
+
+/// How [`Client::send_text`] transports texts that do not fit in a single short message.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ConcatenationMode {
+    /// Concatenated short messages with an 8-bit reference in the `UDH`.
+    #[default]
+    Udh,
+    /// Concatenated short messages with a 16-bit reference in the `UDH`.
+    Udh16,
+    /// Concatenated short messages using the `sar_msg_ref_num`, `sar_total_segments` and `sar_segment_seqnum` TLVs.
+    Sar,
+    /// A single [`SubmitSm`] carrying the whole text in the `message_payload` TLV.
+    MessagePayload,
+}
+
+/// Options for [`Client::send_text`].
+#[derive(Debug, Clone, Default)]
+pub struct SendTextOptions {
+    encoding: TextEncoding,
+    concatenation: ConcatenationMode,
+    reference: Option<u16>,
+    max_short_message_size: Option<usize>,
+    template: SubmitSm,
+}
+
+impl SendTextOptions {
+    /// Creates new [`SendTextOptions`].
+    ///
+    /// # Defaults
+    /// - `encoding`: [`TextEncoding::Auto`]
+    /// - `concatenation`: [`ConcatenationMode::Udh`]
+    /// - `reference`: a process wide counter
+    /// - `max_short_message_size`: [`SubmitSm::default_max_short_message_size`]
+    /// - `template`: [`SubmitSm::default`]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the encoding.
+    pub const fn encoding(mut self, encoding: TextEncoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    /// Sets the concatenation mode.
+    pub const fn concatenation(mut self, concatenation: ConcatenationMode) -> Self {
+        self.concatenation = concatenation;
+        self
+    }
+
+    /// Sets the reference number of the concatenated message.
+    ///
+    /// Truncated to 8 bits with [`ConcatenationMode::Udh`].
+    pub const fn reference(mut self, reference: u16) -> Self {
+        self.reference = Some(reference);
+        self
+    }
+
+    /// Overrides the maximum size of each short message.
+    pub const fn max_short_message_size(mut self, size: usize) -> Self {
+        self.max_short_message_size = Some(size);
+        self
+    }
+
+    /// Sets the [`SubmitSm`] every part is created from.
+    ///
+    /// Use it to set fields like `service_type`, `registered_delivery` or the address `ton`/`npi`.
+    /// The `source_addr`, `destination_addr`, `data_coding` and `short_message` are overridden.
+    pub fn template(mut self, template: SubmitSm) -> Self {
+        self.template = template;
+        self
+    }
+}
+
+/// The result of [`Client::send_text`].
+///
+/// Holds the response or error of each submitted part, in order.
+#[derive(Debug)]
+pub struct MultipartSubmitResult {
+    data_coding: DataCoding,
+    parts: Vec<Result<SubmitSmResp, Error>>,
+}
+
+impl MultipartSubmitResult {
+    /// Returns the [`DataCoding`] the text was encoded with.
+    pub const fn data_coding(&self) -> DataCoding {
+        self.data_coding
+    }
+
+    /// Returns the number of submitted parts.
+    pub fn len(&self) -> usize {
+        self.parts.len()
+    }
+
+    /// Returns `true` if there are no parts.
+    pub fn is_empty(&self) -> bool {
+        self.parts.is_empty()
+    }
+
+    /// Returns `true` if every part was accepted by the server.
+    pub fn is_success(&self) -> bool {
+        self.parts.iter().all(Result::is_ok)
+    }
+
+    /// Returns the result of each part.
+    pub fn parts(&self) -> &[Result<SubmitSmResp, Error>] {
+        &self.parts
+    }
+
+    /// Returns the `message_id` or error of each part.
+    pub fn message_ids(&self) -> impl Iterator<Item = Result<&str, &Error>> {
+        self.parts
+            .iter()
+            .map(|part| part.as_ref().map(|response| response.message_id().as_str()))
+    }
+
+    /// Consumes the result and returns the result of each part.
+    pub fn into_parts(self) -> Vec<Result<SubmitSmResp, Error>> {
+        self.parts
+    }
+}
+
+impl<T: Timeout> Client<T> {
+    /// Encodes, splits and submits a text.
+    ///
+    /// The text is encoded according to [`SendTextOptions::encoding`] and split into concatenated parts according to [`SendTextOptions::concatenation`].
+    /// All parts are sent in order without waiting for the previous response.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::TextEncoding`] if the text can not be encoded or split. Nothing is sent in this case.
+    /// Errors of individual parts are returned in the [`MultipartSubmitResult`].
+    pub async fn send_text(
+        &self,
+        from: COctetString<1, 21>,
+        to: COctetString<1, 21>,
+        text: &str,
+        options: SendTextOptions,
+    ) -> Result<MultipartSubmitResult, Error> {
+        let parts = match options.encoding {
+            TextEncoding::Auto => build(
+                text,
+                from,
+                to,
+                options,
+                Fallback::new(
+                    Fallback::new(Gsm7BitUnpacked::new(), Latin1::new()),
+                    Ucs2::new(),
+                ),
+            ),
+            TextEncoding::Gsm7Bit => build(text, from, to, options, Gsm7BitUnpacked::new()),
+            TextEncoding::Latin1 => build(text, from, to, options, Latin1::new()),
+            TextEncoding::Ucs2 => build(text, from, to, options, Ucs2::new()),
+        }?;
+
+        let data_coding = parts.first().map(|sm| sm.data_coding).unwrap_or_default();
+
+        tracing::debug!(target: TARGET, parts = parts.len(), ?data_coding, "Submitting text");
+
+        let parts = join_all(parts.into_iter().map(|sm| self.submit_sm(sm))).await;
+
+        Ok(MultipartSubmitResult { data_coding, parts })
+    }
+}
+
+fn build<E>(
+    text: &str,
+    from: COctetString<1, 21>,
+    to: COctetString<1, 21>,
+    options: SendTextOptions,
+    encoder: E,
+) -> Result<Vec<SubmitSm>, Error>
+where
+    E: Encoder + Concatenator,
+    <E as Encoder>::Error: std::error::Error + Send + Sync + 'static,
+    <E as Concatenator>::Error: std::error::Error + Send + Sync + 'static,
+{
+    let mut sm = options.template;
+
+    sm.source_addr = from;
+    sm.destination_addr = to;
+
+    let reference = options
+        .reference
+        .unwrap_or_else(|| REFERENCE.fetch_add(1, Ordering::Relaxed));
+
+    let max_short_message_size = options
+        .max_short_message_size
+        .unwrap_or(SubmitSm::default_max_short_message_size());
+
+    match options.concatenation {
+        ConcatenationMode::Udh => sm
+            .multipart(text)
+            .encoder(encoder)
+            .reference_u8(reference as u8)
+            .max_short_message_size(max_short_message_size)
+            .build()
+            .map_err(Error::text_encoding),
+        ConcatenationMode::Udh16 => sm
+            .multipart(text)
+            .encoder(encoder)
+            .reference_u16(reference)
+            .max_short_message_size(max_short_message_size)
+            .build()
+            .map_err(Error::text_encoding),
+        ConcatenationMode::Sar => sm
+            .sar_multipart(text)
+            .encoder(encoder)
+            .reference(reference)
+            .max_short_message_size(max_short_message_size)
+            .build()
+            .map_err(Error::text_encoding),
+        ConcatenationMode::MessagePayload => {
+            let (payload, data_coding) = encoder.encode(text).map_err(Error::text_encoding)?;
+
+            let mut sm = sm
+                .with_short_message(OctetString::empty())
+                .with_data_coding(data_coding);
+
+            sm.push_tlv(MessageSubmissionRequestTlvValue::MessagePayload(
+                MessagePayload::new(AnyOctetString::from_vec(payload)),
+            ));
+
+            Ok(vec![sm])
+        }
+    }
+}
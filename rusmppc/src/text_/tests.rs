@@ -0,0 +1,254 @@
+use std::str::FromStr;
+
+use futures::{SinkExt, StreamExt};
+use rusmpp::{
+    Command, CommandId, CommandStatus, Pdu,
+    pdus::{BindTransmitter, BindTransmitterResp, SubmitSm, SubmitSmResp},
+    tlvs::TlvTag,
+    tokio_codec::CommandCodec,
+    types::COctetString,
+    values::{DataCoding, GsmFeatures},
+};
+use tokio::{io::DuplexStream, sync::mpsc};
+use tokio_util::codec::Framed;
+
+use crate::{
+    Client, ConnectionBuilder,
+    error::Error,
+    tests::init_tracing,
+    text::{ConcatenationMode, SendTextOptions, TextEncoding},
+};
+
+// c-spell: disable
+const LONG_GSM_TEXT: &str = "Lorem ipsum dolor sit amet, consectetur adipiscing elit, sed do eiusmod tempor incididunt ut labore et dolore magna aliqua. Ut enim ad minim veniam, quis nostrud exercitation ullamco laboris nisi ut aliquip ex ea commodo consequat.";
+// c-spell: enable
+
+/// Server that answers [`SubmitSm`]s with the message id `id-<n>` and forwards them through the channel.
+///
+/// Rejects the part with the given number (1-based) with [`CommandStatus::EsmeRsyserr`].
+async fn run_server(
+    stream: DuplexStream,
+    reject: Option<usize>,
+    parts: mpsc::UnboundedSender<SubmitSm>,
+) {
+    let mut framed = Framed::new(stream, CommandCodec::new());
+    let mut received = 0;
+
+    while let Some(Ok(command)) = framed.next().await {
+        let sequence_number = command.sequence_number();
+
+        let (status, pdu): (CommandStatus, Pdu) = match command.id() {
+            CommandId::BindTransmitter => (
+                CommandStatus::EsmeRok,
+                BindTransmitterResp::default().into(),
+            ),
+            CommandId::SubmitSm => {
+                let Some(Pdu::SubmitSm(sm)) = command.into_parts().raw().3 else {
+                    unreachable!()
+                };
+
+                received += 1;
+
+                let _ = parts.send(sm);
+
+                if reject == Some(received) {
+                    (CommandStatus::EsmeRsyserr, Pdu::GenericNack)
+                } else {
+                    let message_id = COctetString::from_str(&format!("id-{received}")).unwrap();
+
+                    (
+                        CommandStatus::EsmeRok,
+                        SubmitSmResp::builder()
+                            .message_id(message_id)
+                            .build()
+                            .into(),
+                    )
+                }
+            }
+            CommandId::EnquireLink => (CommandStatus::EsmeRok, Pdu::EnquireLinkResp),
+            _ => continue,
+        };
+
+        let response = Command::builder()
+            .status(status)
+            .sequence_number(sequence_number)
+            .pdu(pdu);
+
+        if framed.send(response).await.is_err() {
+            break;
+        }
+    }
+}
+
+async fn connect(reject: Option<usize>) -> (Client, mpsc::UnboundedReceiver<SubmitSm>) {
+    init_tracing();
+
+    let (server, client) = tokio::io::duplex(4096);
+    let (tx, rx) = mpsc::unbounded_channel();
+
+    tokio::spawn(run_server(server, reject, tx));
+
+    let (client, _events) = ConnectionBuilder::new().connected(client);
+
+    client
+        .bind_transmitter(BindTransmitter::default())
+        .await
+        .expect("Failed to bind");
+
+    (client, rx)
+}
+
+fn addr(addr: &str) -> COctetString<1, 21> {
+    COctetString::from_str(addr).unwrap()
+}
+
+async fn received(rx: &mut mpsc::UnboundedReceiver<SubmitSm>, count: usize) -> Vec<SubmitSm> {
+    let mut parts = Vec::with_capacity(count);
+
+    for _ in 0..count {
+        parts.push(rx.recv().await.expect("Server closed"));
+    }
+
+    parts
+}
+
+#[tokio::test]
+async fn short_gsm_text_should_be_sent_in_one_part() {
+    let (client, mut rx) = connect(None).await;
+
+    let result = client
+        .send_text(addr("123"), addr("456"), "Hello", SendTextOptions::new())
+        .await
+        .expect("Failed to send text");
+
+    assert!(result.is_success());
+    assert_eq!(result.len(), 1);
+    assert_eq!(result.data_coding(), DataCoding::McSpecific);
+    assert_eq!(
+        result.message_ids().collect::<Result<Vec<_>, _>>().ok(),
+        Some(vec!["id-1"])
+    );
+
+    let sm = &received(&mut rx, 1).await[0];
+
+    assert_eq!(sm.source_addr.as_str(), "123");
+    assert_eq!(sm.destination_addr.as_str(), "456");
+    assert_eq!(sm.short_message().to_str(), Ok("Hello"));
+    assert!(!matches!(
+        sm.esm_class.gsm_features,
+        GsmFeatures::UdhIndicator
+    ));
+}
+
+#[tokio::test]
+async fn non_gsm_text_should_fall_back_to_ucs2_with_udh() {
+    let (client, mut rx) = connect(None).await;
+
+    let text = "你好".repeat(50);
+
+    let result = client
+        .send_text(addr("123"), addr("456"), &text, SendTextOptions::new())
+        .await
+        .expect("Failed to send text");
+
+    assert!(result.is_success());
+    assert_eq!(result.len(), 2);
+    assert_eq!(result.data_coding(), DataCoding::Ucs2);
+    assert_eq!(
+        result.message_ids().collect::<Result<Vec<_>, _>>().ok(),
+        Some(vec!["id-1", "id-2"])
+    );
+
+    for sm in received(&mut rx, 2).await {
+        assert_eq!(sm.data_coding, DataCoding::Ucs2);
+        assert!(matches!(
+            sm.esm_class.gsm_features,
+            GsmFeatures::UdhIndicator
+        ));
+    }
+}
+
+#[tokio::test]
+async fn sar_concatenation_should_set_sar_tlvs() {
+    let (client, mut rx) = connect(None).await;
+
+    let options = SendTextOptions::new()
+        .encoding(TextEncoding::Gsm7Bit)
+        .concatenation(ConcatenationMode::Sar)
+        .reference(42);
+
+    let result = client
+        .send_text(addr("123"), addr("456"), LONG_GSM_TEXT, options)
+        .await
+        .expect("Failed to send text");
+
+    assert!(result.is_success());
+    assert_eq!(result.len(), 2);
+
+    for (index, sm) in received(&mut rx, 2).await.into_iter().enumerate() {
+        assert_eq!(sm.sar_msg_ref_num(), Some(42));
+        assert_eq!(sm.sar_total_segments(), Some(2));
+        assert_eq!(sm.sar_segment_seqnum(), Some(index as u8 + 1));
+    }
+}
+
+#[tokio::test]
+async fn message_payload_should_send_one_part() {
+    let (client, mut rx) = connect(None).await;
+
+    let options = SendTextOptions::new().concatenation(ConcatenationMode::MessagePayload);
+
+    let result = client
+        .send_text(addr("123"), addr("456"), LONG_GSM_TEXT, options)
+        .await
+        .expect("Failed to send text");
+
+    assert!(result.is_success());
+    assert_eq!(result.len(), 1);
+
+    let sm = &received(&mut rx, 1).await[0];
+
+    assert!(sm.short_message().is_empty());
+    assert!(
+        sm.tlvs()
+            .iter()
+            .any(|tlv| tlv.tag() == TlvTag::MessagePayload)
+    );
+}
+
+#[tokio::test]
+async fn failed_part_should_be_reported_without_failing_other_parts() {
+    let (client, _rx) = connect(Some(2)).await;
+
+    let options = SendTextOptions::new().encoding(TextEncoding::Ucs2);
+    let text = "a".repeat(200);
+
+    let result = client
+        .send_text(addr("123"), addr("456"), &text, options)
+        .await
+        .expect("Failed to send text");
+
+    assert!(!result.is_success());
+
+    let parts = result.into_parts();
+
+    assert_eq!(parts.len(), 3);
+    assert!(parts[0].is_ok());
+    assert!(matches!(parts[1], Err(Error::UnexpectedResponse { .. })));
+    assert!(parts[2].is_ok());
+}
+
+#[tokio::test]
+async fn unencodable_text_should_fail_without_sending() {
+    let (client, mut rx) = connect(None).await;
+
+    let options = SendTextOptions::new().encoding(TextEncoding::Gsm7Bit);
+
+    let err = client
+        .send_text(addr("123"), addr("456"), "你好", options)
+        .await
+        .expect_err("Expected error");
+
+    assert!(matches!(err, Error::TextEncoding(_)));
+    assert!(rx.try_recv().is_err());
+}
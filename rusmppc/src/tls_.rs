@@ -0,0 +1,244 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use rustls::{
+    ClientConfig, DigitallySignedStruct, RootCertStore, SignatureScheme,
+    client::{
+        WebPkiServerVerifier,
+        danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier},
+    },
+    crypto::hash::{Hash, HashAlgorithm},
+};
+use rustls_pki_types::{
+    CertificateDer, PrivateKeyDer, ServerName, UnixTime,
+    pem::{self, PemObject},
+};
+
+use crate::{error::Error, tcp_stream::default_root_store};
+
+#[cfg(test)]
+mod tests;
+
+const TARGET: &str = "rusmppc::connection::tls::rustls";
+
+/// A pinned server certificate or public key.
+///
+/// The server is accepted only if its certificate chain is valid and one of the certificates in the chain matches one of the pins.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum TlsPin {
+    /// `SHA-256` hash of the `DER` encoded certificate.
+    Certificate([u8; 32]),
+    /// `SHA-256` hash of the `DER` encoded `SubjectPublicKeyInfo` of the certificate.
+    ///
+    /// Survives certificate renewals that keep the key pair.
+    Spki([u8; 32]),
+}
+
+impl std::fmt::Debug for TlsPin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (name, hash) = match self {
+            TlsPin::Certificate(hash) => ("Certificate", hash),
+            TlsPin::Spki(hash) => ("Spki", hash),
+        };
+
+        let hex = hash.iter().map(|b| format!("{b:02x}")).collect::<String>();
+
+        f.debug_tuple(name).field(&hex).finish()
+    }
+}
+
+/// TLS configuration loaded from files on every connect.
+///
+/// Loading reads the files with blocking I/O, call [`client_config`](Self::client_config) on a blocking thread.
+///
+/// Reconnects of a [`ManagedClient`](crate::managed::ManagedClient) pick up rotated files.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct TlsFiles {
+    /// PEM bundle of trusted CA certificates. Replaces the default roots.
+    pub(crate) ca_file: Option<PathBuf>,
+    /// PEM certificate chain and private key presented to the server.
+    pub(crate) client_auth: Option<(PathBuf, PathBuf)>,
+    pub(crate) pins: Vec<TlsPin>,
+}
+
+impl TlsFiles {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.ca_file.is_none() && self.client_auth.is_none() && self.pins.is_empty()
+    }
+
+    /// Loads the files and builds the client configuration.
+    pub(crate) fn client_config(&self) -> Result<ClientConfig, Error> {
+        let roots = match &self.ca_file {
+            Some(path) => {
+                tracing::debug!(target: TARGET, ?path, "Loading CA bundle");
+
+                let mut roots = RootCertStore::empty();
+
+                for cert in load_certs(path)? {
+                    roots.add(cert).map_err(|err| tls_error(path, err))?;
+                }
+
+                roots
+            }
+            None => default_root_store()?,
+        };
+
+        let roots = Arc::new(roots);
+
+        let builder = ClientConfig::builder().with_root_certificates(roots.clone());
+
+        let mut config = match &self.client_auth {
+            Some((cert_path, key_path)) => {
+                tracing::debug!(target: TARGET, ?cert_path, ?key_path, "Loading client certificate");
+
+                let certs = load_certs(cert_path)?;
+                let key = PrivateKeyDer::from_pem_file(key_path)
+                    .map_err(|err| tls_error(key_path, err))?;
+
+                builder
+                    .with_client_auth_cert(certs, key)
+                    .map_err(|err| tls_error(cert_path, err))?
+            }
+            None => builder.with_no_client_auth(),
+        };
+
+        if !self.pins.is_empty() {
+            let provider = config.crypto_provider().clone();
+
+            let sha256 = provider
+                .cipher_suites
+                .iter()
+                .filter_map(|suite| suite.tls13())
+                .map(|suite| suite.common.hash_provider)
+                .find(|hash| hash.algorithm() == HashAlgorithm::SHA256)
+                .ok_or_else(|| {
+                    Error::Connect(std::io::Error::other(
+                        "The crypto provider has no SHA-256 implementation for certificate pinning",
+                    ))
+                })?;
+
+            let inner = WebPkiServerVerifier::builder_with_provider(roots, provider)
+                .build()
+                .map_err(std::io::Error::other)
+                .map_err(Error::Connect)?;
+
+            config
+                .dangerous()
+                .set_certificate_verifier(Arc::new(PinningVerifier {
+                    inner,
+                    pins: self.pins.clone(),
+                    sha256,
+                }));
+        }
+
+        Ok(config)
+    }
+}
+
+fn load_certs(path: &Path) -> Result<Vec<CertificateDer<'static>>, Error> {
+    let certs = CertificateDer::pem_file_iter(path)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, pem::Error>>())
+        .map_err(|err| tls_error(path, err))?;
+
+    if certs.is_empty() {
+        return Err(tls_error(path, "No certificates found"));
+    }
+
+    Ok(certs)
+}
+
+fn tls_error(path: &Path, err: impl std::fmt::Display) -> Error {
+    Error::Connect(std::io::Error::new(
+        std::io::ErrorKind::InvalidData,
+        format!("Failed to load {}: {err}", path.display()),
+    ))
+}
+
+/// Verifies the chain with the inner verifier, then requires a certificate of the chain to match a pin.
+struct PinningVerifier {
+    inner: Arc<WebPkiServerVerifier>,
+    pins: Vec<TlsPin>,
+    sha256: &'static dyn Hash,
+}
+
+impl std::fmt::Debug for PinningVerifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PinningVerifier")
+            .field("inner", &self.inner)
+            .field("pins", &self.pins)
+            .finish_non_exhaustive()
+    }
+}
+
+impl PinningVerifier {
+    fn matches(&self, cert: &CertificateDer<'_>) -> bool {
+        let cert_hash = self.sha256.hash(cert.as_ref());
+
+        let spki_hash = webpki::EndEntityCert::try_from(cert)
+            .ok()
+            .map(|cert| self.sha256.hash(cert.subject_public_key_info().as_ref()));
+
+        self.pins.iter().any(|pin| match pin {
+            TlsPin::Certificate(hash) => cert_hash.as_ref() == hash,
+            TlsPin::Spki(hash) => spki_hash
+                .as_ref()
+                .is_some_and(|spki_hash| spki_hash.as_ref() == hash),
+        })
+    }
+}
+
+impl ServerCertVerifier for PinningVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        let verified = self.inner.verify_server_cert(
+            end_entity,
+            intermediates,
+            server_name,
+            ocsp_response,
+            now,
+        )?;
+
+        if std::iter::once(end_entity)
+            .chain(intermediates)
+            .any(|cert| self.matches(cert))
+        {
+            return Ok(verified);
+        }
+
+        tracing::warn!(target: TARGET, ?server_name, "No certificate matches the pins");
+
+        Err(rustls::Error::InvalidCertificate(
+            rustls::CertificateError::ApplicationVerificationFailure,
+        ))
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.inner.verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.inner.verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.inner.supported_verify_schemes()
+    }
+}
@@ -0,0 +1,350 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use futures::{SinkExt, StreamExt};
+use rcgen::{BasicConstraints, CertificateParams, CertifiedIssuer, IsCa, KeyPair, PublicKeyData};
+use rusmpp::{
+    Command, CommandId, CommandStatus,
+    pdus::{BindTransceiver, BindTransceiverResp},
+    tokio_codec::CommandCodec,
+};
+use rustls::{ServerConfig, server::WebPkiClientVerifier};
+use rustls_pki_types::PrivatePkcs8KeyDer;
+use tokio::{net::TcpListener, sync::mpsc};
+use tokio_rustls::TlsAcceptor;
+use tokio_util::codec::Framed;
+
+use super::*;
+use crate::{ConnectionBuilder, tests::init_tracing};
+
+fn temp_dir() -> PathBuf {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    let dir = std::env::temp_dir().join(format!(
+        "rusmppc-tls-{}-{}",
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+
+    std::fs::create_dir_all(&dir).unwrap();
+
+    dir
+}
+
+/// Feature unification in the workspace may enable both `aws-lc-rs` and `ring`, so the default provider is not chosen automatically.
+fn install_crypto_provider() {
+    #[cfg(feature = "rustls-aws-lc-rs")]
+    let provider = rustls::crypto::aws_lc_rs::default_provider();
+    #[cfg(not(feature = "rustls-aws-lc-rs"))]
+    let provider = rustls::crypto::ring::default_provider();
+
+    let _ = provider.install_default();
+}
+
+fn sha256(bytes: &[u8]) -> [u8; 32] {
+    let provider = ClientConfig::builder().crypto_provider().clone();
+
+    let hash = provider
+        .cipher_suites
+        .iter()
+        .filter_map(|suite| suite.tls13())
+        .map(|suite| suite.common.hash_provider)
+        .find(|hash| hash.algorithm() == HashAlgorithm::SHA256)
+        .unwrap()
+        .hash(bytes);
+
+    hash.as_ref().try_into().unwrap()
+}
+
+/// A CA issuing a server certificate for `127.0.0.1` and client certificates.
+struct Pki {
+    ca: CertifiedIssuer<'static, KeyPair>,
+    server_cert: CertificateDer<'static>,
+    server_key: KeyPair,
+}
+
+impl Pki {
+    fn new() -> Self {
+        install_crypto_provider();
+
+        let mut params = CertificateParams::new(Vec::<String>::new()).unwrap();
+        params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+
+        let ca = CertifiedIssuer::self_signed(params, KeyPair::generate().unwrap()).unwrap();
+
+        let server_key = KeyPair::generate().unwrap();
+        let server_cert = CertificateParams::new(vec![String::from("127.0.0.1")])
+            .unwrap()
+            .signed_by(&server_key, &ca)
+            .unwrap()
+            .der()
+            .clone();
+
+        Self {
+            ca,
+            server_cert,
+            server_key,
+        }
+    }
+
+    /// Issues a client certificate and returns its PEM certificate and key.
+    fn client(&self) -> (String, String) {
+        let key = KeyPair::generate().unwrap();
+        let cert = CertificateParams::new(vec![String::from("esme")])
+            .unwrap()
+            .signed_by(&key, &self.ca)
+            .unwrap();
+
+        (cert.pem(), key.serialize_pem())
+    }
+
+    fn server_config(&self) -> ServerConfig {
+        let mut roots = RootCertStore::empty();
+        roots.add(self.ca.der().clone()).unwrap();
+
+        let verifier = WebPkiClientVerifier::builder(Arc::new(roots))
+            .allow_unauthenticated()
+            .build()
+            .unwrap();
+
+        ServerConfig::builder()
+            .with_client_cert_verifier(verifier)
+            .with_single_cert(
+                vec![self.server_cert.clone()],
+                PrivatePkcs8KeyDer::from(self.server_key.serialize_der()).into(),
+            )
+            .unwrap()
+    }
+}
+
+/// TLS server that answers binds and reports the client certificate of every connection.
+async fn run_server(
+    listener: TcpListener,
+    config: ServerConfig,
+    clients: mpsc::UnboundedSender<Option<CertificateDer<'static>>>,
+) {
+    let acceptor = TlsAcceptor::from(Arc::new(config));
+
+    while let Ok((stream, _)) = listener.accept().await {
+        let acceptor = acceptor.clone();
+        let clients = clients.clone();
+
+        tokio::spawn(async move {
+            let Ok(stream) = acceptor.accept(stream).await else {
+                return;
+            };
+
+            let client_cert = stream
+                .get_ref()
+                .1
+                .peer_certificates()
+                .and_then(|certs| certs.first())
+                .map(|cert| cert.clone().into_owned());
+
+            let _ = clients.send(client_cert);
+
+            let mut framed = Framed::new(stream, CommandCodec::new());
+
+            while let Some(Ok(command)) = framed.next().await {
+                if command.id() != CommandId::BindTransceiver {
+                    continue;
+                }
+
+                let response = Command::builder()
+                    .status(CommandStatus::EsmeRok)
+                    .sequence_number(command.sequence_number())
+                    .pdu(BindTransceiverResp::default());
+
+                if framed.send(response).await.is_err() {
+                    break;
+                }
+            }
+        });
+    }
+}
+
+async fn start_server(
+    pki: &Pki,
+) -> (
+    String,
+    mpsc::UnboundedReceiver<Option<CertificateDer<'static>>>,
+) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    let (tx, rx) = mpsc::unbounded_channel();
+
+    tokio::spawn(run_server(listener, pki.server_config(), tx));
+
+    (format!("smpps://127.0.0.1:{port}"), rx)
+}
+
+fn write_ca(pki: &Pki, dir: &Path) -> PathBuf {
+    let path = dir.join("ca.pem");
+
+    std::fs::write(&path, pki.ca.pem()).unwrap();
+
+    path
+}
+
+fn write_client(pki: &Pki, dir: &Path) -> (PathBuf, PathBuf, CertificateDer<'static>) {
+    let (cert, key) = pki.client();
+
+    let cert_path = dir.join("client.pem");
+    let key_path = dir.join("client.key");
+
+    std::fs::write(&cert_path, &cert).unwrap();
+    std::fs::write(&key_path, key).unwrap();
+
+    let der = CertificateDer::from_pem_slice(cert.as_bytes()).unwrap();
+
+    (cert_path, key_path, der)
+}
+
+#[tokio::test]
+async fn client_certificate_should_be_presented_to_server_trusted_by_ca_file() {
+    init_tracing();
+
+    let pki = Pki::new();
+    let dir = temp_dir();
+    let (url, mut clients) = start_server(&pki).await;
+
+    let ca_path = write_ca(&pki, &dir);
+    let (cert_path, key_path, client_cert) = write_client(&pki, &dir);
+
+    let (client, _events) = ConnectionBuilder::new()
+        .no_enquire_link_interval()
+        .tls_ca_file(ca_path)
+        .tls_client_auth_pem(cert_path, key_path)
+        .connect(url)
+        .await
+        .expect("Failed to connect");
+
+    assert_eq!(clients.recv().await, Some(Some(client_cert)));
+
+    client
+        .bind_transceiver(BindTransceiver::default())
+        .await
+        .expect("Failed to bind");
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn server_should_be_accepted_only_if_a_pin_matches() {
+    init_tracing();
+
+    let pki = Pki::new();
+    let dir = temp_dir();
+    let (url, _clients) = start_server(&pki).await;
+
+    let ca_path = write_ca(&pki, &dir);
+
+    let spki = TlsPin::Spki(sha256(&pki.server_key.subject_public_key_info()));
+    let certificate = TlsPin::Certificate(sha256(&pki.server_cert));
+    let ca = TlsPin::Certificate(sha256(pki.ca.der()));
+
+    for pin in [spki, certificate] {
+        let (_client, _events) = ConnectionBuilder::new()
+            .no_enquire_link_interval()
+            .tls_ca_file(&ca_path)
+            .tls_pin(TlsPin::Spki([0; 32]))
+            .tls_pin(pin)
+            .connect(&url)
+            .await
+            .unwrap_or_else(|err| panic!("Pin {pin:?} should match: {err}"));
+    }
+
+    // The CA is not part of the chain sent by the server.
+    let error = ConnectionBuilder::new()
+        .no_enquire_link_interval()
+        .tls_ca_file(&ca_path)
+        .tls_pin(ca)
+        .connect(&url)
+        .await
+        .err()
+        .expect("Connecting should fail");
+
+    assert!(matches!(error, Error::Connect(_)));
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn server_should_be_rejected_without_its_ca() {
+    init_tracing();
+
+    let pki = Pki::new();
+    let other = Pki::new();
+    let dir = temp_dir();
+    let (url, _clients) = start_server(&pki).await;
+
+    let error = ConnectionBuilder::new()
+        .tls_ca_file(write_ca(&other, &dir))
+        .connect(url)
+        .await
+        .err()
+        .expect("Connecting should fail");
+
+    assert!(matches!(error, Error::Connect(_)));
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn missing_ca_file_should_fail_to_connect() {
+    let pki = Pki::new();
+    let (url, _clients) = start_server(&pki).await;
+
+    let error = ConnectionBuilder::new()
+        .tls_ca_file("/nonexistent/ca.pem")
+        .connect(url)
+        .await
+        .err()
+        .expect("Connecting should fail");
+
+    let Error::Connect(error) = error else {
+        panic!("Unexpected error: {error:?}");
+    };
+
+    assert!(error.to_string().contains("/nonexistent/ca.pem"));
+}
+
+#[tokio::test]
+async fn managed_client_should_reload_rotated_client_certificate() {
+    init_tracing();
+
+    let pki = Pki::new();
+    let dir = temp_dir();
+    let (url, mut clients) = start_server(&pki).await;
+
+    let ca_path = write_ca(&pki, &dir);
+    let (cert_path, key_path, first_cert) = write_client(&pki, &dir);
+
+    let (client, _events) = ConnectionBuilder::new()
+        .no_enquire_link_interval()
+        .tls_ca_file(ca_path)
+        .tls_client_auth_pem(cert_path, key_path)
+        .managed()
+        .transceiver(BindTransceiver::default())
+        .connect(url)
+        .await
+        .expect("Failed to connect");
+
+    assert_eq!(clients.recv().await, Some(Some(first_cert)));
+
+    let (_, _, second_cert) = write_client(&pki, &dir);
+
+    client
+        .get()
+        .await
+        .expect("Failed to get the client")
+        .close_and_wait()
+        .await
+        .expect("Failed to close");
+
+    client.get().await.expect("Failed to reconnect");
+
+    assert_eq!(clients.recv().await, Some(Some(second_cert)));
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,490 @@
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use rusmpp::{
+    Command, CommandStatus, Pdu,
+    tlvs::{Tlv, TlvValue},
+    values::{MessageState, MessageType},
+};
+use tokio::sync::oneshot;
+
+use crate::{error::Error, runtime_::Timeout};
+
+const TARGET: &str = "rusmppc::tracking";
+
+/// A delivery receipt matched to a tracked submission.
+///
+/// Built from an incoming [`DeliverSm`](rusmpp::pdus::DeliverSm) or [`DataSm`](rusmpp::pdus::DataSm) that carries a delivery receipt.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeliveryReceipt {
+    /// The sequence number of the command carrying the receipt.
+    pub sequence_number: u32,
+    /// The `message_id` of the original submission.
+    ///
+    /// Taken from the `receipted_message_id` TLV if present, otherwise from the `id` field of the receipt text.
+    pub message_id: String,
+    /// The final state of the message.
+    ///
+    /// Taken from the `message_state` TLV if present, otherwise mapped from the `stat` field of the receipt text.
+    pub message_state: Option<MessageState>,
+    /// The `err` field of the receipt text.
+    pub error: Option<String>,
+    /// The raw receipt text.
+    pub text: Option<String>,
+}
+
+impl DeliveryReceipt {
+    /// Extracts a [`DeliveryReceipt`] from an incoming [`Command`].
+    ///
+    /// Returns `None` if the command is not a [`DeliverSm`](rusmpp::pdus::DeliverSm) or [`DataSm`](rusmpp::pdus::DataSm) carrying a delivery receipt,
+    /// or if the receipted `message_id` can not be determined.
+    pub fn from_command(command: &Command) -> Option<Self> {
+        let (message_type, tlvs, short_message) = match command.pdu()? {
+            Pdu::DeliverSm(deliver_sm) => (
+                deliver_sm.esm_class.message_type,
+                deliver_sm.tlvs(),
+                Some(deliver_sm.short_message().as_ref()),
+            ),
+            Pdu::DataSm(data_sm) => (data_sm.esm_class.message_type, data_sm.tlvs(), None),
+            _ => return None,
+        };
+
+        let receipted_message_id = tlvs.iter().find_map(|tlv| match tlv.value() {
+            Some(TlvValue::ReceiptedMessageId(id)) => Some(id.as_str().to_owned()),
+            _ => None,
+        });
+
+        let is_receipt = matches!(
+            message_type,
+            MessageType::ShortMessageContainsMcDeliveryReceipt
+                | MessageType::ShortMessageContainsIntermediateDeliveryNotification
+        ) || receipted_message_id.is_some();
+
+        if !is_receipt {
+            return None;
+        }
+
+        let text = short_message
+            .or_else(|| message_payload(tlvs))
+            .and_then(|bytes| std::str::from_utf8(bytes).ok())
+            .filter(|text| !text.is_empty());
+
+        let message_id = receipted_message_id
+            .or_else(|| text.and_then(|text| field(text, "id")).map(Into::into))?;
+
+        let message_state = tlvs
+            .iter()
+            .find_map(|tlv| match tlv.value() {
+                Some(TlvValue::MessageState(state)) => Some(*state),
+                _ => None,
+            })
+            .or_else(|| {
+                text.and_then(|text| field(text, "stat"))
+                    .and_then(stat_to_state)
+            });
+
+        Some(Self {
+            sequence_number: command.sequence_number(),
+            message_id,
+            message_state,
+            error: text.and_then(|text| field(text, "err")).map(Into::into),
+            text: text.map(Into::into),
+        })
+    }
+
+    /// Returns `true` if the receipt reports a final state.
+    ///
+    /// Intermediate notifications ([`MessageState::Scheduled`] and [`MessageState::Enroute`]) are not final.
+    /// A receipt without a known state is considered final.
+    pub fn is_final(&self) -> bool {
+        !matches!(
+            self.message_state,
+            Some(MessageState::Scheduled | MessageState::Enroute)
+        )
+    }
+}
+
+fn message_payload(tlvs: &[Tlv]) -> Option<&[u8]> {
+    tlvs.iter().find_map(|tlv| match tlv.value() {
+        Some(TlvValue::MessagePayload(payload)) => Some(payload.value.as_ref()),
+        _ => None,
+    })
+}
+
+/// Finds the value of `key` in a receipt text of the form `id:123 sub:001 ... stat:DELIVRD err:000 text:...`.
+///
+/// Keys are matched case insensitively. The value of `text` extends to the end of the receipt.
+fn field<'a>(text: &'a str, key: &str) -> Option<&'a str> {
+    let lower = text.to_ascii_lowercase();
+    let pattern = format!("{key}:");
+
+    let start = lower.match_indices(&pattern).find_map(|(index, _)| {
+        (index == 0 || lower.as_bytes()[index - 1] == b' ').then_some(index + pattern.len())
+    })?;
+
+    let rest = &text[start..];
+
+    if key == "text" {
+        return Some(rest);
+    }
+
+    Some(rest.split(' ').next().unwrap_or(rest))
+}
+
+fn stat_to_state(stat: &str) -> Option<MessageState> {
+    let state = match stat.to_ascii_uppercase().as_str() {
+        "SCHEDULED" => MessageState::Scheduled,
+        "ENROUTE" => MessageState::Enroute,
+        "DELIVRD" => MessageState::Delivered,
+        "EXPIRED" => MessageState::Expired,
+        "DELETED" => MessageState::Deleted,
+        "UNDELIV" => MessageState::Undeliverable,
+        "ACCEPTD" => MessageState::Accepted,
+        "UNKNOWN" => MessageState::Unknown,
+        "REJECTD" => MessageState::Rejected,
+        "SKIPPED" => MessageState::Skipped,
+        _ => return None,
+    };
+
+    Some(state)
+}
+
+/// A submission waiting for its delivery receipt, as recorded in a [`TrackingStore`].
+///
+/// Holds plain data only, so that a store can persist it. The futures waiting for the receipts are kept by the clients.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TrackedSubmission {
+    /// The `message_id` assigned by the server.
+    pub message_id: String,
+    /// The sequence number of the submission.
+    pub sequence_number: u32,
+}
+
+/// Storage for submissions waiting for a delivery receipt, keyed by `message_id`.
+///
+/// Entries are inserted when the server accepts a tracked submission and removed when the matching receipt arrives
+/// or when the [`DeliveryTracker`] is dropped or times out.
+pub trait TrackingStore: Send + Sync + 'static {
+    /// Stores the given submission.
+    fn insert(&self, submission: TrackedSubmission);
+
+    /// Removes and returns the submission for the given `message_id`.
+    fn remove(&self, message_id: &str) -> Option<TrackedSubmission>;
+
+    /// Returns the number of tracked submissions.
+    fn len(&self) -> usize;
+
+    /// Returns `true` if no submissions are tracked.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// The default in-memory [`TrackingStore`].
+#[derive(Debug, Default)]
+pub struct InMemoryTrackingStore {
+    entries: Mutex<HashMap<String, TrackedSubmission>>,
+}
+
+impl InMemoryTrackingStore {
+    /// Creates a new empty [`InMemoryTrackingStore`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl TrackingStore for InMemoryTrackingStore {
+    fn insert(&self, submission: TrackedSubmission) {
+        self.entries
+            .lock()
+            .expect("Tracking store poisoned")
+            .insert(submission.message_id.clone(), submission);
+    }
+
+    fn remove(&self, message_id: &str) -> Option<TrackedSubmission> {
+        self.entries
+            .lock()
+            .expect("Tracking store poisoned")
+            .remove(message_id)
+    }
+
+    fn len(&self) -> usize {
+        self.entries.lock().expect("Tracking store poisoned").len()
+    }
+}
+
+/// Delivery tracking configuration.
+///
+/// Shared by all connections created from the same [`ConnectionBuilder`](crate::ConnectionBuilder),
+/// so that a receipt arriving on a reconnected [`ManagedClient`](crate::managed::ManagedClient) still resolves its tracker.
+#[derive(Clone)]
+pub(crate) struct DeliveryTracking {
+    store: Arc<dyn TrackingStore>,
+    // The trackers waiting for a receipt, keyed by `message_id`.
+    waiters: Arc<Mutex<HashMap<String, oneshot::Sender<DeliveryReceipt>>>>,
+    ttl: Duration,
+}
+
+impl Debug for DeliveryTracking {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DeliveryTracking")
+            .field("ttl", &self.ttl)
+            .finish()
+    }
+}
+
+impl DeliveryTracking {
+    pub(crate) fn new(store: Arc<dyn TrackingStore>, ttl: Duration) -> Self {
+        Self {
+            store,
+            waiters: Arc::new(Mutex::new(HashMap::new())),
+            ttl,
+        }
+    }
+
+    fn insert(&self, submission: TrackedSubmission, sender: oneshot::Sender<DeliveryReceipt>) {
+        self.waiters
+            .lock()
+            .expect("Delivery tracking poisoned")
+            .insert(submission.message_id.clone(), sender);
+
+        self.store.insert(submission);
+    }
+
+    fn remove(&self, message_id: &str) -> Option<oneshot::Sender<DeliveryReceipt>> {
+        self.store.remove(message_id)?;
+
+        self.waiters
+            .lock()
+            .expect("Delivery tracking poisoned")
+            .remove(message_id)
+    }
+
+    fn forget(&self, message_id: &str) {
+        self.store.remove(message_id);
+
+        self.waiters
+            .lock()
+            .expect("Delivery tracking poisoned")
+            .remove(message_id);
+    }
+}
+
+/// Per connection tracking state shared by the [`Client`](crate::Client) and the background connection.
+///
+/// Tracked requests are registered by sequence number before they are sent.
+/// The connection moves them into the [`TrackingStore`] when the response carrying the `message_id` arrives,
+/// before the response is handed to the client. This way a receipt can never overtake its own registration.
+pub(crate) struct Tracker {
+    tracking: DeliveryTracking,
+    pending: Mutex<HashMap<u32, oneshot::Sender<DeliveryReceipt>>>,
+}
+
+impl Debug for Tracker {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Tracker")
+            .field("tracking", &self.tracking)
+            .finish()
+    }
+}
+
+impl Tracker {
+    pub(crate) fn new(tracking: DeliveryTracking) -> Self {
+        Self {
+            tracking,
+            pending: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub(crate) const fn ttl(&self) -> Duration {
+        self.tracking.ttl
+    }
+
+    pub(crate) fn register(&self, sequence_number: u32) -> oneshot::Receiver<DeliveryReceipt> {
+        let (tx, rx) = oneshot::channel();
+
+        self.pending
+            .lock()
+            .expect("Tracker poisoned")
+            .insert(sequence_number, tx);
+
+        rx
+    }
+
+    pub(crate) fn cancel(&self, sequence_number: u32) {
+        self.pending
+            .lock()
+            .expect("Tracker poisoned")
+            .remove(&sequence_number);
+    }
+
+    pub(crate) fn forget(&self, message_id: &str) {
+        self.tracking.forget(message_id);
+    }
+
+    /// Called by the connection for every response, before it is handed to the client.
+    pub(crate) fn on_response(&self, command: &Command) {
+        let Some(sender) = self
+            .pending
+            .lock()
+            .expect("Tracker poisoned")
+            .remove(&command.sequence_number())
+        else {
+            return;
+        };
+
+        if !matches!(command.status(), CommandStatus::EsmeRok) {
+            return;
+        }
+
+        let message_id = match command.pdu() {
+            Some(Pdu::SubmitSmResp(response)) => response.message_id().as_str(),
+            Some(Pdu::DataSmResp(response)) => response.message_id().as_str(),
+            _ => return,
+        };
+
+        tracing::trace!(target: TARGET, sequence_number=command.sequence_number(), message_id, "Tracking");
+
+        self.tracking.insert(
+            TrackedSubmission {
+                message_id: message_id.to_owned(),
+                sequence_number: command.sequence_number(),
+            },
+            sender,
+        );
+    }
+
+    /// Called by the connection for every incoming operation.
+    pub(crate) fn on_incoming(&self, command: &Command) {
+        let Some(receipt) = DeliveryReceipt::from_command(command) else {
+            return;
+        };
+
+        if !receipt.is_final() {
+            return;
+        }
+
+        if let Some(sender) = self.tracking.remove(&receipt.message_id) {
+            tracing::debug!(target: TARGET, message_id=receipt.message_id, state=?receipt.message_state, "Resolved");
+
+            let _ = sender.send(receipt);
+        }
+    }
+}
+
+/// Removes a pending registration if the tracked request fails or is dropped before its response arrives.
+pub(crate) struct PendingReceiptGuard {
+    tracker: Arc<Tracker>,
+    sequence_number: u32,
+}
+
+impl PendingReceiptGuard {
+    pub(crate) const fn new(tracker: Arc<Tracker>, sequence_number: u32) -> Self {
+        Self {
+            tracker,
+            sequence_number,
+        }
+    }
+}
+
+impl Drop for PendingReceiptGuard {
+    fn drop(&mut self) {
+        // No-op if the connection already moved the registration into the store.
+        self.tracker.cancel(self.sequence_number);
+    }
+}
+
+pin_project_lite::pin_project! {
+    /// A future resolving to the final [`DeliveryReceipt`] of a tracked submission.
+    ///
+    /// Created by [`Client::submit_sm_tracked`](crate::Client::submit_sm_tracked).
+    ///
+    /// Dropping the tracker stops tracking the submission.
+    pub struct DeliveryTracker<T: Timeout> {
+        message_id: String,
+        ttl: Duration,
+        done: bool,
+        tracker: Arc<Tracker>,
+        #[pin]
+        future: T::Future<oneshot::Receiver<DeliveryReceipt>>,
+    }
+
+    impl<T: Timeout> PinnedDrop for DeliveryTracker<T> {
+        fn drop(this: Pin<&mut Self>) {
+            let this = this.project();
+
+            if !*this.done {
+                this.tracker.forget(this.message_id);
+            }
+        }
+    }
+}
+
+impl<T: Timeout> Debug for DeliveryTracker<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DeliveryTracker")
+            .field("message_id", &self.message_id)
+            .field("ttl", &self.ttl)
+            .finish()
+    }
+}
+
+impl<T: Timeout> DeliveryTracker<T> {
+    pub(crate) fn new(
+        message_id: String,
+        tracker: Arc<Tracker>,
+        receipt: oneshot::Receiver<DeliveryReceipt>,
+    ) -> Self {
+        let ttl = tracker.ttl();
+
+        Self {
+            message_id,
+            ttl,
+            done: false,
+            tracker,
+            future: T::timeout(ttl, receipt),
+        }
+    }
+
+    /// Returns the `message_id` being tracked.
+    pub fn message_id(&self) -> &str {
+        &self.message_id
+    }
+}
+
+impl<T: Timeout> Future for DeliveryTracker<T> {
+    type Output = Result<DeliveryReceipt, Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+
+        let result = match this.future.poll(cx) {
+            Poll::Ready(Some(Ok(receipt))) => Ok(receipt),
+            Poll::Ready(Some(Err(_))) => Err(Error::DeliveryTrackingClosed {
+                message_id: this.message_id.clone(),
+            }),
+            Poll::Ready(None) => Err(Error::DeliveryReceiptTimeout {
+                message_id: this.message_id.clone(),
+                timeout: *this.ttl,
+            }),
+            Poll::Pending => return Poll::Pending,
+        };
+
+        if result.is_err() {
+            this.tracker.forget(this.message_id);
+        }
+
+        *this.done = true;
+
+        Poll::Ready(result)
+    }
+}
+
+#[cfg(test)]
+mod tests;
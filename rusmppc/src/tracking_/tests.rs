@@ -0,0 +1,127 @@
+use std::str::FromStr;
+
+use rusmpp::{
+    pdus::DeliverSm,
+    tlvs::MessageDeliveryRequestTlvValue,
+    types::{COctetString, OctetString},
+    values::EsmClass,
+};
+
+use super::*;
+
+fn command(deliver_sm: DeliverSm) -> Command {
+    Command::builder()
+        .status(CommandStatus::EsmeRok)
+        .sequence_number(7)
+        .pdu(deliver_sm)
+}
+
+fn receipt_esm_class() -> EsmClass {
+    EsmClass {
+        message_type: MessageType::ShortMessageContainsMcDeliveryReceipt,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn receipt_from_text() {
+    let text = "id:0123456789 sub:001 dlvrd:001 submit date:2401011200 done date:2401011201 stat:DELIVRD err:000 text:Hello world";
+
+    let deliver_sm = DeliverSm::builder()
+        .esm_class(receipt_esm_class())
+        .short_message(OctetString::from_str(text).unwrap())
+        .build();
+
+    let receipt = DeliveryReceipt::from_command(&command(deliver_sm)).unwrap();
+
+    assert_eq!(receipt.sequence_number, 7);
+    assert_eq!(receipt.message_id, "0123456789");
+    assert_eq!(receipt.message_state, Some(MessageState::Delivered));
+    assert_eq!(receipt.error.as_deref(), Some("000"));
+    assert!(receipt.is_final());
+}
+
+#[test]
+fn receipt_tlvs_take_precedence_over_text() {
+    let deliver_sm = DeliverSm::builder()
+        .esm_class(receipt_esm_class())
+        .short_message(OctetString::from_str("id:abc stat:DELIVRD").unwrap())
+        .tlvs(vec![
+            MessageDeliveryRequestTlvValue::ReceiptedMessageId(
+                COctetString::from_str("xyz").unwrap(),
+            ),
+            MessageDeliveryRequestTlvValue::MessageState(MessageState::Enroute),
+        ])
+        .build();
+
+    let receipt = DeliveryReceipt::from_command(&command(deliver_sm)).unwrap();
+
+    assert_eq!(receipt.message_id, "xyz");
+    assert_eq!(receipt.message_state, Some(MessageState::Enroute));
+    assert!(!receipt.is_final());
+}
+
+#[test]
+fn non_receipt_is_ignored() {
+    let deliver_sm = DeliverSm::builder()
+        .short_message(OctetString::from_str("id:abc stat:DELIVRD").unwrap())
+        .build();
+
+    assert!(DeliveryReceipt::from_command(&command(deliver_sm)).is_none());
+}
+
+#[test]
+fn field_requires_word_boundary() {
+    assert_eq!(field("uid:1 id:2", "id"), Some("2"));
+    assert_eq!(field("ID:3 STAT:UNDELIV", "stat"), Some("UNDELIV"));
+    assert_eq!(field("id:1 text:a b c", "text"), Some("a b c"));
+    assert_eq!(field("id:1", "err"), None);
+}
+
+#[test]
+fn store_holds_submissions_and_tracker_resolves_waiter() {
+    use rusmpp::pdus::SubmitSmResp;
+
+    let store = Arc::new(InMemoryTrackingStore::new());
+
+    let tracker = Tracker::new(DeliveryTracking::new(store.clone(), Duration::from_secs(5)));
+    let mut receipt = tracker.register(3);
+
+    let response = Command::builder()
+        .status(CommandStatus::EsmeRok)
+        .sequence_number(3)
+        .pdu(
+            SubmitSmResp::builder()
+                .message_id(COctetString::from_str("abc").unwrap())
+                .build(),
+        );
+
+    tracker.on_response(&response);
+
+    assert_eq!(
+        store.remove("abc"),
+        Some(TrackedSubmission {
+            message_id: String::from("abc"),
+            sequence_number: 3,
+        })
+    );
+
+    // The waiter is only resolved while the store still tracks the submission.
+    let deliver_sm = DeliverSm::builder()
+        .esm_class(receipt_esm_class())
+        .short_message(OctetString::from_str("id:abc stat:DELIVRD").unwrap())
+        .build();
+
+    tracker.on_incoming(&command(deliver_sm.clone()));
+    assert!(receipt.try_recv().is_err());
+
+    store.insert(TrackedSubmission {
+        message_id: String::from("abc"),
+        sequence_number: 3,
+    });
+
+    tracker.on_incoming(&command(deliver_sm));
+
+    assert_eq!(receipt.try_recv().unwrap().message_id, "abc");
+    assert!(store.is_empty());
+}
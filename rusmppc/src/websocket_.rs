@@ -0,0 +1,324 @@
+//! `SMPP` over `WebSocket`.
+//!
+//! Every command is sent in its own binary message. Received binary messages are decoded as a byte stream,
+//! so that commands split across messages or sharing a message are accepted as well.
+
+use std::{
+    pin::Pin,
+    task::{Context, Poll, ready},
+};
+
+use futures::{Sink, Stream};
+use rusmpp::{
+    Command,
+    tokio_codec::{CommandCodec, DecodeError, EncodeError},
+};
+use tokio_util::{
+    bytes::{Bytes, BytesMut},
+    codec::{Decoder, Encoder},
+};
+
+#[cfg(all(test, feature = "websocket"))]
+mod tests;
+
+pin_project_lite::pin_project! {
+    /// Frames [`Command`]s over a stream and sink of binary messages.
+    #[derive(Debug)]
+    pub(crate) struct WebSocketFramed<W> {
+        #[pin]
+        messages: W,
+        codec: CommandCodec,
+        read: BytesMut,
+        write: BytesMut,
+    }
+}
+
+impl<W> WebSocketFramed<W> {
+    pub(crate) fn new(messages: W, max_command_length: usize) -> Self {
+        Self {
+            messages,
+            codec: CommandCodec::new().with_max_length(max_command_length),
+            read: BytesMut::new(),
+            write: BytesMut::new(),
+        }
+    }
+}
+
+impl<W, B> Stream for WebSocketFramed<W>
+where
+    W: Stream<Item = std::io::Result<B>>,
+    B: AsRef<[u8]>,
+{
+    type Item = Result<Command, DecodeError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut this = self.project();
+
+        loop {
+            if let Some(command) = this.codec.decode(this.read)? {
+                return Poll::Ready(Some(Ok(command)));
+            }
+
+            match ready!(this.messages.as_mut().poll_next(cx)) {
+                Some(Ok(message)) => this.read.extend_from_slice(message.as_ref()),
+                Some(Err(err)) => return Poll::Ready(Some(Err(DecodeError::Io(err)))),
+                None if this.read.is_empty() => return Poll::Ready(None),
+                None => {
+                    let remaining = this.read.len();
+
+                    this.read.clear();
+
+                    return Poll::Ready(Some(Err(DecodeError::Io(std::io::Error::new(
+                        std::io::ErrorKind::UnexpectedEof,
+                        format!("WebSocket closed with {remaining} bytes of an incomplete command"),
+                    )))));
+                }
+            }
+        }
+    }
+}
+
+impl<W> Sink<&Command> for WebSocketFramed<W>
+where
+    W: Sink<Bytes, Error = std::io::Error>,
+{
+    type Error = EncodeError;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.project().messages.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn start_send(self: Pin<&mut Self>, command: &Command) -> Result<(), Self::Error> {
+        let this = self.project();
+
+        this.codec.encode(command, this.write)?;
+
+        this.messages.start_send(this.write.split().freeze())?;
+
+        Ok(())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.project().messages.poll_flush(cx).map_err(Into::into)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.project().messages.poll_close(cx).map_err(Into::into)
+    }
+}
+
+#[cfg(feature = "websocket")]
+pub(crate) use tungstenite::connect;
+
+#[cfg(feature = "wasm")]
+pub(crate) use browser::open;
+
+#[cfg(feature = "websocket")]
+mod tungstenite {
+    use tokio::io::{AsyncRead, AsyncWrite};
+    use tokio_tungstenite::{WebSocketStream, tungstenite::Message};
+
+    use super::*;
+
+    /// Performs the `WebSocket` handshake over the stream.
+    pub(crate) async fn connect<S>(
+        url: &url::Url,
+        stream: S,
+        max_command_length: usize,
+    ) -> Result<WebSocketFramed<BinaryMessages<S>>, crate::error::Error>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        tracing::debug!(target: "rusmppc::connection::websocket", %url, "Performing WebSocket handshake");
+
+        let (stream, _) = tokio_tungstenite::client_async(url.as_str(), stream)
+            .await
+            .map_err(std::io::Error::other)
+            .map_err(crate::error::Error::Connect)?;
+
+        tracing::debug!(target: "rusmppc::connection::websocket", %url, "WebSocket handshake completed");
+
+        Ok(WebSocketFramed::new(
+            BinaryMessages { stream },
+            max_command_length,
+        ))
+    }
+
+    /// The binary messages of a [`WebSocketStream`].
+    ///
+    /// Control messages are answered by the stream itself and skipped.
+    #[derive(Debug)]
+    pub(crate) struct BinaryMessages<S> {
+        stream: WebSocketStream<S>,
+    }
+
+    impl<S: AsyncRead + AsyncWrite + Unpin> Stream for BinaryMessages<S> {
+        type Item = std::io::Result<Bytes>;
+
+        fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+            loop {
+                let message = match ready!(Pin::new(&mut self.stream).poll_next(cx)) {
+                    Some(Ok(message)) => message,
+                    Some(Err(err)) => return Poll::Ready(Some(Err(std::io::Error::other(err)))),
+                    None => return Poll::Ready(None),
+                };
+
+                match message {
+                    Message::Binary(bytes) => return Poll::Ready(Some(Ok(bytes))),
+                    Message::Text(_) => {
+                        return Poll::Ready(Some(Err(std::io::Error::new(
+                            std::io::ErrorKind::InvalidData,
+                            "Received a text message, SMPP commands must be sent in binary messages",
+                        ))));
+                    }
+                    Message::Close(_) => return Poll::Ready(None),
+                    Message::Ping(_) | Message::Pong(_) | Message::Frame(_) => continue,
+                }
+            }
+        }
+    }
+
+    impl<S: AsyncRead + AsyncWrite + Unpin> Sink<Bytes> for BinaryMessages<S> {
+        type Error = std::io::Error;
+
+        fn poll_ready(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+        ) -> Poll<Result<(), Self::Error>> {
+            Pin::new(&mut self.stream)
+                .poll_ready(cx)
+                .map_err(std::io::Error::other)
+        }
+
+        fn start_send(mut self: Pin<&mut Self>, bytes: Bytes) -> Result<(), Self::Error> {
+            Pin::new(&mut self.stream)
+                .start_send(Message::Binary(bytes))
+                .map_err(std::io::Error::other)
+        }
+
+        fn poll_flush(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+        ) -> Poll<Result<(), Self::Error>> {
+            Pin::new(&mut self.stream)
+                .poll_flush(cx)
+                .map_err(std::io::Error::other)
+        }
+
+        fn poll_close(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+        ) -> Poll<Result<(), Self::Error>> {
+            Pin::new(&mut self.stream)
+                .poll_close(cx)
+                .map_err(std::io::Error::other)
+        }
+    }
+}
+
+#[cfg(feature = "wasm")]
+mod browser {
+    use futures::SinkExt;
+    use gloo_net::websocket::{Message, State, WebSocketError, futures::WebSocket};
+
+    use super::*;
+
+    /// Opens a browser `WebSocket` and waits until it is open.
+    pub(crate) async fn open(
+        url: &url::Url,
+        max_command_length: usize,
+    ) -> Result<WebSocketFramed<BinaryMessages>, crate::error::Error> {
+        tracing::debug!(target: "rusmppc::connection::websocket", %url, "Opening WebSocket");
+
+        let mut socket = WebSocket::open(url.as_str())
+            .map_err(|err| std::io::Error::other(err.to_string()))
+            .map_err(crate::error::Error::Connect)?;
+
+        // The sink is ready once the socket is no longer connecting.
+        futures::future::poll_fn(|cx| socket.poll_ready_unpin(cx))
+            .await
+            .map_err(|err| std::io::Error::other(err.to_string()))
+            .map_err(crate::error::Error::Connect)?;
+
+        if !matches!(socket.state(), State::Open) {
+            return Err(crate::error::Error::Connect(std::io::Error::new(
+                std::io::ErrorKind::ConnectionRefused,
+                "Failed to open WebSocket",
+            )));
+        }
+
+        tracing::debug!(target: "rusmppc::connection::websocket", %url, "WebSocket opened");
+
+        Ok(WebSocketFramed::new(
+            BinaryMessages { socket },
+            max_command_length,
+        ))
+    }
+
+    /// The binary messages of a browser [`WebSocket`].
+    pub(crate) struct BinaryMessages {
+        socket: WebSocket,
+    }
+
+    impl std::fmt::Debug for BinaryMessages {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.debug_struct("BinaryMessages")
+                .field("state", &self.socket.state())
+                .finish()
+        }
+    }
+
+    impl Stream for BinaryMessages {
+        type Item = std::io::Result<Vec<u8>>;
+
+        fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+            match ready!(Pin::new(&mut self.socket).poll_next(cx)) {
+                Some(Ok(Message::Bytes(bytes))) => Poll::Ready(Some(Ok(bytes))),
+                Some(Ok(Message::Text(_))) => Poll::Ready(Some(Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    "Received a text message, SMPP commands must be sent in binary messages",
+                )))),
+                // The close event is followed by the end of the stream.
+                Some(Err(WebSocketError::ConnectionClose(_))) | None => Poll::Ready(None),
+                Some(Err(err)) => Poll::Ready(Some(Err(std::io::Error::other(err.to_string())))),
+            }
+        }
+    }
+
+    impl Sink<Bytes> for BinaryMessages {
+        type Error = std::io::Error;
+
+        fn poll_ready(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+        ) -> Poll<Result<(), Self::Error>> {
+            Pin::new(&mut self.socket)
+                .poll_ready(cx)
+                .map_err(|err| std::io::Error::other(err.to_string()))
+        }
+
+        fn start_send(mut self: Pin<&mut Self>, bytes: Bytes) -> Result<(), Self::Error> {
+            Pin::new(&mut self.socket)
+                .start_send(Message::Bytes(bytes.into()))
+                .map_err(|err| std::io::Error::other(err.to_string()))
+        }
+
+        fn poll_flush(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+        ) -> Poll<Result<(), Self::Error>> {
+            Pin::new(&mut self.socket)
+                .poll_flush(cx)
+                .map_err(|err| std::io::Error::other(err.to_string()))
+        }
+
+        fn poll_close(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+        ) -> Poll<Result<(), Self::Error>> {
+            Pin::new(&mut self.socket)
+                .poll_close(cx)
+                .map_err(|err| std::io::Error::other(err.to_string()))
+        }
+    }
+}
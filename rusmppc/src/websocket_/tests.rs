@@ -0,0 +1,229 @@
+use std::time::Duration;
+
+use futures::{SinkExt, StreamExt};
+use rusmpp::{
+    CommandId, CommandStatus, Pdu,
+    pdus::{BindTransceiver, BindTransceiverResp},
+};
+use tokio::{net::TcpListener, sync::mpsc};
+use tokio_tungstenite::{
+    WebSocketStream,
+    tungstenite::{
+        Message,
+        handshake::server::{Request, Response},
+    },
+};
+
+use super::*;
+use crate::{ConnectionBuilder, tests::init_tracing};
+
+fn encode(command: &Command) -> Bytes {
+    let mut bytes = BytesMut::new();
+
+    CommandCodec::new().encode(command, &mut bytes).unwrap();
+
+    bytes.freeze()
+}
+
+/// Decodes a binary message that must hold exactly one command.
+fn decode(bytes: &[u8]) -> Command {
+    let mut bytes = BytesMut::from(bytes);
+
+    let command = CommandCodec::new()
+        .decode(&mut bytes)
+        .unwrap()
+        .expect("Message does not hold a complete command");
+
+    assert!(bytes.is_empty(), "Message holds more than one command");
+
+    command
+}
+
+fn response(command: &Command) -> Command {
+    let builder = Command::builder()
+        .status(CommandStatus::EsmeRok)
+        .sequence_number(command.sequence_number());
+
+    match command.id() {
+        CommandId::BindTransceiver => builder.pdu(BindTransceiverResp::default()),
+        CommandId::EnquireLink => builder.pdu(Pdu::EnquireLinkResp),
+        CommandId::Unbind => builder.pdu(Pdu::UnbindResp),
+        id => panic!("Unexpected command: {id:?}"),
+    }
+}
+
+/// Accepts a single `WebSocket` connection and reports the requested path.
+#[allow(clippy::result_large_err)]
+async fn accept(
+    listener: TcpListener,
+    paths: mpsc::UnboundedSender<String>,
+) -> WebSocketStream<tokio::net::TcpStream> {
+    let (stream, _) = listener.accept().await.unwrap();
+
+    tokio_tungstenite::accept_hdr_async(stream, |request: &Request, response: Response| {
+        let _ = paths.send(request.uri().to_string());
+
+        Ok(response)
+    })
+    .await
+    .unwrap()
+}
+
+/// Skips the control messages and decodes the next binary message.
+async fn next_command(ws: &mut WebSocketStream<tokio::net::TcpStream>) -> Option<Command> {
+    while let Some(Ok(message)) = ws.next().await {
+        if let Message::Binary(bytes) = message {
+            return Some(decode(&bytes));
+        }
+    }
+
+    None
+}
+
+async fn listen() -> (TcpListener, u16) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    (listener, port)
+}
+
+#[tokio::test]
+async fn every_command_should_be_sent_in_its_own_binary_message() {
+    init_tracing();
+
+    let (listener, port) = listen().await;
+    let (paths, mut requested) = mpsc::unbounded_channel();
+    let (received, mut commands) = mpsc::unbounded_channel();
+
+    tokio::spawn(async move {
+        let mut ws = accept(listener, paths).await;
+
+        while let Some(command) = next_command(&mut ws).await {
+            ws.send(Message::Binary(encode(&response(&command))))
+                .await
+                .unwrap();
+
+            received.send(command.id()).unwrap();
+        }
+    });
+
+    let (client, _events) = ConnectionBuilder::new()
+        .no_enquire_link_interval()
+        .connect(format!("ws://127.0.0.1:{port}/smpp?account=esme"))
+        .await
+        .expect("Failed to connect");
+
+    assert_eq!(requested.recv().await.unwrap(), "/smpp?account=esme");
+
+    client
+        .bind_transceiver(BindTransceiver::default())
+        .await
+        .expect("Failed to bind");
+
+    client.enquire_link().await.expect("Failed to enquire link");
+
+    client.unbind().await.expect("Failed to unbind");
+
+    assert_eq!(commands.recv().await, Some(CommandId::BindTransceiver));
+    assert_eq!(commands.recv().await, Some(CommandId::EnquireLink));
+    assert_eq!(commands.recv().await, Some(CommandId::Unbind));
+}
+
+#[tokio::test]
+async fn commands_split_across_and_sharing_messages_should_be_decoded() {
+    init_tracing();
+
+    let (listener, port) = listen().await;
+    let (paths, _requested) = mpsc::unbounded_channel();
+
+    tokio::spawn(async move {
+        let mut ws = accept(listener, paths).await;
+
+        let bind = encode(&response(&next_command(&mut ws).await.unwrap()));
+        let (head, tail) = bind.split_at(5);
+
+        ws.send(Message::Binary(Bytes::copy_from_slice(head)))
+            .await
+            .unwrap();
+        ws.send(Message::Ping(Bytes::new())).await.unwrap();
+        ws.send(Message::Binary(Bytes::copy_from_slice(tail)))
+            .await
+            .unwrap();
+
+        let mut shared = Vec::new();
+
+        for _ in 0..2 {
+            let command = next_command(&mut ws).await.unwrap();
+
+            shared.extend_from_slice(&encode(&response(&command)));
+        }
+
+        ws.send(Message::Binary(shared.into())).await.unwrap();
+
+        // Keep the connection open until the client is done.
+        while ws.next().await.is_some() {}
+    });
+
+    let (client, _events) = ConnectionBuilder::new()
+        .no_enquire_link_interval()
+        .connect(format!("ws://127.0.0.1:{port}"))
+        .await
+        .expect("Failed to connect");
+
+    client
+        .bind_transceiver(BindTransceiver::default())
+        .await
+        .expect("Failed to bind");
+
+    let (first, second) = tokio::join!(client.enquire_link(), client.enquire_link());
+
+    first.expect("Failed to enquire link");
+    second.expect("Failed to enquire link");
+}
+
+#[tokio::test]
+async fn text_message_should_close_the_connection() {
+    init_tracing();
+
+    let (listener, port) = listen().await;
+    let (paths, _requested) = mpsc::unbounded_channel();
+
+    tokio::spawn(async move {
+        let mut ws = accept(listener, paths).await;
+
+        ws.send(Message::text("bind_transceiver")).await.unwrap();
+
+        while ws.next().await.is_some() {}
+    });
+
+    let (client, _events) = ConnectionBuilder::new()
+        .no_enquire_link_interval()
+        .connect(format!("ws://127.0.0.1:{port}"))
+        .await
+        .expect("Failed to connect");
+
+    tokio::time::timeout(Duration::from_secs(5), client.closed())
+        .await
+        .expect("Connection was not closed");
+}
+
+#[tokio::test]
+async fn failed_handshake_should_fail_to_connect() {
+    init_tracing();
+
+    let (listener, port) = listen().await;
+
+    tokio::spawn(async move {
+        // Closes the connection before the handshake.
+        let _ = listener.accept().await.unwrap();
+    });
+
+    let error = ConnectionBuilder::new()
+        .no_enquire_link_interval()
+        .connect(format!("ws://127.0.0.1:{port}"))
+        .await
+        .err()
+        .expect("Handshake should fail");
+
+    assert!(matches!(error, crate::error::Error::Connect(_)));
+}
@@ -0,0 +1,126 @@
+use std::net::SocketAddr;
+
+use axum::{
+    Json, Router,
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{get, post},
+};
+use serde::{Deserialize, Serialize};
+use tokio::net::TcpListener;
+
+use crate::control::{Control, ControlError, MoMessage, SessionInfo};
+
+/// An `HTTP` listener exposing the [`Control`] API.
+#[derive(Debug, Clone, Deserialize)]
+pub struct AdminConfig {
+    pub socket_addr: SocketAddr,
+}
+
+#[derive(Debug, Serialize)]
+struct SessionResponse {
+    system_id: String,
+    session_id: u64,
+    session_state: String,
+    addr: SocketAddr,
+}
+
+impl From<SessionInfo> for SessionResponse {
+    fn from(session: SessionInfo) -> Self {
+        Self {
+            system_id: session.system_id,
+            session_id: session.session_id,
+            session_state: format!("{:?}", session.session_state),
+            addr: session.addr,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct DeliverSmResponse {
+    /// The sequence numbers of the `deliver_sm` sent, in part order.
+    sequence_numbers: Vec<u32>,
+}
+
+#[derive(Debug, Serialize)]
+struct DataSmResponse {
+    sequence_number: u32,
+}
+
+#[derive(Debug, Serialize)]
+struct ErrorResponse {
+    error: String,
+}
+
+impl IntoResponse for ControlError {
+    fn into_response(self) -> Response {
+        let status = match self {
+            ControlError::NoReceiver { .. } | ControlError::UnknownSession(_) => {
+                StatusCode::NOT_FOUND
+            }
+            ControlError::SessionClosed => StatusCode::GONE,
+            ControlError::InvalidAddress(_)
+            | ControlError::InvalidServiceType(_)
+            | ControlError::Encoding(_) => StatusCode::UNPROCESSABLE_ENTITY,
+        };
+
+        let error = ErrorResponse {
+            error: self.to_string(),
+        };
+
+        (status, Json(error)).into_response()
+    }
+}
+
+/// Serves the admin API on the `listener` until it fails.
+///
+/// - `GET /sessions` lists the bound sessions.
+/// - `POST /accounts/{system_id}/deliver_sm` delivers a [`MoMessage`] using `deliver_sm`.
+/// - `POST /accounts/{system_id}/data_sm` delivers a [`MoMessage`] using `data_sm`.
+/// - `POST /sessions/{session_id}/unbind` unbinds the session.
+pub async fn serve(listener: TcpListener, control: Control) -> std::io::Result<()> {
+    let app = Router::new()
+        .route("/sessions", get(sessions))
+        .route("/accounts/{system_id}/deliver_sm", post(deliver_sm))
+        .route("/accounts/{system_id}/data_sm", post(data_sm))
+        .route("/sessions/{session_id}/unbind", post(unbind))
+        .with_state(control);
+
+    axum::serve(listener, app).await
+}
+
+async fn sessions(State(control): State<Control>) -> Json<Vec<SessionResponse>> {
+    let sessions = control.sessions().await;
+
+    Json(sessions.into_iter().map(SessionResponse::from).collect())
+}
+
+async fn deliver_sm(
+    State(control): State<Control>,
+    Path(system_id): Path<String>,
+    Json(message): Json<MoMessage>,
+) -> Result<Json<DeliverSmResponse>, ControlError> {
+    let sequence_numbers = control.deliver_sm(&system_id, message).await?;
+
+    Ok(Json(DeliverSmResponse { sequence_numbers }))
+}
+
+async fn data_sm(
+    State(control): State<Control>,
+    Path(system_id): Path<String>,
+    Json(message): Json<MoMessage>,
+) -> Result<Json<DataSmResponse>, ControlError> {
+    let sequence_number = control.data_sm(&system_id, message).await?;
+
+    Ok(Json(DataSmResponse { sequence_number }))
+}
+
+async fn unbind(
+    State(control): State<Control>,
+    Path(session_id): Path<u64>,
+) -> Result<StatusCode, ControlError> {
+    control.unbind(session_id).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -9,6 +9,6 @@ pub struct Args {
         env = "RUSMPPS_CONFIG_FILE",
         default_value = "rusmpps-config.yaml"
     )]
-    /// Config file: The path to the configuration file
+    /// Config file: The path to the configuration file. The default configuration is used if it does not exist
     pub config_file: PathBuf,
 }
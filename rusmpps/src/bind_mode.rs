@@ -1,12 +1,18 @@
 use rusmpp::session::SessionState;
+use serde::Deserialize;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum BindMode {
     Tx,
     Rx,
     Trx,
 }
 
+impl BindMode {
+    pub const ALL: [BindMode; 3] = [BindMode::Tx, BindMode::Rx, BindMode::Trx];
+}
+
 impl From<BindMode> for SessionState {
     fn from(bind_mode: BindMode) -> Self {
         match bind_mode {
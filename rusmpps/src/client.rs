@@ -1,22 +1,145 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::HashMap,
+    net::{IpAddr, SocketAddr},
+    sync::{
+        Arc,
+        atomic::{AtomicU32, Ordering},
+    },
+};
 
-use rusmpp::{Pdu, session::SessionState};
-use tokio::sync::{RwLock, RwLockReadGuard, mpsc::Sender};
+use ipnet::IpNet;
+use rusmpp::{CommandId, CommandStatus, Pdu, session::SessionState};
+use serde::Deserialize;
+use subtle::ConstantTimeEq;
+use tokio::sync::{
+    RwLock, RwLockReadGuard,
+    mpsc::{Sender, error::SendError},
+};
 
-#[derive(Debug)]
+use crate::{bind_mode::BindMode, service::Bind, store::Lifecycle};
+
+#[cfg(test)]
+mod tests;
+
+/// An account allowed to bind to the server.
+#[derive(Clone, Deserialize)]
 pub struct Client {
     pub system_id: String,
     pub password: String,
+    /// All bind modes are allowed if not set.
+    #[serde(default = "all_bind_modes")]
+    pub bind_modes: Vec<BindMode>,
+    /// Source IP ranges in CIDR notation, e.g. `10.0.0.0/8`. Any address is allowed if empty.
+    #[serde(default)]
+    pub allowed_ips: Vec<IpNet>,
+    /// Maximum number of concurrent sessions. Unlimited if not set.
+    #[serde(default)]
+    pub max_sessions: Option<usize>,
+    /// Overrides the message store `delivery_delay` and `outcomes` of the account's messages.
+    #[serde(flatten)]
+    pub lifecycle: Lifecycle,
+}
+
+impl std::fmt::Debug for Client {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Client")
+            .field("system_id", &self.system_id)
+            .field("password", &"***")
+            .field("bind_modes", &self.bind_modes)
+            .field("allowed_ips", &self.allowed_ips)
+            .field("max_sessions", &self.max_sessions)
+            .field("lifecycle", &self.lifecycle)
+            .finish()
+    }
+}
+
+fn all_bind_modes() -> Vec<BindMode> {
+    BindMode::ALL.to_vec()
+}
+
+impl Client {
+    pub fn allows_bind_mode(&self, bind_mode: BindMode) -> bool {
+        self.bind_modes.contains(&bind_mode)
+    }
+
+    /// Compares the passwords in constant time, so that the timing does not reveal how much of the `password` matches.
+    ///
+    /// Only the length of the password may leak.
+    pub fn password_matches(&self, password: &str) -> bool {
+        self.password.as_bytes().ct_eq(password.as_bytes()).into()
+    }
+
+    pub fn allows_ip(&self, ip: IpAddr) -> bool {
+        let ip = ip.to_canonical();
+
+        self.allowed_ips.is_empty() || self.allowed_ips.iter().any(|net| net.contains(&ip))
+    }
+}
+
+/// Returns the account matching the bind, or the status to reject the bind with.
+///
+/// Every bind is accepted if no accounts are configured.
+pub fn authenticate<'a>(
+    clients: &'a [Client],
+    bind: &Bind,
+) -> Result<Option<&'a Client>, CommandStatus> {
+    if clients.is_empty() {
+        return Ok(None);
+    }
+
+    let system_id = bind.system_id.as_str();
+
+    let Some(client) = clients.iter().find(|client| client.system_id == system_id) else {
+        tracing::warn!(system_id, "Unknown system_id");
+
+        return Err(CommandStatus::EsmeRinvsysid);
+    };
+
+    let ip = bind.addr.ip();
+
+    if !client.allows_ip(ip) {
+        tracing::warn!(system_id, %ip, "Source address not allowed");
+
+        return Err(CommandStatus::EsmeRbindfail);
+    }
+
+    if !client.password_matches(&bind.password) {
+        tracing::warn!(system_id, "Invalid password");
+
+        return Err(CommandStatus::EsmeRinvpaswd);
+    }
+
+    if !client.allows_bind_mode(bind.bind_mode) {
+        tracing::warn!(system_id, bind_mode=?bind.bind_mode, "Bind mode not allowed");
+
+        return Err(CommandStatus::EsmeRbindfail);
+    }
+
+    Ok(Some(client))
 }
 
 #[derive(Debug)]
+#[allow(clippy::large_enum_variant)]
 pub enum Action {
-    Send(Pdu),
+    /// Sends the `pdu` with a sequence number taken from the [`SequenceNumber`] of the session.
+    Send { sequence_number: u32, pdu: Pdu },
+    /// Sends an `unbind` and closes the session once it is answered.
+    Unbind,
 }
 
-#[derive(Debug)]
+/// The smallest valid sequence number.
+const MIN_SEQUENCE_NUMBER: u32 = 0x00000001;
+
+/// The largest valid sequence number.
+const MAX_SEQUENCE_NUMBER: u32 = 0x7FFFFFFF;
+
+/// The sequence numbers of the commands sent to an `ESME`.
+///
+/// Shared by the session and its [`SessionSender`]s, so that senders know the sequence number of the commands they send.
+/// Counts up from [`MIN_SEQUENCE_NUMBER`] and wraps back to it after [`MAX_SEQUENCE_NUMBER`].
+#[derive(Debug, Clone)]
 pub struct SequenceNumber {
-    current: u32,
+    current: Arc<AtomicU32>,
 }
 
 impl Default for SequenceNumber {
@@ -27,15 +150,79 @@ impl Default for SequenceNumber {
 
 impl SequenceNumber {
     pub fn new() -> Self {
-        Self { current: 1 }
+        Self {
+            current: Arc::new(AtomicU32::new(MIN_SEQUENCE_NUMBER)),
+        }
+    }
+
+    pub fn current_and_increment(&self) -> u32 {
+        self.current
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |current| {
+                if current >= MAX_SEQUENCE_NUMBER {
+                    Some(MIN_SEQUENCE_NUMBER)
+                } else {
+                    Some(current + 1)
+                }
+            })
+            .expect("The closure always returns Some")
+    }
+}
+
+/// Sends actions to a session.
+#[derive(Debug, Clone)]
+pub struct SessionSender {
+    tx: Sender<Action>,
+    sequence_number: SequenceNumber,
+}
+
+impl SessionSender {
+    pub fn new(tx: Sender<Action>, sequence_number: SequenceNumber) -> Self {
+        Self {
+            tx,
+            sequence_number,
+        }
     }
 
-    pub fn current_and_increment(&mut self) -> u32 {
-        let seq = self.current;
+    /// Sends the `pdu`, waiting for the session to have room for it.
+    ///
+    /// Returns the sequence number of the command, or the `pdu` back if the session is closed.
+    pub async fn send(&self, pdu: Pdu) -> Result<u32, SendError<Pdu>> {
+        let Ok(permit) = self.tx.reserve().await else {
+            return Err(SendError(pdu));
+        };
+
+        let sequence_number = self.sequence_number.current_and_increment();
+
+        permit.send(Action::Send {
+            sequence_number,
+            pdu,
+        });
+
+        Ok(sequence_number)
+    }
+
+    /// Sends the `pdu` without waiting for a full session.
+    ///
+    /// Returns the sequence number of the command, or the `pdu` back if the session is closed or full.
+    #[allow(clippy::result_large_err)]
+    pub fn try_send(&self, pdu: Pdu) -> Result<u32, Pdu> {
+        let Ok(permit) = self.tx.try_reserve() else {
+            return Err(pdu);
+        };
+
+        let sequence_number = self.sequence_number.current_and_increment();
+
+        permit.send(Action::Send {
+            sequence_number,
+            pdu,
+        });
 
-        self.current += 1;
+        Ok(sequence_number)
+    }
 
-        seq
+    /// Unbinds the session.
+    pub async fn unbind(&self) -> Result<(), SendError<Action>> {
+        self.tx.send(Action::Unbind).await
     }
 }
 
@@ -55,6 +242,16 @@ impl ConnectedClient {
         self.sessions.get(&session_id)
     }
 
+    pub fn session_count(&self) -> usize {
+        self.sessions.len()
+    }
+
+    pub fn sessions(&self) -> impl Iterator<Item = (u64, &ClientSession)> {
+        self.sessions
+            .iter()
+            .map(|(session_id, session)| (*session_id, session))
+    }
+
     fn insert_session(&mut self, session_id: u64, session: ClientSession) {
         self.sessions.insert(session_id, session);
     }
@@ -66,15 +263,17 @@ impl ConnectedClient {
 
 #[derive(Debug)]
 pub struct ClientSession {
-    pub tx: Sender<Action>,
+    pub sender: SessionSender,
     pub session_state: SessionState,
+    pub addr: SocketAddr,
 }
 
 impl ClientSession {
-    pub fn new(sender: Sender<Action>, session_state: SessionState) -> Self {
+    pub fn new(sender: SessionSender, session_state: SessionState, addr: SocketAddr) -> Self {
         Self {
-            tx: sender,
+            sender,
             session_state,
+            addr,
         }
     }
 }
@@ -99,6 +298,34 @@ impl ConnectedClients {
         client.insert_session(session_id, session);
     }
 
+    /// Inserts the session unless the client already has `max_sessions` sessions.
+    ///
+    /// Returns `false` if the session was not inserted.
+    pub async fn try_insert_session(
+        &self,
+        system_id: String,
+        session_id: u64,
+        session: ClientSession,
+        max_sessions: Option<usize>,
+    ) -> bool {
+        let mut clients = self.clients.write().await;
+
+        let sessions = clients
+            .get(&system_id)
+            .map_or(0, ConnectedClient::session_count);
+
+        if max_sessions.is_some_and(|max_sessions| sessions >= max_sessions) {
+            return false;
+        }
+
+        clients
+            .entry(system_id)
+            .or_insert_with(ConnectedClient::new)
+            .insert_session(session_id, session);
+
+        true
+    }
+
     pub async fn remove_session(&self, system_id: &str, session_id: u64) -> Option<ClientSession> {
         tracing::debug!(system_id, session_id, "Removing session");
 
@@ -120,6 +347,70 @@ impl ConnectedClients {
         }
     }
 
+    /// Sends the `pdu` to a session of the client allowed to receive it, e.g. a receiver or transceiver for a `deliver_sm`.
+    ///
+    /// Returns the sequence number of the command, or the `pdu` back if no session can take it.
+    pub async fn send_to_receiver(&self, system_id: &str, mut pdu: Pdu) -> Result<u32, Pdu> {
+        let clients = self.clients.read().await;
+
+        let Some(client) = clients.get(system_id) else {
+            return Err(pdu);
+        };
+
+        for session in client.sessions.values() {
+            if !session.session_state.can_send_as_mc(pdu.command_id()) {
+                continue;
+            }
+
+            match session.sender.try_send(pdu) {
+                Ok(sequence_number) => return Ok(sequence_number),
+                Err(returned) => pdu = returned,
+            }
+        }
+
+        Err(pdu)
+    }
+
+    /// Sends the `pdu` to the session with the given `session_id` if it is allowed to receive it.
+    ///
+    /// Returns the sequence number of the command, or the `pdu` back if the session is unknown, not allowed to receive it or full.
+    pub async fn send_to_session(&self, session_id: u64, pdu: Pdu) -> Result<u32, Pdu> {
+        let clients = self.clients.read().await;
+
+        let Some(session) = clients
+            .values()
+            .find_map(|client| client.session(session_id))
+            .filter(|session| session.session_state.can_send_as_mc(pdu.command_id()))
+        else {
+            return Err(pdu);
+        };
+
+        session.sender.try_send(pdu)
+    }
+
+    /// Returns the sender of a session of the client allowed to receive commands with the given `id`.
+    pub async fn receiver(&self, system_id: &str, id: CommandId) -> Option<SessionSender> {
+        let clients = self.clients.read().await;
+
+        clients.get(system_id).and_then(|client| {
+            client
+                .sessions
+                .values()
+                .find(|session| session.session_state.can_send_as_mc(id))
+                .map(|session| session.sender.clone())
+        })
+    }
+
+    /// Returns the sender of the session with the given `session_id`.
+    pub async fn session(&self, session_id: u64) -> Option<SessionSender> {
+        let clients = self.clients.read().await;
+
+        clients
+            .values()
+            .find_map(|client| client.session(session_id))
+            .map(|session| session.sender.clone())
+    }
+
     pub async fn clients(&self) -> RwLockReadGuard<'_, HashMap<String, ConnectedClient>> {
         self.clients.read().await
     }
@@ -0,0 +1,213 @@
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+use rusmpp::values::InterfaceVersion;
+use tokio::sync::mpsc;
+
+use super::*;
+
+fn client(system_id: &str, password: &str) -> Client {
+    Client {
+        system_id: system_id.to_string(),
+        password: password.to_string(),
+        bind_modes: all_bind_modes(),
+        allowed_ips: Vec::new(),
+        max_sessions: None,
+        lifecycle: Lifecycle::default(),
+    }
+}
+
+fn bind(system_id: &str, password: &str, bind_mode: BindMode, ip: IpAddr) -> Bind {
+    Bind {
+        session_id: 1,
+        addr: SocketAddr::new(ip, 40000),
+        bind_mode,
+        system_id: system_id.to_string(),
+        password: password.to_string(),
+        system_type: String::new(),
+        interface_version: InterfaceVersion::Smpp5_0,
+    }
+}
+
+const LOCALHOST: IpAddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+
+fn session() -> ClientSession {
+    let (tx, _rx) = mpsc::channel(1);
+    let addr = SocketAddr::new(LOCALHOST, 40000);
+
+    ClientSession::new(
+        SessionSender::new(tx, SequenceNumber::new()),
+        SessionState::BoundTrx,
+        addr,
+    )
+}
+
+#[test]
+fn every_bind_should_be_accepted_without_accounts() {
+    let bind = bind("anyone", "anything", BindMode::Trx, LOCALHOST);
+
+    assert!(matches!(authenticate(&[], &bind), Ok(None)));
+}
+
+#[test]
+fn matching_binds_should_be_accepted() {
+    let clients = [client("other", "secret"), client("esme", "secret")];
+    let bind = bind("esme", "secret", BindMode::Tx, LOCALHOST);
+
+    let client = authenticate(&clients, &bind).unwrap().unwrap();
+
+    assert_eq!(client.system_id, "esme");
+}
+
+#[test]
+fn unknown_system_ids_should_be_rejected() {
+    let clients = [client("esme", "secret")];
+    let bind = bind("unknown", "secret", BindMode::Trx, LOCALHOST);
+
+    assert!(matches!(
+        authenticate(&clients, &bind),
+        Err(CommandStatus::EsmeRinvsysid)
+    ));
+}
+
+#[test]
+fn wrong_passwords_should_be_rejected() {
+    let clients = [client("esme", "secret")];
+
+    for password in ["wrong", "secre", "secrets", ""] {
+        let bind = bind("esme", password, BindMode::Trx, LOCALHOST);
+
+        assert!(matches!(
+            authenticate(&clients, &bind),
+            Err(CommandStatus::EsmeRinvpaswd)
+        ));
+    }
+}
+
+#[test]
+fn binds_from_disallowed_addresses_should_be_rejected() {
+    let clients = [Client {
+        allowed_ips: vec!["10.0.0.0/8".parse().unwrap()],
+        ..client("esme", "secret")
+    }];
+
+    let allowed = IpAddr::V4(Ipv4Addr::new(10, 1, 2, 3));
+    let disallowed = IpAddr::V4(Ipv4Addr::new(192, 168, 1, 1));
+
+    assert!(authenticate(&clients, &bind("esme", "secret", BindMode::Trx, allowed)).is_ok());
+    assert!(matches!(
+        authenticate(&clients, &bind("esme", "secret", BindMode::Trx, disallowed)),
+        Err(CommandStatus::EsmeRbindfail)
+    ));
+}
+
+#[test]
+fn ipv4_mapped_ipv6_addresses_should_be_matched_as_ipv4() {
+    let clients = [Client {
+        allowed_ips: vec!["10.0.0.0/8".parse().unwrap()],
+        ..client("esme", "secret")
+    }];
+
+    // A dual stack listener reports IPv4 peers as `::ffff:a.b.c.d`.
+    let allowed = IpAddr::V6(Ipv4Addr::new(10, 1, 2, 3).to_ipv6_mapped());
+    let disallowed = IpAddr::V6(Ipv4Addr::new(192, 168, 1, 1).to_ipv6_mapped());
+
+    assert!(authenticate(&clients, &bind("esme", "secret", BindMode::Trx, allowed)).is_ok());
+    assert!(matches!(
+        authenticate(&clients, &bind("esme", "secret", BindMode::Trx, disallowed)),
+        Err(CommandStatus::EsmeRbindfail)
+    ));
+    assert!(matches!(
+        authenticate(
+            &clients,
+            &bind(
+                "esme",
+                "secret",
+                BindMode::Trx,
+                IpAddr::V6(Ipv6Addr::LOCALHOST)
+            )
+        ),
+        Err(CommandStatus::EsmeRbindfail)
+    ));
+}
+
+#[test]
+fn disallowed_bind_modes_should_be_rejected() {
+    let clients = [Client {
+        bind_modes: vec![BindMode::Tx],
+        ..client("esme", "secret")
+    }];
+
+    assert!(authenticate(&clients, &bind("esme", "secret", BindMode::Tx, LOCALHOST)).is_ok());
+
+    for bind_mode in [BindMode::Rx, BindMode::Trx] {
+        assert!(matches!(
+            authenticate(&clients, &bind("esme", "secret", bind_mode, LOCALHOST)),
+            Err(CommandStatus::EsmeRbindfail)
+        ));
+    }
+}
+
+#[tokio::test]
+async fn sessions_exceeding_max_sessions_should_not_be_inserted() {
+    let connected_clients = ConnectedClients::new();
+
+    for session_id in 1..=2 {
+        assert!(
+            connected_clients
+                .try_insert_session("esme".to_string(), session_id, session(), Some(2))
+                .await
+        );
+    }
+
+    assert!(
+        !connected_clients
+            .try_insert_session("esme".to_string(), 3, session(), Some(2))
+            .await
+    );
+    assert!(connected_clients.session(3).await.is_none());
+
+    // Other accounts are counted separately.
+    assert!(
+        connected_clients
+            .try_insert_session("other".to_string(), 4, session(), Some(2))
+            .await
+    );
+
+    // A closed session frees its slot.
+    connected_clients.remove_session("esme", 1).await;
+
+    assert!(
+        connected_clients
+            .try_insert_session("esme".to_string(), 3, session(), Some(2))
+            .await
+    );
+}
+
+#[tokio::test]
+async fn sessions_should_be_unlimited_without_max_sessions() {
+    let connected_clients = ConnectedClients::new();
+
+    for session_id in 1..=10 {
+        assert!(
+            connected_clients
+                .try_insert_session("esme".to_string(), session_id, session(), None)
+                .await
+        );
+    }
+}
+
+#[test]
+fn sequence_numbers_should_wrap_within_the_valid_range() {
+    let sequence_number = SequenceNumber {
+        current: Arc::new(AtomicU32::new(MAX_SEQUENCE_NUMBER - 1)),
+    };
+
+    let sequence_numbers = (0..4)
+        .map(|_| sequence_number.current_and_increment())
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        sequence_numbers,
+        [MAX_SEQUENCE_NUMBER - 1, MAX_SEQUENCE_NUMBER, 1, 2]
+    );
+}
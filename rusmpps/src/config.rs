@@ -6,9 +6,23 @@ use std::{
 
 use serde::Deserialize;
 
+use crate::{
+    admin::AdminConfig, client::Client, faults::FaultRule, router::RouterConfig,
+    store::MessageStoreConfig, tls::TlsConfig, websocket::WebSocketConfig,
+};
+
 #[derive(Debug, Deserialize)]
 pub struct Config {
     pub socket_addr: SocketAddr,
+    /// Accepts TLS connections alongside the plain ones on `socket_addr`.
+    #[serde(default)]
+    pub tls: Option<TlsConfig>,
+    /// Accepts `WebSocket` connections alongside the plain ones on `socket_addr`.
+    #[serde(default)]
+    pub websocket: Option<WebSocketConfig>,
+    /// Serves the control API over `HTTP`.
+    #[serde(default)]
+    pub admin: Option<AdminConfig>,
     #[serde(with = "humantime_serde")]
     #[serde(default)]
     pub enquire_link_interval: Option<Duration>,
@@ -25,6 +39,24 @@ pub struct Config {
     #[serde(with = "humantime_serde")]
     #[serde(default)]
     pub enquire_link_response_delay: Option<Duration>,
+    /// Maximum number of requests handled concurrently, and of commands sent to the `ESME` awaiting a response.
+    #[serde(default = "default_window_size")]
+    pub window_size: usize,
+    /// Time after which a command sent to the `ESME` no longer counts against the window.
+    #[serde(with = "humantime_serde")]
+    #[serde(default = "default_response_timeout")]
+    pub response_timeout: Duration,
+    /// Accounts allowed to bind. Every bind is accepted if empty.
+    #[serde(default)]
+    pub accounts: Vec<Client>,
+    #[serde(default)]
+    pub message_store: MessageStoreConfig,
+    /// Fault injection rules. The first rule matching a command applies.
+    #[serde(default)]
+    pub faults: Vec<FaultRule>,
+    /// Forwards the messages to upstream `MC`s instead of simulating them.
+    #[serde(default)]
+    pub router: Option<RouterConfig>,
 }
 
 impl Default for Config {
@@ -33,16 +65,33 @@ impl Default for Config {
             enquire_link_interval: Some(Duration::from_secs(10)),
             enquire_link_response_timeout: Duration::from_secs(3),
             enquire_link_response_delay: Some(Duration::from_millis(100)),
+            window_size: default_window_size(),
+            response_timeout: default_response_timeout(),
             session_timeout: Duration::from_secs(3),
             bind_delay: Some(Duration::from_millis(100)),
             response_delay: Some(Duration::from_millis(100)),
+            accounts: Vec::new(),
+            message_store: MessageStoreConfig::default(),
+            faults: Vec::new(),
+            router: None,
             socket_addr: "127.0.0.1:2775"
                 .parse()
                 .expect("Failed to parse socket address"),
+            tls: None,
+            websocket: None,
+            admin: None,
         }
     }
 }
 
+fn default_window_size() -> usize {
+    10
+}
+
+fn default_response_timeout() -> Duration {
+    Duration::from_secs(30)
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum LoadConfigErrorKind {
     #[error("Failed to read config file: {0}")]
@@ -58,6 +107,13 @@ pub struct LoadConfigError {
     kind: LoadConfigErrorKind,
 }
 
+impl LoadConfigError {
+    /// Whether the config file does not exist.
+    pub fn is_not_found(&self) -> bool {
+        matches!(&self.kind, LoadConfigErrorKind::Read(err) if err.kind() == std::io::ErrorKind::NotFound)
+    }
+}
+
 impl Config {
     fn from_yaml(yaml: &[u8]) -> Result<Self, serde_yaml::Error> {
         serde_yaml::from_slice(yaml)
@@ -1,52 +1,80 @@
-use std::{str::FromStr, sync::Arc, time::Duration};
+use std::{
+    collections::HashMap, net::SocketAddr, ops::ControlFlow, str::FromStr, sync::Arc,
+    time::Duration,
+};
 
-use futures::{SinkExt, StreamExt, TryStreamExt, future};
+use futures::{SinkExt, StreamExt};
 use rusmpp::{
     Command, CommandId, CommandStatus, Pdu,
-    pdus::{BindReceiverResp, BindTransceiverResp, BindTransmitterResp, SubmitSmResp},
+    pdus::{BindReceiverResp, BindTransceiverResp, BindTransmitterResp},
+    session::SessionState,
     tokio_codec::CommandCodec,
     types::COctetString,
     values::InterfaceVersion,
 };
 use tokio::{
-    io::{AsyncRead, AsyncWrite},
+    io::{AsyncRead, AsyncWrite, AsyncWriteExt},
     sync::mpsc,
 };
 use tokio_stream::wrappers::ReceiverStream;
-use tokio_util::codec::{FramedRead, FramedWrite};
+use tokio_util::{
+    codec::{FramedRead, FramedWrite},
+    time::DelayQueue,
+};
 
 use crate::{
     bind_mode::BindMode,
-    client::{Action, Client, ClientSession, ConnectedClients, SequenceNumber},
+    client::{Action, ClientSession, ConnectedClients, SequenceNumber, SessionSender},
+    service::{Bind, Response, Service, SessionHandler},
     timer::Timer,
 };
 
+#[cfg(test)]
+mod tests;
+
 #[derive(Debug)]
 pub struct ConnectionConfig {
     pub connected_clients: ConnectedClients,
-    pub clients: Vec<Client>,
     pub enquire_link_interval: Option<Duration>,
     pub enquire_link_response_timeout: Duration,
     pub session_timeout: Duration,
-    pub bind_delay: Option<Duration>,
-    pub response_delay: Option<Duration>,
     pub enquire_link_response_delay: Option<Duration>,
+    /// Maximum number of requests handled concurrently, and of commands sent to the `ESME` awaiting a response.
+    pub window_size: usize,
+    /// Time after which a command sent to the `ESME` no longer counts against the window.
+    pub response_timeout: Duration,
 }
 
 #[derive(Debug)]
-pub struct Connection {
+pub struct Connection<S> {
     session_id: u64,
+    addr: SocketAddr,
     config: Arc<ConnectionConfig>,
+    service: Arc<S>,
 }
 
-impl Connection {
-    pub fn new(session_id: u64, config: Arc<ConnectionConfig>) -> Self {
-        Self { session_id, config }
+impl<S: Service> Connection<S> {
+    pub fn new(
+        session_id: u64,
+        addr: SocketAddr,
+        config: Arc<ConnectionConfig>,
+        service: Arc<S>,
+    ) -> Self {
+        Self {
+            session_id,
+            addr,
+            config,
+            service,
+        }
+    }
+
+    pub fn session_id(&self) -> u64 {
+        self.session_id
     }
 
-    pub async fn run<S>(self, stream: S)
+    pub async fn run<T>(self, stream: T)
     where
-        S: AsyncRead + AsyncWrite + Send + Unpin + 'static,
+        T: AsyncRead + AsyncWrite + Send + Unpin + 'static,
     {
         let session_id = self.session_id;
 
@@ -57,134 +85,212 @@ impl Connection {
 
         tracing::debug!(session_id, "Awaiting bind operation");
 
-        let mut filtered = (&mut reader).try_filter(|command| {
-            tracing::debug!(session_id, sequence_number=command.sequence_number(), id=?command.id(), "Received command");
+        let session_timeout = tokio::time::sleep(self.config.session_timeout);
+        tokio::pin!(session_timeout);
 
-            future::ready(matches!(
-                command.id(),
-                CommandId::BindTransmitter | CommandId::BindReceiver | CommandId::BindTransceiver
-            ))
-        });
+        let (tx, rx) = mpsc::channel(100);
 
-        let (sequence_number, system_id, _, bind_mode) = tokio::select! {
-            _ = tokio::time::sleep(self.config.session_timeout) => {
-                tracing::warn!(session_id, "Session timeout reached, closing connection");
+        // Shared with the senders of the session, so that they know the sequence numbers of the commands they send.
+        let sequence_numbers = SequenceNumber::new();
 
-                return;
-            },
-            command = filtered.next() => {
-                match command {
-                    None => {
-                        tracing::warn!(session_id, "Connection closed before bind command was received");
+        // Rejected binds may be retried until the session timeout is reached.
+        let (system_id, session_state, handler) = loop {
+            let (sequence_number, bind) = tokio::select! {
+                _ = &mut session_timeout => {
+                    tracing::warn!(session_id, "Session timeout reached, closing connection");
 
-                        return;
-                    },
-                    Some(Err(err)) => {
-                        tracing::error!(session_id, ?err, "Failed read command");
+                    return;
+                },
+                command = reader.next() => {
+                    match command {
+                        None => {
+                            tracing::warn!(session_id, "Connection closed before bind command was received");
 
-                        return;
-                    },
-                    Some(Ok(command)) => {
-                        tracing::debug!(session_id, id=?command.id(), "Received bind command");
-                        tracing::trace!(session_id, ?command, "Received bind command");
+                            return;
+                        },
+                        Some(Err(err)) => {
+                            tracing::error!(session_id, ?err, "Failed read command");
 
-                        let (_, status, sequence_number, pdu) = command.into_parts().raw();
+                            return;
+                        },
+                        Some(Ok(command)) if !is_bind(command.id()) => {
+                            let (id, sequence_number) = (command.id(), command.sequence_number());
 
-                        if !(matches!(status, CommandStatus::EsmeRok)) {
-                            // Really?
+                            tracing::debug!(session_id, sequence_number, ?id, "Received command");
 
-                            tracing::error!(session_id, ?status, "Received bind command with non-OK status");
+                            let response = match id {
+                                CommandId::EnquireLink => Response::ok(Pdu::EnquireLinkResp),
+                                CommandId::Other(_) => {
+                                    tracing::warn!(session_id, sequence_number, ?id, "Received unknown command");
 
-                            return;
-                        }
+                                    Response::Send {
+                                        status: CommandStatus::EsmeRinvcmdid,
+                                        pdu: Pdu::GenericNack,
+                                    }
+                                }
+                                _ if id.is_operation() => {
+                                    tracing::warn!(session_id, sequence_number, ?id, "Received command before bind");
 
-                        let (system_id, password, bind_mode) = match pdu {
-                            None => {
-                                tracing::error!(session_id, "Received bind command without PDU");
+                                    Response::error(id, CommandStatus::EsmeRinvbndsts)
+                                }
+                                _ => {
+                                    tracing::warn!(session_id, sequence_number, ?id, "Received response before bind");
+
+                                    continue;
+                                }
+                            };
 
+                            if send_response(&mut writer, session_id, id, sequence_number, response).await.is_break() {
                                 return;
                             }
-                            Some(Pdu::BindTransmitter(bind)) => {
-                                (bind.system_id, bind.password, BindMode::Tx)
-                            }
-                            Some(Pdu::BindReceiver(bind)) => {
-                                (bind.system_id, bind.password, BindMode::Rx)
-                            }
-                            Some(Pdu::BindTransceiver(bind)) => {
-                                (bind.system_id, bind.password, BindMode::Trx)
-                            },
-                            _ => {
-                                // Should not happen
+
+                            continue;
+                        }
+                        Some(Ok(command)) => {
+                            tracing::debug!(session_id, id=?command.id(), "Received bind command");
+                            tracing::trace!(session_id, ?command, "Received bind command");
+
+                            let (_, status, sequence_number, pdu) = command.into_parts().raw();
+
+                            if !(matches!(status, CommandStatus::EsmeRok)) {
+                                // Really?
+
+                                tracing::error!(session_id, ?status, "Received bind command with non-OK status");
 
                                 return;
                             }
-                        };
 
-                        (sequence_number, system_id, password, bind_mode)
+                            let (system_id, password, system_type, interface_version, bind_mode) = match pdu {
+                                None => {
+                                    tracing::error!(session_id, "Received bind command without PDU");
+
+                                    return;
+                                }
+                                Some(Pdu::BindTransmitter(bind)) => {
+                                    (bind.system_id, bind.password, bind.system_type, bind.interface_version, BindMode::Tx)
+                                }
+                                Some(Pdu::BindReceiver(bind)) => {
+                                    (bind.system_id, bind.password, bind.system_type, bind.interface_version, BindMode::Rx)
+                                }
+                                Some(Pdu::BindTransceiver(bind)) => {
+                                    (bind.system_id, bind.password, bind.system_type, bind.interface_version, BindMode::Trx)
+                                }
+                                _ => {
+                                    // Should not happen
+
+                                    return;
+                                }
+                            };
+
+                            let bind = Bind {
+                                session_id,
+                                addr: self.addr,
+                                bind_mode,
+                                system_id: system_id.to_string(),
+                                password: password.to_string(),
+                                system_type: system_type.to_string(),
+                                interface_version,
+                            };
+
+                            (sequence_number, bind)
+                        }
                     }
                 }
-            }
-        };
+            };
+
+            let system_id = bind.system_id.clone();
+            let bind_mode = bind.bind_mode;
+
+            let mut handler = None;
+
+            let status = match self.service.bind(&bind).await {
+                Ok(bound) => {
+                    let session = ClientSession::new(
+                        SessionSender::new(tx.clone(), sequence_numbers.clone()),
+                        bind_mode.into(),
+                        self.addr,
+                    );
+                    let max_sessions = bound.max_sessions;
+
+                    if self
+                        .config
+                        .connected_clients
+                        .try_insert_session(system_id.clone(), session_id, session, max_sessions)
+                        .await
+                    {
+                        handler = Some(bound.handler);
+
+                        CommandStatus::EsmeRok
+                    } else {
+                        tracing::warn!(
+                            session_id,
+                            system_id,
+                            ?max_sessions,
+                            "Maximum number of sessions reached"
+                        );
+
+                        CommandStatus::EsmeRalybnd
+                    }
+                }
+                Err(status) => status,
+            };
 
-        // TODO: Do the verification of system_id and password here
-
-        let mc_system_id = COctetString::from_str("Rusmpps").expect("Must be valid system ID");
-        let sc_interface_version = Some(InterfaceVersion::Smpp5_0);
-
-        let pdu: Pdu = match bind_mode {
-            BindMode::Tx => BindTransmitterResp::builder()
-                .system_id(mc_system_id)
-                .sc_interface_version(sc_interface_version)
-                .build()
-                .into(),
-            BindMode::Rx => BindReceiverResp::builder()
-                .system_id(mc_system_id)
-                .sc_interface_version(sc_interface_version)
-                .build()
-                .into(),
-            BindMode::Trx => BindTransceiverResp::builder()
-                .system_id(mc_system_id)
-                .sc_interface_version(sc_interface_version)
-                .build()
-                .into(),
-        };
+            let command = bind_response(bind_mode, status, sequence_number);
 
-        let command = Command::builder()
-            .status(CommandStatus::EsmeRok)
-            .sequence_number(sequence_number)
-            .pdu(pdu);
+            tracing::debug!(session_id, id=?command.id(), ?status, "Sending response");
+            tracing::trace!(session_id, ?command, "Sending response");
 
-        if let Some(delay) = self.config.bind_delay {
-            tokio::time::sleep(delay).await;
-        }
+            if let Err(err) = writer.send(command).await {
+                tracing::error!(session_id, ?err, "Failed to send response");
 
-        tracing::debug!(session_id, id=?command.id(), "Sending response");
-        tracing::trace!(session_id, ?command, "Sending response");
+                if let Some(handler) = handler {
+                    self.config
+                        .connected_clients
+                        .remove_session(&system_id, session_id)
+                        .await;
 
-        if let Err(err) = writer.send(command).await {
-            tracing::error!(session_id, ?err, "Failed to send response");
+                    handler.closed().await;
+                }
 
-            return;
-        }
+                return;
+            }
 
-        let (tx, rx) = mpsc::channel(100);
+            if let Some(handler) = handler {
+                tracing::info!(session_id, system_id, ?bind_mode, "Bound");
 
-        let mut sequence_number = SequenceNumber::new();
-        let system_id = system_id.to_string();
-        let session = ClientSession::new(tx, bind_mode.into());
+                break (system_id, SessionState::from(bind_mode), Arc::new(handler));
+            }
 
-        self.config
-            .connected_clients
-            .insert_session(system_id.clone(), session_id, session)
-            .await;
+            tracing::warn!(session_id, system_id, ?bind_mode, ?status, "Rejected bind");
+        };
+
+        // The session holds the only sender, the actions end when the session is removed.
+        drop(tx);
+
+        let window_size = self.config.window_size;
 
         let mut actions = ReceiverStream::new(rx);
 
+        // Requests are handled in spawned tasks, so that slow handlers do not block reading.
+        let (responses_tx, mut responses_rx) =
+            mpsc::unbounded_channel::<(u32, CommandId, Response)>();
+
+        let mut in_flight = 0;
+
+        // Commands sent to the ESME awaiting a response.
+        let mut outstanding = DelayQueue::new();
+        let mut outstanding_keys = HashMap::new();
+
         let mut last_enquire_link_sequence_number = None;
 
         let enquire_link_resp_timer = Timer::new();
         tokio::pin!(enquire_link_resp_timer);
 
+        let mut unbind_sequence_number = None;
+
+        let unbind_resp_timer = Timer::new();
+        tokio::pin!(unbind_resp_timer);
+
         let enquire_link_timer = self
             .config
             .enquire_link_interval
@@ -200,10 +306,15 @@ impl Connection {
 
                     break
                 }
+                _ = &mut unbind_resp_timer => {
+                    tracing::warn!(session_id, "Unbind response timeout reached, closing connection");
+
+                    break
+                }
                 _ = &mut enquire_link_timer => {
                     tracing::debug!(session_id, "Sending EnquireLink command");
 
-                    let sequence_number = sequence_number.current_and_increment();
+                    let sequence_number = sequence_numbers.current_and_increment();
 
                     last_enquire_link_sequence_number = Some(sequence_number);
 
@@ -226,15 +337,28 @@ impl Connection {
 
                     tracing::debug!(session_id, sequence_number, "EnquireLink response timer activated");
                 }
-                action = actions.next() => {
+                Some((sequence_number, id, response)) = responses_rx.recv() => {
+                    in_flight -= 1;
+
+                    if send_response(&mut writer, session_id, id, sequence_number, response).await.is_break() {
+                        break
+                    }
+                }
+                Some(expired) = outstanding.next() => {
+                    let sequence_number = expired.into_inner();
+
+                    outstanding_keys.remove(&sequence_number);
+
+                    tracing::warn!(session_id, sequence_number, "Response timeout reached");
+                }
+                action = actions.next(), if outstanding_keys.len() < window_size => {
                     let action = match action {
                         None => break,
                         Some(action) => action,
                     };
 
                     match action {
-                        Action::Send(pdu) => {
-                            let sequence_number = sequence_number.current_and_increment();
+                        Action::Send { sequence_number, pdu } => {
                             let command = Command::builder()
                                 .status(CommandStatus::EsmeRok)
                                 .sequence_number(sequence_number)
@@ -247,10 +371,34 @@ impl Connection {
 
                                 break
                             }
+
+                            let key = outstanding.insert(sequence_number, self.config.response_timeout);
+
+                            outstanding_keys.insert(sequence_number, key);
+                        }
+                        Action::Unbind => {
+                            let sequence_number = sequence_numbers.current_and_increment();
+
+                            unbind_sequence_number = Some(sequence_number);
+
+                            let command = Command::builder()
+                                .status(CommandStatus::EsmeRok)
+                                .sequence_number(sequence_number)
+                                .pdu(Pdu::Unbind);
+
+                            tracing::info!(session_id, sequence_number, "Unbinding");
+
+                            if let Err(err) = writer.send(command).await {
+                                tracing::error!(session_id, ?err, "Failed to send Unbind command");
+
+                                break
+                            }
+
+                            unbind_resp_timer.as_mut().activate(self.config.enquire_link_response_timeout);
                         }
                     }
                 }
-                command = reader.next() => {
+                command = reader.next(), if in_flight < window_size => {
                     let command = match command {
                         None =>  break,
                         Some(Ok(command)) => command,
@@ -266,27 +414,35 @@ impl Connection {
                     tracing::debug!(session_id, sequence_number, id=?command.id(), "Received command");
                     tracing::trace!(session_id, sequence_number, ?command, "Received command");
 
-                    let (id, _, sequence_number, pdu) = command.into_parts().raw();
+                    let (id, status, sequence_number, pdu) = command.into_parts().raw();
 
-                    let pdu: Pdu = match pdu {
-                        Some(Pdu::Unbind) => {
-                            Pdu::UnbindResp
-                        },
-                        Some(Pdu::EnquireLink) => {
-                            Pdu::EnquireLinkResp
-                        },
-                        Some(Pdu::SubmitSm(_)) => {
-                            SubmitSmResp::builder()
-                                .build()
-                                .into()
-                        },
+                    let response = match pdu {
+                        Some(Pdu::BindTransmitter(_) | Pdu::BindReceiver(_) | Pdu::BindTransceiver(_)) => {
+                            tracing::warn!(session_id, sequence_number, id=?id, "Received bind while bound");
+
+                            let bind_mode = match id {
+                                CommandId::BindTransmitter => BindMode::Tx,
+                                CommandId::BindReceiver => BindMode::Rx,
+                                _ => BindMode::Trx,
+                            };
+
+                            let command = bind_response(bind_mode, CommandStatus::EsmeRalybnd, sequence_number);
+
+                            if let Err(err) = writer.send(command).await {
+                                tracing::error!(session_id, sequence_number, ?err, "Failed to send response");
+
+                                break;
+                            }
+
+                            continue
+                        }
                         Some(Pdu::EnquireLinkResp) => {
                             match last_enquire_link_sequence_number {
                                 Some(seq) => {
                                     if sequence_number != seq {
                                         tracing::warn!(session_id, id=?id, expected=seq, got=sequence_number, "Received EnquireLinkResp with unexpected sequence number");
 
-                                        return
+                                        break
                                     }
 
                                     tracing::trace!(session_id, sequence_number, id=?id, "Received EnquireLinkResp");
@@ -304,35 +460,94 @@ impl Connection {
 
                             continue
                         }
-                        _ => {
-                            tracing::warn!(session_id, sequence_number, id=?id, "Received unsupported command");
+                        Some(Pdu::UnbindResp) if unbind_sequence_number == Some(sequence_number) => {
+                            tracing::info!(session_id, sequence_number, "Unbound");
+
+                            break
+                        }
+                        _ if matches!(id, CommandId::Other(_)) => {
+                            tracing::warn!(session_id, sequence_number, id=?id, "Received unknown command");
+
+                            Response::Send {
+                                status: CommandStatus::EsmeRinvcmdid,
+                                pdu: Pdu::GenericNack,
+                            }
+                        }
+                        _ if !session_state.can_receive_as_mc(id) => {
+                            tracing::warn!(session_id, sequence_number, id=?id, ?session_state, "Received command not allowed in the current bind mode");
+
+                            Response::error(id, CommandStatus::EsmeRinvbndsts)
+                        }
+                        Some(pdu @ (Pdu::DeliverSmResp(_) | Pdu::DataSmResp(_) | Pdu::GenericNack | Pdu::UnbindResp)) => {
+                            tracing::debug!(session_id, sequence_number, id=?id, ?status, "Received response");
+
+                            if let Some(key) = outstanding_keys.remove(&sequence_number) {
+                                outstanding.remove(&key);
+                            }
+
+                            let handler = handler.clone();
+
+                            tokio::spawn(async move {
+                                handler.response(sequence_number, status, pdu).await;
+                            });
 
                             continue
                         }
-                    };
+                        Some(Pdu::Unbind) => {
+                            tracing::info!(session_id, sequence_number, "Unbound by the ESME");
 
-                    let delay = match pdu {
-                        Pdu::EnquireLinkResp => self.config.enquire_link_response_delay,
-                        _ => self.config.response_delay,
-                    };
+                            // `send` flushes, so the unbind_resp is written before the session closes.
+                            let _ = send_response(&mut writer, session_id, id, sequence_number, Response::ok(Pdu::UnbindResp)).await;
 
-                    let command = Command::builder()
-                        .status(CommandStatus::EsmeRok)
-                        .sequence_number(sequence_number)
-                        .pdu(pdu);
+                            break
+                        }
+                        Some(Pdu::EnquireLink) => match self.config.enquire_link_response_delay {
+                            None => Response::ok(Pdu::EnquireLinkResp),
+                            Some(delay) => {
+                                in_flight += 1;
 
-                    if let Some(delay) = delay {
-                        // XXX: This will block the reading process
-                        tokio::time::sleep(delay).await;
-                    }
+                                let responses_tx = responses_tx.clone();
 
-                    tracing::debug!(session_id, sequence_number, id=?command.id(), "Sending response");
-                    tracing::trace!(session_id, sequence_number, ?command, "Sending response");
+                                tokio::spawn(async move {
+                                    tokio::time::sleep(delay).await;
 
-                    if let Err(err) = writer.send(command).await {
-                        tracing::error!(session_id, sequence_number, ?err, "Failed to send response");
+                                    let _ = responses_tx.send((sequence_number, id, Response::ok(Pdu::EnquireLinkResp)));
+                                });
 
-                        break;
+                                continue
+                            }
+                        },
+                        Some(pdu) => {
+                            in_flight += 1;
+
+                            let handler = handler.clone();
+                            let responses_tx = responses_tx.clone();
+
+                            tokio::spawn(async move {
+                                // A panicking handler must still be answered, or it would hold a slot of the window forever.
+                                let response = match tokio::spawn(async move { handler.request(pdu).await }).await {
+                                    Ok(response) => response,
+                                    Err(err) => {
+                                        tracing::error!(session_id, sequence_number, id=?id, ?err, "Request handler failed");
+
+                                        Response::error(id, CommandStatus::EsmeRsyserr)
+                                    }
+                                };
+
+                                let _ = responses_tx.send((sequence_number, id, response));
+                            });
+
+                            continue
+                        }
+                        None => {
+                            tracing::warn!(session_id, sequence_number, id=?id, "Received command without PDU");
+
+                            Response::error(id, CommandStatus::EsmeRinvcmdlen)
+                        }
+                    };
+
+                    if send_response(&mut writer, session_id, id, sequence_number, response).await.is_break() {
+                        break
                     }
                 }
             }
@@ -342,5 +557,124 @@ impl Connection {
             .connected_clients
             .remove_session(&system_id, session_id)
             .await;
+
+        handler.closed().await;
+    }
+}
+
+/// Sends the response to the request `id` with the `sequence_number`.
+///
+/// Breaks if the connection must be closed.
+async fn send_response<W>(
+    writer: &mut FramedWrite<W, CommandCodec>,
+    session_id: u64,
+    id: CommandId,
+    sequence_number: u32,
+    response: Response,
+) -> ControlFlow<()>
+where
+    W: AsyncWrite + Unpin,
+{
+    match response {
+        Response::Send { status, pdu } => {
+            let command = Command::builder()
+                .status(status)
+                .sequence_number(sequence_number)
+                .pdu(pdu);
+
+            tracing::debug!(session_id, sequence_number, id=?command.id(), ?status, "Sending response");
+            tracing::trace!(session_id, sequence_number, ?command, "Sending response");
+
+            if let Err(err) = writer.send(command).await {
+                tracing::error!(session_id, sequence_number, ?err, "Failed to send response");
+
+                return ControlFlow::Break(());
+            }
+        }
+        Response::Ignore => {
+            tracing::debug!(session_id, sequence_number, id=?id, "Not responding");
+        }
+        Response::Disconnect => {
+            tracing::debug!(session_id, sequence_number, id=?id, "Closing connection without responding");
+
+            return ControlFlow::Break(());
+        }
+        Response::Malformed => {
+            if let Err(err) = send_malformed(writer, id.matching_response(), sequence_number).await
+            {
+                tracing::error!(session_id, sequence_number, ?err, "Failed to send response");
+
+                return ControlFlow::Break(());
+            }
+        }
     }
+
+    ControlFlow::Continue(())
+}
+
+/// Sends a response whose body cannot be decoded: a `0xFF` filled body without any NULL terminator.
+///
+/// The `command_length` is consistent, so the stream stays in sync.
+async fn send_malformed<W>(
+    writer: &mut FramedWrite<W, CommandCodec>,
+    id: CommandId,
+    sequence_number: u32,
+) -> std::io::Result<()>
+where
+    W: AsyncWrite + Unpin,
+{
+    const BODY_LENGTH: usize = 8;
+
+    let mut buf = Vec::with_capacity(16 + BODY_LENGTH);
+
+    buf.extend_from_slice(&(16 + BODY_LENGTH as u32).to_be_bytes());
+    buf.extend_from_slice(&u32::from(id).to_be_bytes());
+    buf.extend_from_slice(&u32::from(CommandStatus::EsmeRok).to_be_bytes());
+    buf.extend_from_slice(&sequence_number.to_be_bytes());
+    buf.extend_from_slice(&[0xFF; BODY_LENGTH]);
+
+    SinkExt::<Command>::flush(writer)
+        .await
+        .map_err(std::io::Error::other)?;
+
+    let writer = writer.get_mut();
+
+    writer.write_all(&buf).await?;
+
+    writer.flush().await
+}
+
+const fn is_bind(id: CommandId) -> bool {
+    matches!(
+        id,
+        CommandId::BindTransmitter | CommandId::BindReceiver | CommandId::BindTransceiver
+    )
+}
+
+fn bind_response(bind_mode: BindMode, status: CommandStatus, sequence_number: u32) -> Command {
+    let mc_system_id = COctetString::from_str("Rusmpps").expect("Must be valid system ID");
+    let sc_interface_version = Some(InterfaceVersion::Smpp5_0);
+
+    let pdu: Pdu = match bind_mode {
+        BindMode::Tx => BindTransmitterResp::builder()
+            .system_id(mc_system_id)
+            .sc_interface_version(sc_interface_version)
+            .build()
+            .into(),
+        BindMode::Rx => BindReceiverResp::builder()
+            .system_id(mc_system_id)
+            .sc_interface_version(sc_interface_version)
+            .build()
+            .into(),
+        BindMode::Trx => BindTransceiverResp::builder()
+            .system_id(mc_system_id)
+            .sc_interface_version(sc_interface_version)
+            .build()
+            .into(),
+    };
+
+    Command::builder()
+        .status(status)
+        .sequence_number(sequence_number)
+        .pdu(pdu)
 }
@@ -0,0 +1,318 @@
+use std::net::{Ipv4Addr, SocketAddrV4};
+
+use rusmpp::pdus::{BindTransceiver, DeliverSm, QuerySm, SubmitSm};
+use tokio::{
+    io::DuplexStream,
+    sync::mpsc::{UnboundedReceiver, UnboundedSender},
+};
+use tokio_util::codec::Framed;
+
+use super::*;
+use crate::service::Bound;
+
+const WINDOW_SIZE: usize = 10;
+
+struct AcceptAll {
+    responses: UnboundedSender<(u32, CommandStatus)>,
+    max_sessions: Option<usize>,
+}
+
+impl Service for AcceptAll {
+    type Handler = Submissions;
+
+    async fn bind(&self, _bind: &Bind) -> Result<Bound<Self::Handler>, CommandStatus> {
+        Ok(Bound::new(Submissions {
+            responses: self.responses.clone(),
+        })
+        .max_sessions(self.max_sessions))
+    }
+}
+
+/// Accepts `submit_sm`, panics on `query_sm` and records the responses of the `ESME`.
+struct Submissions {
+    responses: UnboundedSender<(u32, CommandStatus)>,
+}
+
+impl SessionHandler for Submissions {
+    async fn request(&self, pdu: Pdu) -> Response {
+        match pdu {
+            Pdu::SubmitSm(_) => Response::ok(rusmpp::pdus::SubmitSmResp::default()),
+            Pdu::QuerySm(_) => panic!("Failed to handle query_sm"),
+            _ => Response::Ignore,
+        }
+    }
+
+    async fn response(&self, sequence_number: u32, status: CommandStatus, _pdu: Pdu) {
+        let _ = self.responses.send((sequence_number, status));
+    }
+}
+
+struct Esme {
+    framed: Framed<DuplexStream, CommandCodec>,
+    connected_clients: ConnectedClients,
+    /// The responses passed to [`SessionHandler::response`].
+    responses: UnboundedReceiver<(u32, CommandStatus)>,
+}
+
+fn connect() -> Esme {
+    connect_to(config(), 1, None)
+}
+
+fn config() -> Arc<ConnectionConfig> {
+    Arc::new(ConnectionConfig {
+        connected_clients: ConnectedClients::new(),
+        enquire_link_interval: None,
+        enquire_link_response_timeout: Duration::from_secs(5),
+        session_timeout: Duration::from_secs(5),
+        enquire_link_response_delay: None,
+        window_size: WINDOW_SIZE,
+        response_timeout: Duration::from_secs(5),
+    })
+}
+
+/// Opens the session `session_id` on a server sharing the `config` with other sessions.
+fn connect_to(config: Arc<ConnectionConfig>, session_id: u64, max_sessions: Option<usize>) -> Esme {
+    let (server, client) = tokio::io::duplex(4096);
+    let (responses_tx, responses) = mpsc::unbounded_channel();
+
+    let addr = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 2775));
+
+    let connected_clients = config.connected_clients.clone();
+
+    let service = Arc::new(AcceptAll {
+        responses: responses_tx,
+        max_sessions,
+    });
+
+    tokio::spawn(Connection::new(session_id, addr, config, service).run(server));
+
+    Esme {
+        framed: Framed::new(client, CommandCodec::new()),
+        connected_clients,
+        responses,
+    }
+}
+
+fn command(sequence_number: u32, pdu: impl Into<Pdu>) -> Command {
+    Command::builder()
+        .status(CommandStatus::EsmeRok)
+        .sequence_number(sequence_number)
+        .pdu(pdu)
+}
+
+async fn recv(framed: &mut Framed<DuplexStream, CommandCodec>) -> Command {
+    tokio::time::timeout(Duration::from_secs(1), framed.next())
+        .await
+        .expect("Timed out waiting for a command")
+        .expect("Connection closed")
+        .expect("Failed to decode command")
+}
+
+#[tokio::test]
+async fn commands_before_bind_should_be_answered() {
+    let Esme { mut framed, .. } = connect();
+
+    framed.send(command(1, Pdu::EnquireLink)).await.unwrap();
+
+    let response = recv(&mut framed).await;
+
+    assert_eq!(response.id(), CommandId::EnquireLinkResp);
+    assert_eq!(response.status(), CommandStatus::EsmeRok);
+    assert_eq!(response.sequence_number(), 1);
+
+    // Responses are not answered.
+    framed.send(command(2, Pdu::GenericNack)).await.unwrap();
+    framed.send(command(3, SubmitSm::default())).await.unwrap();
+
+    let response = recv(&mut framed).await;
+
+    assert_eq!(response.id(), CommandId::SubmitSmResp);
+    assert_eq!(response.status(), CommandStatus::EsmeRinvbndsts);
+    assert_eq!(response.sequence_number(), 3);
+
+    framed
+        .send(command(4, BindTransceiver::default()))
+        .await
+        .unwrap();
+
+    let response = recv(&mut framed).await;
+
+    assert_eq!(response.id(), CommandId::BindTransceiverResp);
+    assert_eq!(response.status(), CommandStatus::EsmeRok);
+
+    framed.send(command(5, SubmitSm::default())).await.unwrap();
+
+    let response = recv(&mut framed).await;
+
+    assert_eq!(response.id(), CommandId::SubmitSmResp);
+    assert_eq!(response.status(), CommandStatus::EsmeRok);
+}
+
+async fn bind(framed: &mut Framed<DuplexStream, CommandCodec>) {
+    framed
+        .send(command(1, BindTransceiver::default()))
+        .await
+        .unwrap();
+
+    let response = recv(framed).await;
+
+    assert_eq!(response.id(), CommandId::BindTransceiverResp);
+    assert_eq!(response.status(), CommandStatus::EsmeRok);
+}
+
+#[tokio::test]
+async fn binds_exceeding_max_sessions_should_be_rejected() {
+    let config = config();
+
+    let Esme { mut framed, .. } = connect_to(config.clone(), 1, Some(1));
+
+    bind(&mut framed).await;
+
+    let Esme {
+        framed: mut second, ..
+    } = connect_to(config, 2, Some(1));
+
+    second
+        .send(command(1, BindTransceiver::default()))
+        .await
+        .unwrap();
+
+    let response = recv(&mut second).await;
+
+    assert_eq!(response.id(), CommandId::BindTransceiverResp);
+    assert_eq!(response.status(), CommandStatus::EsmeRalybnd);
+}
+
+#[tokio::test]
+async fn panicking_requests_should_be_answered_and_release_the_window() {
+    let Esme { mut framed, .. } = connect();
+
+    bind(&mut framed).await;
+
+    // More than the window, a request holding its slot would stop the reading.
+    for sequence_number in 2..WINDOW_SIZE as u32 + 4 {
+        framed
+            .send(command(sequence_number, QuerySm::default()))
+            .await
+            .unwrap();
+    }
+
+    for _ in 2..WINDOW_SIZE as u32 + 4 {
+        let response = recv(&mut framed).await;
+
+        assert_eq!(response.id(), CommandId::QuerySmResp);
+        assert_eq!(response.status(), CommandStatus::EsmeRsyserr);
+    }
+
+    framed
+        .send(command(100, SubmitSm::default()))
+        .await
+        .unwrap();
+
+    let response = recv(&mut framed).await;
+
+    assert_eq!(response.id(), CommandId::SubmitSmResp);
+    assert_eq!(response.status(), CommandStatus::EsmeRok);
+    assert_eq!(response.sequence_number(), 100);
+}
+
+#[tokio::test]
+async fn responses_should_be_passed_with_the_sequence_number_of_the_sent_command() {
+    let Esme {
+        mut framed,
+        connected_clients,
+        mut responses,
+    } = connect();
+
+    bind(&mut framed).await;
+
+    let first = connected_clients
+        .send_to_receiver("", DeliverSm::default().into())
+        .await
+        .unwrap();
+
+    let second = connected_clients
+        .send_to_receiver("", DeliverSm::default().into())
+        .await
+        .unwrap();
+
+    assert_ne!(first, second);
+
+    assert_eq!(recv(&mut framed).await.sequence_number(), first);
+    assert_eq!(recv(&mut framed).await.sequence_number(), second);
+
+    // Answered out of order.
+    for (sequence_number, status) in [
+        (second, CommandStatus::EsmeRok),
+        (first, CommandStatus::EsmeRxTAppn),
+    ] {
+        let response = Command::builder()
+            .status(status)
+            .sequence_number(sequence_number)
+            .pdu(rusmpp::pdus::DeliverSmResp::default());
+
+        framed.send(response).await.unwrap();
+    }
+
+    let mut received = Vec::new();
+
+    for _ in 0..2 {
+        let response = tokio::time::timeout(Duration::from_secs(1), responses.recv())
+            .await
+            .expect("Timed out waiting for a response")
+            .expect("Handler dropped");
+
+        received.push(response);
+    }
+
+    received.sort();
+
+    let mut expected = vec![
+        (first, CommandStatus::EsmeRxTAppn),
+        (second, CommandStatus::EsmeRok),
+    ];
+
+    expected.sort();
+
+    assert_eq!(received, expected);
+}
+
+#[tokio::test]
+async fn unbind_should_close_the_session() {
+    let Esme {
+        mut framed,
+        connected_clients,
+        ..
+    } = connect();
+
+    bind(&mut framed).await;
+
+    assert!(connected_clients.session(1).await.is_some());
+
+    framed.send(command(2, Pdu::Unbind)).await.unwrap();
+
+    let response = recv(&mut framed).await;
+
+    assert_eq!(response.id(), CommandId::UnbindResp);
+    assert_eq!(response.status(), CommandStatus::EsmeRok);
+    assert_eq!(response.sequence_number(), 2);
+
+    // The session is removed before the connection is closed.
+    let _ = framed.send(command(3, SubmitSm::default())).await;
+
+    let next = tokio::time::timeout(Duration::from_secs(1), framed.next())
+        .await
+        .expect("Timed out waiting for the connection to close");
+
+    assert!(
+        next.is_none(),
+        "Expected the connection to close, got {next:?}"
+    );
+    assert!(connected_clients.session(1).await.is_none());
+    assert!(
+        connected_clients
+            .send_to_receiver("", DeliverSm::default().into())
+            .await
+            .is_err()
+    );
+}
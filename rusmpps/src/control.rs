@@ -0,0 +1,329 @@
+use std::{net::SocketAddr, str::FromStr, sync::Arc};
+
+use crate::{client::SessionSender, connection::ConnectionConfig};
+use rusmpp::{
+    CommandId, Pdu,
+    extra::{
+        concatenation::{Concatenation, Concatenator, MAX_PARTS},
+        encoding::{Encoder, gsm7bit::Gsm7BitUnpacked, ucs2::Ucs2},
+        fallback::Fallback,
+    },
+    pdus::{AlertNotification, DataSm, DeliverSm},
+    session::SessionState,
+    tlvs::MessageSubmissionRequestTlvValue,
+    types::{AnyOctetString, COctetString, OctetString},
+    udhs::concatenation::ConcatenatedShortMessageType,
+    values::{EsmClass, MessagePayload, Npi, ServiceType, Ton},
+};
+use serde::Deserialize;
+
+#[cfg(test)]
+mod tests;
+
+/// Maximum size of a `deliver_sm` short message, UDH included.
+const MAX_SHORT_MESSAGE_SIZE: usize = 140;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ControlError {
+    #[error("No session of `{system_id}` can receive {id:?}")]
+    NoReceiver { system_id: String, id: CommandId },
+    #[error("Unknown session: {0}")]
+    UnknownSession(u64),
+    #[error("Session closed")]
+    SessionClosed,
+    #[error("Invalid address: {0}")]
+    InvalidAddress(String),
+    #[error("Invalid service type: {0}")]
+    InvalidServiceType(String),
+    #[error("Failed to encode message: {0}")]
+    Encoding(String),
+}
+
+/// A bound session.
+#[derive(Debug, Clone)]
+pub struct SessionInfo {
+    pub system_id: String,
+    pub session_id: u64,
+    pub session_state: SessionState,
+    pub addr: SocketAddr,
+}
+
+/// The address of a mobile originated message.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct MoAddress {
+    pub ton: Ton,
+    pub npi: Npi,
+    pub addr: String,
+}
+
+impl MoAddress {
+    pub fn new(ton: Ton, npi: Npi, addr: impl Into<String>) -> Self {
+        Self {
+            ton,
+            npi,
+            addr: addr.into(),
+        }
+    }
+}
+
+/// A mobile originated message.
+///
+/// The text is encoded as `GSM 7-bit` if possible, `UCS2` otherwise.
+#[derive(Debug, Clone, Deserialize)]
+pub struct MoMessage {
+    #[serde(default)]
+    pub service_type: String,
+    /// The mobile station sending the message.
+    pub source: MoAddress,
+    /// The address of the `ESME`, e.g. a short code.
+    pub destination: MoAddress,
+    pub text: String,
+}
+
+impl MoMessage {
+    pub fn new(source: MoAddress, destination: MoAddress, text: impl Into<String>) -> Self {
+        Self {
+            service_type: String::new(),
+            source,
+            destination,
+            text: text.into(),
+        }
+    }
+
+    pub fn service_type(mut self, service_type: impl Into<String>) -> Self {
+        self.service_type = service_type.into();
+        self
+    }
+}
+
+/// Controls a running [`Server`](crate::server::Server).
+///
+/// Created using [`Server::control`](crate::server::Server::control).
+#[derive(Debug, Clone)]
+pub struct Control {
+    config: Arc<ConnectionConfig>,
+}
+
+impl Control {
+    pub(crate) fn new(config: Arc<ConnectionConfig>) -> Self {
+        Self { config }
+    }
+
+    /// Returns the bound sessions ordered by session id.
+    pub async fn sessions(&self) -> Vec<SessionInfo> {
+        let clients = self.config.connected_clients.clients().await;
+
+        let mut sessions = clients
+            .iter()
+            .flat_map(|(system_id, client)| {
+                client.sessions().map(|(session_id, session)| SessionInfo {
+                    system_id: system_id.clone(),
+                    session_id,
+                    session_state: session.session_state,
+                    addr: session.addr,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        sessions.sort_by_key(|session| session.session_id);
+
+        sessions
+    }
+
+    /// Delivers the message to a receiver or transceiver session of `system_id`.
+    ///
+    /// Messages not fitting in a single `deliver_sm` are concatenated using UDHs,
+    /// all parts are sent over the same session.
+    ///
+    /// Returns the sequence numbers of the `deliver_sm` sent, in part order.
+    pub async fn deliver_sm(
+        &self,
+        system_id: &str,
+        message: MoMessage,
+    ) -> Result<Vec<u32>, ControlError> {
+        let reference = ConcatenatedShortMessageType::u8(rand::random());
+
+        let (concatenation, data_coding) = Fallback::new(Gsm7BitUnpacked::new(), Ucs2::new())
+            .concatenate(
+                &message.text,
+                MAX_SHORT_MESSAGE_SIZE,
+                reference.udh_length(),
+            )
+            .map_err(|err| ControlError::Encoding(err.to_string()))?;
+
+        let deliver_sm = |esm_class: EsmClass,
+                          short_message: OctetString<0, 255>|
+         -> Result<DeliverSm, ControlError> {
+            Ok(DeliverSm::builder()
+                .service_type(service_type(&message.service_type)?)
+                .source_addr_ton(message.source.ton)
+                .source_addr_npi(message.source.npi)
+                .source_addr(address(&message.source.addr)?)
+                .dest_addr_ton(message.destination.ton)
+                .dest_addr_npi(message.destination.npi)
+                .destination_addr(address(&message.destination.addr)?)
+                .esm_class(esm_class)
+                .data_coding(data_coding)
+                .short_message(short_message)
+                .build())
+        };
+
+        let parts = match concatenation {
+            Concatenation::Single(bytes) => {
+                vec![deliver_sm(EsmClass::default(), short_message(bytes)?)?]
+            }
+            Concatenation::Concatenated(parts) => {
+                if parts.len() > MAX_PARTS {
+                    return Err(ControlError::Encoding(format!(
+                        "{} parts exceed the maximum of {MAX_PARTS}",
+                        parts.len()
+                    )));
+                }
+
+                let total = parts.len() as u8;
+                let esm_class = EsmClass::default().with_udh_indicator();
+
+                parts
+                    .into_iter()
+                    .enumerate()
+                    .map(|(index, part)| {
+                        let udh =
+                            reference.concatenated_short_message_unchecked(total, index as u8 + 1);
+
+                        let mut payload = Vec::with_capacity(udh.udh_length() + part.len());
+
+                        payload.extend_from_slice(udh.udh_bytes().as_bytes());
+                        payload.extend_from_slice(&part);
+
+                        deliver_sm(esm_class, short_message(payload)?)
+                    })
+                    .collect::<Result<Vec<_>, ControlError>>()?
+            }
+        };
+
+        let sender = self.receiver(system_id, CommandId::DeliverSm).await?;
+
+        let mut sequence_numbers = Vec::with_capacity(parts.len());
+
+        for deliver_sm in parts {
+            sequence_numbers.push(send(&sender, Pdu::from(deliver_sm)).await?);
+        }
+
+        Ok(sequence_numbers)
+    }
+
+    /// Delivers the message as a single `data_sm` carrying a `message_payload` to a receiver or transceiver session of `system_id`.
+    ///
+    /// Returns the sequence number of the `data_sm`.
+    pub async fn data_sm(&self, system_id: &str, message: MoMessage) -> Result<u32, ControlError> {
+        let (bytes, data_coding) = Fallback::new(Gsm7BitUnpacked::new(), Ucs2::new())
+            .encode(&message.text)
+            .map_err(|err| ControlError::Encoding(err.to_string()))?;
+
+        let data_sm = DataSm::builder()
+            .service_type(service_type(&message.service_type)?)
+            .source_addr_ton(message.source.ton)
+            .source_addr_npi(message.source.npi)
+            .source_addr(address(&message.source.addr)?)
+            .dest_addr_ton(message.destination.ton)
+            .dest_addr_npi(message.destination.npi)
+            .destination_addr(address(&message.destination.addr)?)
+            .data_coding(data_coding)
+            .push_tlv(MessageSubmissionRequestTlvValue::MessagePayload(
+                MessagePayload::new(AnyOctetString::from_vec(bytes)),
+            ))
+            .build();
+
+        let sender = self.receiver(system_id, CommandId::DataSm).await?;
+
+        send(&sender, Pdu::from(data_sm)).await
+    }
+
+    /// Sends the `alert_notification` to a receiver or transceiver session of `system_id`.
+    ///
+    /// Returns the sequence number of the `alert_notification`.
+    pub async fn alert_notification(
+        &self,
+        system_id: &str,
+        alert_notification: AlertNotification,
+    ) -> Result<u32, ControlError> {
+        let sender = self
+            .receiver(system_id, CommandId::AlertNotification)
+            .await?;
+
+        send(&sender, Pdu::from(alert_notification)).await
+    }
+
+    /// Sends the `pdu` to a session of `system_id` allowed to receive it, without waiting for a full session.
+    ///
+    /// Returns the sequence number of the command, or the `pdu` back if no session can take it.
+    pub async fn try_send(&self, system_id: &str, pdu: Pdu) -> Result<u32, Pdu> {
+        self.config
+            .connected_clients
+            .send_to_receiver(system_id, pdu)
+            .await
+    }
+
+    /// Sends the `pdu` to the session with the given `session_id` if it is allowed to receive it, without waiting for a full session.
+    ///
+    /// Returns the sequence number of the command, or the `pdu` back if the session cannot take it.
+    pub async fn try_send_to_session(&self, session_id: u64, pdu: Pdu) -> Result<u32, Pdu> {
+        self.config
+            .connected_clients
+            .send_to_session(session_id, pdu)
+            .await
+    }
+
+    /// Unbinds the session.
+    ///
+    /// The session is closed once the `unbind_resp` is received or the `enquire_link_response_timeout` is reached.
+    pub async fn unbind(&self, session_id: u64) -> Result<(), ControlError> {
+        let sender = self
+            .config
+            .connected_clients
+            .session(session_id)
+            .await
+            .ok_or(ControlError::UnknownSession(session_id))?;
+
+        sender
+            .unbind()
+            .await
+            .map_err(|_| ControlError::SessionClosed)
+    }
+
+    async fn receiver(
+        &self,
+        system_id: &str,
+        id: CommandId,
+    ) -> Result<SessionSender, ControlError> {
+        self.config
+            .connected_clients
+            .receiver(system_id, id)
+            .await
+            .ok_or_else(|| ControlError::NoReceiver {
+                system_id: system_id.to_string(),
+                id,
+            })
+    }
+}
+
+async fn send(sender: &SessionSender, pdu: Pdu) -> Result<u32, ControlError> {
+    sender
+        .send(pdu)
+        .await
+        .map_err(|_| ControlError::SessionClosed)
+}
+
+fn address(addr: &str) -> Result<COctetString<1, 21>, ControlError> {
+    COctetString::from_str(addr).map_err(|_| ControlError::InvalidAddress(addr.to_string()))
+}
+
+fn service_type(service_type: &str) -> Result<ServiceType, ControlError> {
+    COctetString::from_str(service_type)
+        .map(ServiceType::new)
+        .map_err(|_| ControlError::InvalidServiceType(service_type.to_string()))
+}
+
+fn short_message(bytes: Vec<u8>) -> Result<OctetString<0, 255>, ControlError> {
+    OctetString::from_vec(bytes).map_err(|err| ControlError::Encoding(err.to_string()))
+}
@@ -0,0 +1,215 @@
+use std::{
+    net::{Ipv4Addr, SocketAddrV4},
+    time::Duration,
+};
+
+use rusmpp::{tlvs::TlvValue, values::DataCoding};
+use tokio::sync::mpsc::{self, Receiver};
+
+use super::*;
+use crate::client::{Action, ClientSession, ConnectedClients, SequenceNumber};
+
+const SYSTEM_ID: &str = "esme";
+
+async fn control(session_state: SessionState) -> (Control, Receiver<Action>) {
+    let config = Arc::new(ConnectionConfig {
+        connected_clients: ConnectedClients::new(),
+        enquire_link_interval: None,
+        enquire_link_response_timeout: Duration::from_secs(5),
+        session_timeout: Duration::from_secs(5),
+        enquire_link_response_delay: None,
+        window_size: 10,
+        response_timeout: Duration::from_secs(5),
+    });
+
+    let (tx, rx) = mpsc::channel(MAX_PARTS);
+    let addr = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 2775));
+
+    config
+        .connected_clients
+        .insert_session(
+            SYSTEM_ID.to_string(),
+            1,
+            ClientSession::new(
+                SessionSender::new(tx, SequenceNumber::new()),
+                session_state,
+                addr,
+            ),
+        )
+        .await;
+
+    (Control::new(config), rx)
+}
+
+fn message(text: &str) -> MoMessage {
+    MoMessage::new(
+        MoAddress::new(Ton::International, Npi::Isdn, "491701234567"),
+        MoAddress::new(Ton::Unknown, Npi::Unknown, "1234"),
+        text,
+    )
+}
+
+fn sent(rx: &mut Receiver<Action>) -> Vec<Pdu> {
+    std::iter::from_fn(|| rx.try_recv().ok())
+        .map(|action| match action {
+            Action::Send { pdu, .. } => pdu,
+            Action::Unbind => panic!("Unexpected unbind"),
+        })
+        .collect()
+}
+
+fn deliver_sms(rx: &mut Receiver<Action>) -> Vec<DeliverSm> {
+    sent(rx)
+        .into_iter()
+        .map(|pdu| match pdu {
+            Pdu::DeliverSm(deliver_sm) => deliver_sm,
+            pdu => panic!("Expected deliver_sm, got {pdu:?}"),
+        })
+        .collect()
+}
+
+/// Splits a concatenated part into its `reference`, `total`, `index` and text.
+fn part(deliver_sm: &DeliverSm) -> (u8, u8, u8, &[u8]) {
+    let bytes = deliver_sm.short_message().as_ref();
+
+    // IEI 0x00, concatenated short messages with an 8-bit reference.
+    assert_eq!(&bytes[..3], &[0x05, 0x00, 0x03]);
+
+    (bytes[3], bytes[4], bytes[5], &bytes[6..])
+}
+
+#[tokio::test]
+async fn short_gsm7_messages_should_be_sent_in_a_single_deliver_sm() {
+    let (control, mut rx) = control(SessionState::BoundTrx).await;
+
+    let text = "a".repeat(MAX_SHORT_MESSAGE_SIZE);
+
+    let sequence_numbers = control.deliver_sm(SYSTEM_ID, message(&text)).await.unwrap();
+
+    let deliver_sms = deliver_sms(&mut rx);
+
+    assert_eq!(sequence_numbers, [1]);
+    assert_eq!(deliver_sms.len(), 1);
+    assert_eq!(deliver_sms[0].esm_class, EsmClass::default());
+    assert_eq!(deliver_sms[0].data_coding, DataCoding::McSpecific);
+    assert_eq!(deliver_sms[0].short_message().as_ref(), text.as_bytes());
+    assert_eq!(deliver_sms[0].source_addr.to_string(), "491701234567");
+    assert_eq!(deliver_sms[0].destination_addr.to_string(), "1234");
+}
+
+#[tokio::test]
+async fn long_gsm7_messages_should_be_concatenated() {
+    let (control, mut rx) = control(SessionState::BoundRx).await;
+
+    let text = "a".repeat(MAX_SHORT_MESSAGE_SIZE + 1);
+
+    let sequence_numbers = control.deliver_sm(SYSTEM_ID, message(&text)).await.unwrap();
+
+    let deliver_sms = deliver_sms(&mut rx);
+
+    assert_eq!(sequence_numbers, [1, 2]);
+    assert_eq!(deliver_sms.len(), 2);
+
+    let (first_reference, first_total, first_index, first_text) = part(&deliver_sms[0]);
+    let (second_reference, second_total, second_index, second_text) = part(&deliver_sms[1]);
+
+    assert_eq!(first_reference, second_reference);
+    assert_eq!((first_total, first_index), (2, 1));
+    assert_eq!((second_total, second_index), (2, 2));
+    assert_eq!([first_text, second_text].concat(), text.as_bytes());
+
+    for deliver_sm in &deliver_sms {
+        assert_eq!(
+            deliver_sm.esm_class,
+            EsmClass::default().with_udh_indicator()
+        );
+        assert_eq!(deliver_sm.data_coding, DataCoding::McSpecific);
+        assert!(deliver_sm.short_message().len() <= MAX_SHORT_MESSAGE_SIZE);
+    }
+}
+
+#[tokio::test]
+async fn non_gsm7_messages_should_be_concatenated_as_ucs2() {
+    let (control, mut rx) = control(SessionState::BoundTrx).await;
+
+    // 71 characters do not fit in the 140 bytes of a single UCS2 message.
+    let text = "ж".repeat(71);
+
+    let sequence_numbers = control.deliver_sm(SYSTEM_ID, message(&text)).await.unwrap();
+
+    let deliver_sms = deliver_sms(&mut rx);
+
+    assert_eq!(sequence_numbers.len(), 2);
+    assert_eq!(deliver_sms.len(), 2);
+
+    let ucs2 = text
+        .encode_utf16()
+        .flat_map(u16::to_be_bytes)
+        .collect::<Vec<_>>();
+
+    let texts = deliver_sms
+        .iter()
+        .map(|deliver_sm| part(deliver_sm).3)
+        .collect::<Vec<_>>();
+
+    assert_eq!(texts.concat(), ucs2);
+
+    for deliver_sm in &deliver_sms {
+        assert_eq!(deliver_sm.data_coding, DataCoding::Ucs2);
+        // Parts do not split a character.
+        assert_eq!(part(deliver_sm).3.len() % 2, 0);
+    }
+}
+
+#[tokio::test]
+async fn messages_exceeding_the_maximum_number_of_parts_should_be_rejected() {
+    let (control, mut rx) = control(SessionState::BoundTrx).await;
+
+    let text = "a".repeat(MAX_SHORT_MESSAGE_SIZE * (MAX_PARTS + 1));
+
+    let result = control.deliver_sm(SYSTEM_ID, message(&text)).await;
+
+    assert!(matches!(result, Err(ControlError::Encoding(_))));
+    assert!(sent(&mut rx).is_empty());
+}
+
+#[tokio::test]
+async fn messages_should_not_be_delivered_to_transmitter_sessions() {
+    let (control, mut rx) = control(SessionState::BoundTx).await;
+
+    let result = control.deliver_sm(SYSTEM_ID, message("Hello")).await;
+
+    assert!(matches!(
+        result,
+        Err(ControlError::NoReceiver {
+            id: CommandId::DeliverSm,
+            ..
+        })
+    ));
+    assert!(sent(&mut rx).is_empty());
+}
+
+#[tokio::test]
+async fn data_sm_should_carry_the_whole_message_in_a_message_payload() {
+    let (control, mut rx) = control(SessionState::BoundTrx).await;
+
+    let text = "a".repeat(MAX_SHORT_MESSAGE_SIZE * 2);
+
+    control.data_sm(SYSTEM_ID, message(&text)).await.unwrap();
+
+    let pdus = sent(&mut rx);
+
+    assert_eq!(pdus.len(), 1);
+
+    let Pdu::DataSm(data_sm) = &pdus[0] else {
+        panic!("Expected data_sm, got {:?}", pdus[0]);
+    };
+
+    let payload = data_sm.tlvs().iter().find_map(|tlv| match tlv.value() {
+        Some(TlvValue::MessagePayload(payload)) => Some(payload.value.as_ref()),
+        _ => None,
+    });
+
+    assert_eq!(payload, Some(text.as_bytes()));
+    assert_eq!(data_sm.data_coding, DataCoding::McSpecific);
+}
@@ -0,0 +1,196 @@
+use std::time::{Duration, Instant};
+
+use rusmpp::{CommandId, CommandStatus, Pdu};
+use serde::Deserialize;
+
+use crate::service::Response;
+
+#[cfg(test)]
+mod tests;
+
+/// A fault injection rule.
+///
+/// Rates are probabilities between `0.0` and `1.0` evaluated for every matching command.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct FaultRule {
+    /// Commands the rule applies to. All commands if empty.
+    pub commands: Vec<CommandId>,
+    /// Accounts the rule applies to. All accounts if empty.
+    pub system_ids: Vec<String>,
+    /// Responds with the given statuses at the given rates.
+    pub statuses: Vec<StatusRate>,
+    /// Responds with `ESME_RTHROTTLED` above this number of commands per second and session.
+    pub max_tps: Option<u32>,
+    /// Does not respond.
+    pub drop_rate: f64,
+    /// Closes the connection without responding.
+    pub disconnect_rate: f64,
+    /// Responds with an undecodable PDU.
+    pub malformed_rate: f64,
+    /// Responds with a `generic_nack`.
+    pub generic_nack_rate: f64,
+    /// Delays the responses without blocking the session. Overrides the global `response_delay`.
+    #[serde(with = "humantime_serde")]
+    pub delay: Option<Duration>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct StatusRate {
+    pub status: CommandStatus,
+    pub rate: f64,
+}
+
+impl FaultRule {
+    fn matches(&self, system_id: &str, id: CommandId) -> bool {
+        (self.commands.is_empty() || self.commands.contains(&id))
+            && (self.system_ids.is_empty() || self.system_ids.iter().any(|s| s == system_id))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    Drop,
+    Disconnect,
+    Malformed,
+    GenericNack,
+    Status(CommandStatus),
+}
+
+impl Fault {
+    /// Returns the response replacing the one of the session to the request `id`.
+    pub fn response(self, session_id: u64, id: CommandId) -> Response {
+        match self {
+            Fault::Disconnect => {
+                tracing::warn!(session_id, id=?id, "Injecting disconnect");
+
+                Response::Disconnect
+            }
+            Fault::Drop => {
+                tracing::warn!(session_id, id=?id, "Injecting dropped response");
+
+                Response::Ignore
+            }
+            Fault::Malformed => {
+                tracing::warn!(session_id, id=?id, "Injecting malformed response");
+
+                Response::Malformed
+            }
+            Fault::GenericNack => {
+                tracing::warn!(session_id, id=?id, "Injecting generic_nack");
+
+                Response::Send {
+                    status: CommandStatus::EsmeRsyserr,
+                    pdu: Pdu::GenericNack,
+                }
+            }
+            Fault::Status(status) => {
+                tracing::warn!(session_id, id=?id, ?status, "Injecting status");
+
+                Response::error(id, status)
+            }
+        }
+    }
+}
+
+/// The outcome of the fault rules for a command.
+#[derive(Debug, Default)]
+pub struct Injection {
+    pub fault: Option<Fault>,
+    pub delay: Option<Duration>,
+}
+
+/// Per session state of the fault rules.
+#[derive(Debug)]
+pub struct Faults {
+    rules: Vec<FaultRule>,
+    windows: Vec<ThrottleWindow>,
+}
+
+/// Counts the commands of the current second.
+#[derive(Debug, Clone, Copy)]
+pub struct ThrottleWindow {
+    start: Instant,
+    count: u32,
+}
+
+impl Default for ThrottleWindow {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ThrottleWindow {
+    pub fn new() -> Self {
+        Self {
+            start: Instant::now(),
+            count: 0,
+        }
+    }
+
+    /// Counts a command, returns `false` if the window is already full.
+    pub fn admit(&mut self, max_tps: u32, now: Instant) -> bool {
+        if now.duration_since(self.start) >= Duration::from_secs(1) {
+            self.start = now;
+            self.count = 0;
+        }
+
+        if self.count >= max_tps {
+            return false;
+        }
+
+        self.count += 1;
+
+        true
+    }
+}
+
+impl Faults {
+    pub fn new(rules: Vec<FaultRule>) -> Self {
+        let windows = vec![ThrottleWindow::new(); rules.len()];
+
+        Self { rules, windows }
+    }
+
+    /// Evaluates the first rule matching the command.
+    pub fn inject(&mut self, system_id: &str, id: CommandId) -> Injection {
+        let Some(index) = self
+            .rules
+            .iter()
+            .position(|rule| rule.matches(system_id, id))
+        else {
+            return Injection::default();
+        };
+
+        let rule = &self.rules[index];
+
+        let fault = if chance(rule.disconnect_rate) {
+            Some(Fault::Disconnect)
+        } else if chance(rule.drop_rate) {
+            Some(Fault::Drop)
+        } else if chance(rule.malformed_rate) {
+            Some(Fault::Malformed)
+        } else if chance(rule.generic_nack_rate) {
+            Some(Fault::GenericNack)
+        } else if rule
+            .max_tps
+            .is_some_and(|max_tps| !self.windows[index].admit(max_tps, Instant::now()))
+        {
+            Some(Fault::Status(CommandStatus::EsmeRthrottled))
+        } else {
+            rule.statuses
+                .iter()
+                .find(|status| chance(status.rate))
+                .map(|status| Fault::Status(status.status))
+        };
+
+        Injection {
+            fault,
+            delay: rule.delay,
+        }
+    }
+}
+
+fn chance(rate: f64) -> bool {
+    rate > 0.0 && rand::random_bool(rate.min(1.0))
+}
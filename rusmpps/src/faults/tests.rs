@@ -0,0 +1,248 @@
+use super::*;
+
+fn rule() -> FaultRule {
+    FaultRule::default()
+}
+
+fn fault(faults: &mut Faults, system_id: &str, id: CommandId) -> Option<Fault> {
+    faults.inject(system_id, id).fault
+}
+
+#[test]
+fn rules_should_match_commands_and_system_ids() {
+    let mut faults = Faults::new(vec![FaultRule {
+        commands: vec![CommandId::SubmitSm],
+        system_ids: vec![String::from("esme")],
+        drop_rate: 1.0,
+        ..rule()
+    }]);
+
+    assert_eq!(
+        fault(&mut faults, "esme", CommandId::SubmitSm),
+        Some(Fault::Drop)
+    );
+    assert_eq!(fault(&mut faults, "esme", CommandId::DataSm), None);
+    assert_eq!(fault(&mut faults, "other", CommandId::SubmitSm), None);
+
+    // Empty lists match everything.
+    let mut faults = Faults::new(vec![FaultRule {
+        drop_rate: 1.0,
+        ..rule()
+    }]);
+
+    assert_eq!(
+        fault(&mut faults, "other", CommandId::QuerySm),
+        Some(Fault::Drop)
+    );
+}
+
+#[test]
+fn only_the_first_matching_rule_should_apply() {
+    let mut faults = Faults::new(vec![
+        FaultRule {
+            commands: vec![CommandId::SubmitSm],
+            delay: Some(Duration::from_secs(1)),
+            ..rule()
+        },
+        FaultRule {
+            drop_rate: 1.0,
+            delay: Some(Duration::from_secs(2)),
+            ..rule()
+        },
+    ]);
+
+    let injection = faults.inject("esme", CommandId::SubmitSm);
+
+    assert_eq!(injection.fault, None);
+    assert_eq!(injection.delay, Some(Duration::from_secs(1)));
+
+    let injection = faults.inject("esme", CommandId::DataSm);
+
+    assert_eq!(injection.fault, Some(Fault::Drop));
+    assert_eq!(injection.delay, Some(Duration::from_secs(2)));
+
+    assert!(faults.inject("esme", CommandId::DataSm).delay.is_some());
+    assert!(
+        Faults::new(Vec::new())
+            .inject("esme", CommandId::SubmitSm)
+            .delay
+            .is_none()
+    );
+}
+
+#[test]
+fn faults_should_be_evaluated_in_order() {
+    let all = FaultRule {
+        disconnect_rate: 1.0,
+        drop_rate: 1.0,
+        malformed_rate: 1.0,
+        generic_nack_rate: 1.0,
+        statuses: vec![StatusRate {
+            status: CommandStatus::EsmeRsyserr,
+            rate: 1.0,
+        }],
+        ..rule()
+    };
+
+    let cases = [
+        (all.clone(), Fault::Disconnect),
+        (
+            FaultRule {
+                disconnect_rate: 0.0,
+                ..all.clone()
+            },
+            Fault::Drop,
+        ),
+        (
+            FaultRule {
+                disconnect_rate: 0.0,
+                drop_rate: 0.0,
+                ..all.clone()
+            },
+            Fault::Malformed,
+        ),
+        (
+            FaultRule {
+                disconnect_rate: 0.0,
+                drop_rate: 0.0,
+                malformed_rate: 0.0,
+                ..all.clone()
+            },
+            Fault::GenericNack,
+        ),
+        (
+            FaultRule {
+                disconnect_rate: 0.0,
+                drop_rate: 0.0,
+                malformed_rate: 0.0,
+                generic_nack_rate: 0.0,
+                ..all
+            },
+            Fault::Status(CommandStatus::EsmeRsyserr),
+        ),
+    ];
+
+    for (rule, expected) in cases {
+        let mut faults = Faults::new(vec![rule]);
+
+        assert_eq!(
+            fault(&mut faults, "esme", CommandId::SubmitSm),
+            Some(expected)
+        );
+    }
+}
+
+#[test]
+fn rates_should_be_probabilities() {
+    let mut never = Faults::new(vec![FaultRule {
+        drop_rate: 0.0,
+        ..rule()
+    }]);
+    let mut always = Faults::new(vec![FaultRule {
+        // Clamped to 1.0.
+        drop_rate: 2.0,
+        ..rule()
+    }]);
+    let mut half = Faults::new(vec![FaultRule {
+        drop_rate: 0.5,
+        ..rule()
+    }]);
+
+    let mut dropped = 0;
+
+    for _ in 0..1000 {
+        assert_eq!(fault(&mut never, "esme", CommandId::SubmitSm), None);
+        assert_eq!(
+            fault(&mut always, "esme", CommandId::SubmitSm),
+            Some(Fault::Drop)
+        );
+
+        if fault(&mut half, "esme", CommandId::SubmitSm).is_some() {
+            dropped += 1;
+        }
+    }
+
+    assert!((350..650).contains(&dropped), "{dropped}");
+}
+
+#[test]
+fn first_status_hit_should_apply() {
+    let mut faults = Faults::new(vec![FaultRule {
+        statuses: vec![
+            StatusRate {
+                status: CommandStatus::EsmeRsyserr,
+                rate: 0.0,
+            },
+            StatusRate {
+                status: CommandStatus::EsmeRmsgqful,
+                rate: 1.0,
+            },
+            StatusRate {
+                status: CommandStatus::EsmeRinvdstadr,
+                rate: 1.0,
+            },
+        ],
+        ..rule()
+    }]);
+
+    assert_eq!(
+        fault(&mut faults, "esme", CommandId::SubmitSm),
+        Some(Fault::Status(CommandStatus::EsmeRmsgqful))
+    );
+}
+
+#[test]
+fn max_tps_should_throttle_above_the_limit() {
+    let mut faults = Faults::new(vec![FaultRule {
+        max_tps: Some(2),
+        ..rule()
+    }]);
+
+    assert_eq!(fault(&mut faults, "esme", CommandId::SubmitSm), None);
+    assert_eq!(fault(&mut faults, "esme", CommandId::SubmitSm), None);
+    assert_eq!(
+        fault(&mut faults, "esme", CommandId::SubmitSm),
+        Some(Fault::Status(CommandStatus::EsmeRthrottled))
+    );
+}
+
+#[test]
+fn throttle_window_should_reset_every_second() {
+    let start = Instant::now();
+    let mut window = ThrottleWindow { start, count: 0 };
+
+    assert!(window.admit(1, start));
+    assert!(!window.admit(1, start + Duration::from_millis(999)));
+    assert!(window.admit(1, start + Duration::from_secs(1)));
+    assert!(!window.admit(1, start + Duration::from_millis(1500)));
+    assert!(!window.admit(0, start + Duration::from_secs(3)));
+}
+
+#[test]
+fn rules_should_be_deserialized() {
+    let rules: Vec<FaultRule> = serde_yaml::from_str(
+        r#"
+- commands: [SubmitSm]
+  system_ids: ["esme"]
+  statuses:
+    - { status: EsmeRmsgqful, rate: 0.25 }
+  max_tps: 10
+  drop_rate: 0.5
+  delay: "2s"
+- generic_nack_rate: 1.0
+"#,
+    )
+    .unwrap();
+
+    assert_eq!(rules.len(), 2);
+    assert_eq!(rules[0].commands, vec![CommandId::SubmitSm]);
+    assert_eq!(rules[0].system_ids, vec![String::from("esme")]);
+    assert_eq!(rules[0].statuses[0].status, CommandStatus::EsmeRmsgqful);
+    assert_eq!(rules[0].statuses[0].rate, 0.25);
+    assert_eq!(rules[0].max_tps, Some(10));
+    assert_eq!(rules[0].drop_rate, 0.5);
+    assert_eq!(rules[0].delay, Some(Duration::from_secs(2)));
+    assert!(rules[1].commands.is_empty());
+    assert_eq!(rules[1].generic_nack_rate, 1.0);
+    assert_eq!(rules[1].drop_rate, 0.0);
+}
@@ -1,7 +1,19 @@
+pub mod admin;
 pub mod args;
 pub mod bind_mode;
 pub mod client;
 pub mod config;
 pub mod connection;
+pub mod control;
+pub mod faults;
+pub mod lifecycle;
+pub mod receipt;
+pub mod router;
 pub mod server;
+pub mod service;
+pub mod simulator;
+pub mod smpp_time;
+pub mod store;
 pub mod timer;
+pub mod tls;
+pub mod websocket;
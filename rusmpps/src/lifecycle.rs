@@ -0,0 +1,97 @@
+use std::{
+    collections::VecDeque,
+    sync::Arc,
+    time::{Duration, Instant, SystemTime},
+};
+
+use rusmpp::{Pdu, pdus::DeliverSm};
+
+use crate::{control::Control, receipt, store::MessageStore};
+
+/// Interval at which the messages lifecycle is advanced.
+const TICK: Duration = Duration::from_millis(100);
+
+#[derive(Debug)]
+struct PendingReceipt {
+    system_id: String,
+    deliver_sm: DeliverSm,
+    created_at: Instant,
+}
+
+/// Advances the messages lifecycle and persists the message store.
+///
+/// Requested receipts are pushed to a receiver or transceiver session of the submitting `system_id`.
+/// Receipts are kept for the store retention while no such session is bound.
+///
+/// The store is saved at most once per `persist_interval`, and only if it changed.
+pub async fn run(store: Arc<MessageStore>, control: Control) {
+    let mut interval = tokio::time::interval(TICK);
+    let mut pending = VecDeque::new();
+    let mut persisted_at = Instant::now();
+
+    loop {
+        interval.tick().await;
+
+        for message in store.advance(SystemTime::now()) {
+            tracing::debug!(message_id = message.message_id, state = ?message.state, "Message state changed");
+
+            if let Some(deliver_sm) = receipt::receipt(&message) {
+                pending.push_back(PendingReceipt {
+                    system_id: message.system_id,
+                    deliver_sm,
+                    created_at: Instant::now(),
+                });
+            }
+        }
+
+        let retention = store.retention();
+
+        for _ in 0..pending.len() {
+            let Some(receipt) = pending.pop_front() else {
+                break;
+            };
+
+            let pdu = Pdu::from(receipt.deliver_sm);
+
+            match control.try_send(&receipt.system_id, pdu).await {
+                Ok(sequence_number) => {
+                    tracing::debug!(
+                        system_id = receipt.system_id,
+                        sequence_number,
+                        "Receipt sent"
+                    );
+                }
+                Err(_) if receipt.created_at.elapsed() >= retention => {
+                    tracing::warn!(
+                        system_id = receipt.system_id,
+                        "Dropping undelivered receipt"
+                    );
+                }
+                Err(Pdu::DeliverSm(deliver_sm)) => pending.push_back(PendingReceipt {
+                    deliver_sm,
+                    ..receipt
+                }),
+                Err(_) => {}
+            }
+        }
+
+        if persisted_at.elapsed() >= store.persist_interval() && store.is_dirty() {
+            persisted_at = Instant::now();
+
+            persist(store.clone()).await;
+        }
+    }
+}
+
+/// Saves the message store on a blocking thread.
+pub async fn persist(store: Arc<MessageStore>) {
+    match tokio::task::spawn_blocking(move || store.persist()).await {
+        Ok(Ok(())) => {}
+        Ok(Err(err)) => {
+            tracing::error!(?err, "Failed to save message store");
+        }
+        Err(err) => {
+            tracing::error!(?err, "Message store save task failed");
+        }
+    }
+}
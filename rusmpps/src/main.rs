@@ -1,8 +1,15 @@
+use std::sync::Arc;
+
 use clap::Parser;
 use rusmpps::{
     args::Args,
     config::Config,
+    lifecycle,
+    router::{Router, RouterParameters},
     server::{Server, ServerParameters},
+    service::Service,
+    simulator::{Simulator, SimulatorParameters},
+    store::MessageStore,
 };
 
 #[tokio::main]
@@ -15,30 +22,79 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let args = Args::parse();
 
-    let config = Config::from_yaml_file(args.config_file).unwrap_or_else(|err| {
-        tracing::error!("Failed to load config: {}", err);
-        tracing::warn!("Using default configuration");
+    let config = match Config::from_yaml_file(args.config_file) {
+        Ok(config) => config,
+        Err(err) if err.is_not_found() => {
+            tracing::warn!("{}", err);
+            tracing::warn!("Using default configuration");
 
-        Config::default()
-    });
+            Config::default()
+        }
+        Err(err) => {
+            tracing::error!("Failed to load config: {}", err);
+
+            return Err(err.into());
+        }
+    };
 
     tracing::info!(?config);
 
     let parameters = ServerParameters {
-        clients: vec![],
         enquire_link_interval: config.enquire_link_interval,
         enquire_link_response_timeout: config.enquire_link_response_timeout,
         enquire_link_response_delay: config.enquire_link_response_delay,
         session_timeout: config.session_timeout,
-        bind_delay: config.bind_delay,
-        response_delay: config.response_delay,
+        window_size: config.window_size,
+        response_timeout: config.response_timeout,
         socket_addr: config.socket_addr,
+        tls: config.tls,
+        websocket: config.websocket,
+        admin: config.admin,
     };
 
-    let server = Server::new(parameters);
+    if let Some(router) = config.router {
+        let router = Router::new(RouterParameters {
+            clients: config.accounts,
+            config: router,
+            bind_delay: config.bind_delay,
+            response_delay: config.response_delay,
+            faults: config.faults,
+        })?;
+
+        let server = Server::new(parameters, router.clone());
+
+        tokio::spawn(router.run(server.control()));
+
+        tracing::info!("Starting router");
+
+        return run(server).await;
+    }
+
+    let message_store = Arc::new(MessageStore::load(config.message_store)?);
+
+    let simulator = Simulator::new(SimulatorParameters {
+        clients: config.accounts,
+        message_store: message_store.clone(),
+        bind_delay: config.bind_delay,
+        response_delay: config.response_delay,
+        faults: config.faults,
+    });
+
+    let server = Server::new(parameters, simulator);
+
+    tokio::spawn(lifecycle::run(message_store.clone(), server.control()));
 
     tracing::info!("Starting server");
 
+    let result = run(server).await;
+
+    // Saves the changes since the last periodic save.
+    lifecycle::persist(message_store).await;
+
+    result
+}
+
+async fn run<S: Service>(server: Server<S>) -> Result<(), Box<dyn std::error::Error>> {
     tokio::select! {
         result = server.run() => {
             result?;
@@ -0,0 +1,118 @@
+use std::{str::FromStr, time::SystemTime};
+
+use rusmpp::{
+    pdus::DeliverSm,
+    tlvs::MessageDeliveryRequestTlvValue,
+    types::{COctetString, OctetString},
+    values::{
+        Ansi41Specific, EsmClass, GsmFeatures, IntermediateNotification, McDeliveryReceipt,
+        MessageState, MessageType, MessagingMode, ServiceType,
+    },
+};
+
+use crate::{smpp_time, store::Message};
+
+#[cfg(test)]
+mod tests;
+
+/// Returns the receipt or intermediate notification requested for the current state of the message.
+pub fn receipt(message: &Message) -> Option<DeliverSm> {
+    let message_type = match message.state {
+        MessageState::Scheduled | MessageState::Accepted => return None,
+        MessageState::Enroute => match message.registered_delivery.intermediate_notification() {
+            IntermediateNotification::IntermediateNotificationRequested => {
+                MessageType::ShortMessageContainsIntermediateDeliveryNotification
+            }
+            _ => return None,
+        },
+        state => {
+            let requested = match message.registered_delivery.mc_delivery_receipt() {
+                McDeliveryReceipt::McDeliveryReceiptRequestedWhereFinalDeliveryOutcomeIsSuccessOrFailure => true,
+                McDeliveryReceipt::McDeliveryReceiptRequestedWhereFinalDeliveryOutcomeIsFailure => {
+                    state != MessageState::Delivered
+                }
+                McDeliveryReceipt::McDeliveryReceiptRequestedWhereFinalDeliveryOutcomeIsSuccess => {
+                    state == MessageState::Delivered
+                }
+                _ => false,
+            };
+
+            if !requested {
+                return None;
+            }
+
+            MessageType::ShortMessageContainsMcDeliveryReceipt
+        }
+    };
+
+    let short_message = OctetString::from_str(&text(message)).expect("Must be valid short message");
+
+    let deliver_sm = DeliverSm::builder()
+        .service_type(ServiceType::new(
+            COctetString::from_str(&message.service_type).unwrap_or_default(),
+        ))
+        .source_addr_ton(message.destination.ton)
+        .source_addr_npi(message.destination.npi)
+        .source_addr(COctetString::from_str(&message.destination.addr).unwrap_or_default())
+        .dest_addr_ton(message.source.ton)
+        .dest_addr_npi(message.source.npi)
+        .destination_addr(COctetString::from_str(&message.source.addr).unwrap_or_default())
+        .esm_class(EsmClass::new(
+            MessagingMode::Default,
+            message_type,
+            Ansi41Specific::NotSelected,
+            GsmFeatures::NotSelected,
+        ))
+        .short_message(short_message)
+        .tlvs(vec![
+            MessageDeliveryRequestTlvValue::ReceiptedMessageId(
+                COctetString::from_str(&message.message_id).expect("Must be valid message ID"),
+            ),
+            MessageDeliveryRequestTlvValue::MessageState(message.state),
+        ])
+        .build();
+
+    Some(deliver_sm)
+}
+
+/// The de facto standard receipt text:
+///
+/// `id:IIIIIIIIII sub:SSS dlvrd:DDD submit date:YYMMDDhhmm done date:YYMMDDhhmm stat:DDDDDDD err:E text: . . . . . . . . .`
+fn text(message: &Message) -> String {
+    let delivered = u8::from(message.state == MessageState::Delivered);
+    let done_date = message.final_date.unwrap_or_else(SystemTime::now);
+
+    // Only the first 20 characters of the original message are echoed.
+    let original = message
+        .short_message
+        .iter()
+        .take(20)
+        .map(|byte| match byte {
+            0x20..=0x7e => *byte as char,
+            _ => '.',
+        })
+        .collect::<String>();
+
+    format!(
+        "id:{} sub:001 dlvrd:{delivered:03} submit date:{} done date:{} stat:{} err:000 text:{original}",
+        message.message_id,
+        &smpp_time::format(message.submit_time)[..10],
+        &smpp_time::format(done_date)[..10],
+        stat(message.state),
+    )
+}
+
+fn stat(state: MessageState) -> &'static str {
+    match state {
+        MessageState::Scheduled => "SCHEDLD",
+        MessageState::Enroute => "ENROUTE",
+        MessageState::Delivered => "DELIVRD",
+        MessageState::Expired => "EXPIRED",
+        MessageState::Deleted => "DELETED",
+        MessageState::Undeliverable => "UNDELIV",
+        MessageState::Accepted => "ACCEPTD",
+        MessageState::Rejected => "REJECTD",
+        MessageState::Skipped => "SKIPPED",
+        _ => "UNKNOWN",
+    }
+}
@@ -0,0 +1,231 @@
+use std::time::Duration;
+
+use rusmpp::{
+    tlvs::TlvValue,
+    values::{
+        DataCoding, IntermediateNotification, Npi, RegisteredDelivery,
+        SmeOriginatedAcknowledgement, Ton,
+    },
+};
+
+use super::*;
+use crate::store::{Address, Lifecycle, MessageStore, MessageStoreConfig, Outcomes, Submission};
+
+const DELIVERY_DELAY: Duration = Duration::from_secs(10);
+
+fn registered_delivery(
+    mc_delivery_receipt: McDeliveryReceipt,
+    intermediate_notification: IntermediateNotification,
+) -> RegisteredDelivery {
+    RegisteredDelivery::new(
+        mc_delivery_receipt,
+        SmeOriginatedAcknowledgement::NoReceiptSmeAcknowledgementRequested,
+        intermediate_notification,
+        0,
+    )
+}
+
+/// Submits a message and returns it in its enroute and final states.
+fn message(
+    registered_delivery: RegisteredDelivery,
+    outcome: MessageState,
+    short_message: &[u8],
+) -> (Message, Message) {
+    let outcomes = Outcomes {
+        delivered: u32::from(outcome == MessageState::Delivered),
+        undeliverable: u32::from(outcome == MessageState::Undeliverable),
+        rejected: u32::from(outcome == MessageState::Rejected),
+    };
+
+    let store = MessageStore::new(MessageStoreConfig {
+        delivery_delay: DELIVERY_DELAY,
+        outcomes,
+        ..Default::default()
+    });
+
+    store
+        .submit(Submission {
+            system_id: String::from("esme"),
+            service_type: String::from("CMT"),
+            source: Address::new(Ton::International, Npi::Isdn, "1111"),
+            destination: Address::new(Ton::National, Npi::Isdn, "2222"),
+            registered_delivery,
+            data_coding: DataCoding::default(),
+            short_message: short_message.to_vec(),
+            schedule_delivery_time: String::new(),
+            validity_period: String::new(),
+            lifecycle: Lifecycle::default(),
+        })
+        .unwrap();
+
+    let now = SystemTime::now();
+
+    let enroute = store.advance(now).pop().unwrap();
+    let final_ = store.advance(now + DELIVERY_DELAY).pop().unwrap();
+
+    (enroute, final_)
+}
+
+#[test]
+fn receipt_should_follow_the_requested_outcome() {
+    let cases = [
+        (
+            McDeliveryReceipt::McDeliveryReceiptRequestedWhereFinalDeliveryOutcomeIsSuccessOrFailure,
+            MessageState::Delivered,
+            true,
+        ),
+        (
+            McDeliveryReceipt::McDeliveryReceiptRequestedWhereFinalDeliveryOutcomeIsSuccessOrFailure,
+            MessageState::Undeliverable,
+            true,
+        ),
+        (
+            McDeliveryReceipt::McDeliveryReceiptRequestedWhereFinalDeliveryOutcomeIsSuccess,
+            MessageState::Delivered,
+            true,
+        ),
+        (
+            McDeliveryReceipt::McDeliveryReceiptRequestedWhereFinalDeliveryOutcomeIsSuccess,
+            MessageState::Rejected,
+            false,
+        ),
+        (
+            McDeliveryReceipt::McDeliveryReceiptRequestedWhereFinalDeliveryOutcomeIsFailure,
+            MessageState::Delivered,
+            false,
+        ),
+        (
+            McDeliveryReceipt::McDeliveryReceiptRequestedWhereFinalDeliveryOutcomeIsFailure,
+            MessageState::Undeliverable,
+            true,
+        ),
+        (
+            McDeliveryReceipt::NoMcDeliveryReceiptRequested,
+            MessageState::Delivered,
+            false,
+        ),
+    ];
+
+    for (mc_delivery_receipt, outcome, expected) in cases {
+        let (enroute, final_) = message(
+            registered_delivery(
+                mc_delivery_receipt,
+                IntermediateNotification::NoIntermediaryNotificationRequested,
+            ),
+            outcome,
+            b"Hello",
+        );
+
+        assert_eq!(final_.state, outcome);
+        assert!(receipt(&enroute).is_none());
+        assert_eq!(
+            receipt(&final_).is_some(),
+            expected,
+            "{mc_delivery_receipt:?} {outcome:?}"
+        );
+    }
+}
+
+#[test]
+fn receipt_should_be_addressed_back_to_the_submitter() {
+    let (_, final_) = message(
+        RegisteredDelivery::request_all(),
+        MessageState::Delivered,
+        b"Hello",
+    );
+
+    let deliver_sm = receipt(&final_).unwrap();
+
+    assert_eq!(deliver_sm.source_addr.to_string(), "2222");
+    assert_eq!(deliver_sm.source_addr_ton, Ton::National);
+    assert_eq!(deliver_sm.destination_addr.to_string(), "1111");
+    assert_eq!(deliver_sm.dest_addr_ton, Ton::International);
+    assert_eq!(
+        deliver_sm.esm_class.message_type,
+        MessageType::ShortMessageContainsMcDeliveryReceipt
+    );
+
+    let tlvs = deliver_sm
+        .tlvs()
+        .iter()
+        .filter_map(|tlv| tlv.value().cloned())
+        .collect::<Vec<_>>();
+
+    assert!(tlvs.contains(&TlvValue::ReceiptedMessageId(
+        COctetString::from_str(&final_.message_id).unwrap()
+    )));
+    assert!(tlvs.contains(&TlvValue::MessageState(MessageState::Delivered)));
+}
+
+#[test]
+fn intermediate_notification_should_be_sent_when_enroute() {
+    let (enroute, final_) = message(
+        registered_delivery(
+            McDeliveryReceipt::NoMcDeliveryReceiptRequested,
+            IntermediateNotification::IntermediateNotificationRequested,
+        ),
+        MessageState::Delivered,
+        b"Hello",
+    );
+
+    let deliver_sm = receipt(&enroute).unwrap();
+
+    assert_eq!(
+        deliver_sm.esm_class.message_type,
+        MessageType::ShortMessageContainsIntermediateDeliveryNotification
+    );
+    assert!(receipt(&final_).is_none());
+}
+
+#[test]
+fn text_should_use_the_standard_format() {
+    let (_, final_) = message(
+        RegisteredDelivery::request_all(),
+        MessageState::Delivered,
+        b"Hello",
+    );
+
+    let submit_date = &smpp_time::format(final_.submit_time)[..10];
+    let done_date = &smpp_time::format(final_.final_date.unwrap())[..10];
+
+    assert_eq!(
+        text(&final_),
+        format!(
+            "id:{} sub:001 dlvrd:001 submit date:{submit_date} done date:{done_date} stat:DELIVRD err:000 text:Hello",
+            final_.message_id
+        )
+    );
+
+    let (_, final_) = message(
+        RegisteredDelivery::request_all(),
+        MessageState::Rejected,
+        b"Hello",
+    );
+
+    assert!(text(&final_).contains(" dlvrd:000 "));
+    assert!(text(&final_).contains(" stat:REJECTD "));
+}
+
+#[test]
+fn text_should_echo_the_first_20_printable_characters() {
+    let (_, final_) = message(
+        RegisteredDelivery::request_all(),
+        MessageState::Delivered,
+        b"\x00\x01Hello, this message is longer than twenty characters",
+    );
+
+    assert!(text(&final_).ends_with(" text:..Hello, this messag"));
+}
+
+#[test]
+fn stat_should_name_every_state() {
+    assert_eq!(stat(MessageState::Scheduled), "SCHEDLD");
+    assert_eq!(stat(MessageState::Enroute), "ENROUTE");
+    assert_eq!(stat(MessageState::Delivered), "DELIVRD");
+    assert_eq!(stat(MessageState::Expired), "EXPIRED");
+    assert_eq!(stat(MessageState::Deleted), "DELETED");
+    assert_eq!(stat(MessageState::Undeliverable), "UNDELIV");
+    assert_eq!(stat(MessageState::Accepted), "ACCEPTD");
+    assert_eq!(stat(MessageState::Rejected), "REJECTD");
+    assert_eq!(stat(MessageState::Skipped), "SKIPPED");
+}
@@ -0,0 +1,1051 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    path::PathBuf,
+    str::FromStr,
+    sync::{
+        Arc, Mutex, OnceLock,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use futures::StreamExt;
+use rusmpp::{
+    Command, CommandId, CommandStatus, Pdu,
+    pdus::{BindTransceiver, DeliverSm, DeliverSmResp},
+    tlvs::{MessageDeliveryRequestTlvValue, TlvValue},
+    types::{COctetString, OctetString},
+};
+use rusmppc::{
+    Client, ConnectionBuilder, error::Error, event::Event, managed::ManagedEvent,
+    tracking::DeliveryReceipt,
+};
+use serde::Deserialize;
+use tokio::sync::mpsc;
+
+use crate::{
+    client::{self, Client as Account},
+    control::Control,
+    faults::{FaultRule, Faults, ThrottleWindow},
+    service::{Bind, Bound, Response, Service, SessionHandler},
+};
+
+#[cfg(test)]
+mod tests;
+
+/// Interval at which undelivered receipts are retried.
+const TICK: Duration = Duration::from_millis(100);
+
+/// Interval at which messages without final receipt are dropped.
+const EXPIRY_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Forwards the messages of the bound `ESME`s to upstream `MC`s.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RouterConfig {
+    pub upstreams: Vec<UpstreamConfig>,
+    /// The first route matching a message applies.
+    pub routes: Vec<Route>,
+    /// Time to wait for the response of an upstream. Receipts of unknown messages are also kept for this time,
+    /// as they may arrive before the response of the upstream is processed.
+    #[serde(with = "humantime_serde")]
+    #[serde(default = "default_upstream_timeout")]
+    pub upstream_timeout: Duration,
+    /// Time a forwarded message waits for its final receipt.
+    #[serde(with = "humantime_serde")]
+    #[serde(default = "default_message_ttl")]
+    pub message_ttl: Duration,
+    /// Time a receipt is kept while no session of the `ESME` can receive it.
+    #[serde(with = "humantime_serde")]
+    #[serde(default = "default_receipt_retention")]
+    pub receipt_retention: Duration,
+}
+
+fn default_upstream_timeout() -> Duration {
+    Duration::from_secs(10)
+}
+
+fn default_message_ttl() -> Duration {
+    Duration::from_secs(48 * 60 * 60)
+}
+
+fn default_receipt_retention() -> Duration {
+    Duration::from_secs(60 * 60)
+}
+
+/// An upstream `MC`, bound as a transceiver.
+#[derive(Clone, Deserialize)]
+pub struct UpstreamConfig {
+    pub name: String,
+    /// `smpp`, `smpps`, `ws` or `wss` URL.
+    pub url: String,
+    pub system_id: String,
+    pub password: String,
+    #[serde(default)]
+    pub system_type: String,
+    /// PEM encoded CA certificates used to verify `smpps` and `wss` upstreams. The system roots are used if not set.
+    #[serde(default)]
+    pub tls_ca: Option<PathBuf>,
+    #[serde(with = "humantime_serde")]
+    #[serde(default = "default_enquire_link_interval")]
+    pub enquire_link_interval: Duration,
+    /// Interval at which a lost connection is re-established.
+    #[serde(with = "humantime_serde")]
+    #[serde(default = "default_reconnect_interval")]
+    pub reconnect_interval: Duration,
+}
+
+impl std::fmt::Debug for UpstreamConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UpstreamConfig")
+            .field("name", &self.name)
+            .field("url", &self.url)
+            .field("system_id", &self.system_id)
+            .field("password", &"***")
+            .field("system_type", &self.system_type)
+            .field("tls_ca", &self.tls_ca)
+            .field("enquire_link_interval", &self.enquire_link_interval)
+            .field("reconnect_interval", &self.reconnect_interval)
+            .finish()
+    }
+}
+
+fn default_enquire_link_interval() -> Duration {
+    Duration::from_secs(30)
+}
+
+fn default_reconnect_interval() -> Duration {
+    Duration::from_secs(5)
+}
+
+/// A routing rule for `submit_sm` and `data_sm`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Route {
+    pub name: String,
+    /// Destination address prefixes. All destinations if empty.
+    pub destination_prefixes: Vec<String>,
+    /// Accounts the route applies to. All accounts if empty.
+    pub system_ids: Vec<String>,
+    /// Service types the route applies to. All service types if empty.
+    pub service_types: Vec<String>,
+    /// Names of the upstreams in failover order.
+    pub upstreams: Vec<String>,
+    /// Responds with `ESME_RTHROTTLED` above this number of messages per second over the route.
+    pub max_tps: Option<u32>,
+}
+
+impl Route {
+    fn matches(&self, system_id: &str, service_type: &str, destination: &str) -> bool {
+        (self.destination_prefixes.is_empty()
+            || self
+                .destination_prefixes
+                .iter()
+                .any(|prefix| destination.starts_with(prefix.as_str())))
+            && (self.system_ids.is_empty() || self.system_ids.iter().any(|s| s == system_id))
+            && (self.service_types.is_empty()
+                || self.service_types.iter().any(|s| s == service_type))
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum RouterError {
+    #[error("Route `{route}` uses the unknown upstream `{upstream}`")]
+    UnknownUpstream { route: String, upstream: String },
+    #[error("Route `{0}` has no upstreams")]
+    NoUpstreams(String),
+    #[error("Invalid {field} of upstream `{upstream}`")]
+    InvalidUpstream {
+        upstream: String,
+        field: &'static str,
+    },
+}
+
+#[derive(Debug)]
+pub struct RouterParameters {
+    /// Accounts allowed to bind. Every bind is accepted if empty.
+    pub clients: Vec<Account>,
+    pub config: RouterConfig,
+    pub bind_delay: Option<Duration>,
+    pub response_delay: Option<Duration>,
+    /// Applied before forwarding, a faulted request is not forwarded.
+    pub faults: Vec<FaultRule>,
+}
+
+/// Forwards `submit_sm` and `data_sm` to upstream `MC`s using [`rusmppc`], and routes their receipts back.
+///
+/// `message_id`s are replaced by ids of the router, so that `query_sm`, `cancel_sm` and `replace_sm` reach the upstream that accepted the message.
+#[derive(Debug, Clone)]
+pub struct Router {
+    inner: Arc<RouterInner>,
+}
+
+#[derive(Debug)]
+struct RouterInner {
+    clients: Vec<Account>,
+    upstreams: Vec<Upstream>,
+    routes: Vec<RouteState>,
+    upstream_timeout: Duration,
+    message_ttl: Duration,
+    receipt_retention: Duration,
+    bind_delay: Option<Duration>,
+    response_delay: Option<Duration>,
+    faults: Vec<FaultRule>,
+    messages: Mutex<Messages>,
+}
+
+#[derive(Debug)]
+struct RouteState {
+    route: Route,
+    /// Indices of the upstreams in failover order.
+    upstreams: Vec<usize>,
+    window: Mutex<ThrottleWindow>,
+}
+
+#[derive(Debug)]
+struct Upstream {
+    config: UpstreamConfig,
+    bind: BindTransceiver,
+    /// Set once the first connection is established.
+    client: OnceLock<rusmppc::managed::ManagedClient>,
+    bound: AtomicBool,
+}
+
+impl Upstream {
+    fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    /// Returns the client if the upstream is bound.
+    async fn client(&self, timeout: Duration) -> Option<Client> {
+        if !self.bound.load(Ordering::Relaxed) {
+            return None;
+        }
+
+        match self.client.get()?.get_with_timeout(timeout).await {
+            Some(Ok(client)) => Some(client),
+            Some(Err(err)) => {
+                tracing::warn!(upstream = self.name(), %err, "Upstream unavailable");
+
+                None
+            }
+            None => None,
+        }
+    }
+}
+
+/// A message forwarded to an upstream.
+#[derive(Debug, Clone)]
+struct Leg {
+    system_id: String,
+    session_id: u64,
+    upstream: usize,
+    message_id: String,
+    created_at: Instant,
+}
+
+/// Maps the `message_id`s of the router to the `message_id`s of the upstreams.
+#[derive(Debug, Default)]
+struct Messages {
+    next_id: u64,
+    legs: HashMap<String, Leg>,
+    /// Upstream index and `message_id` to the `message_id` of the router.
+    ids: HashMap<(usize, String), String>,
+}
+
+impl Messages {
+    fn insert(&mut self, leg: Leg) -> String {
+        let message_id = format!("{:016x}", self.next_id);
+
+        self.next_id += 1;
+
+        self.ids
+            .insert((leg.upstream, leg.message_id.clone()), message_id.clone());
+        self.legs.insert(message_id.clone(), leg);
+
+        message_id
+    }
+
+    /// Returns the leg of a message submitted by `system_id`.
+    fn leg(&self, system_id: &str, message_id: &str) -> Option<&Leg> {
+        self.legs
+            .get(message_id)
+            .filter(|leg| leg.system_id == system_id)
+    }
+
+    fn remove(&mut self, message_id: &str) -> Option<Leg> {
+        let leg = self.legs.remove(message_id)?;
+
+        self.ids.remove(&(leg.upstream, leg.message_id.clone()));
+
+        Some(leg)
+    }
+
+    fn expire(&mut self, ttl: Duration) {
+        let expired = self
+            .legs
+            .iter()
+            .filter(|(_, leg)| leg.created_at.elapsed() >= ttl)
+            .map(|(message_id, _)| message_id.clone())
+            .collect::<Vec<_>>();
+
+        for message_id in expired {
+            tracing::debug!(message_id, "Dropping message without final receipt");
+
+            self.remove(&message_id);
+        }
+    }
+}
+
+/// A receipt sent by an upstream, not yet matched to a forwarded message.
+#[derive(Debug)]
+struct UpstreamReceipt {
+    upstream: usize,
+    /// The `message_id` of the upstream.
+    message_id: String,
+    is_final: bool,
+    deliver_sm: DeliverSm,
+    received_at: Instant,
+}
+
+/// A receipt of an upstream, rewritten for the `ESME`.
+#[derive(Debug)]
+struct PendingReceipt {
+    system_id: String,
+    session_id: u64,
+    deliver_sm: DeliverSm,
+    created_at: Instant,
+}
+
+impl Router {
+    pub fn new(parameters: RouterParameters) -> Result<Self, RouterError> {
+        let config = parameters.config;
+
+        let upstreams = config
+            .upstreams
+            .into_iter()
+            .map(|config| {
+                let invalid = |field| RouterError::InvalidUpstream {
+                    upstream: config.name.clone(),
+                    field,
+                };
+
+                let bind = BindTransceiver::builder()
+                    .system_id(
+                        COctetString::from_str(&config.system_id)
+                            .map_err(|_| invalid("system_id"))?,
+                    )
+                    .password(
+                        COctetString::from_str(&config.password)
+                            .map_err(|_| invalid("password"))?,
+                    )
+                    .system_type(
+                        COctetString::from_str(&config.system_type)
+                            .map_err(|_| invalid("system_type"))?,
+                    )
+                    .build();
+
+                Ok(Upstream {
+                    config,
+                    bind,
+                    client: OnceLock::new(),
+                    bound: AtomicBool::new(false),
+                })
+            })
+            .collect::<Result<Vec<_>, RouterError>>()?;
+
+        let routes = config
+            .routes
+            .into_iter()
+            .map(|route| {
+                if route.upstreams.is_empty() {
+                    return Err(RouterError::NoUpstreams(route.name));
+                }
+
+                let indices = route
+                    .upstreams
+                    .iter()
+                    .map(|name| {
+                        upstreams
+                            .iter()
+                            .position(|upstream| upstream.name() == name)
+                            .ok_or_else(|| RouterError::UnknownUpstream {
+                                route: route.name.clone(),
+                                upstream: name.clone(),
+                            })
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                Ok(RouteState {
+                    route,
+                    upstreams: indices,
+                    window: Mutex::new(ThrottleWindow::new()),
+                })
+            })
+            .collect::<Result<Vec<_>, RouterError>>()?;
+
+        Ok(Self {
+            inner: Arc::new(RouterInner {
+                clients: parameters.clients,
+                upstreams,
+                routes,
+                upstream_timeout: config.upstream_timeout,
+                message_ttl: config.message_ttl,
+                receipt_retention: config.receipt_retention,
+                bind_delay: parameters.bind_delay,
+                response_delay: parameters.response_delay,
+                faults: parameters.faults,
+                messages: Mutex::new(Messages::default()),
+            }),
+        })
+    }
+
+    /// Connects the upstreams and pushes their receipts to the `ESME`s.
+    ///
+    /// Receipts are pushed to the session that submitted the message, or to another receiver or transceiver session of its `system_id`.
+    /// They are kept for the `receipt_retention` while no such session is bound.
+    ///
+    /// Receipts of unknown messages are retried for the `upstream_timeout`, since an upstream may send a receipt before its response is processed.
+    pub async fn run(self, control: Control) {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+
+        for index in 0..self.inner.upstreams.len() {
+            tokio::spawn(self.inner.clone().connect(index, tx.clone()));
+        }
+
+        let mut interval = tokio::time::interval(TICK);
+        let mut expiry = tokio::time::interval(EXPIRY_INTERVAL);
+        let mut unmatched = VecDeque::new();
+        let mut pending = VecDeque::new();
+
+        loop {
+            tokio::select! {
+                Some(receipt) = rx.recv() => {
+                    unmatched.push_back(receipt);
+                }
+                _ = interval.tick() => {}
+                _ = expiry.tick() => {
+                    self.inner
+                        .messages
+                        .lock()
+                        .expect("Messages lock poisoned")
+                        .expire(self.inner.message_ttl);
+                }
+            }
+
+            for _ in 0..unmatched.len() {
+                let Some(receipt) = unmatched.pop_front() else {
+                    break;
+                };
+
+                match self.inner.match_receipt(receipt) {
+                    Ok(receipt) => pending.push_back(receipt),
+                    Err(receipt)
+                        if receipt.received_at.elapsed() >= self.inner.upstream_timeout =>
+                    {
+                        tracing::warn!(
+                            upstream = self.inner.upstreams[receipt.upstream].name(),
+                            message_id = receipt.message_id,
+                            "Dropping receipt of unknown message"
+                        );
+                    }
+                    Err(receipt) => unmatched.push_back(receipt),
+                }
+            }
+
+            for _ in 0..pending.len() {
+                let Some(receipt) = pending.pop_front() else {
+                    break;
+                };
+
+                let pdu = Pdu::from(receipt.deliver_sm);
+
+                let result = match control.try_send_to_session(receipt.session_id, pdu).await {
+                    Ok(sequence_number) => Ok(sequence_number),
+                    Err(pdu) => control.try_send(&receipt.system_id, pdu).await,
+                };
+
+                match result {
+                    Ok(sequence_number) => {
+                        tracing::debug!(
+                            system_id = receipt.system_id,
+                            sequence_number,
+                            "Receipt sent"
+                        );
+                    }
+                    Err(_) if receipt.created_at.elapsed() >= self.inner.receipt_retention => {
+                        tracing::warn!(
+                            system_id = receipt.system_id,
+                            "Dropping undelivered receipt"
+                        );
+                    }
+                    Err(Pdu::DeliverSm(deliver_sm)) => pending.push_back(PendingReceipt {
+                        deliver_sm,
+                        ..receipt
+                    }),
+                    Err(_) => {}
+                }
+            }
+        }
+    }
+}
+
+impl RouterInner {
+    /// Keeps the upstream connected and bound, and handles the commands it sends.
+    async fn connect(
+        self: Arc<Self>,
+        index: usize,
+        receipts: mpsc::UnboundedSender<UpstreamReceipt>,
+    ) {
+        let upstream = &self.upstreams[index];
+        let config = &upstream.config;
+
+        let (client, mut events) = loop {
+            let mut builder = ConnectionBuilder::new()
+                .enquire_link_interval(config.enquire_link_interval)
+                .response_timeout(self.upstream_timeout);
+
+            if let Some(tls_ca) = &config.tls_ca {
+                builder = builder.tls_ca_file(tls_ca);
+            }
+
+            let result = builder
+                .managed()
+                .transceiver(upstream.bind.clone())
+                .auto_reconnect_interval(config.reconnect_interval)
+                .fixed_backoff(config.reconnect_interval)
+                .connect(config.url.clone())
+                .await;
+
+            match result {
+                Ok(connected) => break connected,
+                Err(err) => {
+                    tracing::warn!(upstream = upstream.name(), %err, "Failed to connect to upstream");
+
+                    tokio::time::sleep(config.reconnect_interval).await;
+                }
+            }
+        };
+
+        let _ = upstream.client.set(client);
+
+        upstream.bound.store(true, Ordering::Relaxed);
+
+        while let Some(event) = events.next().await {
+            match event {
+                ManagedEvent::Bound => {
+                    tracing::info!(upstream = upstream.name(), "Upstream bound");
+
+                    upstream.bound.store(true, Ordering::Relaxed);
+                }
+                ManagedEvent::Disconnected => {
+                    tracing::warn!(upstream = upstream.name(), "Upstream disconnected");
+
+                    upstream.bound.store(false, Ordering::Relaxed);
+                }
+                ManagedEvent::Event(Event::Incoming(command)) => {
+                    self.incoming(index, command, &receipts).await;
+                }
+                ManagedEvent::Event(Event::Error(err)) => {
+                    tracing::warn!(upstream = upstream.name(), %err, "Upstream error");
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Passes the receipts sent by the upstream on to be matched to the forwarded messages.
+    ///
+    /// Mobile originated messages are not routed.
+    async fn incoming(
+        &self,
+        index: usize,
+        command: Command,
+        receipts: &mpsc::UnboundedSender<UpstreamReceipt>,
+    ) {
+        let upstream = &self.upstreams[index];
+        let sequence_number = command.sequence_number();
+
+        let receipt = DeliveryReceipt::from_command(&command);
+
+        let Some(Pdu::DeliverSm(deliver_sm)) = command.pdu() else {
+            return;
+        };
+
+        let status = match receipt {
+            Some(receipt) => {
+                let _ = receipts.send(UpstreamReceipt {
+                    upstream: index,
+                    is_final: receipt.is_final(),
+                    message_id: receipt.message_id,
+                    deliver_sm: deliver_sm.clone(),
+                    received_at: Instant::now(),
+                });
+
+                CommandStatus::EsmeRok
+            }
+            None => {
+                tracing::warn!(
+                    upstream = upstream.name(),
+                    "Rejecting mobile originated message"
+                );
+
+                CommandStatus::EsmeRinvdstadr
+            }
+        };
+
+        let Some(client) = upstream.client.get() else {
+            return;
+        };
+
+        let Some(Ok(client)) = client.get_with_timeout(self.upstream_timeout).await else {
+            return;
+        };
+
+        if let Err(err) = client
+            .status(status)
+            .deliver_sm_resp(sequence_number, DeliverSmResp::default())
+            .await
+        {
+            tracing::warn!(upstream = upstream.name(), %err, "Failed to respond to deliver_sm");
+        }
+    }
+
+    /// Rewrites the receipt for the `ESME` that submitted the message.
+    ///
+    /// Returns the receipt back if the message is unknown. The message is forgotten once its final receipt is matched.
+    #[allow(clippy::result_large_err)]
+    fn match_receipt(&self, receipt: UpstreamReceipt) -> Result<PendingReceipt, UpstreamReceipt> {
+        let mut messages = self.messages.lock().expect("Messages lock poisoned");
+
+        let Some(message_id) = messages
+            .ids
+            .get(&(receipt.upstream, receipt.message_id.clone()))
+            .cloned()
+        else {
+            return Err(receipt);
+        };
+
+        let leg = if receipt.is_final {
+            messages.remove(&message_id)
+        } else {
+            messages.legs.get(&message_id).cloned()
+        };
+
+        drop(messages);
+
+        let Some(leg) = leg else {
+            return Err(receipt);
+        };
+
+        let mut deliver_sm = receipt.deliver_sm;
+
+        rewrite_receipt(&mut deliver_sm, &receipt.message_id, &message_id);
+
+        Ok(PendingReceipt {
+            system_id: leg.system_id,
+            session_id: leg.session_id,
+            deliver_sm,
+            created_at: receipt.received_at,
+        })
+    }
+
+    /// Returns the first route matching the message, or the status to reject the message with.
+    fn route(
+        &self,
+        system_id: &str,
+        service_type: &str,
+        destination: &str,
+    ) -> Result<&RouteState, CommandStatus> {
+        let Some(route) = self
+            .routes
+            .iter()
+            .find(|route| route.route.matches(system_id, service_type, destination))
+        else {
+            tracing::warn!(system_id, destination, "No route");
+
+            return Err(CommandStatus::EsmeRinvdstadr);
+        };
+
+        if let Some(max_tps) = route.route.max_tps {
+            let admitted = route
+                .window
+                .lock()
+                .expect("Route window lock poisoned")
+                .admit(max_tps, Instant::now());
+
+            if !admitted {
+                tracing::debug!(system_id, route = route.route.name, "Route throttled");
+
+                return Err(CommandStatus::EsmeRthrottled);
+            }
+        }
+
+        Ok(route)
+    }
+
+    /// Sends the request to the upstreams of the route in order, until one of them responds.
+    ///
+    /// Fails over only if the request was not sent, i.e. the upstream is not bound or the request could not be written.
+    /// An upstream failing afterwards, e.g. not responding in time, may have accepted the message, so the request is rejected with `ESME_RSYSERR`.
+    ///
+    /// Returns the index of the upstream and its response.
+    async fn forward<T, F, Fut>(
+        &self,
+        route: &RouteState,
+        request: F,
+    ) -> Result<(usize, T), CommandStatus>
+    where
+        F: Fn(Client) -> Fut,
+        Fut: Future<Output = Result<T, Error>>,
+    {
+        for &index in &route.upstreams {
+            let upstream = &self.upstreams[index];
+
+            let Some(client) = upstream.client(self.upstream_timeout).await else {
+                tracing::debug!(
+                    route = route.route.name,
+                    upstream = upstream.name(),
+                    "Upstream not bound, failing over"
+                );
+
+                continue;
+            };
+
+            match request(client).await {
+                Ok(response) => return Ok((index, response)),
+                Err(Error::UnexpectedResponse { response }) => return Err(response.status()),
+                Err(err) if is_unsent(&err) => {
+                    tracing::warn!(route = route.route.name, upstream = upstream.name(), %err, "Failed to send to upstream, failing over");
+                }
+                Err(err) => {
+                    tracing::error!(route = route.route.name, upstream = upstream.name(), %err, "Upstream failed");
+
+                    return Err(CommandStatus::EsmeRsyserr);
+                }
+            }
+        }
+
+        tracing::error!(route = route.route.name, "No upstream available");
+
+        Err(CommandStatus::EsmeRsyserr)
+    }
+
+    /// Sends the request to the upstream that accepted the message.
+    ///
+    /// Returns the `message_id` of the upstream and its response.
+    async fn follow<T, F, Fut>(
+        &self,
+        system_id: &str,
+        message_id: &str,
+        request: F,
+    ) -> Result<T, CommandStatus>
+    where
+        F: FnOnce(Client, COctetString<1, 65>) -> Fut,
+        Fut: Future<Output = Result<T, Error>>,
+    {
+        let (index, upstream_message_id) = {
+            let messages = self.messages.lock().expect("Messages lock poisoned");
+
+            let leg = messages
+                .leg(system_id, message_id)
+                .ok_or(CommandStatus::EsmeRinvmsgid)?;
+
+            (leg.upstream, leg.message_id.clone())
+        };
+
+        let upstream = &self.upstreams[index];
+
+        let client = upstream
+            .client(self.upstream_timeout)
+            .await
+            .ok_or(CommandStatus::EsmeRsyserr)?;
+
+        match request(client, to_message_id(&upstream_message_id)).await {
+            Ok(response) => Ok(response),
+            Err(Error::UnexpectedResponse { response }) => Err(response.status()),
+            Err(err) => {
+                tracing::warn!(upstream = upstream.name(), %err, "Upstream failed");
+
+                Err(CommandStatus::EsmeRsyserr)
+            }
+        }
+    }
+
+    /// Records the message accepted by the upstream and returns the `message_id` of the router.
+    fn accepted(
+        &self,
+        session: &RouterSession,
+        upstream: usize,
+        message_id: &COctetString<1, 65>,
+    ) -> COctetString<1, 65> {
+        let message_id = self
+            .messages
+            .lock()
+            .expect("Messages lock poisoned")
+            .insert(Leg {
+                system_id: session.system_id.clone(),
+                session_id: session.session_id,
+                upstream,
+                message_id: message_id.to_string(),
+                created_at: Instant::now(),
+            });
+
+        tracing::debug!(
+            session_id = session.session_id,
+            upstream = self.upstreams[upstream].name(),
+            message_id,
+            "Message forwarded"
+        );
+
+        to_message_id(&message_id)
+    }
+}
+
+impl Service for Router {
+    type Handler = RouterSession;
+
+    async fn bind(&self, bind: &Bind) -> Result<Bound<RouterSession>, CommandStatus> {
+        if let Some(delay) = self.inner.bind_delay {
+            tokio::time::sleep(delay).await;
+        }
+
+        let client = client::authenticate(&self.inner.clients, bind)?;
+
+        let session = RouterSession {
+            session_id: bind.session_id,
+            system_id: bind.system_id.clone(),
+            faults: Mutex::new(Faults::new(self.inner.faults.clone())),
+            router: self.inner.clone(),
+        };
+
+        Ok(Bound::new(session).max_sessions(client.and_then(|client| client.max_sessions)))
+    }
+}
+
+#[derive(Debug)]
+pub struct RouterSession {
+    session_id: u64,
+    system_id: String,
+    faults: Mutex<Faults>,
+    router: Arc<RouterInner>,
+}
+
+impl SessionHandler for RouterSession {
+    async fn request(&self, pdu: Pdu) -> Response {
+        let id = pdu.command_id();
+
+        let injection = self
+            .faults
+            .lock()
+            .expect("Faults lock poisoned")
+            .inject(&self.system_id, id);
+
+        let response = match injection.fault {
+            Some(fault) => fault.response(self.session_id, id),
+            None => self.respond(pdu).await,
+        };
+
+        // Dropped responses, disconnects and malformed responses are not delayed.
+        if let (Response::Send { .. }, Some(delay)) =
+            (&response, injection.delay.or(self.router.response_delay))
+        {
+            tokio::time::sleep(delay).await;
+        }
+
+        response
+    }
+}
+
+impl RouterSession {
+    /// Forwards the request to the upstreams.
+    async fn respond(&self, pdu: Pdu) -> Response {
+        let id = pdu.command_id();
+
+        let response = match pdu {
+            Pdu::SubmitSm(submit_sm) => self.submit_sm(submit_sm).await,
+            Pdu::DataSm(data_sm) => self.data_sm(data_sm).await,
+            Pdu::QuerySm(query_sm) => {
+                let message_id = query_sm.message_id.clone();
+
+                self.router
+                    .follow(
+                        &self.system_id,
+                        &message_id.to_string(),
+                        |client, upstream_message_id| async move {
+                            client
+                                .query_sm(rusmpp::pdus::QuerySm {
+                                    message_id: upstream_message_id,
+                                    ..query_sm
+                                })
+                                .await
+                        },
+                    )
+                    .await
+                    .map(|query_sm_resp| {
+                        rusmpp::pdus::QuerySmResp {
+                            message_id,
+                            ..query_sm_resp
+                        }
+                        .into()
+                    })
+            }
+            Pdu::CancelSm(cancel_sm) => {
+                let message_id = cancel_sm.message_id.to_string();
+
+                self.router
+                    .follow(
+                        &self.system_id,
+                        &message_id,
+                        |client, upstream_message_id| async move {
+                            client
+                                .cancel_sm(rusmpp::pdus::CancelSm {
+                                    message_id: upstream_message_id,
+                                    ..cancel_sm
+                                })
+                                .await
+                        },
+                    )
+                    .await
+                    .map(|_| Pdu::CancelSmResp)
+            }
+            Pdu::ReplaceSm(mut replace_sm) => {
+                let message_id = replace_sm.message_id.to_string();
+
+                self.router
+                    .follow(
+                        &self.system_id,
+                        &message_id,
+                        |client, upstream_message_id| async move {
+                            replace_sm.message_id = upstream_message_id;
+
+                            client.replace_sm(replace_sm).await
+                        },
+                    )
+                    .await
+                    .map(|_| Pdu::ReplaceSmResp)
+            }
+            _ if matches!(id, CommandId::Other(_)) => {
+                tracing::warn!(session_id = self.session_id, id=?id, "Received unknown command");
+
+                return Response::Send {
+                    status: CommandStatus::EsmeRinvcmdid,
+                    pdu: Pdu::GenericNack,
+                };
+            }
+            _ => {
+                tracing::warn!(session_id = self.session_id, id=?id, "Received command that is not routed");
+
+                return Response::error(id, CommandStatus::EsmeRsyserr);
+            }
+        };
+
+        match response {
+            Ok(pdu) => Response::ok(pdu),
+            Err(status) => Response::error(id, status),
+        }
+    }
+
+    async fn submit_sm(&self, submit_sm: rusmpp::pdus::SubmitSm) -> Result<Pdu, CommandStatus> {
+        let route = self.router.route(
+            &self.system_id,
+            &submit_sm.service_type.value().to_string(),
+            &submit_sm.destination_addr.to_string(),
+        )?;
+
+        let (upstream, mut submit_sm_resp) = self
+            .router
+            .forward(route, |client| {
+                let submit_sm = submit_sm.clone();
+
+                async move { client.submit_sm(submit_sm).await }
+            })
+            .await?;
+
+        submit_sm_resp.message_id =
+            self.router
+                .accepted(self, upstream, &submit_sm_resp.message_id);
+
+        Ok(submit_sm_resp.into())
+    }
+
+    async fn data_sm(&self, data_sm: rusmpp::pdus::DataSm) -> Result<Pdu, CommandStatus> {
+        let route = self.router.route(
+            &self.system_id,
+            &data_sm.service_type.value().to_string(),
+            &data_sm.destination_addr.to_string(),
+        )?;
+
+        let (upstream, mut data_sm_resp) = self
+            .router
+            .forward(route, |client| {
+                let data_sm = data_sm.clone();
+
+                async move { client.data_sm(data_sm).await }
+            })
+            .await?;
+
+        data_sm_resp.message_id = self
+            .router
+            .accepted(self, upstream, &data_sm_resp.message_id);
+
+        Ok(data_sm_resp.into())
+    }
+}
+
+/// Returns `true` if the request failed before being written to the upstream, so that failing over cannot send the message twice.
+fn is_unsent(err: &Error) -> bool {
+    matches!(
+        err,
+        Error::Connect(_)
+            | Error::Io(_)
+            | Error::Encode(_)
+            | Error::InvalidSessionState { .. }
+            | Error::ShuttingDown
+    )
+}
+
+/// Replaces the `message_id` of the upstream in the `receipted_message_id` TLV and the receipt text.
+fn rewrite_receipt(deliver_sm: &mut DeliverSm, from: &str, to: &str) {
+    let had_tlv = deliver_sm.receipted_message_id().is_some();
+
+    if had_tlv {
+        deliver_sm.retain_tlvs(|tlv| !matches!(tlv.value(), Some(TlvValue::ReceiptedMessageId(_))));
+        deliver_sm.push_tlv(MessageDeliveryRequestTlvValue::ReceiptedMessageId(
+            to_message_id(to),
+        ));
+    }
+
+    let text = std::str::from_utf8(deliver_sm.short_message().as_ref())
+        .ok()
+        .and_then(|text| replace_id(text, from, to))
+        .and_then(|text| OctetString::from_str(&text).ok());
+
+    if let Some(text) = text {
+        deliver_sm.set_short_message(text);
+    }
+}
+
+/// Replaces the `id` field of a receipt text of the form `id:123 sub:001 ... stat:DELIVRD err:000 text:...`.
+fn replace_id(text: &str, from: &str, to: &str) -> Option<String> {
+    let lower = text.to_ascii_lowercase();
+
+    let start = lower.match_indices("id:").find_map(|(index, _)| {
+        (index == 0 || lower.as_bytes()[index - 1] == b' ').then_some(index + 3)
+    })?;
+
+    let value = text[start..].split(' ').next().unwrap_or_default();
+
+    if value != from {
+        return None;
+    }
+
+    Some(format!(
+        "{}{to}{}",
+        &text[..start],
+        &text[start + value.len()..]
+    ))
+}
+
+fn to_message_id(message_id: &str) -> COctetString<1, 65> {
+    COctetString::from_str(message_id).expect("Must be valid message ID")
+}
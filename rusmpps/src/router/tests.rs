@@ -0,0 +1,418 @@
+use std::{
+    net::SocketAddr,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use rusmpp::{
+    CommandId,
+    pdus::{BroadcastSm, SubmitMulti, SubmitSm, SubmitSmResp},
+};
+
+use super::*;
+use crate::{
+    faults::StatusRate,
+    server::{Server, ServerParameters},
+};
+
+const SYSTEM_ID: &str = "esme";
+const SESSION_ID: u64 = 1;
+
+/// How an upstream `MC` answers `submit_sm`.
+#[derive(Debug, Clone, Copy)]
+enum Behavior {
+    Accept(&'static str),
+    Reject(CommandStatus),
+    Ignore,
+}
+
+#[derive(Debug, Clone)]
+struct Mc {
+    behavior: Behavior,
+    submissions: Arc<AtomicUsize>,
+}
+
+impl Service for Mc {
+    type Handler = Mc;
+
+    async fn bind(&self, _bind: &Bind) -> Result<Bound<Mc>, CommandStatus> {
+        Ok(Bound::new(self.clone()))
+    }
+}
+
+impl SessionHandler for Mc {
+    async fn request(&self, pdu: Pdu) -> Response {
+        let Pdu::SubmitSm(_) = pdu else {
+            return Response::Ignore;
+        };
+
+        self.submissions.fetch_add(1, Ordering::Relaxed);
+
+        match self.behavior {
+            Behavior::Accept(message_id) => Response::ok(
+                SubmitSmResp::builder()
+                    .message_id(to_message_id(message_id))
+                    .build(),
+            ),
+            Behavior::Reject(status) => Response::error(CommandId::SubmitSm, status),
+            Behavior::Ignore => Response::Ignore,
+        }
+    }
+}
+
+fn free_addr() -> SocketAddr {
+    std::net::TcpListener::bind("127.0.0.1:0")
+        .and_then(|listener| listener.local_addr())
+        .expect("Failed to find a free port")
+}
+
+fn upstream_config(name: &str, addr: SocketAddr) -> UpstreamConfig {
+    UpstreamConfig {
+        name: name.to_string(),
+        url: format!("smpp://{addr}"),
+        system_id: "router".to_string(),
+        password: "secret".to_string(),
+        system_type: String::new(),
+        tls_ca: None,
+        enquire_link_interval: Duration::from_secs(30),
+        reconnect_interval: Duration::from_millis(100),
+    }
+}
+
+/// Starts an upstream `MC` and returns its config and the number of `submit_sm` it received.
+fn upstream(name: &str, behavior: Behavior) -> (UpstreamConfig, Arc<AtomicUsize>) {
+    let addr = free_addr();
+    let submissions = Arc::new(AtomicUsize::new(0));
+
+    let server = Server::new(
+        ServerParameters {
+            enquire_link_interval: None,
+            enquire_link_response_timeout: Duration::from_secs(5),
+            enquire_link_response_delay: None,
+            session_timeout: Duration::from_secs(5),
+            window_size: 10,
+            response_timeout: Duration::from_secs(5),
+            socket_addr: addr,
+            tls: None,
+            websocket: None,
+            admin: None,
+        },
+        Mc {
+            behavior,
+            submissions: submissions.clone(),
+        },
+    );
+
+    tokio::spawn(server.run());
+
+    (upstream_config(name, addr), submissions)
+}
+
+fn router(upstreams: Vec<UpstreamConfig>, upstream_timeout: Duration) -> Router {
+    let route = Route {
+        name: "default".to_string(),
+        upstreams: upstreams
+            .iter()
+            .map(|upstream| upstream.name.clone())
+            .collect(),
+        ..Default::default()
+    };
+
+    Router::new(RouterParameters {
+        clients: Vec::new(),
+        config: RouterConfig {
+            upstreams,
+            routes: vec![route],
+            upstream_timeout,
+            message_ttl: default_message_ttl(),
+            receipt_retention: default_receipt_retention(),
+        },
+        bind_delay: None,
+        response_delay: None,
+        faults: Vec::new(),
+    })
+    .expect("Failed to create router")
+}
+
+/// Connects the upstreams and waits for the given ones to be bound.
+async fn connect(router: &Router, bound: &[usize]) {
+    let (tx, _rx) = mpsc::unbounded_channel();
+
+    for index in 0..router.inner.upstreams.len() {
+        tokio::spawn(router.inner.clone().connect(index, tx.clone()));
+    }
+
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while !bound
+            .iter()
+            .all(|&index| router.inner.upstreams[index].bound.load(Ordering::Relaxed))
+        {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("Timed out waiting for the upstreams to bind");
+}
+
+fn session(router: &Router, faults: Vec<FaultRule>) -> RouterSession {
+    RouterSession {
+        session_id: SESSION_ID,
+        system_id: SYSTEM_ID.to_string(),
+        faults: Mutex::new(Faults::new(faults)),
+        router: router.inner.clone(),
+    }
+}
+
+fn sent(response: Response) -> (CommandStatus, Pdu) {
+    match response {
+        Response::Send { status, pdu } => (status, pdu),
+        response => panic!("Expected a response, got {response:?}"),
+    }
+}
+
+fn receipt_text(message_id: &str) -> String {
+    format!(
+        "id:{message_id} sub:001 dlvrd:001 submit date:2601011200 done date:2601011201 stat:DELIVRD err:000 text:Hello"
+    )
+}
+
+fn receipt(message_id: &str) -> DeliverSm {
+    DeliverSm::builder()
+        .short_message(OctetString::from_str(&receipt_text(message_id)).unwrap())
+        .push_tlv(MessageDeliveryRequestTlvValue::ReceiptedMessageId(
+            to_message_id(message_id),
+        ))
+        .build()
+}
+
+fn upstream_receipt(message_id: &str, is_final: bool) -> UpstreamReceipt {
+    UpstreamReceipt {
+        upstream: 0,
+        message_id: message_id.to_string(),
+        is_final,
+        deliver_sm: receipt(message_id),
+        received_at: Instant::now(),
+    }
+}
+
+#[test]
+fn replace_id_should_replace_the_id_field_only() {
+    assert_eq!(
+        replace_id(&receipt_text("abc"), "abc", "0001"),
+        Some(receipt_text("0001"))
+    );
+    assert_eq!(
+        replace_id("ID:abc stat:DELIVRD", "abc", "0001"),
+        Some("ID:0001 stat:DELIVRD".to_string())
+    );
+    // `id:` inside another field is not the id field.
+    assert_eq!(
+        replace_id("msgid:abc id:abc", "abc", "0001"),
+        Some("msgid:abc id:0001".to_string())
+    );
+}
+
+#[test]
+fn replace_id_should_ignore_other_ids() {
+    assert_eq!(replace_id(&receipt_text("abcd"), "abc", "0001"), None);
+    assert_eq!(replace_id("stat:DELIVRD err:000", "abc", "0001"), None);
+}
+
+#[test]
+fn rewrite_receipt_should_replace_the_tlv_and_the_text() {
+    let mut deliver_sm = receipt("abc");
+
+    rewrite_receipt(&mut deliver_sm, "abc", "0001");
+
+    assert_eq!(
+        deliver_sm.receipted_message_id().map(ToString::to_string),
+        Some("0001".to_string())
+    );
+    assert_eq!(
+        deliver_sm.short_message().as_ref(),
+        receipt_text("0001").as_bytes()
+    );
+
+    let receipted_message_ids = deliver_sm
+        .tlvs()
+        .iter()
+        .filter(|tlv| matches!(tlv.value(), Some(TlvValue::ReceiptedMessageId(_))))
+        .count();
+
+    assert_eq!(receipted_message_ids, 1);
+}
+
+#[test]
+fn receipts_arriving_before_the_response_should_be_matched_once_the_message_is_recorded() {
+    let router = router(
+        vec![upstream_config("upstream", free_addr())],
+        Duration::from_secs(1),
+    );
+    let session = session(&router, Vec::new());
+
+    // The upstream sends the receipt before its submit_sm_resp is processed.
+    let receipt = router
+        .inner
+        .match_receipt(upstream_receipt("abc", false))
+        .expect_err("Receipt of an unknown message must be returned");
+
+    let message_id = router
+        .inner
+        .accepted(&session, 0, &to_message_id("abc"))
+        .to_string();
+
+    let pending = router
+        .inner
+        .match_receipt(receipt)
+        .expect("Receipt must be matched once the message is recorded");
+
+    assert_eq!(pending.system_id, SYSTEM_ID);
+    assert_eq!(pending.session_id, SESSION_ID);
+    assert_eq!(
+        pending
+            .deliver_sm
+            .receipted_message_id()
+            .map(ToString::to_string),
+        Some(message_id.clone())
+    );
+    assert_eq!(
+        pending.deliver_sm.short_message().as_ref(),
+        receipt_text(&message_id).as_bytes()
+    );
+
+    // The final receipt forgets the message.
+    assert!(
+        router
+            .inner
+            .match_receipt(upstream_receipt("abc", true))
+            .is_ok()
+    );
+    assert!(
+        router
+            .inner
+            .match_receipt(upstream_receipt("abc", true))
+            .is_err()
+    );
+}
+
+#[tokio::test]
+async fn faults_should_apply_before_forwarding() {
+    let (upstream, submissions) = upstream("upstream", Behavior::Accept("abc"));
+
+    let router = router(vec![upstream], Duration::from_secs(1));
+
+    connect(&router, &[0]).await;
+
+    let session = session(
+        &router,
+        vec![FaultRule {
+            statuses: vec![StatusRate {
+                status: CommandStatus::EsmeRthrottled,
+                rate: 1.0,
+            }],
+            ..Default::default()
+        }],
+    );
+
+    let (status, _) = sent(session.request(SubmitSm::default().into()).await);
+
+    assert_eq!(status, CommandStatus::EsmeRthrottled);
+    assert_eq!(submissions.load(Ordering::Relaxed), 0);
+}
+
+#[tokio::test]
+async fn submissions_should_fail_over_to_the_next_bound_upstream_in_order() {
+    // Nothing listens on the first upstream.
+    let down = upstream_config("down", free_addr());
+    let (first, first_submissions) = upstream("first", Behavior::Accept("first-id"));
+    let (second, second_submissions) = upstream("second", Behavior::Accept("second-id"));
+
+    let router = router(vec![down, first, second], Duration::from_secs(1));
+
+    connect(&router, &[1, 2]).await;
+
+    let session = session(&router, Vec::new());
+
+    let (status, pdu) = sent(session.request(SubmitSm::default().into()).await);
+
+    assert_eq!(status, CommandStatus::EsmeRok);
+
+    let Pdu::SubmitSmResp(submit_sm_resp) = pdu else {
+        panic!("Expected submit_sm_resp, got {pdu:?}");
+    };
+
+    let messages = router.inner.messages.lock().unwrap();
+    let leg = messages
+        .leg(SYSTEM_ID, &submit_sm_resp.message_id.to_string())
+        .expect("Message must be recorded");
+
+    assert_eq!(leg.upstream, 1);
+    assert_eq!(leg.message_id, "first-id");
+    assert_eq!(first_submissions.load(Ordering::Relaxed), 1);
+    assert_eq!(second_submissions.load(Ordering::Relaxed), 0);
+}
+
+#[tokio::test]
+async fn upstream_timeouts_should_not_fail_over() {
+    let (slow, slow_submissions) = upstream("slow", Behavior::Ignore);
+    let (next, next_submissions) = upstream("next", Behavior::Accept("next-id"));
+
+    let router = router(vec![slow, next], Duration::from_millis(300));
+
+    connect(&router, &[0, 1]).await;
+
+    let session = session(&router, Vec::new());
+
+    let (status, _) = sent(session.request(SubmitSm::default().into()).await);
+
+    // The slow upstream may have accepted the message.
+    assert_eq!(status, CommandStatus::EsmeRsyserr);
+    assert_eq!(slow_submissions.load(Ordering::Relaxed), 1);
+    assert_eq!(next_submissions.load(Ordering::Relaxed), 0);
+}
+
+#[tokio::test]
+async fn upstream_error_responses_should_be_passed_on() {
+    let (rejecting, _) = upstream("rejecting", Behavior::Reject(CommandStatus::EsmeRinvdstadr));
+    let (next, next_submissions) = upstream("next", Behavior::Accept("next-id"));
+
+    let router = router(vec![rejecting, next], Duration::from_secs(1));
+
+    connect(&router, &[0, 1]).await;
+
+    let session = session(&router, Vec::new());
+
+    let (status, pdu) = sent(session.request(SubmitSm::default().into()).await);
+
+    assert_eq!(status, CommandStatus::EsmeRinvdstadr);
+    assert!(matches!(pdu, Pdu::SubmitSmResp(_)));
+    assert_eq!(next_submissions.load(Ordering::Relaxed), 0);
+}
+
+#[tokio::test]
+async fn unrouted_commands_should_be_answered_with_their_response() {
+    let router = router(
+        vec![upstream_config("upstream", free_addr())],
+        Duration::from_secs(1),
+    );
+    let session = session(&router, Vec::new());
+
+    let (status, pdu) = sent(session.request(SubmitMulti::default().into()).await);
+
+    assert_eq!(status, CommandStatus::EsmeRsyserr);
+    assert!(matches!(pdu, Pdu::SubmitMultiResp(_)));
+
+    let (status, pdu) = sent(session.request(BroadcastSm::default().into()).await);
+
+    assert_eq!(status, CommandStatus::EsmeRsyserr);
+    assert!(matches!(pdu, Pdu::BroadcastSmResp(_)));
+
+    let unknown = Pdu::Other {
+        command_id: CommandId::Other(0x0000_1234),
+        body: Default::default(),
+    };
+
+    let (status, pdu) = sent(session.request(unknown).await);
+
+    assert_eq!(status, CommandStatus::EsmeRinvcmdid);
+    assert!(matches!(pdu, Pdu::GenericNack));
+}
@@ -2,52 +2,77 @@ use std::{net::SocketAddr, sync::Arc, time::Duration};
 
 use anyhow::Context;
 
-use tokio::net::TcpListener;
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    net::{TcpListener, TcpStream},
+};
 
 use crate::{
-    client::{Client, ConnectedClients},
+    admin::{self, AdminConfig},
+    client::ConnectedClients,
     connection::{Connection, ConnectionConfig},
+    control::Control,
+    service::Service,
+    tls::{ReloadingTlsAcceptor, TlsConfig},
+    websocket::{WebSocketConfig, WebSocketIo},
 };
 
 #[derive(Debug)]
 pub struct ServerParameters {
-    pub clients: Vec<Client>,
     pub enquire_link_interval: Option<Duration>,
     pub enquire_link_response_timeout: Duration,
     pub enquire_link_response_delay: Option<Duration>,
     pub session_timeout: Duration,
-    pub bind_delay: Option<Duration>,
-    pub response_delay: Option<Duration>,
+    pub window_size: usize,
+    pub response_timeout: Duration,
     pub socket_addr: SocketAddr,
+    /// Accepts TLS connections alongside the plain ones.
+    pub tls: Option<TlsConfig>,
+    /// Accepts `WebSocket` connections alongside the plain ones.
+    pub websocket: Option<WebSocketConfig>,
+    /// Serves the [`Control`] API over `HTTP`.
+    pub admin: Option<AdminConfig>,
 }
 
 #[derive(Debug)]
-pub struct Server {
+pub struct Server<S> {
     config: Arc<ConnectionConfig>,
+    service: Arc<S>,
     socket_addr: SocketAddr,
+    tls: Option<TlsConfig>,
+    websocket: Option<WebSocketConfig>,
+    admin: Option<AdminConfig>,
     session_id: u64,
 }
 
-impl Server {
-    pub fn new(parameters: ServerParameters) -> Self {
+impl<S: Service> Server<S> {
+    pub fn new(parameters: ServerParameters, service: S) -> Self {
         let config = Arc::new(ConnectionConfig {
             connected_clients: ConnectedClients::new(),
-            clients: parameters.clients,
             enquire_link_interval: parameters.enquire_link_interval,
             enquire_link_response_timeout: parameters.enquire_link_response_timeout,
             session_timeout: parameters.session_timeout,
-            bind_delay: parameters.bind_delay,
-            response_delay: parameters.response_delay,
             enquire_link_response_delay: parameters.enquire_link_response_delay,
+            window_size: parameters.window_size,
+            response_timeout: parameters.response_timeout,
         });
 
         Self {
             config,
+            service: Arc::new(service),
             socket_addr: parameters.socket_addr,
+            tls: parameters.tls,
+            websocket: parameters.websocket,
+            admin: parameters.admin,
             session_id: 0,
         }
     }
 
+    /// Returns a handle to inspect and drive the sessions of the server.
+    pub fn control(&self) -> Control {
+        Control::new(self.config.clone())
+    }
+
     fn next_session_id(&mut self) -> u64 {
         let session_id = self.session_id;
 
@@ -63,23 +88,161 @@ impl Server {
 
         tracing::info!(socket_addr=%self.socket_addr, "Listening");
 
-        loop {
-            let (stream, addr) = listener
-                .accept()
+        let tls = match self.tls.take() {
+            Some(config) => {
+                let socket_addr = config.socket_addr;
+
+                let acceptor = Arc::new(
+                    ReloadingTlsAcceptor::load(config)
+                        .context("Failed to load TLS certificates")?,
+                );
+
+                let listener = TcpListener::bind(socket_addr)
+                    .await
+                    .context("Failed to bind TLS listener")?;
+
+                tracing::info!(%socket_addr, "Listening for TLS connections");
+
+                tokio::spawn(acceptor.clone().watch());
+
+                Some((listener, acceptor))
+            }
+            None => None,
+        };
+
+        let websocket = match self.websocket.take() {
+            Some(config) => {
+                let acceptor = match config.tls {
+                    true => Some(
+                        tls.as_ref()
+                            .map(|(_, acceptor)| acceptor.clone())
+                            .context("WebSocket TLS requires the TLS listener to be configured")?,
+                    ),
+                    false => None,
+                };
+
+                let listener = TcpListener::bind(config.socket_addr)
+                    .await
+                    .context("Failed to bind WebSocket listener")?;
+
+                tracing::info!(socket_addr=%config.socket_addr, tls=config.tls, "Listening for WebSocket connections");
+
+                Some((listener, acceptor))
+            }
+            None => None,
+        };
+
+        if let Some(config) = self.admin.take() {
+            let listener = TcpListener::bind(config.socket_addr)
                 .await
-                .context("Failed to accept connection")?;
+                .context("Failed to bind admin listener")?;
+
+            tracing::info!(socket_addr=%config.socket_addr, "Listening for admin requests");
+
+            let control = self.control();
+
+            tokio::spawn(async move {
+                if let Err(err) = admin::serve(listener, control).await {
+                    tracing::error!(?err, "Admin listener failed");
+                }
+            });
+        }
+
+        loop {
+            let (stream, addr, acceptor, websocket) = tokio::select! {
+                accepted = listener.accept() => {
+                    let (stream, addr) = accepted.context("Failed to accept connection")?;
+
+                    (stream, addr, None, false)
+                }
+                accepted = accept(tls.as_ref().map(|(listener, _)| listener)) => {
+                    let (stream, addr) = accepted.context("Failed to accept TLS connection")?;
+
+                    (stream, addr, tls.as_ref().map(|(_, acceptor)| acceptor.acceptor()), false)
+                }
+                accepted = accept(websocket.as_ref().map(|(listener, _)| listener)) => {
+                    let (stream, addr) = accepted.context("Failed to accept WebSocket connection")?;
+
+                    let acceptor = websocket
+                        .as_ref()
+                        .and_then(|(_, acceptor)| acceptor.as_ref())
+                        .map(|acceptor| acceptor.acceptor());
+
+                    (stream, addr, acceptor, true)
+                }
+            };
 
             let session_id = self.next_session_id();
 
-            tracing::info!(%addr, session_id, "Accepted connection");
+            tracing::info!(%addr, session_id, tls = acceptor.is_some(), websocket, "Accepted connection");
+
+            let connection =
+                Connection::new(session_id, addr, self.config.clone(), self.service.clone());
 
-            let connection = Connection::new(session_id, self.config.clone());
+            let handshake_timeout = self.config.session_timeout;
 
             tokio::spawn(async move {
-                connection.run(stream).await;
+                match acceptor {
+                    None => serve(connection, stream, websocket, handshake_timeout).await,
+                    Some(acceptor) => {
+                        match tokio::time::timeout(handshake_timeout, acceptor.accept(stream)).await
+                        {
+                            Ok(Ok(stream)) => {
+                                tracing::debug!(session_id, "TLS handshake completed");
+
+                                serve(connection, stream, websocket, handshake_timeout).await
+                            }
+                            Ok(Err(err)) => {
+                                tracing::warn!(session_id, %err, "TLS handshake failed");
+                            }
+                            Err(_) => {
+                                tracing::warn!(session_id, "TLS handshake timeout reached");
+                            }
+                        }
+                    }
+                }
 
                 tracing::info!(%addr, session_id, "Connection closed");
             });
         }
     }
 }
+
+/// Accepts a connection on an optional listener, never completes if there is none.
+async fn accept(listener: Option<&TcpListener>) -> std::io::Result<(TcpStream, SocketAddr)> {
+    match listener {
+        Some(listener) => listener.accept().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Runs the connection over the stream, after the `WebSocket` handshake if required.
+async fn serve<S, T>(
+    connection: Connection<S>,
+    stream: T,
+    websocket: bool,
+    handshake_timeout: Duration,
+) where
+    S: Service,
+    T: AsyncRead + AsyncWrite + Send + Unpin + 'static,
+{
+    if !websocket {
+        return connection.run(stream).await;
+    }
+
+    let session_id = connection.session_id();
+
+    match tokio::time::timeout(handshake_timeout, tokio_tungstenite::accept_async(stream)).await {
+        Ok(Ok(stream)) => {
+            tracing::debug!(session_id, "WebSocket handshake completed");
+
+            connection.run(WebSocketIo::new(stream)).await
+        }
+        Ok(Err(err)) => {
+            tracing::warn!(session_id, %err, "WebSocket handshake failed");
+        }
+        Err(_) => {
+            tracing::warn!(session_id, "WebSocket handshake timeout reached");
+        }
+    }
+}
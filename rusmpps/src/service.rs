@@ -0,0 +1,135 @@
+use std::net::SocketAddr;
+
+use rusmpp::{
+    CommandId, CommandStatus, Pdu,
+    pdus::{
+        BroadcastSmResp, DataSmResp, DeliverSmResp, QueryBroadcastSmResp, QuerySmResp,
+        SubmitMultiResp, SubmitSmResp,
+    },
+    values::InterfaceVersion,
+};
+
+use crate::bind_mode::BindMode;
+
+/// A bind received from an `ESME`.
+#[derive(Debug, Clone)]
+pub struct Bind {
+    pub session_id: u64,
+    pub addr: SocketAddr,
+    pub bind_mode: BindMode,
+    pub system_id: String,
+    pub password: String,
+    pub system_type: String,
+    pub interface_version: InterfaceVersion,
+}
+
+/// An accepted bind.
+#[derive(Debug)]
+pub struct Bound<H> {
+    pub handler: H,
+    /// Maximum number of concurrent sessions of the `system_id`. Unlimited if not set.
+    pub max_sessions: Option<usize>,
+}
+
+impl<H> Bound<H> {
+    pub fn new(handler: H) -> Self {
+        Self {
+            handler,
+            max_sessions: None,
+        }
+    }
+
+    pub fn max_sessions(mut self, max_sessions: Option<usize>) -> Self {
+        self.max_sessions = max_sessions;
+        self
+    }
+}
+
+/// The answer of a [`SessionHandler`] to a request.
+#[derive(Debug)]
+#[allow(clippy::large_enum_variant)]
+pub enum Response {
+    /// Responds with the status and PDU.
+    Send { status: CommandStatus, pdu: Pdu },
+    /// Does not respond.
+    Ignore,
+    /// Closes the connection without responding.
+    Disconnect,
+    /// Responds with a PDU whose body cannot be decoded.
+    Malformed,
+}
+
+impl Response {
+    pub fn ok(pdu: impl Into<Pdu>) -> Self {
+        Self::Send {
+            status: CommandStatus::EsmeRok,
+            pdu: pdu.into(),
+        }
+    }
+
+    /// Rejects the request with the given status.
+    ///
+    /// Responds with the default response of the request, or a [`Pdu::GenericNack`] for requests without a matching response.
+    pub fn error(id: CommandId, status: CommandStatus) -> Self {
+        let pdu = match id {
+            CommandId::SubmitSm => SubmitSmResp::default().into(),
+            CommandId::DataSm => DataSmResp::default().into(),
+            CommandId::SubmitMulti => SubmitMultiResp::default().into(),
+            CommandId::DeliverSm => DeliverSmResp::default().into(),
+            CommandId::QuerySm => QuerySmResp::default().into(),
+            CommandId::CancelSm => Pdu::CancelSmResp,
+            CommandId::ReplaceSm => Pdu::ReplaceSmResp,
+            CommandId::BroadcastSm => BroadcastSmResp::default().into(),
+            CommandId::QueryBroadcastSm => QueryBroadcastSmResp::default().into(),
+            CommandId::CancelBroadcastSm => Pdu::CancelBroadcastSmResp,
+            CommandId::Unbind => Pdu::UnbindResp,
+            _ => Pdu::GenericNack,
+        };
+
+        Self::Send { status, pdu }
+    }
+}
+
+/// The `MC` behavior of a [`Server`](crate::server::Server).
+///
+/// The server owns the framing, `enquire_link` and `unbind` handling, session state enforcement and windowing.
+/// The service authenticates binds and creates a [`SessionHandler`] for every bound session.
+pub trait Service: Send + Sync + 'static {
+    type Handler: SessionHandler;
+
+    /// Authenticates the bind.
+    ///
+    /// The bind is rejected with the returned status on error.
+    fn bind(
+        &self,
+        bind: &Bind,
+    ) -> impl Future<Output = Result<Bound<Self::Handler>, CommandStatus>> + Send;
+}
+
+/// Handles the commands of a bound session.
+pub trait SessionHandler: Send + Sync + 'static {
+    /// Handles a request allowed in the current session state.
+    ///
+    /// Requests are handled concurrently, up to the window size of the server.
+    /// A request whose handler panics is answered with `ESME_RSYSERR`.
+    fn request(&self, pdu: Pdu) -> impl Future<Output = Response> + Send;
+
+    /// Called with the responses to the commands sent to the `ESME`, e.g. a `deliver_sm_resp`.
+    ///
+    /// The `sequence_number` matches the one returned when sending the command, e.g. by [`Control::deliver_sm`](crate::control::Control::deliver_sm).
+    fn response(
+        &self,
+        sequence_number: u32,
+        status: CommandStatus,
+        pdu: Pdu,
+    ) -> impl Future<Output = ()> + Send {
+        let _ = (sequence_number, status, pdu);
+
+        async {}
+    }
+
+    /// Called once the session is closed.
+    fn closed(&self) -> impl Future<Output = ()> + Send {
+        async {}
+    }
+}
@@ -0,0 +1,288 @@
+use std::{
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use rusmpp::{
+    CommandId, CommandStatus, Pdu,
+    pdus::{
+        BroadcastSmResp, DataSmResp, QueryBroadcastSmResp, QuerySmResp, SubmitMulti,
+        SubmitMultiResp, SubmitSmResp,
+    },
+    tlvs::QueryBroadcastResponseTlvValue,
+    types::{COctetString, EmptyOrFullCOctetString},
+    values::{
+        BroadcastAreaIdentifier, BroadcastAreaSuccess, DestAddressValue, MessageState, Npi, Ton,
+        UnsuccessSme,
+    },
+};
+
+use crate::{
+    client::{self, Client},
+    faults::{FaultRule, Faults},
+    service::{Bind, Bound, Response, Service, SessionHandler},
+    smpp_time,
+    store::{Address, Cancellation, Lifecycle, MessageStore, Replacement, Submission},
+};
+
+#[cfg(test)]
+mod tests;
+
+#[derive(Debug)]
+pub struct SimulatorParameters {
+    pub clients: Vec<Client>,
+    pub message_store: Arc<MessageStore>,
+    pub bind_delay: Option<Duration>,
+    pub response_delay: Option<Duration>,
+    pub faults: Vec<FaultRule>,
+}
+
+/// The simulated `MC`: authenticates the configured accounts, stores the submitted messages and injects faults.
+#[derive(Debug)]
+pub struct Simulator {
+    clients: Vec<Client>,
+    message_store: Arc<MessageStore>,
+    bind_delay: Option<Duration>,
+    response_delay: Option<Duration>,
+    faults: Vec<FaultRule>,
+}
+
+impl Simulator {
+    pub fn new(parameters: SimulatorParameters) -> Self {
+        Self {
+            clients: parameters.clients,
+            message_store: parameters.message_store,
+            bind_delay: parameters.bind_delay,
+            response_delay: parameters.response_delay,
+            faults: parameters.faults,
+        }
+    }
+}
+
+impl Service for Simulator {
+    type Handler = SimulatorSession;
+
+    async fn bind(&self, bind: &Bind) -> Result<Bound<SimulatorSession>, CommandStatus> {
+        if let Some(delay) = self.bind_delay {
+            tokio::time::sleep(delay).await;
+        }
+
+        let client = client::authenticate(&self.clients, bind)?;
+
+        let session = SimulatorSession {
+            session_id: bind.session_id,
+            system_id: bind.system_id.clone(),
+            lifecycle: client.map(|client| client.lifecycle).unwrap_or_default(),
+            message_store: self.message_store.clone(),
+            response_delay: self.response_delay,
+            faults: Mutex::new(Faults::new(self.faults.clone())),
+        };
+
+        Ok(Bound::new(session).max_sessions(client.and_then(|client| client.max_sessions)))
+    }
+}
+
+#[derive(Debug)]
+pub struct SimulatorSession {
+    session_id: u64,
+    system_id: String,
+    lifecycle: Lifecycle,
+    message_store: Arc<MessageStore>,
+    response_delay: Option<Duration>,
+    faults: Mutex<Faults>,
+}
+
+impl SessionHandler for SimulatorSession {
+    async fn request(&self, pdu: Pdu) -> Response {
+        let session_id = self.session_id;
+        let id = pdu.command_id();
+
+        let injection = self
+            .faults
+            .lock()
+            .expect("Faults lock poisoned")
+            .inject(&self.system_id, id);
+
+        let response = match injection.fault {
+            Some(fault) => fault.response(session_id, id),
+            None => self.respond(id, pdu).unwrap_or_else(|| {
+                tracing::warn!(session_id, id=?id, "Received unsupported command");
+
+                Response::Send {
+                    status: CommandStatus::EsmeRinvcmdid,
+                    pdu: Pdu::GenericNack,
+                }
+            }),
+        };
+
+        // Dropped responses, disconnects and malformed responses are not delayed.
+        if let (Response::Send { .. }, Some(delay)) =
+            (&response, injection.delay.or(self.response_delay))
+        {
+            tokio::time::sleep(delay).await;
+        }
+
+        response
+    }
+}
+
+impl SimulatorSession {
+    /// Builds the response to a request allowed in the current bind mode.
+    ///
+    /// Returns [`None`] if the request is not supported.
+    fn respond(&self, id: CommandId, pdu: Pdu) -> Option<Response> {
+        let system_id = self.system_id.as_str();
+        let lifecycle = self.lifecycle;
+        let store = &self.message_store;
+
+        let response = match pdu {
+            Pdu::SubmitSm(submit_sm) => store
+                .submit(Submission::submit_sm(system_id, &submit_sm).lifecycle(lifecycle))
+                .map(|message_id| {
+                    SubmitSmResp::builder()
+                        .message_id(to_message_id(&message_id))
+                        .build()
+                        .into()
+                }),
+            Pdu::DataSm(data_sm) => store
+                .submit(Submission::data_sm(system_id, &data_sm).lifecycle(lifecycle))
+                .map(|message_id| {
+                    DataSmResp::builder()
+                        .message_id(to_message_id(&message_id))
+                        .build()
+                        .into()
+                }),
+            Pdu::SubmitMulti(submit_multi) => {
+                submit_multi_response(system_id, lifecycle, &submit_multi, store).map(Into::into)
+            }
+            Pdu::QuerySm(query_sm) => store
+                .query(
+                    system_id,
+                    &query_sm.message_id.to_string(),
+                    &Address::new(
+                        query_sm.source_addr_ton,
+                        query_sm.source_addr_npi,
+                        query_sm.source_addr.to_string(),
+                    ),
+                )
+                .map(|message| {
+                    let final_date = message
+                        .final_date
+                        .map(smpp_time::format)
+                        .map(|final_date| {
+                            EmptyOrFullCOctetString::from_str(&final_date)
+                                .expect("Must be valid final date")
+                        })
+                        .unwrap_or_default();
+
+                    QuerySmResp::builder()
+                        .message_id(query_sm.message_id)
+                        .final_date(final_date)
+                        .message_state(message.state)
+                        .build()
+                        .into()
+                }),
+            Pdu::CancelSm(cancel_sm) => store
+                .cancel(system_id, Cancellation::from(cancel_sm))
+                .map(|_| Pdu::CancelSmResp),
+            Pdu::ReplaceSm(replace_sm) => store
+                .replace(system_id, Replacement::from(replace_sm))
+                .map(|_| Pdu::ReplaceSmResp),
+            Pdu::BroadcastSm(_) => Ok(BroadcastSmResp::builder()
+                .message_id(to_message_id(&store.next_message_id()))
+                .build()
+                .into()),
+            Pdu::QueryBroadcastSm(query_broadcast_sm) => Ok(QueryBroadcastSmResp::builder()
+                .message_id(query_broadcast_sm.message_id)
+                .tlvs(vec![
+                    QueryBroadcastResponseTlvValue::MessageState(MessageState::Enroute),
+                    QueryBroadcastResponseTlvValue::BroadcastAreaIdentifier(
+                        BroadcastAreaIdentifier::default(),
+                    ),
+                    QueryBroadcastResponseTlvValue::BroadcastAreaSuccess(
+                        BroadcastAreaSuccess::InformationNotAvailable,
+                    ),
+                ])
+                .build()
+                .into()),
+            Pdu::CancelBroadcastSm(_) => Ok(Pdu::CancelBroadcastSmResp),
+            _ => return None,
+        };
+
+        Some(match response {
+            Ok(pdu) => Response::ok(pdu),
+            Err(status) => Response::error(id, status),
+        })
+    }
+}
+
+fn to_message_id(message_id: &str) -> COctetString<1, 65> {
+    COctetString::from_str(message_id).expect("Must be valid message ID")
+}
+
+/// Stores the message for the first valid SME address.
+///
+/// The store tracks a single destination per message, the other valid SME addresses are reported back
+/// as unsuccessful destinations with `ESME_RINVNUMDESTS`. Distribution lists are not supported and empty addresses are invalid,
+/// both are reported back as unsuccessful destinations too.
+fn submit_multi_response(
+    system_id: &str,
+    lifecycle: Lifecycle,
+    submit_multi: &SubmitMulti,
+    store: &MessageStore,
+) -> Result<SubmitMultiResp, CommandStatus> {
+    let mut destination = None;
+
+    let unsuccess_sme = submit_multi
+        .dest_address()
+        .iter()
+        .filter_map(|dest_address| match dest_address.value() {
+            DestAddressValue::SmeAddress(sme_address)
+                if sme_address.destination_addr.is_empty() =>
+            {
+                Some(UnsuccessSme::new(
+                    sme_address.dest_addr_ton,
+                    sme_address.dest_addr_npi,
+                    sme_address.destination_addr.clone(),
+                    CommandStatus::EsmeRinvdstadr,
+                ))
+            }
+            DestAddressValue::SmeAddress(sme_address) if destination.is_some() => {
+                Some(UnsuccessSme::new(
+                    sme_address.dest_addr_ton,
+                    sme_address.dest_addr_npi,
+                    sme_address.destination_addr.clone(),
+                    CommandStatus::EsmeRinvnumdests,
+                ))
+            }
+            DestAddressValue::SmeAddress(sme_address) => {
+                destination = Some(Address::new(
+                    sme_address.dest_addr_ton,
+                    sme_address.dest_addr_npi,
+                    sme_address.destination_addr.to_string(),
+                ));
+
+                None
+            }
+            DestAddressValue::DistributionListName(distribution_list) => Some(UnsuccessSme::new(
+                Ton::Unknown,
+                Npi::Unknown,
+                distribution_list.dl_name.clone(),
+                CommandStatus::EsmeRinvdlname,
+            )),
+        })
+        .collect();
+
+    let message_id = match destination {
+        Some(destination) => to_message_id(&store.submit(
+            Submission::submit_multi(system_id, submit_multi, destination).lifecycle(lifecycle),
+        )?),
+        None => COctetString::default(),
+    };
+
+    Ok(SubmitMultiResp::builder()
+        .message_id(message_id)
+        .unsuccess_sme(unsuccess_sme)
+        .build())
+}
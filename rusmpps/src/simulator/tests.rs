@@ -0,0 +1,414 @@
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+
+use futures::{SinkExt, StreamExt};
+use rusmpp::{
+    Command,
+    pdus::{
+        BindReceiver, BindTransmitter, BroadcastSm, CancelSm, DataSm, DeliverSmResp,
+        QueryBroadcastSm, QuerySm, ReplaceSm, SubmitMulti, SubmitSm,
+    },
+    tlvs::{MessageSubmissionRequestTlvValue, TlvValue},
+    tokio_codec::CommandCodec,
+    types::{AnyOctetString, OctetString},
+    values::{DestAddress, DistributionListName, MessagePayload, SmeAddress},
+};
+use tokio::io::DuplexStream;
+use tokio_util::codec::Framed;
+
+use super::*;
+use crate::{
+    client::ConnectedClients,
+    connection::{Connection, ConnectionConfig},
+    store::MessageStoreConfig,
+};
+
+const SYSTEM_ID: &str = "esme";
+
+fn session() -> SimulatorSession {
+    SimulatorSession {
+        session_id: 1,
+        system_id: SYSTEM_ID.to_string(),
+        lifecycle: Lifecycle::default(),
+        message_store: Arc::new(MessageStore::new(MessageStoreConfig::default())),
+        response_delay: None,
+        faults: Mutex::new(Faults::new(Vec::new())),
+    }
+}
+
+fn sent(response: Response) -> (CommandStatus, Pdu) {
+    match response {
+        Response::Send { status, pdu } => (status, pdu),
+        response => panic!("Expected a response, got {response:?}"),
+    }
+}
+
+fn sme_address(addr: &str) -> DestAddress {
+    DestAddress::new(SmeAddress::new(
+        Ton::International,
+        Npi::Isdn,
+        COctetString::from_str(addr).unwrap(),
+    ))
+}
+
+fn unsuccess_sme(addr: &str, ton: Ton, npi: Npi, status: CommandStatus) -> UnsuccessSme {
+    UnsuccessSme::new(ton, npi, COctetString::from_str(addr).unwrap(), status)
+}
+
+#[tokio::test]
+async fn submit_multi_should_store_the_first_sme_address_and_report_the_others() {
+    let session = session();
+
+    let submit_multi = SubmitMulti::builder()
+        .dest_address(vec![
+            DestAddress::new(DistributionListName::new(
+                COctetString::from_str("friends").unwrap(),
+            )),
+            sme_address("491701111111"),
+            sme_address("491702222222"),
+        ])
+        .build();
+
+    let (status, pdu) = sent(session.request(submit_multi.into()).await);
+
+    assert_eq!(status, CommandStatus::EsmeRok);
+
+    let Pdu::SubmitMultiResp(submit_multi_resp) = pdu else {
+        panic!("Expected submit_multi_resp, got {pdu:?}");
+    };
+
+    assert_eq!(
+        submit_multi_resp.unsuccess_sme(),
+        [
+            unsuccess_sme(
+                "friends",
+                Ton::Unknown,
+                Npi::Unknown,
+                CommandStatus::EsmeRinvdlname
+            ),
+            unsuccess_sme(
+                "491702222222",
+                Ton::International,
+                Npi::Isdn,
+                CommandStatus::EsmeRinvnumdests
+            ),
+        ]
+    );
+
+    let message = session
+        .message_store
+        .query(
+            SYSTEM_ID,
+            &submit_multi_resp.message_id.to_string(),
+            &Address::new(Ton::Unknown, Npi::Unknown, ""),
+        )
+        .expect("The message must be stored");
+
+    assert_eq!(message.destination.addr, "491701111111");
+}
+
+#[tokio::test]
+async fn submit_multi_should_report_empty_addresses() {
+    let session = session();
+
+    let submit_multi = SubmitMulti::builder()
+        .dest_address(vec![
+            DestAddress::new(SmeAddress::new(
+                Ton::Unknown,
+                Npi::Unknown,
+                COctetString::default(),
+            )),
+            sme_address("491701111111"),
+        ])
+        .build();
+
+    let (status, pdu) = sent(session.request(submit_multi.into()).await);
+
+    assert_eq!(status, CommandStatus::EsmeRok);
+
+    let Pdu::SubmitMultiResp(submit_multi_resp) = pdu else {
+        panic!("Expected submit_multi_resp, got {pdu:?}");
+    };
+
+    assert!(!submit_multi_resp.message_id.is_empty());
+    assert_eq!(
+        submit_multi_resp.unsuccess_sme(),
+        [unsuccess_sme(
+            "",
+            Ton::Unknown,
+            Npi::Unknown,
+            CommandStatus::EsmeRinvdstadr
+        )]
+    );
+}
+
+#[tokio::test]
+async fn submit_multi_without_sme_address_should_not_store_a_message() {
+    let session = session();
+
+    let submit_multi = SubmitMulti::builder()
+        .dest_address(vec![DestAddress::new(DistributionListName::new(
+            COctetString::from_str("friends").unwrap(),
+        ))])
+        .build();
+
+    let (status, pdu) = sent(session.request(submit_multi.into()).await);
+
+    assert_eq!(status, CommandStatus::EsmeRok);
+
+    let Pdu::SubmitMultiResp(submit_multi_resp) = pdu else {
+        panic!("Expected submit_multi_resp, got {pdu:?}");
+    };
+
+    assert!(submit_multi_resp.message_id.is_empty());
+    assert_eq!(submit_multi_resp.unsuccess_sme().len(), 1);
+}
+
+#[tokio::test]
+async fn data_sm_should_store_the_message_payload() {
+    let session = session();
+
+    let data_sm = DataSm::builder()
+        .destination_addr(COctetString::from_str("491701111111").unwrap())
+        .push_tlv(MessageSubmissionRequestTlvValue::MessagePayload(
+            MessagePayload::new(AnyOctetString::from_vec(b"Hello".to_vec())),
+        ))
+        .build();
+
+    let (status, pdu) = sent(session.request(data_sm.into()).await);
+
+    assert_eq!(status, CommandStatus::EsmeRok);
+
+    let Pdu::DataSmResp(data_sm_resp) = pdu else {
+        panic!("Expected data_sm_resp, got {pdu:?}");
+    };
+
+    let message = session
+        .message_store
+        .query(
+            SYSTEM_ID,
+            &data_sm_resp.message_id.to_string(),
+            &Address::new(Ton::Unknown, Npi::Unknown, ""),
+        )
+        .expect("The message must be stored");
+
+    assert_eq!(message.destination.addr, "491701111111");
+    assert_eq!(message.short_message, b"Hello");
+}
+
+/// Submits a message and returns its id.
+async fn submit(session: &SimulatorSession) -> COctetString<1, 65> {
+    let (status, pdu) = sent(session.request(SubmitSm::default().into()).await);
+
+    assert_eq!(status, CommandStatus::EsmeRok);
+
+    let Pdu::SubmitSmResp(submit_sm_resp) = pdu else {
+        panic!("Expected submit_sm_resp, got {pdu:?}");
+    };
+
+    submit_sm_resp.message_id
+}
+
+async fn query(session: &SimulatorSession, message_id: &COctetString<1, 65>) -> MessageState {
+    let query_sm = QuerySm::builder().message_id(message_id.clone()).build();
+
+    let (status, pdu) = sent(session.request(query_sm.into()).await);
+
+    assert_eq!(status, CommandStatus::EsmeRok);
+
+    let Pdu::QuerySmResp(query_sm_resp) = pdu else {
+        panic!("Expected query_sm_resp, got {pdu:?}");
+    };
+
+    assert_eq!(&query_sm_resp.message_id, message_id);
+
+    query_sm_resp.message_state
+}
+
+#[tokio::test]
+async fn query_sm_should_report_the_message_state() {
+    let session = session();
+
+    let message_id = submit(&session).await;
+
+    assert_eq!(query(&session, &message_id).await, MessageState::Enroute);
+
+    let query_sm = QuerySm::builder()
+        .message_id(COctetString::from_str("unknown").unwrap())
+        .build();
+
+    let (status, pdu) = sent(session.request(query_sm.into()).await);
+
+    assert_eq!(status, CommandStatus::EsmeRqueryfail);
+    assert!(matches!(pdu, Pdu::QuerySmResp(_)));
+}
+
+#[tokio::test]
+async fn cancel_sm_should_delete_the_message_once() {
+    let session = session();
+
+    let message_id = submit(&session).await;
+
+    let cancel_sm = CancelSm::builder().message_id(message_id.clone()).build();
+
+    let (status, pdu) = sent(session.request(cancel_sm.clone().into()).await);
+
+    assert_eq!(status, CommandStatus::EsmeRok);
+    assert!(matches!(pdu, Pdu::CancelSmResp));
+    assert_eq!(query(&session, &message_id).await, MessageState::Deleted);
+
+    let (status, pdu) = sent(session.request(cancel_sm.into()).await);
+
+    assert_eq!(status, CommandStatus::EsmeRcancelfail);
+    assert!(matches!(pdu, Pdu::CancelSmResp));
+}
+
+#[tokio::test]
+async fn replace_sm_should_replace_pending_messages_only() {
+    let session = session();
+
+    let message_id = submit(&session).await;
+
+    let replace_sm = ReplaceSm::builder()
+        .message_id(message_id.clone())
+        .short_message(OctetString::from_str("Replaced").unwrap())
+        .build();
+
+    let (status, pdu) = sent(session.request(replace_sm.clone().into()).await);
+
+    assert_eq!(status, CommandStatus::EsmeRok);
+    assert!(matches!(pdu, Pdu::ReplaceSmResp));
+
+    let message = session
+        .message_store
+        .query(
+            SYSTEM_ID,
+            &message_id.to_string(),
+            &Address::new(Ton::Unknown, Npi::Unknown, ""),
+        )
+        .unwrap();
+
+    assert_eq!(message.short_message, b"Replaced");
+
+    let cancel_sm = CancelSm::builder().message_id(message_id).build();
+
+    sent(session.request(cancel_sm.into()).await);
+
+    let (status, pdu) = sent(session.request(replace_sm.into()).await);
+
+    assert_eq!(status, CommandStatus::EsmeRreplacefail);
+    assert!(matches!(pdu, Pdu::ReplaceSmResp));
+}
+
+#[tokio::test]
+async fn broadcast_sm_should_be_accepted_and_queried() {
+    let session = session();
+
+    let (status, pdu) = sent(session.request(BroadcastSm::default().into()).await);
+
+    assert_eq!(status, CommandStatus::EsmeRok);
+
+    let Pdu::BroadcastSmResp(broadcast_sm_resp) = pdu else {
+        panic!("Expected broadcast_sm_resp, got {pdu:?}");
+    };
+
+    assert!(!broadcast_sm_resp.message_id.is_empty());
+
+    let query_broadcast_sm = QueryBroadcastSm::builder()
+        .message_id(broadcast_sm_resp.message_id.clone())
+        .build();
+
+    let (status, pdu) = sent(session.request(query_broadcast_sm.into()).await);
+
+    assert_eq!(status, CommandStatus::EsmeRok);
+
+    let Pdu::QueryBroadcastSmResp(query_broadcast_sm_resp) = pdu else {
+        panic!("Expected query_broadcast_sm_resp, got {pdu:?}");
+    };
+
+    assert_eq!(
+        query_broadcast_sm_resp.message_id,
+        broadcast_sm_resp.message_id
+    );
+    assert!(query_broadcast_sm_resp.tlvs().iter().any(|tlv| matches!(
+        tlv.value(),
+        Some(TlvValue::MessageState(MessageState::Enroute))
+    )));
+}
+
+/// Runs a [`SimulatorSession`] behind a [`Connection`], which checks the bind state of the requests.
+fn connect() -> Framed<DuplexStream, CommandCodec> {
+    let (server, client) = tokio::io::duplex(4096);
+
+    let config = Arc::new(ConnectionConfig {
+        connected_clients: ConnectedClients::new(),
+        enquire_link_interval: None,
+        enquire_link_response_timeout: Duration::from_secs(5),
+        session_timeout: Duration::from_secs(5),
+        enquire_link_response_delay: None,
+        window_size: 10,
+        response_timeout: Duration::from_secs(5),
+    });
+
+    let simulator = Simulator::new(SimulatorParameters {
+        clients: Vec::new(),
+        message_store: Arc::new(MessageStore::new(MessageStoreConfig::default())),
+        bind_delay: None,
+        response_delay: None,
+        faults: Vec::new(),
+    });
+
+    let addr = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 2775));
+
+    tokio::spawn(Connection::new(1, addr, config, Arc::new(simulator)).run(server));
+
+    Framed::new(client, CommandCodec::new())
+}
+
+/// Sends the `pdu` and returns the response.
+async fn exchange(
+    framed: &mut Framed<DuplexStream, CommandCodec>,
+    sequence_number: u32,
+    pdu: impl Into<Pdu>,
+) -> Command {
+    let command = Command::builder()
+        .status(CommandStatus::EsmeRok)
+        .sequence_number(sequence_number)
+        .pdu(pdu);
+
+    framed.send(command).await.unwrap();
+
+    tokio::time::timeout(Duration::from_secs(1), framed.next())
+        .await
+        .expect("Timed out waiting for a response")
+        .expect("Connection closed")
+        .expect("Failed to decode response")
+}
+
+#[tokio::test]
+async fn transmitters_should_not_send_receiver_commands() {
+    let mut framed = connect();
+
+    let response = exchange(&mut framed, 1, BindTransmitter::default()).await;
+
+    assert_eq!(response.status(), CommandStatus::EsmeRok);
+
+    let response = exchange(&mut framed, 2, DeliverSmResp::default()).await;
+
+    assert_eq!(response.status(), CommandStatus::EsmeRinvbndsts);
+    assert_eq!(response.sequence_number(), 2);
+}
+
+#[tokio::test]
+async fn receivers_should_not_send_transmitter_commands() {
+    let mut framed = connect();
+
+    let response = exchange(&mut framed, 1, BindReceiver::default()).await;
+
+    assert_eq!(response.status(), CommandStatus::EsmeRok);
+
+    let response = exchange(&mut framed, 2, SubmitSm::default()).await;
+
+    assert_eq!(response.id(), CommandId::SubmitSmResp);
+    assert_eq!(response.status(), CommandStatus::EsmeRinvbndsts);
+    assert_eq!(response.sequence_number(), 2);
+}
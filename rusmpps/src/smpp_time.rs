@@ -0,0 +1,131 @@
+//! `SMPP` time format: `YYMMDDhhmmsstnnp`.
+//!
+//! Absolute times carry the tenths of a second `t`, the quarter hours `nn` difference
+//! from UTC and the direction `p` (`+` or `-`) of that difference.
+//! Relative times end with `R` and are offsets from the current time.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[cfg(test)]
+mod tests;
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[error("Invalid SMPP time")]
+pub struct InvalidTime;
+
+/// Parses an `SMPP` time.
+///
+/// Returns [`None`] for an empty time.
+pub fn parse(value: &str, now: SystemTime) -> Result<Option<SystemTime>, InvalidTime> {
+    if value.is_empty() {
+        return Ok(None);
+    }
+
+    let bytes = value.as_bytes();
+
+    if bytes.len() != 16 || !bytes[..15].iter().all(u8::is_ascii_digit) {
+        return Err(InvalidTime);
+    }
+
+    let field = |index: usize| u64::from((bytes[index] - b'0') * 10 + (bytes[index + 1] - b'0'));
+
+    let (year, month, day) = (field(0), field(2), field(4));
+    let (hour, minute, second) = (field(6), field(8), field(10));
+
+    if bytes[15] == b'R' {
+        // Years and months have no fixed length, they are approximated.
+        let seconds = ((year * 365 + month * 30 + day) * SECONDS_PER_DAY)
+            + hour * 3600
+            + minute * 60
+            + second;
+
+        return Ok(Some(now + Duration::from_secs(seconds)));
+    }
+
+    if !(1..=12).contains(&month)
+        || !(1..=31).contains(&day)
+        || hour > 23
+        || minute > 59
+        || second > 59
+    {
+        return Err(InvalidTime);
+    }
+
+    let tenths = u64::from(bytes[12] - b'0');
+    let quarters = field(13);
+
+    if quarters > 48 {
+        return Err(InvalidTime);
+    }
+
+    let days = days_from_civil(2000 + year as i64, month as u32, day as u32);
+    let local = days * SECONDS_PER_DAY as i64 + (hour * 3600 + minute * 60 + second) as i64;
+
+    let offset = (quarters * 15 * 60) as i64;
+
+    let utc = match bytes[15] {
+        b'+' => local - offset,
+        b'-' => local + offset,
+        _ => return Err(InvalidTime),
+    };
+
+    let utc = u64::try_from(utc).map_err(|_| InvalidTime)?;
+
+    Ok(Some(
+        UNIX_EPOCH + Duration::from_secs(utc) + Duration::from_millis(tenths * 100),
+    ))
+}
+
+/// Formats an absolute `SMPP` time in UTC.
+pub fn format(time: SystemTime) -> String {
+    let seconds = time
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+
+    let (year, month, day) = civil_from_days((seconds / SECONDS_PER_DAY) as i64);
+    let seconds = seconds % SECONDS_PER_DAY;
+
+    format!(
+        "{:02}{:02}{:02}{:02}{:02}{:02}000+",
+        year % 100,
+        month,
+        day,
+        seconds / 3600,
+        seconds % 3600 / 60,
+        seconds % 60
+    )
+}
+
+/// Days since the unix epoch of a proleptic Gregorian date.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month as i64 + 9) % 12) + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+    era * 146097 + day_of_era - 719468
+}
+
+/// Proleptic Gregorian date of the days since the unix epoch.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_from_march + 2) / 5 + 1) as u32;
+    let month = if month_from_march < 10 {
+        month_from_march + 3
+    } else {
+        month_from_march - 9
+    } as u32;
+    let year = year_of_era + era * 400;
+
+    (if month <= 2 { year + 1 } else { year }, month, day)
+}
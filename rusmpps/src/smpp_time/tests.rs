@@ -0,0 +1,137 @@
+use super::*;
+
+fn at(seconds: u64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs(seconds)
+}
+
+#[test]
+fn empty_time_should_be_none() {
+    assert_eq!(parse("", SystemTime::now()), Ok(None));
+}
+
+#[test]
+fn absolute_time_should_be_parsed_in_utc() {
+    let now = SystemTime::now();
+
+    // 2000-01-01T00:00:00Z
+    assert_eq!(parse("000101000000000+", now), Ok(Some(at(946_684_800))));
+    // 2024-01-01T12:00:00Z
+    assert_eq!(parse("240101120000000+", now), Ok(Some(at(1_704_110_400))));
+    // Tenths of a second.
+    assert_eq!(
+        parse("240101120000500+", now),
+        Ok(Some(at(1_704_110_400) + Duration::from_millis(500)))
+    );
+}
+
+#[test]
+fn absolute_time_should_apply_the_utc_offset() {
+    let now = SystemTime::now();
+
+    // 12:00 one hour ahead of UTC is 11:00 UTC.
+    assert_eq!(
+        parse("240101120000004+", now),
+        Ok(Some(at(1_704_110_400 - 3600)))
+    );
+    // 12:00 one hour behind UTC is 13:00 UTC.
+    assert_eq!(
+        parse("240101120000004-", now),
+        Ok(Some(at(1_704_110_400 + 3600)))
+    );
+    // Crosses the day boundary.
+    assert_eq!(
+        parse("240101000000008+", now),
+        Ok(Some(at(1_704_067_200 - 2 * 3600)))
+    );
+}
+
+#[test]
+fn leap_days_should_be_parsed() {
+    let now = SystemTime::now();
+
+    // 2024-02-29T00:00:00Z
+    assert_eq!(parse("240229000000000+", now), Ok(Some(at(1_709_164_800))));
+    // 2024-03-01T00:00:00Z
+    assert_eq!(parse("240301000000000+", now), Ok(Some(at(1_709_251_200))));
+    // 2000 is a leap year, 2000-02-29T00:00:00Z
+    assert_eq!(parse("000229000000000+", now), Ok(Some(at(951_782_400))));
+}
+
+#[test]
+fn relative_time_should_be_an_offset_from_now() {
+    let now = SystemTime::now();
+
+    assert_eq!(
+        parse("000001020304000R", now),
+        Ok(Some(
+            now + Duration::from_secs(24 * 3600 + 2 * 3600 + 3 * 60 + 4)
+        ))
+    );
+    // Months are approximated with 30 days and years with 365 days.
+    assert_eq!(
+        parse("010200000000000R", now),
+        Ok(Some(
+            now + Duration::from_secs((365 + 60) * SECONDS_PER_DAY)
+        ))
+    );
+}
+
+#[test]
+fn invalid_times_should_be_rejected() {
+    let now = SystemTime::now();
+
+    for value in [
+        "24010112000000+",
+        "2401011200000000+",
+        "241301120000000+",
+        "240100120000000+",
+        "240132120000000+",
+        "240101240000000+",
+        "240101126000000+",
+        "240101120060000+",
+        "240101120000049+",
+        "240101120000000X",
+        "2401011200a0000+",
+    ] {
+        assert_eq!(parse(value, now), Err(InvalidTime), "{value}");
+    }
+}
+
+#[test]
+fn format_should_write_utc_time() {
+    assert_eq!(format(at(1_704_110_400)), "240101120000000+");
+    assert_eq!(format(at(1_709_164_800 + 59)), "240229000059000+");
+    assert_eq!(format(at(951_782_400)), "000229000000000+");
+}
+
+#[test]
+fn format_should_round_trip() {
+    let now = SystemTime::now();
+
+    for seconds in [946_684_800, 1_704_110_400, 1_709_164_800, 4_102_444_799] {
+        assert_eq!(parse(&format(at(seconds)), now), Ok(Some(at(seconds))));
+    }
+}
+
+#[test]
+fn civil_date_conversion_should_round_trip() {
+    assert_eq!(civil_from_days(0), (1970, 1, 1));
+    assert_eq!(days_from_civil(1970, 1, 1), 0);
+    assert_eq!(civil_from_days(-1), (1969, 12, 31));
+
+    // Four centuries cover every leap year rule.
+    for days in -1_000..(400 * 366) {
+        let (year, month, day) = civil_from_days(days);
+
+        assert_eq!(days_from_civil(year, month, day), days);
+    }
+
+    assert_eq!(
+        civil_from_days(days_from_civil(2100, 2, 28) + 1),
+        (2100, 3, 1)
+    );
+    assert_eq!(
+        civil_from_days(days_from_civil(2400, 2, 28) + 1),
+        (2400, 2, 29)
+    );
+}
@@ -0,0 +1,797 @@
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::{Duration, SystemTime},
+};
+
+use rusmpp::{
+    CommandStatus,
+    pdus::{CancelSm, DataSm, ReplaceSm, SubmitMulti, SubmitSm},
+    tlvs::{Tlv, TlvValue},
+    values::{DataCoding, MessageState, Npi, RegisteredDelivery, Ton},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::smpp_time;
+
+#[cfg(test)]
+mod tests;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct MessageStoreConfig {
+    /// Messages are persisted to this file and restored on startup if set.
+    pub file: Option<PathBuf>,
+    /// Time between the (scheduled) submission of a message and its final state.
+    #[serde(with = "humantime_serde")]
+    pub delivery_delay: Duration,
+    /// Validity period of messages submitted without one. Messages never expire if not set.
+    #[serde(with = "humantime_serde")]
+    pub default_validity_period: Option<Duration>,
+    /// Time messages are kept after reaching a final state.
+    #[serde(with = "humantime_serde")]
+    pub retention: Duration,
+    /// Minimum time between two saves of the messages to the file.
+    #[serde(with = "humantime_serde")]
+    pub persist_interval: Duration,
+    pub outcomes: Outcomes,
+}
+
+impl Default for MessageStoreConfig {
+    fn default() -> Self {
+        Self {
+            file: None,
+            delivery_delay: Duration::from_secs(1),
+            default_validity_period: None,
+            retention: Duration::from_secs(60 * 60),
+            persist_interval: Duration::from_secs(5),
+            outcomes: Outcomes::default(),
+        }
+    }
+}
+
+/// Per account overrides of the [`MessageStoreConfig`] lifecycle settings.
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(default)]
+pub struct Lifecycle {
+    #[serde(with = "humantime_serde")]
+    pub delivery_delay: Option<Duration>,
+    pub outcomes: Option<Outcomes>,
+}
+
+/// Weights of the final states of messages delivered before their expiry.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default)]
+pub struct Outcomes {
+    pub delivered: u32,
+    pub undeliverable: u32,
+    pub rejected: u32,
+}
+
+impl Default for Outcomes {
+    fn default() -> Self {
+        Self {
+            delivered: 1,
+            undeliverable: 0,
+            rejected: 0,
+        }
+    }
+}
+
+impl Outcomes {
+    fn pick(&self) -> MessageState {
+        let total = self.delivered + self.undeliverable + self.rejected;
+
+        if total == 0 {
+            return MessageState::Delivered;
+        }
+
+        let value = rand::random_range(0..total);
+
+        if value < self.delivered {
+            MessageState::Delivered
+        } else if value < self.delivered + self.undeliverable {
+            MessageState::Undeliverable
+        } else {
+            MessageState::Rejected
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Address {
+    pub ton: Ton,
+    pub npi: Npi,
+    pub addr: String,
+}
+
+impl Address {
+    pub fn new(ton: Ton, npi: Npi, addr: impl Into<String>) -> Self {
+        Self {
+            ton,
+            npi,
+            addr: addr.into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Message {
+    pub message_id: String,
+    /// `system_id` of the account that submitted the message.
+    pub system_id: String,
+    pub service_type: String,
+    pub source: Address,
+    pub destination: Address,
+    pub registered_delivery: RegisteredDelivery,
+    pub data_coding: DataCoding,
+    pub short_message: Vec<u8>,
+    #[serde(with = "humantime_serde")]
+    pub submit_time: SystemTime,
+    #[serde(with = "humantime_serde")]
+    pub schedule_delivery_time: Option<SystemTime>,
+    #[serde(with = "humantime_serde")]
+    pub expiry_time: Option<SystemTime>,
+    #[serde(with = "humantime_serde")]
+    pub delivery_delay: Duration,
+    /// Time at which the message reaches its `outcome`.
+    #[serde(with = "humantime_serde")]
+    pub delivery_time: SystemTime,
+    /// Final state of the message if delivered before its expiry.
+    pub outcome: MessageState,
+    pub state: MessageState,
+    #[serde(with = "humantime_serde")]
+    pub final_date: Option<SystemTime>,
+    /// Set when the state changes, until the change is collected by [`MessageStore::advance`].
+    #[serde(skip)]
+    changed: bool,
+    /// Key of the message in the due index. See [`Message::next_due`].
+    #[serde(skip)]
+    due: Option<SystemTime>,
+}
+
+impl Message {
+    pub fn is_final(&self) -> bool {
+        !matches!(
+            self.state,
+            MessageState::Scheduled | MessageState::Enroute | MessageState::Accepted
+        )
+    }
+
+    /// Moves the message to the state it has at `now`.
+    ///
+    /// Returns `true` if the state changed.
+    fn advance(&mut self, now: SystemTime) -> bool {
+        if self.is_final() {
+            return false;
+        }
+
+        let previous = self.state;
+
+        match self.expiry_time {
+            Some(expiry_time) if expiry_time <= now && expiry_time < self.delivery_time => {
+                self.state = MessageState::Expired;
+                self.final_date = Some(expiry_time);
+            }
+            _ if self.delivery_time <= now => {
+                self.state = self.outcome;
+                self.final_date = Some(self.delivery_time);
+            }
+            _ => {
+                self.state = match self.schedule_delivery_time {
+                    Some(schedule_delivery_time) if schedule_delivery_time > now => {
+                        MessageState::Scheduled
+                    }
+                    _ => MessageState::Enroute,
+                };
+            }
+        }
+
+        let changed = self.state != previous;
+
+        self.changed |= changed;
+
+        changed
+    }
+
+    /// Time of the next state change of the message, or of its removal once final.
+    fn next_due(&self, retention: Duration) -> Option<SystemTime> {
+        if self.is_final() {
+            return self.final_date.map(|final_date| final_date + retention);
+        }
+
+        let scheduled = match self.state {
+            MessageState::Scheduled => self.schedule_delivery_time,
+            _ => None,
+        };
+
+        [scheduled, Some(self.delivery_time), self.expiry_time]
+            .into_iter()
+            .flatten()
+            .min()
+    }
+
+    fn finalize(&mut self, state: MessageState, now: SystemTime) {
+        self.state = state;
+        self.final_date = Some(now);
+        self.changed = true;
+    }
+
+    fn reschedule(&mut self) {
+        let start = self
+            .schedule_delivery_time
+            .map_or(self.submit_time, |time| time.max(self.submit_time));
+
+        self.delivery_time = start + self.delivery_delay;
+    }
+}
+
+/// A message accepted by the server.
+#[derive(Debug)]
+pub struct Submission {
+    pub system_id: String,
+    pub service_type: String,
+    pub source: Address,
+    pub destination: Address,
+    pub registered_delivery: RegisteredDelivery,
+    pub data_coding: DataCoding,
+    pub short_message: Vec<u8>,
+    pub schedule_delivery_time: String,
+    pub validity_period: String,
+    pub lifecycle: Lifecycle,
+}
+
+impl Submission {
+    pub fn submit_sm(system_id: &str, submit_sm: &SubmitSm) -> Self {
+        Self {
+            system_id: system_id.to_string(),
+            service_type: submit_sm.service_type.value().to_string(),
+            source: Address::new(
+                submit_sm.source_addr_ton,
+                submit_sm.source_addr_npi,
+                submit_sm.source_addr.to_string(),
+            ),
+            destination: Address::new(
+                submit_sm.dest_addr_ton,
+                submit_sm.dest_addr_npi,
+                submit_sm.destination_addr.to_string(),
+            ),
+            registered_delivery: submit_sm.registered_delivery,
+            data_coding: submit_sm.data_coding,
+            short_message: message_payload(submit_sm.tlvs())
+                .unwrap_or_else(|| submit_sm.short_message().as_ref().to_vec()),
+            schedule_delivery_time: submit_sm.schedule_delivery_time.to_string(),
+            validity_period: submit_sm.validity_period.to_string(),
+            lifecycle: Lifecycle::default(),
+        }
+    }
+
+    pub fn data_sm(system_id: &str, data_sm: &DataSm) -> Self {
+        Self {
+            system_id: system_id.to_string(),
+            service_type: data_sm.service_type.value().to_string(),
+            source: Address::new(
+                data_sm.source_addr_ton,
+                data_sm.source_addr_npi,
+                data_sm.source_addr.to_string(),
+            ),
+            destination: Address::new(
+                data_sm.dest_addr_ton,
+                data_sm.dest_addr_npi,
+                data_sm.destination_addr.to_string(),
+            ),
+            registered_delivery: data_sm.registered_delivery,
+            data_coding: data_sm.data_coding,
+            short_message: message_payload(data_sm.tlvs()).unwrap_or_default(),
+            schedule_delivery_time: String::new(),
+            validity_period: String::new(),
+            lifecycle: Lifecycle::default(),
+        }
+    }
+
+    /// A [`SubmitMulti`] is tracked as a single message to `destination`.
+    pub fn submit_multi(system_id: &str, submit_multi: &SubmitMulti, destination: Address) -> Self {
+        Self {
+            system_id: system_id.to_string(),
+            service_type: submit_multi.service_type.value().to_string(),
+            source: Address::new(
+                submit_multi.source_addr_ton,
+                submit_multi.source_addr_npi,
+                submit_multi.source_addr.to_string(),
+            ),
+            destination,
+            registered_delivery: submit_multi.registered_delivery,
+            data_coding: submit_multi.data_coding,
+            short_message: message_payload(submit_multi.tlvs())
+                .unwrap_or_else(|| submit_multi.short_message().as_ref().to_vec()),
+            schedule_delivery_time: submit_multi.schedule_delivery_time.to_string(),
+            validity_period: submit_multi.validity_period.to_string(),
+            lifecycle: Lifecycle::default(),
+        }
+    }
+
+    pub fn lifecycle(mut self, lifecycle: Lifecycle) -> Self {
+        self.lifecycle = lifecycle;
+        self
+    }
+}
+
+fn message_payload(tlvs: &[Tlv]) -> Option<Vec<u8>> {
+    tlvs.iter().find_map(|tlv| match tlv.value() {
+        Some(TlvValue::MessagePayload(message_payload)) => {
+            Some(message_payload.value.as_ref().to_vec())
+        }
+        _ => None,
+    })
+}
+
+/// Selects the messages to cancel.
+///
+/// All pending messages from `source` to `destination` are cancelled if `message_id` is empty.
+#[derive(Debug)]
+pub struct Cancellation {
+    pub message_id: String,
+    pub service_type: String,
+    pub source: Address,
+    pub destination: Address,
+}
+
+impl From<CancelSm> for Cancellation {
+    fn from(cancel_sm: CancelSm) -> Self {
+        Self {
+            message_id: cancel_sm.message_id.to_string(),
+            service_type: cancel_sm.service_type.value().to_string(),
+            source: Address::new(
+                cancel_sm.source_addr_ton,
+                cancel_sm.source_addr_npi,
+                cancel_sm.source_addr.to_string(),
+            ),
+            destination: Address::new(
+                cancel_sm.dest_addr_ton,
+                cancel_sm.dest_addr_npi,
+                cancel_sm.destination_addr.to_string(),
+            ),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Replacement {
+    pub message_id: String,
+    pub source: Address,
+    pub registered_delivery: RegisteredDelivery,
+    pub short_message: Vec<u8>,
+    /// The original scheduled delivery time is kept if empty.
+    pub schedule_delivery_time: String,
+    /// The original expiry time is kept if empty.
+    pub validity_period: String,
+}
+
+impl From<ReplaceSm> for Replacement {
+    fn from(replace_sm: ReplaceSm) -> Self {
+        let short_message = replace_sm
+            .message_payload()
+            .map(|message_payload| message_payload.value.as_ref().to_vec())
+            .unwrap_or_else(|| replace_sm.short_message().as_ref().to_vec());
+
+        Self {
+            message_id: replace_sm.message_id.to_string(),
+            source: Address::new(
+                replace_sm.source_addr_ton,
+                replace_sm.source_addr_npi,
+                replace_sm.source_addr.to_string(),
+            ),
+            registered_delivery: replace_sm.registered_delivery,
+            short_message,
+            schedule_delivery_time: replace_sm.schedule_delivery_time.to_string(),
+            validity_period: replace_sm.validity_period.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Messages {
+    next_id: u64,
+    messages: HashMap<String, Message>,
+    #[serde(skip)]
+    dirty: bool,
+    /// Messages ordered by their next due time, so that advancing only visits the due ones.
+    #[serde(skip)]
+    due: BTreeSet<(SystemTime, String)>,
+    /// Messages whose state changed outside of [`MessageStore::advance`].
+    #[serde(skip)]
+    changed: HashSet<String>,
+}
+
+/// The persisted part of [`Messages`], taken to save the messages without holding the lock.
+#[derive(Serialize)]
+struct Snapshot {
+    next_id: u64,
+    messages: HashMap<String, Message>,
+}
+
+impl Messages {
+    /// Updates the due index and the changed messages after a change of the message.
+    fn index(&mut self, message_id: &str, retention: Duration) {
+        let Some(message) = self.messages.get_mut(message_id) else {
+            return;
+        };
+
+        if let Some(due) = message.due.take() {
+            self.due.remove(&(due, message_id.to_owned()));
+        }
+
+        message.due = message.next_due(retention);
+
+        if let Some(due) = message.due {
+            self.due.insert((due, message_id.to_owned()));
+        }
+
+        if message.changed {
+            self.changed.insert(message_id.to_owned());
+        }
+    }
+
+    fn next_message_id(&mut self) -> String {
+        let message_id = format!("{:016x}", self.next_id);
+
+        self.next_id += 1;
+        self.dirty = true;
+
+        message_id
+    }
+
+    fn get_mut(
+        &mut self,
+        system_id: &str,
+        message_id: &str,
+        source: &Address,
+    ) -> Option<&mut Message> {
+        self.messages.get_mut(message_id).filter(|message| {
+            message.system_id == system_id
+                && (source.addr.is_empty() || message.source.addr == source.addr)
+        })
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum LoadStoreError {
+    #[error("Failed to read message store file: {0}")]
+    Read(#[from] std::io::Error),
+    #[error("Failed to parse message store file: {0}")]
+    Parse(#[from] serde_yaml::Error),
+}
+
+/// In-memory store of the messages submitted to the server.
+#[derive(Debug)]
+pub struct MessageStore {
+    config: MessageStoreConfig,
+    messages: Mutex<Messages>,
+}
+
+impl MessageStore {
+    pub fn new(config: MessageStoreConfig) -> Self {
+        Self {
+            config,
+            messages: Mutex::new(Messages::default()),
+        }
+    }
+
+    /// Creates the store, restoring the messages from the configured file if it exists.
+    pub fn load(config: MessageStoreConfig) -> Result<Self, LoadStoreError> {
+        let mut messages: Messages = match &config.file {
+            Some(path) if path.exists() => serde_yaml::from_slice(&std::fs::read(path)?)?,
+            _ => Messages::default(),
+        };
+
+        let message_ids = messages.messages.keys().cloned().collect::<Vec<_>>();
+
+        for message_id in message_ids {
+            messages.index(&message_id, config.retention);
+        }
+
+        Ok(Self {
+            config,
+            messages: Mutex::new(messages),
+        })
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Messages> {
+        self.messages.lock().expect("Message store lock poisoned")
+    }
+
+    /// Allocates a message id for a message that is not stored.
+    pub fn next_message_id(&self) -> String {
+        self.lock().next_message_id()
+    }
+
+    /// Stores a new message and returns its id.
+    pub fn submit(&self, submission: Submission) -> Result<String, CommandStatus> {
+        let now = SystemTime::now();
+
+        let schedule_delivery_time = smpp_time::parse(&submission.schedule_delivery_time, now)
+            .map_err(|_| CommandStatus::EsmeRinvsched)?;
+
+        let expiry_time = smpp_time::parse(&submission.validity_period, now)
+            .map_err(|_| CommandStatus::EsmeRinvexpiry)?
+            .or_else(|| {
+                self.config
+                    .default_validity_period
+                    .map(|validity_period| now + validity_period)
+            });
+
+        if expiry_time.is_some_and(|expiry_time| expiry_time <= now) {
+            return Err(CommandStatus::EsmeRinvexpiry);
+        }
+
+        let lifecycle = submission.lifecycle;
+
+        let mut messages = self.lock();
+
+        let message_id = messages.next_message_id();
+
+        let mut message = Message {
+            message_id: message_id.clone(),
+            system_id: submission.system_id,
+            service_type: submission.service_type,
+            source: submission.source,
+            destination: submission.destination,
+            registered_delivery: submission.registered_delivery,
+            data_coding: submission.data_coding,
+            short_message: submission.short_message,
+            submit_time: now,
+            schedule_delivery_time,
+            expiry_time,
+            delivery_delay: lifecycle
+                .delivery_delay
+                .unwrap_or(self.config.delivery_delay),
+            delivery_time: now,
+            outcome: lifecycle.outcomes.unwrap_or(self.config.outcomes).pick(),
+            state: MessageState::Enroute,
+            final_date: None,
+            changed: true,
+            due: None,
+        };
+
+        message.reschedule();
+        message.advance(now);
+
+        messages.messages.insert(message_id.clone(), message);
+        messages.index(&message_id, self.config.retention);
+        messages.dirty = true;
+
+        Ok(message_id)
+    }
+
+    pub fn query(
+        &self,
+        system_id: &str,
+        message_id: &str,
+        source: &Address,
+    ) -> Result<Message, CommandStatus> {
+        let mut messages = self.lock();
+
+        let message = messages
+            .get_mut(system_id, message_id, source)
+            .ok_or(CommandStatus::EsmeRqueryfail)?;
+
+        let message = if message.advance(SystemTime::now()) {
+            let message = message.clone();
+
+            messages.index(message_id, self.config.retention);
+
+            message
+        } else {
+            message.clone()
+        };
+
+        Ok(message)
+    }
+
+    pub fn cancel(&self, system_id: &str, cancellation: Cancellation) -> Result<(), CommandStatus> {
+        let now = SystemTime::now();
+
+        let mut messages = self.lock();
+
+        if !cancellation.message_id.is_empty() {
+            let message = messages
+                .get_mut(system_id, &cancellation.message_id, &cancellation.source)
+                .ok_or(CommandStatus::EsmeRcancelfail)?;
+
+            message.advance(now);
+
+            if message.is_final() {
+                return Err(CommandStatus::EsmeRcancelfail);
+            }
+
+            message.finalize(MessageState::Deleted, now);
+            messages.index(&cancellation.message_id, self.config.retention);
+            messages.dirty = true;
+
+            return Ok(());
+        }
+
+        let mut cancelled = 0;
+        let mut changed = Vec::new();
+
+        for message in messages.messages.values_mut() {
+            if message.advance(now) {
+                changed.push(message.message_id.clone());
+            }
+
+            if message.is_final()
+                || message.system_id != system_id
+                || message.source.addr != cancellation.source.addr
+                || message.destination.addr != cancellation.destination.addr
+                || (!cancellation.service_type.is_empty()
+                    && message.service_type != cancellation.service_type)
+            {
+                continue;
+            }
+
+            message.finalize(MessageState::Deleted, now);
+            changed.push(message.message_id.clone());
+            cancelled += 1;
+        }
+
+        for message_id in changed {
+            messages.index(&message_id, self.config.retention);
+        }
+
+        if cancelled == 0 {
+            return Err(CommandStatus::EsmeRcancelfail);
+        }
+
+        messages.dirty = true;
+
+        Ok(())
+    }
+
+    pub fn replace(&self, system_id: &str, replacement: Replacement) -> Result<(), CommandStatus> {
+        let now = SystemTime::now();
+
+        let schedule_delivery_time = smpp_time::parse(&replacement.schedule_delivery_time, now)
+            .map_err(|_| CommandStatus::EsmeRinvsched)?;
+
+        let expiry_time = smpp_time::parse(&replacement.validity_period, now)
+            .map_err(|_| CommandStatus::EsmeRinvexpiry)?;
+
+        let mut messages = self.lock();
+
+        let message = messages
+            .get_mut(system_id, &replacement.message_id, &replacement.source)
+            .ok_or(CommandStatus::EsmeRreplacefail)?;
+
+        message.advance(now);
+
+        if message.is_final() {
+            return Err(CommandStatus::EsmeRreplacefail);
+        }
+
+        message.registered_delivery = replacement.registered_delivery;
+        message.short_message = replacement.short_message;
+
+        if let Some(schedule_delivery_time) = schedule_delivery_time {
+            message.schedule_delivery_time = Some(schedule_delivery_time);
+            message.reschedule();
+        }
+
+        if let Some(expiry_time) = expiry_time {
+            message.expiry_time = Some(expiry_time);
+        }
+
+        message.advance(now);
+        messages.index(&replacement.message_id, self.config.retention);
+        messages.dirty = true;
+
+        Ok(())
+    }
+
+    pub fn retention(&self) -> Duration {
+        self.config.retention
+    }
+
+    pub fn persist_interval(&self) -> Duration {
+        self.config.persist_interval
+    }
+
+    /// Advances the lifecycle of the due messages and drops the ones past their retention.
+    ///
+    /// Returns the messages whose state changed since the last call, including new messages.
+    pub fn advance(&self, now: SystemTime) -> Vec<Message> {
+        let retention = self.config.retention;
+
+        let mut messages = self.lock();
+        let mut removed = false;
+
+        while let Some((due, message_id)) = messages.due.first().cloned() {
+            if due > now {
+                break;
+            }
+
+            messages.due.pop_first();
+
+            let Some(message) = messages.messages.get_mut(&message_id) else {
+                continue;
+            };
+
+            message.due = None;
+
+            if message.is_final() {
+                messages.messages.remove(&message_id);
+                messages.changed.remove(&message_id);
+                removed = true;
+
+                continue;
+            }
+
+            message.advance(now);
+            messages.index(&message_id, retention);
+        }
+
+        let changed = std::mem::take(&mut messages.changed)
+            .into_iter()
+            .filter_map(|message_id| {
+                let message = messages.messages.get_mut(&message_id)?;
+
+                std::mem::take(&mut message.changed).then(|| message.clone())
+            })
+            .collect::<Vec<_>>();
+
+        if removed || !changed.is_empty() {
+            messages.dirty = true;
+        }
+
+        changed
+    }
+
+    /// Returns `true` if the messages changed since the last save.
+    pub fn is_dirty(&self) -> bool {
+        self.lock().dirty
+    }
+
+    /// Saves the messages to the configured file, if any changed since the last save.
+    ///
+    /// Performs blocking I/O, the lock is only held to take a snapshot of the messages.
+    pub fn persist(&self) -> Result<(), std::io::Error> {
+        let Some(path) = &self.config.file else {
+            return Ok(());
+        };
+
+        self.save(path)
+    }
+
+    fn save(&self, path: &Path) -> Result<(), std::io::Error> {
+        let snapshot = {
+            let mut messages = self.lock();
+
+            if !messages.dirty {
+                return Ok(());
+            }
+
+            messages.dirty = false;
+
+            Snapshot {
+                next_id: messages.next_id,
+                messages: messages.messages.clone(),
+            }
+        };
+
+        let result = serde_yaml::to_string(&snapshot)
+            .map_err(std::io::Error::other)
+            .and_then(|yaml| {
+                let tmp = path.with_extension("tmp");
+
+                std::fs::write(&tmp, yaml)?;
+                std::fs::rename(tmp, path)
+            });
+
+        if result.is_err() {
+            // Retried on the next save.
+            self.lock().dirty = true;
+        }
+
+        result
+    }
+}
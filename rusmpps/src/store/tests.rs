@@ -0,0 +1,418 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use super::*;
+
+const DELIVERY_DELAY: Duration = Duration::from_secs(10);
+const RETENTION: Duration = Duration::from_secs(60);
+
+fn config() -> MessageStoreConfig {
+    MessageStoreConfig {
+        delivery_delay: DELIVERY_DELAY,
+        retention: RETENTION,
+        ..Default::default()
+    }
+}
+
+fn address(addr: &str) -> Address {
+    Address::new(Ton::International, Npi::Isdn, addr)
+}
+
+fn submission(system_id: &str) -> Submission {
+    Submission {
+        system_id: system_id.to_string(),
+        service_type: String::new(),
+        source: address("1111"),
+        destination: address("2222"),
+        registered_delivery: RegisteredDelivery::default(),
+        data_coding: DataCoding::default(),
+        short_message: b"Hello".to_vec(),
+        schedule_delivery_time: String::new(),
+        validity_period: String::new(),
+        lifecycle: Lifecycle::default(),
+    }
+}
+
+fn cancellation(message_id: &str) -> Cancellation {
+    Cancellation {
+        message_id: message_id.to_string(),
+        service_type: String::new(),
+        source: address("1111"),
+        destination: address("2222"),
+    }
+}
+
+fn replacement(message_id: &str, short_message: &[u8]) -> Replacement {
+    Replacement {
+        message_id: message_id.to_string(),
+        source: address("1111"),
+        registered_delivery: RegisteredDelivery::default(),
+        short_message: short_message.to_vec(),
+        schedule_delivery_time: String::new(),
+        validity_period: String::new(),
+    }
+}
+
+fn query(store: &MessageStore, message_id: &str) -> Result<Message, CommandStatus> {
+    store.query("esme", message_id, &address(""))
+}
+
+fn states(changed: Vec<Message>) -> Vec<(String, MessageState)> {
+    let mut states = changed
+        .into_iter()
+        .map(|message| (message.message_id, message.state))
+        .collect::<Vec<_>>();
+
+    states.sort();
+
+    states
+}
+
+fn temp_file() -> PathBuf {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    std::env::temp_dir().join(format!(
+        "rusmpps-store-{}-{}.yaml",
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    ))
+}
+
+#[test]
+fn submitted_message_should_be_delivered_after_the_delay() {
+    let store = MessageStore::new(config());
+
+    let message_id = store.submit(submission("esme")).unwrap();
+    let message = query(&store, &message_id).unwrap();
+
+    assert_eq!(message.state, MessageState::Enroute);
+    assert_eq!(message.short_message, b"Hello");
+
+    let now = SystemTime::now();
+
+    // New messages are reported once.
+    assert_eq!(
+        states(store.advance(now)),
+        vec![(message_id.clone(), MessageState::Enroute)]
+    );
+    assert!(store.advance(now).is_empty());
+
+    assert_eq!(
+        states(store.advance(now + DELIVERY_DELAY)),
+        vec![(message_id.clone(), MessageState::Delivered)]
+    );
+    assert_eq!(
+        query(&store, &message_id).unwrap().state,
+        MessageState::Delivered
+    );
+}
+
+#[test]
+fn message_ids_should_be_unique() {
+    let store = MessageStore::new(config());
+
+    let first = store.submit(submission("esme")).unwrap();
+    let second = store.submit(submission("esme")).unwrap();
+
+    assert_ne!(first, second);
+    assert_ne!(store.next_message_id(), second);
+}
+
+#[test]
+fn scheduled_message_should_be_enroute_at_its_schedule_time() {
+    let store = MessageStore::new(config());
+
+    let message_id = store
+        .submit(Submission {
+            // In 100 seconds.
+            schedule_delivery_time: String::from("000000000140000R"),
+            ..submission("esme")
+        })
+        .unwrap();
+
+    let now = SystemTime::now();
+
+    assert_eq!(
+        states(store.advance(now)),
+        vec![(message_id.clone(), MessageState::Scheduled)]
+    );
+    assert_eq!(
+        states(store.advance(now + Duration::from_secs(101))),
+        vec![(message_id.clone(), MessageState::Enroute)]
+    );
+    assert!(store.advance(now + Duration::from_secs(105)).is_empty());
+    assert_eq!(
+        states(store.advance(now + Duration::from_secs(111))),
+        vec![(message_id, MessageState::Delivered)]
+    );
+}
+
+#[test]
+fn message_should_expire_before_its_delivery() {
+    let store = MessageStore::new(config());
+
+    let message_id = store
+        .submit(Submission {
+            // In 5 seconds.
+            validity_period: String::from("000000000005000R"),
+            ..submission("esme")
+        })
+        .unwrap();
+
+    let now = SystemTime::now();
+
+    store.advance(now);
+
+    assert_eq!(
+        states(store.advance(now + Duration::from_secs(6))),
+        vec![(message_id, MessageState::Expired)]
+    );
+}
+
+#[test]
+fn outcomes_should_pick_the_final_state() {
+    let store = MessageStore::new(MessageStoreConfig {
+        outcomes: Outcomes {
+            delivered: 0,
+            undeliverable: 1,
+            rejected: 0,
+        },
+        ..config()
+    });
+
+    let message_id = store.submit(submission("esme")).unwrap();
+
+    assert_eq!(
+        states(store.advance(SystemTime::now() + DELIVERY_DELAY)),
+        vec![(message_id, MessageState::Undeliverable)]
+    );
+}
+
+#[test]
+fn invalid_times_should_be_rejected() {
+    let store = MessageStore::new(config());
+
+    assert_eq!(
+        store
+            .submit(Submission {
+                schedule_delivery_time: String::from("invalid"),
+                ..submission("esme")
+            })
+            .unwrap_err(),
+        CommandStatus::EsmeRinvsched
+    );
+    assert_eq!(
+        store
+            .submit(Submission {
+                validity_period: String::from("000101000000000+"),
+                ..submission("esme")
+            })
+            .unwrap_err(),
+        CommandStatus::EsmeRinvexpiry
+    );
+}
+
+#[test]
+fn messages_should_only_be_visible_to_their_system_id() {
+    let store = MessageStore::new(config());
+
+    let message_id = store.submit(submission("esme")).unwrap();
+
+    assert_eq!(
+        store.query("other", &message_id, &address("")).unwrap_err(),
+        CommandStatus::EsmeRqueryfail
+    );
+    assert_eq!(
+        store
+            .query("esme", &message_id, &address("3333"))
+            .unwrap_err(),
+        CommandStatus::EsmeRqueryfail
+    );
+    assert_eq!(
+        store
+            .cancel("other", cancellation(&message_id))
+            .unwrap_err(),
+        CommandStatus::EsmeRcancelfail
+    );
+    assert_eq!(
+        store
+            .replace("other", replacement(&message_id, b"Bye"))
+            .unwrap_err(),
+        CommandStatus::EsmeRreplacefail
+    );
+}
+
+#[test]
+fn cancelled_message_should_be_deleted() {
+    let store = MessageStore::new(config());
+
+    let message_id = store.submit(submission("esme")).unwrap();
+
+    store.cancel("esme", cancellation(&message_id)).unwrap();
+
+    assert_eq!(
+        query(&store, &message_id).unwrap().state,
+        MessageState::Deleted
+    );
+    assert_eq!(
+        states(store.advance(SystemTime::now() + DELIVERY_DELAY)),
+        vec![(message_id.clone(), MessageState::Deleted)]
+    );
+    assert_eq!(
+        store.cancel("esme", cancellation(&message_id)).unwrap_err(),
+        CommandStatus::EsmeRcancelfail
+    );
+}
+
+#[test]
+fn cancel_without_message_id_should_cancel_all_matching_messages() {
+    let store = MessageStore::new(config());
+
+    let first = store.submit(submission("esme")).unwrap();
+    let second = store.submit(submission("esme")).unwrap();
+    let other = store
+        .submit(Submission {
+            destination: address("3333"),
+            ..submission("esme")
+        })
+        .unwrap();
+
+    store.cancel("esme", cancellation("")).unwrap();
+
+    assert_eq!(query(&store, &first).unwrap().state, MessageState::Deleted);
+    assert_eq!(query(&store, &second).unwrap().state, MessageState::Deleted);
+    assert_eq!(query(&store, &other).unwrap().state, MessageState::Enroute);
+
+    assert_eq!(
+        store.cancel("esme", cancellation("")).unwrap_err(),
+        CommandStatus::EsmeRcancelfail
+    );
+}
+
+#[test]
+fn replaced_message_should_keep_its_lifecycle() {
+    let store = MessageStore::new(config());
+
+    let message_id = store.submit(submission("esme")).unwrap();
+
+    store
+        .replace("esme", replacement(&message_id, b"Bye"))
+        .unwrap();
+
+    let message = query(&store, &message_id).unwrap();
+
+    assert_eq!(message.short_message, b"Bye");
+    assert_eq!(message.state, MessageState::Enroute);
+
+    store.advance(SystemTime::now() + DELIVERY_DELAY);
+
+    assert_eq!(
+        store
+            .replace("esme", replacement(&message_id, b"Again"))
+            .unwrap_err(),
+        CommandStatus::EsmeRreplacefail
+    );
+}
+
+#[test]
+fn replaced_schedule_should_postpone_the_delivery() {
+    let store = MessageStore::new(config());
+
+    let message_id = store.submit(submission("esme")).unwrap();
+
+    let now = SystemTime::now();
+
+    store.advance(now);
+
+    store
+        .replace(
+            "esme",
+            Replacement {
+                // In 100 seconds.
+                schedule_delivery_time: String::from("000000000140000R"),
+                ..replacement(&message_id, b"Later")
+            },
+        )
+        .unwrap();
+
+    assert_eq!(
+        states(store.advance(now + DELIVERY_DELAY)),
+        vec![(message_id.clone(), MessageState::Scheduled)]
+    );
+    assert_eq!(
+        states(store.advance(now + Duration::from_secs(111))),
+        vec![(message_id, MessageState::Delivered)]
+    );
+}
+
+#[test]
+fn final_messages_should_be_dropped_after_the_retention() {
+    let store = MessageStore::new(config());
+
+    let message_id = store.submit(submission("esme")).unwrap();
+
+    let now = SystemTime::now();
+
+    store.advance(now + DELIVERY_DELAY);
+
+    assert!(query(&store, &message_id).is_ok());
+
+    store.advance(now + DELIVERY_DELAY + RETENTION);
+
+    assert_eq!(
+        query(&store, &message_id).unwrap_err(),
+        CommandStatus::EsmeRqueryfail
+    );
+}
+
+#[test]
+fn store_should_only_be_saved_when_changed() {
+    let file = temp_file();
+
+    let store = MessageStore::load(MessageStoreConfig {
+        file: Some(file.clone()),
+        ..config()
+    })
+    .unwrap();
+
+    assert!(!store.is_dirty());
+
+    let message_id = store.submit(submission("esme")).unwrap();
+
+    assert!(store.is_dirty());
+
+    store.persist().unwrap();
+
+    assert!(!store.is_dirty());
+    assert!(file.exists());
+
+    std::fs::remove_file(&file).unwrap();
+
+    store.persist().unwrap();
+
+    assert!(!file.exists());
+
+    store.cancel("esme", cancellation(&message_id)).unwrap();
+    store.persist().unwrap();
+
+    let restored = MessageStore::load(MessageStoreConfig {
+        file: Some(file.clone()),
+        ..config()
+    })
+    .unwrap();
+
+    let message = query(&restored, &message_id).unwrap();
+
+    assert_eq!(message.state, MessageState::Deleted);
+    assert_eq!(message.short_message, b"Hello");
+    assert_ne!(restored.next_message_id(), message_id);
+
+    // Restored messages are indexed for their retention.
+    restored.advance(SystemTime::now() + RETENTION);
+
+    assert_eq!(
+        query(&restored, &message_id).unwrap_err(),
+        CommandStatus::EsmeRqueryfail
+    );
+
+    std::fs::remove_file(&file).unwrap();
+}
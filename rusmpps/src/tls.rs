@@ -0,0 +1,186 @@
+use std::{
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+    time::{Duration, SystemTime},
+};
+
+use rustls::{
+    RootCertStore, ServerConfig,
+    crypto::CryptoProvider,
+    pki_types::{CertificateDer, PrivateKeyDer, pem::PemObject},
+    server::{WebPkiClientVerifier, danger::ClientCertVerifier},
+};
+use serde::Deserialize;
+use tokio_rustls::TlsAcceptor;
+
+#[cfg(test)]
+mod tests;
+
+/// A TLS listener.
+#[derive(Debug, Clone, Deserialize)]
+pub struct TlsConfig {
+    pub socket_addr: SocketAddr,
+    /// PEM encoded certificate chain.
+    pub cert: PathBuf,
+    /// PEM encoded private key.
+    pub key: PathBuf,
+    /// PEM encoded CA certificates. Client certificates are required and verified against them if set.
+    #[serde(default)]
+    pub client_ca: Option<PathBuf>,
+    /// Interval at which the files are checked for changes.
+    #[serde(with = "humantime_serde")]
+    #[serde(default = "default_reload_interval")]
+    pub reload_interval: Duration,
+}
+
+fn default_reload_interval() -> Duration {
+    Duration::from_secs(10)
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum TlsError {
+    #[error("Failed to read `{path}`: {source}")]
+    Read {
+        path: PathBuf,
+        source: rustls::pki_types::pem::Error,
+    },
+    #[error("No certificates found in `{0}`")]
+    NoCertificates(PathBuf),
+    #[error("Invalid client CA certificate: {0}")]
+    ClientCa(#[source] rustls::Error),
+    #[error("Failed to build client certificate verifier: {0}")]
+    Verifier(#[from] rustls::server::VerifierBuilderError),
+    #[error("Invalid TLS configuration: {0}")]
+    Rustls(#[from] rustls::Error),
+}
+
+/// A [`TlsAcceptor`] rebuilt whenever the configured files change.
+#[derive(Debug)]
+pub struct ReloadingTlsAcceptor {
+    config: TlsConfig,
+    server_config: RwLock<Arc<ServerConfig>>,
+}
+
+impl ReloadingTlsAcceptor {
+    pub fn load(config: TlsConfig) -> Result<Self, TlsError> {
+        let server_config = RwLock::new(server_config(&config)?);
+
+        Ok(Self {
+            config,
+            server_config,
+        })
+    }
+
+    /// Returns the acceptor for the current files.
+    pub fn acceptor(&self) -> TlsAcceptor {
+        let server_config = self
+            .server_config
+            .read()
+            .expect("Server config lock poisoned")
+            .clone();
+
+        TlsAcceptor::from(server_config)
+    }
+
+    /// Reloads the acceptor when the modification time of a file changes.
+    ///
+    /// Invalid files are reported and the previous acceptor is kept.
+    pub async fn watch(self: Arc<Self>) {
+        let mut interval = tokio::time::interval(self.config.reload_interval);
+        let mut modified = self.modified();
+
+        loop {
+            interval.tick().await;
+
+            let current = self.modified();
+
+            if current == modified {
+                continue;
+            }
+
+            modified = current;
+
+            match server_config(&self.config) {
+                Ok(server_config) => {
+                    *self
+                        .server_config
+                        .write()
+                        .expect("Server config lock poisoned") = server_config;
+
+                    tracing::info!(socket_addr=%self.config.socket_addr, "Reloaded TLS certificates");
+                }
+                Err(err) => {
+                    tracing::error!(socket_addr=%self.config.socket_addr, %err, "Failed to reload TLS certificates, keeping the previous ones");
+                }
+            }
+        }
+    }
+
+    fn modified(&self) -> Vec<Option<SystemTime>> {
+        [
+            Some(&self.config.cert),
+            Some(&self.config.key),
+            self.config.client_ca.as_ref(),
+        ]
+        .into_iter()
+        .flatten()
+        .map(|path| {
+            std::fs::metadata(path)
+                .and_then(|metadata| metadata.modified())
+                .ok()
+        })
+        .collect()
+    }
+}
+
+fn server_config(config: &TlsConfig) -> Result<Arc<ServerConfig>, TlsError> {
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+
+    let certs = certificates(&config.cert)?;
+
+    let key = PrivateKeyDer::from_pem_file(&config.key).map_err(|source| TlsError::Read {
+        path: config.key.clone(),
+        source,
+    })?;
+
+    let builder = ServerConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()?;
+
+    let server_config = match &config.client_ca {
+        Some(client_ca) => builder
+            .with_client_cert_verifier(client_verifier(client_ca, provider)?)
+            .with_single_cert(certs, key)?,
+        None => builder.with_no_client_auth().with_single_cert(certs, key)?,
+    };
+
+    Ok(Arc::new(server_config))
+}
+
+fn client_verifier(
+    client_ca: &Path,
+    provider: Arc<CryptoProvider>,
+) -> Result<Arc<dyn ClientCertVerifier>, TlsError> {
+    let mut roots = RootCertStore::empty();
+
+    for cert in certificates(client_ca)? {
+        roots.add(cert).map_err(TlsError::ClientCa)?;
+    }
+
+    Ok(WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider).build()?)
+}
+
+fn certificates(path: &Path) -> Result<Vec<CertificateDer<'static>>, TlsError> {
+    let certs = CertificateDer::pem_file_iter(path)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .map_err(|source| TlsError::Read {
+            path: path.into(),
+            source,
+        })?;
+
+    if certs.is_empty() {
+        return Err(TlsError::NoCertificates(path.into()));
+    }
+
+    Ok(certs)
+}
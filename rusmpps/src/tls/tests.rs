@@ -0,0 +1,236 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use rcgen::{BasicConstraints, CertificateParams, CertifiedIssuer, IsCa, KeyPair};
+use rustls::{ClientConfig, pki_types::ServerName};
+use tokio_rustls::TlsConnector;
+
+use super::*;
+
+const RELOAD_INTERVAL: Duration = Duration::from_millis(50);
+
+fn temp_dir() -> PathBuf {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    let dir = std::env::temp_dir().join(format!(
+        "rusmpps-tls-{}-{}",
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+
+    std::fs::create_dir_all(&dir).unwrap();
+
+    dir
+}
+
+/// A CA issuing server certificates for `127.0.0.1` and client certificates.
+struct Pki {
+    ca: CertifiedIssuer<'static, KeyPair>,
+}
+
+impl Pki {
+    fn new() -> Self {
+        let mut params = CertificateParams::new(Vec::<String>::new()).unwrap();
+        params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+
+        let ca = CertifiedIssuer::self_signed(params, KeyPair::generate().unwrap()).unwrap();
+
+        Self { ca }
+    }
+
+    /// Issues a certificate for `name` and returns its PEM certificate and key.
+    fn issue(&self, name: &str) -> (String, String) {
+        let key = KeyPair::generate().unwrap();
+        let cert = CertificateParams::new(vec![name.to_string()])
+            .unwrap()
+            .signed_by(&key, &self.ca)
+            .unwrap();
+
+        (cert.pem(), key.serialize_pem())
+    }
+
+    fn roots(&self) -> RootCertStore {
+        let mut roots = RootCertStore::empty();
+        roots.add(self.ca.der().clone()).unwrap();
+
+        roots
+    }
+}
+
+/// Writes a new server certificate and returns it.
+fn write_server_cert(pki: &Pki, config: &TlsConfig) -> CertificateDer<'static> {
+    let (cert, key) = pki.issue("127.0.0.1");
+
+    std::fs::write(&config.cert, &cert).unwrap();
+    std::fs::write(&config.key, key).unwrap();
+
+    CertificateDer::from_pem_slice(cert.as_bytes()).unwrap()
+}
+
+fn tls_config(dir: &Path) -> TlsConfig {
+    TlsConfig {
+        socket_addr: "127.0.0.1:0".parse().unwrap(),
+        cert: dir.join("server.pem"),
+        key: dir.join("server.key"),
+        client_ca: None,
+        reload_interval: RELOAD_INTERVAL,
+    }
+}
+
+fn client_config(pki: &Pki, client: Option<(String, String)>) -> ClientConfig {
+    let builder =
+        ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_root_certificates(pki.roots());
+
+    match client {
+        Some((cert, key)) => builder
+            .with_client_auth_cert(
+                vec![CertificateDer::from_pem_slice(cert.as_bytes()).unwrap()],
+                PrivateKeyDer::from_pem_slice(key.as_bytes()).unwrap(),
+            )
+            .unwrap(),
+        None => builder.with_no_client_auth(),
+    }
+}
+
+/// Performs a handshake with the current acceptor and returns the server certificate.
+async fn handshake(
+    acceptor: &ReloadingTlsAcceptor,
+    client_config: ClientConfig,
+) -> std::io::Result<CertificateDer<'static>> {
+    let (server, client) = tokio::io::duplex(64 * 1024);
+
+    let connector = TlsConnector::from(Arc::new(client_config));
+    let server_name = ServerName::try_from("127.0.0.1").unwrap();
+
+    let (accepted, connected) = tokio::join!(
+        acceptor.acceptor().accept(server),
+        connector.connect(server_name, client)
+    );
+
+    accepted?;
+
+    let connected = connected?;
+
+    let cert = connected.get_ref().1.peer_certificates().unwrap()[0]
+        .clone()
+        .into_owned();
+
+    Ok(cert)
+}
+
+/// Waits for the watcher to check the files a few times.
+async fn wait_for_reload() {
+    tokio::time::sleep(RELOAD_INTERVAL * 4).await;
+}
+
+#[tokio::test]
+async fn rewritten_certificates_should_be_picked_up() {
+    let pki = Pki::new();
+    let dir = temp_dir();
+    let config = tls_config(&dir);
+
+    let first = write_server_cert(&pki, &config);
+
+    let acceptor = Arc::new(ReloadingTlsAcceptor::load(config.clone()).unwrap());
+
+    tokio::spawn(acceptor.clone().watch());
+
+    assert_eq!(
+        handshake(&acceptor, client_config(&pki, None))
+            .await
+            .unwrap(),
+        first
+    );
+
+    let second = write_server_cert(&pki, &config);
+
+    wait_for_reload().await;
+
+    assert_eq!(
+        handshake(&acceptor, client_config(&pki, None))
+            .await
+            .unwrap(),
+        second
+    );
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn invalid_replacements_should_keep_the_previous_acceptor() {
+    let pki = Pki::new();
+    let dir = temp_dir();
+    let config = tls_config(&dir);
+
+    let first = write_server_cert(&pki, &config);
+
+    let acceptor = Arc::new(ReloadingTlsAcceptor::load(config.clone()).unwrap());
+
+    tokio::spawn(acceptor.clone().watch());
+
+    std::fs::write(&config.cert, "not a certificate").unwrap();
+
+    wait_for_reload().await;
+
+    assert_eq!(
+        handshake(&acceptor, client_config(&pki, None))
+            .await
+            .unwrap(),
+        first
+    );
+
+    // Invalid files are rejected up front.
+    assert!(matches!(
+        ReloadingTlsAcceptor::load(config),
+        Err(TlsError::NoCertificates(_))
+    ));
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn clients_without_certificate_should_be_rejected_if_client_ca_is_set() {
+    let pki = Pki::new();
+    let dir = temp_dir();
+
+    let client_ca = dir.join("ca.pem");
+
+    std::fs::write(&client_ca, pki.ca.pem()).unwrap();
+
+    let config = TlsConfig {
+        client_ca: Some(client_ca),
+        ..tls_config(&dir)
+    };
+
+    write_server_cert(&pki, &config);
+
+    let acceptor = ReloadingTlsAcceptor::load(config).unwrap();
+
+    assert!(
+        handshake(&acceptor, client_config(&pki, None))
+            .await
+            .is_err()
+    );
+
+    // Certificates issued by another CA are rejected too.
+    let other = Pki::new();
+    let client = other.issue("esme");
+
+    assert!(
+        handshake(&acceptor, client_config(&pki, Some(client)))
+            .await
+            .is_err()
+    );
+
+    let client = pki.issue("esme");
+
+    assert!(
+        handshake(&acceptor, client_config(&pki, Some(client)))
+            .await
+            .is_ok()
+    );
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,115 @@
+use std::{
+    net::SocketAddr,
+    pin::Pin,
+    task::{Context, Poll, ready},
+};
+
+use futures::{Sink, Stream};
+use serde::Deserialize;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio_tungstenite::{
+    WebSocketStream,
+    tungstenite::{Bytes, Message},
+};
+
+#[cfg(test)]
+mod tests;
+
+/// A `WebSocket` listener. `SMPP` commands are carried in binary messages.
+#[derive(Debug, Clone, Deserialize)]
+pub struct WebSocketConfig {
+    pub socket_addr: SocketAddr,
+    /// Accepts `wss` connections using the certificates of the `tls` listener.
+    #[serde(default)]
+    pub tls: bool,
+}
+
+/// A byte stream over the binary messages of a `WebSocket`.
+///
+/// Written bytes are sent as one binary message on every flush, so that every command sent by the connection has its own message.
+/// Received messages are read as a byte stream, commands may be split across messages or share a message.
+#[derive(Debug)]
+pub struct WebSocketIo<S> {
+    stream: WebSocketStream<S>,
+    read: Bytes,
+    write: Vec<u8>,
+}
+
+impl<S> WebSocketIo<S> {
+    pub fn new(stream: WebSocketStream<S>) -> Self {
+        Self {
+            stream,
+            read: Bytes::new(),
+            write: Vec::new(),
+        }
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> AsyncRead for WebSocketIo<S> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        loop {
+            if !self.read.is_empty() {
+                let len = self.read.len().min(buf.remaining());
+
+                buf.put_slice(&self.read.split_to(len));
+
+                return Poll::Ready(Ok(()));
+            }
+
+            match ready!(Pin::new(&mut self.stream).poll_next(cx)) {
+                Some(Ok(Message::Binary(bytes))) => self.read = bytes,
+                Some(Ok(Message::Text(_))) => {
+                    return Poll::Ready(Err(std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        "Received a text message, SMPP commands must be sent in binary messages",
+                    )));
+                }
+                // End of stream
+                Some(Ok(Message::Close(_))) | None => return Poll::Ready(Ok(())),
+                // Pings are answered by the stream itself
+                Some(Ok(Message::Ping(_) | Message::Pong(_) | Message::Frame(_))) => {}
+                Some(Err(err)) => return Poll::Ready(Err(std::io::Error::other(err))),
+            }
+        }
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> AsyncWrite for WebSocketIo<S> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        self.write.extend_from_slice(buf);
+
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        if !self.write.is_empty() {
+            ready!(Pin::new(&mut self.stream).poll_ready(cx)).map_err(std::io::Error::other)?;
+
+            let bytes = std::mem::take(&mut self.write);
+
+            Pin::new(&mut self.stream)
+                .start_send(Message::binary(bytes))
+                .map_err(std::io::Error::other)?;
+        }
+
+        Pin::new(&mut self.stream)
+            .poll_flush(cx)
+            .map_err(std::io::Error::other)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        ready!(self.as_mut().poll_flush(cx))?;
+
+        Pin::new(&mut self.stream)
+            .poll_close(cx)
+            .map_err(std::io::Error::other)
+    }
+}
@@ -0,0 +1,233 @@
+use std::time::Duration;
+
+use futures::{SinkExt, StreamExt};
+use rusmpp::{
+    Command, CommandId, CommandStatus, Pdu,
+    pdus::{BindTransceiver, SubmitSm, SubmitSmResp},
+    tokio_codec::CommandCodec,
+};
+use rusmppc::ConnectionBuilder;
+use tokio::io::{AsyncReadExt, AsyncWriteExt, DuplexStream};
+use tokio_tungstenite::tungstenite::protocol::Role;
+use tokio_util::{
+    bytes::BytesMut,
+    codec::{Encoder, Framed},
+};
+
+use super::*;
+use crate::{
+    server::{Server, ServerParameters},
+    service::{Bind, Bound, Response, Service, SessionHandler},
+};
+
+/// Returns the server side wrapped in a [`WebSocketIo`] and the raw client side.
+async fn websocket() -> (WebSocketIo<DuplexStream>, WebSocketStream<DuplexStream>) {
+    let (server, client) = tokio::io::duplex(4096);
+
+    let (server, client) = tokio::join!(
+        WebSocketStream::from_raw_socket(server, Role::Server, None),
+        WebSocketStream::from_raw_socket(client, Role::Client, None)
+    );
+
+    (WebSocketIo::new(server), client)
+}
+
+fn command(sequence_number: u32, pdu: impl Into<Pdu>) -> Command {
+    Command::builder()
+        .status(CommandStatus::EsmeRok)
+        .sequence_number(sequence_number)
+        .pdu(pdu)
+}
+
+fn encode(commands: impl IntoIterator<Item = Command>) -> Vec<u8> {
+    let mut buf = BytesMut::new();
+
+    for command in commands {
+        CommandCodec::new().encode(command, &mut buf).unwrap();
+    }
+
+    buf.to_vec()
+}
+
+async fn next_message(client: &mut WebSocketStream<DuplexStream>) -> Message {
+    tokio::time::timeout(Duration::from_secs(1), client.next())
+        .await
+        .expect("Timed out waiting for a message")
+        .expect("WebSocket closed")
+        .expect("Failed to read message")
+}
+
+async fn next_command(framed: &mut Framed<WebSocketIo<DuplexStream>, CommandCodec>) -> Command {
+    tokio::time::timeout(Duration::from_secs(1), framed.next())
+        .await
+        .expect("Timed out waiting for a command")
+        .expect("Connection closed")
+        .expect("Failed to decode command")
+}
+
+#[tokio::test]
+async fn every_flush_should_send_one_binary_message() {
+    let (io, mut client) = websocket().await;
+
+    let mut framed = Framed::new(io, CommandCodec::new());
+
+    let first = command(1, Pdu::EnquireLink);
+    let second = command(2, SubmitSm::default());
+
+    // `send` flushes after every command.
+    framed.send(first.clone()).await.unwrap();
+    framed.send(second.clone()).await.unwrap();
+
+    assert_eq!(
+        next_message(&mut client).await,
+        Message::binary(encode([first]))
+    );
+    assert_eq!(
+        next_message(&mut client).await,
+        Message::binary(encode([second]))
+    );
+
+    // Writes are buffered until the flush.
+    let bytes = encode([command(3, Pdu::EnquireLink)]);
+    let (head, tail) = bytes.split_at(6);
+
+    let io = framed.get_mut();
+
+    io.write_all(head).await.unwrap();
+    io.write_all(tail).await.unwrap();
+    io.flush().await.unwrap();
+
+    assert_eq!(next_message(&mut client).await, Message::binary(bytes));
+}
+
+#[tokio::test]
+async fn commands_split_across_messages_should_be_read() {
+    let (io, mut client) = websocket().await;
+
+    let mut framed = Framed::new(io, CommandCodec::new());
+
+    let bytes = encode([command(1, SubmitSm::default())]);
+    let (head, tail) = bytes.split_at(6);
+
+    client.send(Message::binary(head.to_vec())).await.unwrap();
+    client.send(Message::binary(tail.to_vec())).await.unwrap();
+
+    let command = next_command(&mut framed).await;
+
+    assert_eq!(command.id(), CommandId::SubmitSm);
+    assert_eq!(command.sequence_number(), 1);
+}
+
+#[tokio::test]
+async fn commands_sharing_a_message_should_be_read() {
+    let (io, mut client) = websocket().await;
+
+    let mut framed = Framed::new(io, CommandCodec::new());
+
+    let bytes = encode([
+        command(1, Pdu::EnquireLink),
+        command(2, SubmitSm::default()),
+    ]);
+
+    client.send(Message::binary(bytes)).await.unwrap();
+
+    let first = next_command(&mut framed).await;
+    let second = next_command(&mut framed).await;
+
+    assert_eq!(
+        (first.id(), first.sequence_number()),
+        (CommandId::EnquireLink, 1)
+    );
+    assert_eq!(
+        (second.id(), second.sequence_number()),
+        (CommandId::SubmitSm, 2)
+    );
+}
+
+#[tokio::test]
+async fn text_messages_should_be_an_error() {
+    let (mut io, mut client) = websocket().await;
+
+    client.send(Message::text("enquire_link")).await.unwrap();
+
+    let mut buf = [0; 16];
+
+    let err = io.read(&mut buf).await.unwrap_err();
+
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+}
+
+struct AcceptAll;
+
+impl Service for AcceptAll {
+    type Handler = AcceptAll;
+
+    async fn bind(&self, _bind: &Bind) -> Result<Bound<AcceptAll>, CommandStatus> {
+        Ok(Bound::new(AcceptAll))
+    }
+}
+
+impl SessionHandler for AcceptAll {
+    async fn request(&self, pdu: Pdu) -> Response {
+        match pdu {
+            Pdu::SubmitSm(_) => Response::ok(SubmitSmResp::default()),
+            _ => Response::Ignore,
+        }
+    }
+}
+
+fn free_addr() -> SocketAddr {
+    std::net::TcpListener::bind("127.0.0.1:0")
+        .and_then(|listener| listener.local_addr())
+        .expect("Failed to find a free port")
+}
+
+#[tokio::test]
+async fn clients_should_bind_and_submit_over_websocket() {
+    let websocket_addr = free_addr();
+
+    let server = Server::new(
+        ServerParameters {
+            enquire_link_interval: None,
+            enquire_link_response_timeout: Duration::from_secs(5),
+            enquire_link_response_delay: None,
+            session_timeout: Duration::from_secs(5),
+            window_size: 10,
+            response_timeout: Duration::from_secs(5),
+            socket_addr: free_addr(),
+            tls: None,
+            websocket: Some(WebSocketConfig {
+                socket_addr: websocket_addr,
+                tls: false,
+            }),
+            admin: None,
+        },
+        AcceptAll,
+    );
+
+    tokio::spawn(server.run());
+
+    let url = format!("ws://{websocket_addr}");
+
+    // The listener is bound by the spawned server.
+    let (client, _events) = tokio::time::timeout(Duration::from_secs(5), async {
+        loop {
+            match ConnectionBuilder::new().connect(&url).await {
+                Ok(connection) => break connection,
+                Err(_) => tokio::time::sleep(Duration::from_millis(10)).await,
+            }
+        }
+    })
+    .await
+    .expect("Timed out connecting to the WebSocket listener");
+
+    client
+        .bind_transceiver(BindTransceiver::default())
+        .await
+        .expect("Failed to bind");
+
+    client
+        .submit_sm(SubmitSm::default())
+        .await
+        .expect("Failed to submit");
+}